@@ -0,0 +1,181 @@
+use std::{fs, path::Path};
+
+use neat::{
+    config::neat_config::NeatConfig,
+    individual::{
+        genome::genome::{Genome, InnovationId},
+        individual::{EvaluatedIndividual, Individual},
+    },
+    mutation::innovation_number::InnovNumber,
+    population::{binary, population::Population},
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::CliError, experiment::Experiment};
+
+/// State of a run between two generations, saved as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub experiment: Experiment,
+    pub config: NeatConfig,
+    /// Generation of `genomes`, which are not evaluated yet.
+    pub generation: usize,
+    /// Last innovation number handed out.
+    pub innovation: InnovationId,
+    pub genomes: Vec<Genome>,
+    /// Fittest genome evaluated so far and its fitness.
+    pub champion: Option<(Genome, f32)>,
+}
+
+impl Checkpoint {
+    /// Initial population of `experiment`.
+    pub fn new(experiment: Experiment, config: NeatConfig) -> Self {
+        let genomes = vec![experiment.factory().generate_genome(); config.population_size];
+        let innovation = InnovNumber::from_population(&genomes).last();
+        Self {
+            experiment,
+            genomes,
+            config,
+            generation: 0,
+            innovation,
+            champion: None,
+        }
+    }
+
+    /// Load from JSON, or from the binary encoding for `.bin` paths.
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        if is_binary(path) {
+            let bytes = fs::read(path).map_err(|e| CliError::Io(path.to_owned(), e))?;
+            return binary::from_bytes(&bytes).map_err(|e| CliError::Binary(path.to_owned(), e));
+        }
+        let text = fs::read_to_string(path).map_err(|e| CliError::Io(path.to_owned(), e))?;
+        serde_json::from_str(&text).map_err(|e| CliError::Checkpoint(path.to_owned(), e))
+    }
+
+    /// Save as JSON, or in the binary encoding for `.bin` paths.
+    pub fn save(&self, path: &Path) -> Result<(), CliError> {
+        let bytes = if is_binary(path) {
+            binary::to_bytes(self).map_err(|e| CliError::Binary(path.to_owned(), e))?
+        } else {
+            serde_json::to_vec(self).map_err(|e| CliError::Checkpoint(path.to_owned(), e))?
+        };
+        fs::write(path, bytes).map_err(|e| CliError::Io(path.to_owned(), e))
+    }
+
+    pub fn solved(&self) -> bool {
+        self.champion
+            .as_ref()
+            .is_some_and(|(_, fitness)| *fitness >= self.experiment.target())
+    }
+
+    /// Evolve for `generations` generations or until solved, printing a line
+    /// per generation. Saves to `path` every `interval` generations, 0 for
+    /// never, and after the last. Species are rebuilt from scratch, so
+    /// resuming does not replay an uninterrupted run exactly.
+    pub fn run(
+        mut self,
+        generations: usize,
+        interval: usize,
+        path: &Path,
+    ) -> Result<Self, CliError> {
+        let mut population =
+            Population::new(self.config.algorithm(), std::mem::take(&mut self.genomes))
+                .with_innovation(InnovNumber::resume(self.innovation, self.generation));
+        for step in 1..=generations {
+            let experiment = self.experiment;
+            population.evaluate(|genome| experiment.fitness(genome));
+            let best = population.best(1)[0];
+            println!(
+                "generation {}: best {:.3}, {} species, {} hidden nodes",
+                self.generation,
+                best.fitness(),
+                population.species().len(),
+                best.genome.node_list.hidden.len()
+            );
+            self.record(best);
+            let mut rng =
+                ChaCha8Rng::seed_from_u64(self.config.seed.wrapping_add(self.generation as u64));
+            population.evolve(&mut rng);
+            self.generation += 1;
+            let last = step == generations || self.solved();
+            if last || (interval > 0 && self.generation.is_multiple_of(interval)) {
+                self.genomes = genomes(population.members());
+                self.innovation = population.innovation().last();
+                self.save(path)?;
+            }
+            if last {
+                break;
+            }
+        }
+        Ok(self)
+    }
+
+    fn record(&mut self, best: &EvaluatedIndividual) {
+        if self
+            .champion
+            .as_ref()
+            .is_none_or(|(_, fitness)| best.fitness() > *fitness)
+        {
+            self.champion = Some((best.to_genome(), best.fitness()));
+        }
+    }
+}
+
+fn is_binary(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "bin")
+}
+
+fn genomes(members: &[EvaluatedIndividual]) -> Vec<Genome> {
+    members.iter().map(|m| m.to_genome()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_and_resume() {
+        let path = std::env::temp_dir().join(format!("neat-cli-{}.json", std::process::id()));
+        let config = NeatConfig {
+            population_size: 20,
+            ..Default::default()
+        };
+        let checkpoint = Checkpoint::new(Experiment::Xor, config)
+            .run(3, 0, &path)
+            .unwrap();
+        assert_eq!(checkpoint.generation, 3);
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded.generation, 3);
+        assert_eq!(loaded.genomes.len(), 20);
+        assert_eq!(
+            loaded.champion.as_ref().unwrap().1,
+            checkpoint.champion.unwrap().1
+        );
+
+        let resumed = loaded.run(2, 0, &path).unwrap();
+        assert_eq!(resumed.generation, 5);
+        assert!(resumed.innovation >= 4);
+        assert_eq!(Checkpoint::load(&path).unwrap().generation, 5);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_binary() {
+        let path = std::env::temp_dir().join(format!("neat-cli-{}.bin", std::process::id()));
+        let config = NeatConfig {
+            population_size: 10,
+            ..Default::default()
+        };
+        let checkpoint = Checkpoint::new(Experiment::Xor, config)
+            .run(2, 0, &path)
+            .unwrap();
+        assert_eq!(&std::fs::read(&path).unwrap()[..4], b"NEAT");
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded.generation, 2);
+        assert_eq!(loaded.config, checkpoint.config);
+        assert_eq!(loaded.genomes.len(), 10);
+        std::fs::remove_file(path).unwrap();
+    }
+}
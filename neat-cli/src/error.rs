@@ -0,0 +1,27 @@
+use std::{fmt, io, path::PathBuf};
+
+use neat::{config::neat_config::ConfigError, population::binary::BinaryError};
+
+#[derive(Debug)]
+pub enum CliError {
+    Io(PathBuf, io::Error),
+    Config(PathBuf, ConfigError),
+    Checkpoint(PathBuf, serde_json::Error),
+    Binary(PathBuf, BinaryError),
+    /// A setting that parsed but cannot be used.
+    Invalid(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            CliError::Config(path, error) => write!(f, "{}: {error}", path.display()),
+            CliError::Checkpoint(path, error) => write!(f, "{}: {error}", path.display()),
+            CliError::Binary(path, error) => write!(f, "{}: {error}", path.display()),
+            CliError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
@@ -0,0 +1,87 @@
+use clap::ValueEnum;
+use neat::{
+    environment::{action::Discrete, cartpole::CartPole, rollout::RolloutRunner},
+    individual::genome::{
+        genome::{Genome, GenomeFactory},
+        network::network::FFNetwork,
+    },
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Truth table of XOR, inputs and target.
+const XOR: [([f32; 2], f32); 4] = [
+    ([0., 0.], 0.),
+    ([0., 1.], 1.),
+    ([1., 0.], 1.),
+    ([1., 1.], 0.),
+];
+
+/// Cart-pole episodes per evaluation and their length.
+const EPISODES: usize = 3;
+const MAX_STEPS: usize = 500;
+
+/// Built-in experiments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Experiment {
+    /// Learn XOR of two inputs, fitness 4 minus the squared error.
+    Xor,
+    /// Balance a pole on a cart for 500 steps.
+    #[value(name = "cartpole")]
+    CartPole,
+}
+
+impl Experiment {
+    pub fn factory(&self) -> GenomeFactory {
+        match self {
+            Experiment::Xor => GenomeFactory::init(3, 1)
+                .unwrap()
+                .with_input_names(["a", "b", "bias"])
+                .with_output_names(["xor"]),
+            Experiment::CartPole => GenomeFactory::init(4, 2)
+                .unwrap()
+                .with_input_names([
+                    "cart_position",
+                    "cart_velocity",
+                    "pole_angle",
+                    "pole_velocity",
+                ])
+                .with_output_names(["push_left", "push_right"]),
+        }
+    }
+
+    /// Deterministic fitness of `genome`, non-negative.
+    pub fn fitness(&self, genome: &Genome) -> f32 {
+        let mut network = FFNetwork::from(genome);
+        match self {
+            Experiment::Xor => {
+                let error = XOR
+                    .iter()
+                    .map(|([a, b], target)| {
+                        network.reset();
+                        let output = network.forward(&[*a, *b, 1.]).unwrap()[0];
+                        (output - target).powi(2)
+                    })
+                    .sum::<f32>();
+                (4. - error).max(0.)
+            }
+            Experiment::CartPole => {
+                // Seeded episodes, the rng is never drawn from
+                let mut rng = ChaCha8Rng::seed_from_u64(0);
+                RolloutRunner::new(Discrete::greedy(2), EPISODES, MAX_STEPS)
+                    .with_seed(0)
+                    .fitness(&mut rng, &mut CartPole::default(), &mut network)
+            }
+        }
+    }
+
+    /// Fitness of a solution, which ends the run.
+    pub fn target(&self) -> f32 {
+        match self {
+            Experiment::Xor => 3.9,
+            Experiment::CartPole => MAX_STEPS as f32,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Command-line runner of the built-in NEAT experiments.
+
+mod checkpoint;
+mod error;
+mod experiment;
+
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand};
+use neat::config::neat_config::NeatConfig;
+
+use checkpoint::Checkpoint;
+use error::CliError;
+use experiment::Experiment;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Run NEAT experiments")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start an experiment.
+    Run {
+        experiment: Experiment,
+        /// TOML run configuration, defaults for missing keys.
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Saved as JSON, or in the binary encoding with a `.bin` extension.
+        #[arg(long, default_value = "checkpoint.json")]
+        checkpoint: PathBuf,
+        #[command(flatten)]
+        saving: Saving,
+    },
+    /// Continue an experiment from its checkpoint, which is updated.
+    Resume {
+        checkpoint: PathBuf,
+        /// Generations to run, the configured number by default.
+        #[arg(long)]
+        generations: Option<usize>,
+        #[command(flatten)]
+        saving: Saving,
+    },
+    /// Summarize a checkpoint.
+    Inspect {
+        checkpoint: PathBuf,
+        /// Write the champion in DOT to this file, `-` for stdout.
+        #[arg(long)]
+        dot: Option<PathBuf>,
+        /// Write the champion in SVG to this file, `-` for stdout.
+        #[arg(long)]
+        svg: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Saving {
+    /// Save the checkpoint every this many generations, besides the last.
+    /// 0 only saves the last.
+    #[arg(long, default_value_t = 10)]
+    checkpoint_interval: usize,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Run {
+            experiment,
+            config,
+            checkpoint,
+            saving,
+        } => {
+            let config = config
+                .map(|path| NeatConfig::load(&path).map_err(|e| CliError::Config(path, e)))
+                .transpose()?
+                .unwrap_or_default();
+            let generations = config.generations;
+            Checkpoint::new(experiment, config).run(
+                generations,
+                saving.checkpoint_interval,
+                &checkpoint,
+            )?;
+        }
+        Command::Resume {
+            checkpoint,
+            generations,
+            saving,
+        } => {
+            let state = Checkpoint::load(&checkpoint)?;
+            let generations = generations.unwrap_or(state.config.generations);
+            state.run(generations, saving.checkpoint_interval, &checkpoint)?;
+        }
+        Command::Inspect {
+            checkpoint,
+            dot,
+            svg,
+        } => {
+            let state = Checkpoint::load(&checkpoint)?;
+            inspect(&state);
+            let champion = || {
+                state
+                    .champion
+                    .as_ref()
+                    .map(|(champion, _)| champion)
+                    .ok_or_else(|| CliError::Invalid("checkpoint has no champion".into()))
+            };
+            if let Some(path) = dot {
+                write_output(path, &champion()?.to_dot())?;
+            }
+            if let Some(path) = svg {
+                write_output(path, &champion()?.to_svg())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write `text` to `path`, `-` for stdout.
+fn write_output(path: PathBuf, text: &str) -> Result<(), CliError> {
+    if path.as_os_str() == "-" {
+        print!("{text}");
+        Ok(())
+    } else {
+        fs::write(&path, text).map_err(|e| CliError::Io(path, e))
+    }
+}
+
+fn inspect(state: &Checkpoint) {
+    let genomes = &state.genomes;
+    let mean = |count: fn(&neat::individual::genome::genome::Genome) -> usize| {
+        genomes.iter().map(count).sum::<usize>() as f32 / genomes.len().max(1) as f32
+    };
+    println!("experiment: {:?}", state.experiment);
+    println!("generation: {}", state.generation);
+    println!(
+        "population: {} genomes, {:.1} hidden nodes and {:.1} enabled edges on average",
+        genomes.len(),
+        mean(|g| g.node_list.hidden.len()),
+        mean(|g| g.genome_list.iter().filter(|e| e.enabled).count())
+    );
+    match &state.champion {
+        Some((champion, fitness)) => println!(
+            "champion: fitness {fitness:.3}{}, {} hidden nodes, {} of {} edges enabled",
+            if state.solved() { " (solved)" } else { "" },
+            champion.node_list.hidden.len(),
+            champion.genome_list.iter().filter(|e| e.enabled).count(),
+            champion.genome_list.iter().count()
+        ),
+        None => println!("champion: none"),
+    }
+}
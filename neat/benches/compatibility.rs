@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use itertools::Itertools;
+use neat::{
+    individual::genome::genome::{Genome, GenomeEdge, GenomeFactory},
+    speciation::{
+        compatibility::Compatibility,
+        signature::{InnovationSignature, SignatureSpeciation},
+        speciation::{DistanceSpeciation, SpeciationMethod},
+    },
+};
+use rand::{seq::IteratorRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const POPULATION: usize = 1000;
+
+/// Genomes with around 50 edges drawn from 200 innovations, as after a few
+/// hundred generations of a small task.
+fn genomes() -> Vec<Genome> {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let factory = GenomeFactory::init(4, 2).unwrap();
+    (0..POPULATION)
+        .map(|_| {
+            let mut genome = factory.generate_genome();
+            let size = rng.gen_range(30..70);
+            genome.genome_list.edge_list = (0..200)
+                .choose_multiple(&mut rng, size)
+                .into_iter()
+                .sorted()
+                .map(|innov_number| GenomeEdge {
+                    innov_number,
+                    in_node: 0,
+                    out_node: 4,
+                    weight: rng.gen_range(-1. ..1.),
+                    enabled: true,
+                })
+                .collect();
+            genome
+        })
+        .collect()
+}
+
+fn distance(c: &mut Criterion) {
+    let genomes = genomes();
+    let signatures = genomes.iter().map(InnovationSignature::new).collect_vec();
+    let metric = Compatibility::default();
+    let mut group = c.benchmark_group("distance to all");
+    group.bench_function("merge", |b| {
+        b.iter(|| {
+            genomes
+                .iter()
+                .map(|g| metric.distance(black_box(&genomes[0]), g))
+                .sum::<f32>()
+        })
+    });
+    group.bench_function("signature", |b| {
+        b.iter(|| {
+            signatures
+                .iter()
+                .map(|s| metric.signature_distance(black_box(&signatures[0]), s))
+                .sum::<f32>()
+        })
+    });
+    group.finish();
+}
+
+fn speciation(c: &mut Criterion) {
+    let genomes = genomes();
+    // Tight enough to split the population into a few hundred species
+    let naive = DistanceSpeciation::new(Box::new(Compatibility::default()), 0.8);
+    let fast = SignatureSpeciation::new(Compatibility::default(), 0.8);
+    let mut group = c.benchmark_group("speciate 1k genomes");
+    group.sample_size(20);
+    group.bench_function("merge", |b| b.iter(|| naive.speciate(genomes.iter()).len()));
+    group.bench_function("signature", |b| b.iter(|| fast.speciate(genomes.iter()).len()));
+    group.finish();
+}
+
+criterion_group!(benches, distance, speciation);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use itertools::Itertools;
+use neat::individual::genome::{
+    genome::{Genome, GenomeBuilder},
+    network::{network::FFNetwork, sparse::SparseNetwork},
+    node_list::NodeId,
+};
+use num::rational::Ratio;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const INPUTS: usize = 8;
+const OUTPUTS: usize = 4;
+
+/// Genome with hidden nodes on 10 levels and `edges` distinct forward edges.
+fn layered(edges: usize) -> Genome {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let hidden = (edges / 8).max(4);
+    let mut builder = GenomeBuilder::new().input(INPUTS).output(OUTPUTS);
+    let levels = (0..hidden)
+        .map(|_| Ratio::from_integer(10 * rng.gen_range(1..10)))
+        .collect_vec();
+    for level in levels.iter() {
+        builder = builder.hidden(*level);
+    }
+    let level = |id: usize| match id {
+        id if id < INPUTS => Ratio::from_integer(1),
+        id if id < INPUTS + OUTPUTS => Ratio::from_integer(100),
+        id => levels[id - INPUTS - OUTPUTS],
+    };
+    let nodes = INPUTS + OUTPUTS + hidden;
+    let mut pairs = vec![];
+    while pairs.len() < edges {
+        let pair = (rng.gen_range(0..nodes), rng.gen_range(INPUTS..nodes));
+        if level(pair.0) < level(pair.1) && !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+    for (in_node, out_node) in pairs {
+        builder = builder.connect(
+            in_node as NodeId,
+            out_node as NodeId,
+            rng.gen_range(-1. ..1.),
+        );
+    }
+    builder.build().unwrap()
+}
+
+fn forward(c: &mut Criterion) {
+    let input = (0..INPUTS).map(|i| i as f32 / INPUTS as f32).collect_vec();
+    let mut group = c.benchmark_group("forward");
+    for edges in [4, 16, 64, 1000, 5000] {
+        let genome = layered(edges);
+        let mut graph = FFNetwork::<f32>::from(&genome);
+        let mut sparse = SparseNetwork::<f32>::from(&genome);
+        group.bench_with_input(BenchmarkId::new("graph", edges), &input, |b, input| {
+            b.iter(|| graph.forward(black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("sparse", edges), &input, |b, input| {
+            b.iter(|| sparse.forward(black_box(input)))
+        });
+    }
+    group.finish();
+}
+
+/// Compiling the genome and running a single pass, as when evaluating on
+/// one sample.
+fn single_pass(c: &mut Criterion) {
+    let input = (0..INPUTS).map(|i| i as f32 / INPUTS as f32).collect_vec();
+    let mut group = c.benchmark_group("build and forward");
+    for edges in [4, 16, 64, 1000, 5000] {
+        let genome = layered(edges);
+        group.bench_with_input(BenchmarkId::new("graph", edges), &genome, |b, genome| {
+            b.iter(|| FFNetwork::<f32>::from(black_box(genome)).forward(&input))
+        });
+        group.bench_with_input(BenchmarkId::new("sparse", edges), &genome, |b, genome| {
+            b.iter(|| SparseNetwork::<f32>::from(black_box(genome)).forward(&input))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, forward, single_pass);
+criterion_main!(benches);
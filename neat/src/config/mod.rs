@@ -0,0 +1,2 @@
+pub mod neat_config;
+pub mod reference;
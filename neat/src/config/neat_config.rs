@@ -0,0 +1,602 @@
+//! Run configuration loaded from TOML, e.g.
+//!
+//! ```toml
+//! population_size = 150
+//! seed = 7
+//! activations = ["sigmoid", "tanh", "relu"]
+//! softmax_groups = [[0, 4]]
+//!
+//! [mutation]
+//! weight_sigma = 0.5
+//! weight_perturbation = "cauchy"
+//! split_policy = "long"
+//! node_activation = "tanh"
+//!
+//! [mutation.edge]
+//! prob_new_node = 0.03
+//!
+//! [mutation.operators]
+//! AddModule = 0.02
+//!
+//! [backend.auto]
+//! edges = 64
+//!
+//! [speciation]
+//! threshold = 3.0
+//! ```
+//!
+//! Missing keys take their default and unknown keys are rejected.
+
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crossover::crossover::NeatCrossover,
+    individual::genome::{
+        activation::{self, Activation, ActivationSet},
+        genome::Genome,
+        network::{output_groups::OutputGroups, sparse::Backend},
+    },
+    mutation::{
+        gene_pool::EdgeBias,
+        mutation::{
+            Frozen, GaussianMutation, Perturbation, ProbabilityMatrix, ProbabilityMatrixEdge,
+            ProbabilityMatrixNode, SizeLimits, SplitPolicy,
+        },
+        operator::{ExtendedMutation, MutationPipeline},
+    },
+    selection::selection_trait::RoulleteSelection,
+    speciation::{
+        compatibility::Compatibility,
+        speciation::{Assignment, DistanceSpeciation},
+    },
+    GeneticAlgortihm,
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// Malformed TOML, or a key with the wrong type or unknown.
+    Parse(toml::de::Error),
+    /// Setting out of range, at its dotted key.
+    Invalid {
+        key: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{error}"),
+            ConfigError::Parse(error) => write!(f, "{error}"),
+            ConfigError::Invalid { key, message } => write!(f, "`{key}` {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Mutation rates of [`GaussianMutation`], by default those of
+/// [`ProbabilityMatrixNode`] and [`ProbabilityMatrixEdge`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationConfig {
+    pub node: ProbabilityMatrixNode,
+    pub edge: ProbabilityMatrixEdge,
+    /// Scale of weight perturbations.
+    pub weight_sigma: f32,
+    /// Scale of the weight perturbations of recurrent edges.
+    pub recurrent_weight_sigma: f32,
+    /// Range new weights are drawn from.
+    pub replace_range: (f32, f32),
+    /// Attempts to find a new edge.
+    pub max_iteration: usize,
+    /// Parts of the genome mutations leave untouched, none by default.
+    pub frozen: Frozen,
+    /// Gene pool bias of add-edge mutation.
+    pub edge_bias: EdgeBias,
+    /// Largest genome, in nodes including inputs and outputs, structural
+    /// mutations may grow. Unlimited if absent.
+    pub max_nodes: Option<usize>,
+    /// Largest genome, in edges including disabled ones.
+    pub max_edges: Option<usize>,
+    /// Most distinct hidden levels add-node mutations may create.
+    pub max_depth: Option<usize>,
+    /// Which edge add-node mutation splits.
+    pub split_policy: SplitPolicy,
+    /// Function of new nodes, by name. Drawn from `activations` if absent.
+    #[serde(with = "activation::optional_name")]
+    pub node_activation: Option<Activation>,
+    /// Scale of node bias perturbations.
+    pub bias_sigma: f32,
+    /// Distribution of weight perturbations, Gaussian by default.
+    pub weight_perturbation: Perturbation,
+    /// Distribution of bias perturbations.
+    pub bias_perturbation: Perturbation,
+    /// Probability of perturbing each input scale and offset, of genomes
+    /// with input scaling.
+    pub prob_input_scaling: f64,
+    /// Scale of input scale and offset perturbations.
+    pub input_scaling_coeff: f32,
+    /// Probability of turning a hidden node into a gated memory cell.
+    pub prob_new_gate: f64,
+    /// Probabilities of user-defined operators by name, overriding those
+    /// they were registered with, see [`NeatConfig::mutation_with`].
+    pub operators: BTreeMap<String, f64>,
+    /// Probability of duplicating a module of hidden nodes.
+    pub prob_duplicate_module: f64,
+}
+
+impl Default for MutationConfig {
+    fn default() -> Self {
+        let mutation = GaussianMutation::default();
+        Self {
+            node: ProbabilityMatrixNode::default(),
+            edge: ProbabilityMatrixEdge::default(),
+            weight_sigma: mutation.coeff,
+            recurrent_weight_sigma: mutation.recurrent_coeff,
+            replace_range: mutation.replace_range,
+            max_iteration: mutation.max_iteration,
+            frozen: mutation.frozen,
+            edge_bias: mutation.edge_bias,
+            max_nodes: mutation.limits.max_nodes,
+            max_edges: mutation.limits.max_edges,
+            max_depth: mutation.limits.max_depth,
+            split_policy: mutation.split_policy,
+            node_activation: mutation.node_activation,
+            bias_sigma: mutation.bias_coeff,
+            weight_perturbation: mutation.weight_perturbation,
+            bias_perturbation: mutation.bias_perturbation,
+            prob_input_scaling: mutation.prob_input_scaling,
+            input_scaling_coeff: mutation.input_scaling_coeff,
+            prob_new_gate: mutation.prob_new_gate,
+            operators: BTreeMap::new(),
+            prob_duplicate_module: mutation.prob_duplicate_module,
+        }
+    }
+}
+
+/// Speciation on the NEAT compatibility distance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeciationConfig {
+    /// Genomes at most this far apart share a species.
+    pub threshold: f32,
+    pub excess: f32,
+    pub disjoint: f32,
+    pub weight: f32,
+    pub assignment: Assignment,
+}
+
+impl Default for SpeciationConfig {
+    fn default() -> Self {
+        let compatibility = Compatibility::default();
+        Self {
+            threshold: 3.,
+            excess: compatibility.excess,
+            disjoint: compatibility.disjoint,
+            weight: compatibility.weight,
+            assignment: Assignment::default(),
+        }
+    }
+}
+
+/// Everything that determines a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NeatConfig {
+    pub population_size: usize,
+    pub generations: usize,
+    /// Seed of the generator driving evolution.
+    pub seed: u64,
+    pub mutation: MutationConfig,
+    pub speciation: SpeciationConfig,
+    /// Functions new and mutated nodes may use, all by default.
+    pub activations: ActivationSet,
+    /// Outputs read as softmax groups after the forward pass, none by default.
+    pub softmax_groups: OutputGroups,
+    /// Backend the networks are evaluated on, see
+    /// [`Population::evaluate_compiled`](crate::population::population::Population::evaluate_compiled).
+    pub backend: Backend,
+}
+
+impl Default for NeatConfig {
+    fn default() -> Self {
+        Self {
+            population_size: 150,
+            generations: 100,
+            seed: 0,
+            mutation: MutationConfig::default(),
+            speciation: SpeciationConfig::default(),
+            activations: ActivationSet::all(),
+            softmax_groups: OutputGroups::default(),
+            backend: Backend::default(),
+        }
+    }
+}
+
+impl NeatConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_toml(&fs::read_to_string(path).map_err(ConfigError::Io)?)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Configs serialize to TOML")
+    }
+
+    /// Check the settings serde cannot, naming the first offending key.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key: &str, message: &str| {
+            Err(ConfigError::Invalid {
+                key: key.to_string(),
+                message: message.to_string(),
+            })
+        };
+        if self.population_size == 0 {
+            return invalid("population_size", "must be positive");
+        }
+        let MutationConfig { node, edge, .. } = self.mutation;
+        let operators = self
+            .mutation
+            .operators
+            .iter()
+            .map(|(name, prob)| (format!("mutation.operators.{name}"), *prob));
+        let probabilities = [
+            ("mutation.node.prob_clamp", node.prob_clamp),
+            ("mutation.node.prob_activation", node.prob_activation),
+            ("mutation.node.prob_aggregation", node.prob_aggregation),
+            ("mutation.node.prob_bias", node.prob_bias),
+            ("mutation.edge.prob_enabled", edge.prob_enabled),
+            (
+                "mutation.edge.prob_weight_perturb",
+                edge.prob_weight_perturb,
+            ),
+            (
+                "mutation.edge.prob_weight_replace",
+                edge.prob_weight_replace,
+            ),
+            ("mutation.edge.prob_new_node", edge.prob_new_node),
+            ("mutation.edge.prob_new_edge", edge.prob_new_edge),
+            (
+                "mutation.prob_input_scaling",
+                self.mutation.prob_input_scaling,
+            ),
+            ("mutation.prob_new_gate", self.mutation.prob_new_gate),
+            (
+                "mutation.prob_duplicate_module",
+                self.mutation.prob_duplicate_module,
+            ),
+        ];
+        let probabilities = probabilities
+            .into_iter()
+            .map(|(key, prob)| (key.to_string(), prob))
+            .chain(operators);
+        for (key, prob) in probabilities {
+            if !(0. ..=1.).contains(&prob) {
+                return invalid(&key, "must be a probability in [0, 1]");
+            }
+        }
+        if !(0. ..).contains(&self.mutation.weight_sigma) {
+            return invalid("mutation.weight_sigma", "must be non-negative");
+        }
+        if !(0. ..).contains(&self.mutation.recurrent_weight_sigma) {
+            return invalid("mutation.recurrent_weight_sigma", "must be non-negative");
+        }
+        if !(0. ..).contains(&self.mutation.bias_sigma) {
+            return invalid("mutation.bias_sigma", "must be non-negative");
+        }
+        if !(0. ..).contains(&self.mutation.input_scaling_coeff) {
+            return invalid("mutation.input_scaling_coeff", "must be non-negative");
+        }
+        let (min, max) = self.mutation.replace_range;
+        if min.is_nan() || max.is_nan() || min > max {
+            return invalid("mutation.replace_range", "must be an increasing [min, max]");
+        }
+        let speciation = [
+            ("speciation.threshold", self.speciation.threshold),
+            ("speciation.excess", self.speciation.excess),
+            ("speciation.disjoint", self.speciation.disjoint),
+            ("speciation.weight", self.speciation.weight),
+        ];
+        for (key, value) in speciation {
+            if !(0. ..).contains(&value) {
+                return invalid(key, "must be non-negative");
+            }
+        }
+        if !self.softmax_groups.is_valid() {
+            return invalid("softmax_groups", "must be non-empty disjoint [start, end) ranges");
+        }
+        Ok(())
+    }
+
+    pub fn mutation(&self) -> GaussianMutation {
+        let MutationConfig {
+            node,
+            edge,
+            weight_sigma,
+            recurrent_weight_sigma,
+            replace_range: (min, max),
+            max_iteration,
+            frozen,
+            edge_bias,
+            max_nodes,
+            max_edges,
+            max_depth,
+            split_policy,
+            node_activation,
+            bias_sigma,
+            weight_perturbation,
+            bias_perturbation,
+            prob_input_scaling,
+            input_scaling_coeff,
+            prob_new_gate,
+            prob_duplicate_module,
+            ..
+        } = self.mutation;
+        let prob = ProbabilityMatrix {
+            node_probs: node,
+            prob_edge: edge,
+        };
+        GaussianMutation::new(prob, weight_sigma, max_iteration)
+            .with_replace_range(min, max)
+            .with_recurrent_coeff(recurrent_weight_sigma)
+            .with_bias_coeff(bias_sigma)
+            .with_activations(self.activations)
+            .with_frozen(frozen)
+            .with_edge_bias(edge_bias)
+            .with_limits(SizeLimits {
+                max_nodes,
+                max_edges,
+                max_depth,
+            })
+            .with_split_policy(split_policy)
+            .with_node_activation(node_activation)
+            .with_weight_perturbation(weight_perturbation)
+            .with_bias_perturbation(bias_perturbation)
+            .with_input_scaling(prob_input_scaling, input_scaling_coeff)
+            .with_gates(prob_new_gate)
+            .with_module_duplication(prob_duplicate_module)
+    }
+
+    /// Mutation followed by the user-defined `operators`, those named in
+    /// `mutation.operators` at the configured probability. A configured
+    /// name without an operator is an error.
+    pub fn mutation_with(
+        &self,
+        mut operators: MutationPipeline,
+    ) -> Result<ExtendedMutation<GaussianMutation>, ConfigError> {
+        for (name, prob) in self.mutation.operators.iter() {
+            if !operators.set_probability(name, *prob) {
+                return Err(ConfigError::Invalid {
+                    key: format!("mutation.operators.{name}"),
+                    message: "names no registered operator".to_string(),
+                });
+            }
+        }
+        Ok(ExtendedMutation::new(self.mutation(), operators))
+    }
+
+    /// NEAT crossover and roulette selection, with the configured
+    /// speciation and mutation.
+    pub fn algorithm(&self) -> GeneticAlgortihm<DistanceSpeciation<Genome>, RoulleteSelection> {
+        GeneticAlgortihm::new(
+            self.speciation(),
+            RoulleteSelection::new(),
+            Box::new(NeatCrossover::default()),
+            Box::new(self.mutation()),
+        )
+    }
+
+    /// [`NeatConfig::algorithm`] with the user-defined `operators`, see
+    /// [`NeatConfig::mutation_with`].
+    pub fn algorithm_with(
+        &self,
+        operators: MutationPipeline,
+    ) -> Result<GeneticAlgortihm<DistanceSpeciation<Genome>, RoulleteSelection>, ConfigError> {
+        Ok(GeneticAlgortihm::new(
+            self.speciation(),
+            RoulleteSelection::new(),
+            Box::new(NeatCrossover::default()),
+            Box::new(self.mutation_with(operators)?),
+        ))
+    }
+
+    pub fn speciation(&self) -> DistanceSpeciation<Genome> {
+        let SpeciationConfig {
+            threshold,
+            excess,
+            disjoint,
+            weight,
+            assignment,
+        } = self.speciation;
+        DistanceSpeciation::new(
+            Box::new(Compatibility::new(excess, disjoint, weight)),
+            threshold,
+        )
+        .with_assignment(assignment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        individual::genome::activation::Activation,
+        mutation::operator::{AddGate, DeleteNode},
+    };
+
+    #[test]
+    fn test_from_toml() {
+        let config = NeatConfig::from_toml(
+            r#"
+            population_size = 20
+            activations = ["tanh", "sigmoid"]
+            softmax_groups = [[0, 4], [6, 8]]
+
+            [mutation]
+            replace_range = [-2.0, 2.0]
+            max_nodes = 40
+            max_depth = 3
+            split_policy = "recent"
+            node_activation = "sigmoid"
+            bias_sigma = 0.25
+            weight_perturbation = "laplace"
+
+            [mutation.edge]
+            prob_new_node = 0.03
+
+            [speciation]
+            threshold = 1.5
+            assignment = "nearest"
+
+            [backend.auto]
+            edges = 64
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.population_size, 20);
+        assert_eq!(config.mutation.edge.prob_new_node, 0.03);
+        assert_eq!(config.mutation.edge.prob_new_edge, 0.05);
+        assert_eq!(config.mutation.node, ProbabilityMatrixNode::default());
+        assert_eq!(config.speciation.threshold, 1.5);
+        assert_eq!(config.speciation.assignment, Assignment::Nearest);
+        let mutation = config.mutation();
+        assert_eq!(mutation.replace_range, (-2., 2.));
+        assert_eq!(mutation.limits.max_nodes, Some(40));
+        assert_eq!(mutation.limits.max_edges, None);
+        assert_eq!(mutation.limits.max_depth, Some(3));
+        assert_eq!(mutation.split_policy, SplitPolicy::Recent);
+        assert_eq!(mutation.node_activation, Some(Activation::Sigmoid));
+        assert_eq!(mutation.bias_coeff, 0.25);
+        assert_eq!(mutation.weight_perturbation, Perturbation::Laplace);
+        assert_eq!(mutation.bias_perturbation, Perturbation::Gaussian);
+        assert!(mutation.activations.contains(&Activation::Tanh));
+        assert!(!mutation.activations.contains(&Activation::Relu));
+        assert_eq!(config.softmax_groups.groups(), &[(0, 4), (6, 8)]);
+        assert_eq!(config.backend, Backend::Auto { edges: 64 });
+        let graph = NeatConfig::from_toml(r#"backend = "graph""#).unwrap();
+        assert_eq!(graph.backend, Backend::Graph);
+        assert_eq!(NeatConfig::from_toml(&config.to_toml()).unwrap(), config);
+        assert_eq!(NeatConfig::from_toml("").unwrap(), NeatConfig::default());
+    }
+
+    #[test]
+    fn test_mutation_settings() {
+        let config = NeatConfig::from_toml(
+            r#"
+            [mutation]
+            prob_input_scaling = 0.3
+            input_scaling_coeff = 0.05
+            prob_new_gate = 0.02
+            prob_duplicate_module = 0.01
+
+            [mutation.frozen]
+            output_activation = true
+            input_edges = true
+
+            [mutation.edge_bias.avoid_saturated]
+            max_frequency = 0.5
+            "#,
+        )
+        .unwrap();
+        let frozen = Frozen {
+            output_activation: true,
+            input_edges: true,
+            ..Default::default()
+        };
+        let mutation = config.mutation();
+        assert_eq!(mutation.frozen, frozen);
+        assert_eq!(
+            mutation.edge_bias,
+            EdgeBias::AvoidSaturated { max_frequency: 0.5 }
+        );
+        assert_eq!(mutation.prob_input_scaling, 0.3);
+        assert_eq!(mutation.input_scaling_coeff, 0.05);
+        assert_eq!(mutation.prob_new_gate, 0.02);
+        assert_eq!(mutation.prob_duplicate_module, 0.01);
+        let names = mutation
+            .pipeline()
+            .operators()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"AddGate") && names.contains(&"DuplicateModule"));
+        assert_eq!(NeatConfig::from_toml(&config.to_toml()).unwrap(), config);
+
+        // Missing settings take the defaults of the mutation
+        let defaults = NeatConfig::default().mutation();
+        let mutation = NeatConfig::from_toml("[mutation]\nweight_sigma = 0.5")
+            .unwrap()
+            .mutation();
+        assert_eq!(mutation.frozen, defaults.frozen);
+        assert_eq!(mutation.edge_bias, defaults.edge_bias);
+        assert_eq!(mutation.prob_input_scaling, defaults.prob_input_scaling);
+        assert_eq!(mutation.prob_new_gate, 0.);
+        let error = NeatConfig::from_toml("[mutation]\nprob_new_gate = 2.0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`mutation.prob_new_gate` must be a probability in [0, 1]"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| NeatConfig::from_toml(text).unwrap_err().to_string();
+        assert!(
+            error("[mutation.edge]\nprob_new_nod = 0.1").contains("unknown field `prob_new_nod`")
+        );
+        assert!(error("activations = [\"tan\"]").contains("unknown activation `tan`"));
+        assert!(
+            error("[mutation]\nnode_activation = \"tan\"").contains("unknown activation `tan`")
+        );
+        assert!(error("seed = \"seven\"").contains("seed"));
+        assert_eq!(
+            error("[mutation.edge]\nprob_new_node = 1.5"),
+            "`mutation.edge.prob_new_node` must be a probability in [0, 1]"
+        );
+        assert_eq!(
+            error("[speciation]\nthreshold = -1.0"),
+            "`speciation.threshold` must be non-negative"
+        );
+        assert_eq!(
+            error("softmax_groups = [[0, 4], [2, 6]]"),
+            "`softmax_groups` must be non-empty disjoint [start, end) ranges"
+        );
+        assert_eq!(
+            error("population_size = 0"),
+            "`population_size` must be positive"
+        );
+        assert_eq!(
+            error("[mutation.operators]\nAddModule = 2.0"),
+            "`mutation.operators.AddModule` must be a probability in [0, 1]"
+        );
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let config = NeatConfig::from_toml("[mutation.operators]\nDeleteNode = 0.25").unwrap();
+        assert_eq!(NeatConfig::from_toml(&config.to_toml()).unwrap(), config);
+        let operators = MutationPipeline::new()
+            .with_operator(0.5, DeleteNode)
+            .with_operator(0.5, AddGate);
+        let mutation = config.mutation_with(operators).unwrap();
+        assert_eq!(
+            mutation.operators().operators().collect::<Vec<_>>(),
+            vec![("DeleteNode", 0.25), ("AddGate", 0.5)]
+        );
+        assert_eq!(mutation.base().prob, config.mutation().prob);
+
+        let error = config.algorithm_with(MutationPipeline::new()).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "`mutation.operators.DeleteNode` names no registered operator"
+        );
+    }
+}
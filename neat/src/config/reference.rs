@@ -0,0 +1,318 @@
+//! Settings of the original NEAT paper, K. O. Stanley and R. Miikkulainen,
+//! "Evolving Neural Networks through Augmenting Topologies" (2002), and
+//! golden tests of the examples it works through, keeping the compatibility
+//! distance, crossover and speciation comparable to reference
+//! implementations.
+//!
+//! The paper inherits disjoint and excess genes from the fitter parent only,
+//! and from both when the parents are equally fit. [`NeatCrossover`]
+//! always inherits them from both parents, as in the paper's figure 4.
+//!
+//! [`NeatCrossover`]: crate::crossover::crossover::NeatCrossover
+
+use super::neat_config::{MutationConfig, NeatConfig, SpeciationConfig};
+use crate::{
+    individual::genome::activation::Activation,
+    mutation::mutation::{ProbabilityMatrixEdge, ProbabilityMatrixNode},
+    speciation::compatibility::Compatibility,
+};
+
+/// Coefficient `c1` of the excess genes in the compatibility distance.
+pub const EXCESS_COEFFICIENT: f32 = 1.;
+/// Coefficient `c2` of the disjoint genes.
+pub const DISJOINT_COEFFICIENT: f32 = 1.;
+/// Coefficient `c3` of the mean weight difference of matching genes.
+pub const WEIGHT_COEFFICIENT: f32 = 0.4;
+/// Distance `δt` within which genomes share a species.
+pub const COMPATIBILITY_THRESHOLD: f32 = 3.;
+/// Genomes with fewer genes are not normalised by their size, `N = 1`.
+pub use crate::speciation::compatibility::SMALL_GENOME;
+pub const POPULATION_SIZE: usize = 150;
+/// Probability of a weight mutation, a perturbation or else a new weight.
+pub const PROB_WEIGHT_MUTATION: f64 = 0.8;
+/// Probability of a new weight when weights mutate.
+pub const PROB_WEIGHT_REPLACE: f64 = 0.1;
+pub const PROB_ADD_NODE: f64 = 0.03;
+/// Probability of a new connection in small populations.
+pub const PROB_ADD_EDGE: f64 = 0.05;
+
+/// Compatibility distance with the coefficients of the paper.
+pub fn compatibility() -> Compatibility {
+    Compatibility::new(EXCESS_COEFFICIENT, DISJOINT_COEFFICIENT, WEIGHT_COEFFICIENT)
+}
+
+/// Run configuration of the paper's XOR experiment: its population,
+/// speciation and mutation rates, with sigmoid nodes whose functions and
+/// biases never mutate, the bias being an input.
+pub fn neat_config() -> NeatConfig {
+    let compatibility = compatibility();
+    NeatConfig {
+        population_size: POPULATION_SIZE,
+        mutation: MutationConfig {
+            node: ProbabilityMatrixNode {
+                prob_clamp: 0.,
+                prob_activation: 0.,
+                prob_aggregation: 0.,
+                prob_bias: 0.,
+            },
+            edge: ProbabilityMatrixEdge {
+                prob_weight_perturb: PROB_WEIGHT_MUTATION,
+                prob_weight_replace: PROB_WEIGHT_REPLACE,
+                prob_new_node: PROB_ADD_NODE,
+                prob_new_edge: PROB_ADD_EDGE,
+                ..Default::default()
+            },
+            node_activation: Some(Activation::Sigmoid),
+            ..Default::default()
+        },
+        speciation: SpeciationConfig {
+            threshold: COMPATIBILITY_THRESHOLD,
+            excess: compatibility.excess,
+            disjoint: compatibility.disjoint,
+            weight: compatibility.weight,
+            ..Default::default()
+        },
+        activations: [Activation::Sigmoid].into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        crossover::crossover::{CrossoverMethod, Item, NeatCrossover},
+        individual::{
+            genome::{
+                genome::{Genome, GenomeBuilder, InnovationId, OrderedGenomeList},
+                network::network::FFNetwork,
+                node_list::NodeId,
+            },
+            individual::Individual,
+        },
+        population::population::Population,
+        speciation::speciation::SpeciationMethod,
+    };
+
+    /// Genome of the paper's figures, nodes numbered from 1, the inputs
+    /// 1 to 3, the output 4 and hidden nodes from 5. Edges are
+    /// `(innovation, in, out, enabled)` and weigh `weight`.
+    fn figure(
+        hidden: usize,
+        edges: &[(InnovationId, NodeId, NodeId, bool)],
+        weight: f32,
+    ) -> Genome {
+        let builder = (0..hidden).fold(GenomeBuilder::new().input(3).output(1), |builder, i| {
+            builder.hidden(50 + i)
+        });
+        let mut genome = edges
+            .iter()
+            .fold(builder, |builder, (_, i, o, _)| {
+                builder.connect(i - 1, o - 1, weight)
+            })
+            .build()
+            .unwrap();
+        let mut list = genome.genome_list.edge_list.clone();
+        for (edge, (innov_number, _, _, enabled)) in list.iter_mut().zip(edges) {
+            edge.innov_number = *innov_number;
+            edge.enabled = *enabled;
+        }
+        genome.genome_list = OrderedGenomeList::new(list);
+        genome
+    }
+
+    /// Parents of figure 4.
+    fn parents(weight: f32) -> (Genome, Genome) {
+        let parent1 = figure(
+            1,
+            &[
+                (1, 1, 4, true),
+                (2, 2, 4, false),
+                (3, 3, 4, true),
+                (4, 2, 5, true),
+                (5, 5, 4, true),
+                (8, 1, 5, true),
+            ],
+            1.,
+        );
+        let parent2 = figure(
+            2,
+            &[
+                (1, 1, 4, true),
+                (2, 2, 4, false),
+                (3, 3, 4, true),
+                (4, 2, 5, true),
+                (5, 5, 4, false),
+                (6, 5, 6, true),
+                (7, 6, 4, true),
+                (9, 3, 5, true),
+                (10, 1, 6, true),
+            ],
+            weight,
+        );
+        (parent1, parent2)
+    }
+
+    #[test]
+    fn test_figure_4_gene_counts() {
+        assert_eq!(Compatibility::default(), compatibility());
+        let (parent1, parent2) = parents(1.);
+        // Disjoint 6, 7 and 8, excess 9 and 10, disabled genes match
+        let count = |c1, c2| Compatibility::new(c1, c2, 0.).distance(&parent1, &parent2);
+        assert_eq!(count(1., 0.), 2.);
+        assert_eq!(count(0., 1.), 3.);
+        assert_eq!(compatibility().distance(&parent2, &parent1), 5.);
+
+        // Matching genes 1 to 5 differ by 0.5 on average
+        let (parent1, parent2) = parents(0.5);
+        assert_eq!(compatibility().distance(&parent1, &parent2), 5.2);
+        assert!(compatibility().distance(&parent1, &parent2) > COMPATIBILITY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_normalisation() {
+        let chain = |edges: usize| {
+            let edges = (1..=edges as InnovationId)
+                .map(|i| (i, 1, 4, true))
+                .collect::<Vec<_>>();
+            figure(0, &edges, 1.)
+        };
+        // Below SMALL_GENOME genes N is 1, from it the larger genome size
+        let small = chain(SMALL_GENOME - 1);
+        assert_eq!(compatibility().distance(&chain(1), &small), 18.);
+        let large = chain(SMALL_GENOME + 5);
+        assert_eq!(compatibility().distance(&chain(1), &large), 24. / 25.);
+    }
+
+    #[test]
+    fn test_figure_4_offspring() {
+        let (parent1, parent2) = parents(0.5);
+        let item = |genome: &Genome| Item {
+            item: Arc::new(genome.clone()),
+            fitness: 1.,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let child =
+            NeatCrossover::default().crossover_method(&mut rng, &item(&parent1), &item(&parent2));
+        let genes = child
+            .genome_list
+            .iter()
+            .map(|edge| (edge.innov_number, edge.in_node + 1, edge.out_node + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            genes,
+            vec![
+                (1, 1, 4),
+                (2, 2, 4),
+                (3, 3, 4),
+                (4, 2, 5),
+                (5, 5, 4),
+                (6, 5, 6),
+                (7, 6, 4),
+                (8, 1, 5),
+                (9, 3, 5),
+                (10, 1, 6),
+            ]
+        );
+        // Both disabled in the first parent and the second
+        assert!(!child.genome_list.edge_list[1].enabled);
+        assert_eq!(child.node_list.hidden.len(), 2);
+        child.validate().unwrap();
+    }
+
+    #[test]
+    fn test_species_of_previous_representatives() {
+        // Genome `i` adds `i` genes to a shared one, so genomes `i` and `j`
+        // are `|i - j|` apart
+        let genome = |extra: usize| {
+            let edges = (1..=extra as InnovationId + 1)
+                .map(|i| (i, 1, 4, true))
+                .collect::<Vec<_>>();
+            figure(0, &edges, 1.)
+        };
+        let representatives = [genome(0), genome(10)];
+        let population = [genome(8), genome(3), genome(4), genome(5), genome(11)];
+        let speciation = neat_config().speciation();
+        let species = speciation.speciate_with(
+            &representatives.iter().collect::<Vec<_>>(),
+            population.iter(),
+        );
+        let sizes = species
+            .iter()
+            .map(|members| members.iter().map(|g| g.genome_list.edge_list.len() - 1))
+            .map(Vec::from_iter)
+            .collect::<Vec<_>>();
+        // Each joins the first compatible species.
+        // 4 founds a species, joined by 5, 5 from both representatives
+        assert_eq!(sizes, vec![vec![3], vec![8, 11], vec![4, 5]]);
+    }
+
+    const XOR: [([f32; 3], f32); 4] = [
+        ([0., 0., 1.], 0.),
+        ([0., 1., 1.], 1.),
+        ([1., 0., 1.], 1.),
+        ([1., 1., 1.], 0.),
+    ];
+
+    /// Squared `4 - error` over the four patterns, as in the paper.
+    fn xor(genome: &Genome) -> f32 {
+        let mut network = FFNetwork::from(genome);
+        let error = XOR
+            .iter()
+            .map(|(input, target)| {
+                network.reset();
+                (network.forward(input).unwrap()[0] - target).abs()
+            })
+            .sum::<f32>();
+        (4. - error).powi(2)
+    }
+
+    #[test]
+    fn test_xor_milestones() {
+        // Inputs, the last one the bias, fully connected to a sigmoid output
+        let minimal = (0..3)
+            .fold(GenomeBuilder::new().input(3).output(1), |builder, i| {
+                builder.connect(i, 3, 0.)
+            })
+            .activation(3, Activation::Sigmoid)
+            .build()
+            .unwrap();
+        let config = neat_config();
+        let mut population = Population::new(config.algorithm(), vec![minimal; POPULATION_SIZE]);
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        for _ in 0..15 {
+            population.evaluate(xor);
+            population.evolve(&mut rng);
+        }
+        let genomes = population
+            .members()
+            .iter()
+            .map(|m| m.to_genome())
+            .collect::<Vec<_>>();
+        // Topologies grew from the minimal one by splitting connections,
+        // which stay in the genome disabled
+        let split = genomes
+            .iter()
+            .find(|genome| !genome.node_list.hidden.is_empty())
+            .expect("Some genome grew a hidden node");
+        assert!(split.genome_list.iter().any(|edge| !edge.enabled));
+        // and innovated into several species
+        assert!(population.species().len() > 1);
+        // Historical markings: one innovation number per connection
+        let mut markings = std::collections::BTreeMap::new();
+        for edge in genomes.iter().flat_map(|genome| genome.genome_list.iter()) {
+            let endpoints = *markings
+                .entry(edge.innov_number)
+                .or_insert((edge.in_node, edge.out_node));
+            assert_eq!(endpoints, (edge.in_node, edge.out_node));
+        }
+        assert!(genomes
+            .iter()
+            .all(|genome| genome.genome_list.edge_list.len() >= 3));
+    }
+}
@@ -1,15 +1,18 @@
-use rand::RngCore;
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
 
 use crate::individual::genome::{
-    genome::{Genome, OrderedGenomeList},
+    genome::{Genome, InputScaling, OrderedGenomeList, WeightTable},
     node_list::NodeList,
 };
 
 use super::misc_crossover::CrossoverMisc;
 
-/// Helper struct to encapsulate the fitness and the genome.
+/// Helper struct to encapsulate the fitness and the genome. The genome is
+/// shared with the parent, crossover only reads it.
 pub struct Item {
-    pub item: Genome,
+    pub item: Arc<Genome>,
     pub fitness: f32,
 }
 
@@ -67,11 +70,15 @@ impl Crossover for NodeList {
     /// Cross over method for lists. The two lists of genomes will be merged fully.
     /// Hidden lists are by definition sorted.
     fn crossover(&self, rng: &mut dyn RngCore, fit: f32, other: &Self, other_fit: f32) -> Self {
-        Self::new(
-            self.input.clone(),
-            self.output.clone(),
-            merge(self.hidden.iter(), other.hidden.iter(), rng, fit, other_fit),
-        )
+        Self {
+            names: self.names.clone(),
+            input_defaults: self.input_defaults.clone(),
+            ..Self::new(
+                self.input.clone(),
+                self.output.clone(),
+                merge(self.hidden.iter(), other.hidden.iter(), rng, fit, other_fit),
+            )
+        }
     }
 }
 
@@ -82,8 +89,45 @@ impl Crossover for OrderedGenomeList {
     }
 }
 
+/// Union of the tied edges, the fitter parent deciding conflicting groups.
+/// Groups of both parents inherit the weight of a random parent.
+impl Crossover for WeightTable {
+    fn crossover(&self, rng: &mut dyn RngCore, fit: f32, other: &Self, other_fit: f32) -> Self {
+        if self.is_empty() && other.is_empty() {
+            return Self::default();
+        }
+        let (fitter, weaker) = if fit >= other_fit { (self, other) } else { (other, self) };
+        let mut groups = weaker.groups.clone();
+        groups.extend(fitter.groups.iter().map(|(innov, group)| (*innov, *group)));
+        let mut weights = weaker.weights.clone();
+        for (group, weight) in fitter.weights.iter() {
+            if !weights.contains_key(group) || rng.gen_bool(0.5) {
+                weights.insert(*group, *weight);
+            }
+        }
+        Self { groups, weights }
+    }
+}
+
+/// Union of the scaled inputs. Inputs both parents scale inherit the gene
+/// of a random parent.
+impl Crossover for InputScaling {
+    fn crossover(&self, rng: &mut dyn RngCore, _: f32, other: &Self, _: f32) -> Self {
+        if self.is_empty() && other.is_empty() {
+            return Self::default();
+        }
+        let mut genes = other.genes.clone();
+        for (id, gene) in self.genes.iter() {
+            if !genes.contains_key(id) || rng.gen_bool(0.5) {
+                genes.insert(*id, *gene);
+            }
+        }
+        Self { genes }
+    }
+}
+
 /// Trait to implement the crossover method
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct NeatCrossover {
     /// Crossover method for misc calculations (f32, bernoulli).
     pub crossover_misc: CrossoverMisc,
@@ -95,14 +139,6 @@ impl NeatCrossover {
     }
 }
 
-impl Default for NeatCrossover {
-    fn default() -> Self {
-        Self {
-            crossover_misc: CrossoverMisc::default(),
-        }
-    }
-}
-
 /// Crossover implementation for neat. Given two genomes
 /// crossover node list and genome list. Create node list from the result.
 impl CrossoverMethod for NeatCrossover {
@@ -126,10 +162,18 @@ impl CrossoverMethod for NeatCrossover {
         let new_genome_list = item_a
             .genome_list
             .crossover(rng, fit_a, &item_b.genome_list, fit_b);
-        Genome {
+        let mut child = Genome {
             node_list: new_list,
             genome_list: new_genome_list,
-        }
+            weight_table: item_a
+                .weight_table
+                .crossover(rng, fit_a, &item_b.weight_table, fit_b),
+            input_scaling: item_a
+                .input_scaling
+                .crossover(rng, fit_a, &item_b.input_scaling, fit_b),
+        };
+        child.sync_weights();
+        child
     }
 }
 
@@ -138,8 +182,9 @@ mod crossover_tests {
     use super::*;
     use itertools::Itertools;
     use proptest::{array::*, prelude::*};
+    use rand::RngCore;
 
-    #[derive(Debug, Clone, Copy, Hash)]
+    #[derive(Debug, Clone, Copy)]
     struct TestCrossover(pub i32, pub i32);
 
     impl PartialEq for TestCrossover {
@@ -150,9 +195,15 @@ mod crossover_tests {
 
     impl Eq for TestCrossover {}
 
+    impl std::hash::Hash for TestCrossover {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.hash(state)
+        }
+    }
+
     impl PartialOrd for TestCrossover {
         fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-            Some(self.0.cmp(&other.0))
+            Some(self.cmp(other))
         }
     }
 
@@ -180,6 +231,72 @@ mod crossover_tests {
         }
     }
 
+    #[test]
+    fn test_split_level_crossover() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        use crate::{
+            individual::genome::{genome::GenomeBuilder, network::network::FFNetwork},
+            mutation::{
+                innovation_number::InnovNumber,
+                mutation::SizeLimits,
+                operator::{AddNode, MutationOperator},
+            },
+        };
+
+        let parent = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(34)
+            .connect(0, 2, 1.)
+            .connect(0, 1, 1.)
+            .build()
+            .unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        // Two lineages resumed from the same checkpoint split independently:
+        // the depth bound reuses level 34, the unbounded split takes a midpoint
+        let split = |rng: &mut ChaCha8Rng, max_depth| {
+            let add_node = AddNode {
+                limits: SizeLimits {
+                    max_depth,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut child = parent.clone();
+            let mut innov_number = InnovNumber::resume(10, 0);
+            assert!(add_node.apply(rng, &mut child, &mut innov_number));
+            Arc::new(child)
+        };
+        let (bounded, unbounded) = (split(&mut rng, Some(1)), split(&mut rng, None));
+        let (fst, snd) = (&bounded.node_list.hidden[1], &unbounded.node_list.hidden[1]);
+        assert_eq!(fst.node_id, snd.node_id);
+        assert_ne!(fst.level, snd.level);
+
+        // On ties either level goes
+        for (fit_a, fit_b, level) in [(2., 1., Some(fst.level)), (1., 2., Some(snd.level)), (1., 1., None)] {
+            let child = NeatCrossover::default().crossover_method(
+                &mut rng,
+                &Item {
+                    item: bounded.clone(),
+                    fitness: fit_a,
+                },
+                &Item {
+                    item: unbounded.clone(),
+                    fitness: fit_b,
+                },
+            );
+            child.validate().unwrap();
+            let node = child.node_list.hidden.iter().find(|node| node.node_id == fst.node_id);
+            if let Some(level) = level {
+                assert_eq!(node.unwrap().level, level);
+            }
+            let mut network = FFNetwork::<f32>::from(&child);
+            assert!(network.forward(&[1.]).is_some());
+        }
+    }
+
     proptest! {
       #[test]
       fn test_merge_no_conflict(
@@ -237,4 +354,107 @@ mod crossover_tests {
           m.iter().zip_eq(expected.iter()).for_each(|(a,b)| assert_eq!(a.1,b.1));
       }
     }
+
+    mod structure {
+        use rand::{Rng, RngCore, SeedableRng};
+        use rand_chacha::ChaCha8Rng;
+
+        use super::*;
+        use crate::{
+            individual::{
+                genome::{genome::GenomeBuilder, network::network::FFNetwork},
+                individual::EvaluatedIndividual,
+            },
+            mutation::{
+                innovation_number::InnovNumber,
+                mutation::{GaussianMutation, MutationMethod},
+                operator::{AddNode, DeleteNode, MutationOperator, MutationPipeline},
+            },
+            selection::selection_trait::RoulleteSelection,
+            GeneticAlgortihm,
+        };
+
+        fn base() -> Genome {
+            GenomeBuilder::new()
+                .input(2)
+                .output(1)
+                .connect(0, 2, 1.)
+                .connect(1, 2, -1.)
+                .build()
+                .unwrap()
+        }
+
+        /// Two lineages of `base` mutated apart, nodes deleted included,
+        /// numbered by the same registry.
+        fn parents(rng: &mut ChaCha8Rng) -> [Arc<Genome>; 2] {
+            let pipeline = GaussianMutation::default()
+                .pipeline()
+                .with_operator(0.3, DeleteNode);
+            let mut innov_number = InnovNumber::from_population(&[base()]);
+            [(); 2].map(|_| {
+                let mut genome = base();
+                for _ in 0..rng.gen_range(0..8) {
+                    pipeline.mutate(rng, &mut genome, &mut innov_number);
+                }
+                Arc::new(genome)
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn test_child_structure(seed in any::<u64>(), fit_a in -5f32..5., fit_b in -5f32..5.) {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let [a, b] = parents(&mut rng);
+                prop_assert_eq!(a.validate(), Ok(()));
+                prop_assert_eq!(b.validate(), Ok(()));
+                let mut child = NeatCrossover::default().crossover_method(
+                    &mut rng,
+                    &Item { item: a, fitness: fit_a },
+                    &Item { item: b, fitness: fit_b },
+                );
+                prop_assert_eq!(child.validate(), Ok(()));
+                prop_assert_eq!(child.drop_dangling_edges(), 0);
+                prop_assert!(FFNetwork::<f32>::from(&child).forward(&[0.5, -0.5]).is_some());
+            }
+        }
+
+        /// Nodes of the first parent, edges of the second.
+        struct Lossy;
+
+        impl CrossoverMethod for Lossy {
+            fn crossover_method(&self, _: &mut dyn RngCore, a: &Item, b: &Item) -> Genome {
+                Genome {
+                    genome_list: b.item.genome_list.clone(),
+                    ..Genome::clone(&a.item)
+                }
+            }
+        }
+
+        #[test]
+        fn test_dangling_edges() {
+            let mut rng = ChaCha8Rng::seed_from_u64(3);
+            let mut innov_number = InnovNumber::from_population(&[base()]);
+            let mut grown = base();
+            assert!(AddNode::default().apply(&mut rng, &mut grown, &mut innov_number));
+            let members = [
+                EvaluatedIndividual::new(1, base()).with_fitness(1.),
+                EvaluatedIndividual::new(2, grown).with_fitness(1.),
+            ];
+            let algorithm = GeneticAlgortihm::new(
+                (),
+                RoulleteSelection::new().with_no_self_mating(true),
+                Box::new(Lossy),
+                Box::new(MutationPipeline::new()),
+            );
+            let members = members.iter().collect::<Vec<_>>();
+            let children = algorithm.reproduce(&mut rng, &members, 20, &mut innov_number);
+            for child in children.iter() {
+                assert_eq!(child.validate(), Ok(()));
+            }
+            // The edges through the hidden node of the second parent go
+            assert!(children
+                .iter()
+                .any(|child| child.node_list.hidden.is_empty() && child.genome_list.iter().count() == 2));
+        }
+    }
 }
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod crossover;
 pub mod misc_crossover;
 pub mod node_crossover;
@@ -1,12 +1,12 @@
 use itertools::Itertools;
-use rand::RngCore;
+use rand::{Rng, RngCore};
 
 use crate::individual::genome::{
     activation::Activation,
     aggregation::Aggregation,
     clamp::Clamp,
     genome::GenomeEdge,
-    node_list::{Config, Node},
+    node_list::{Config, Gate, Node},
 };
 
 use super::{crossover::Crossover, misc_crossover::CrossoverMisc};
@@ -17,32 +17,58 @@ impl Crossover for Node {
             self.node_id, other.node_id,
             "Should cross over the same node"
         );
-        assert_eq!(self.level, other.level, "Level should be the same");
+        // Independent splits can place the same node at different levels:
+        // the fitter parent decides, a random one on ties
+        let level = if self.level == other.level || fit > other_fit {
+            self.level
+        } else if fit < other_fit || rng.gen_bool(0.5) {
+            other.level
+        } else {
+            self.level
+        };
+        let config = Config {
+            aggregation: self.config.aggregation.crossover(
+                rng,
+                fit,
+                &other.config.aggregation,
+                other_fit,
+            ),
+            clamp: self
+                .config
+                .clamp
+                .crossover(rng, fit, &other.config.clamp, other_fit),
+            activation: self.config.activation.crossover(
+                rng,
+                fit,
+                &other.config.activation,
+                other_fit,
+            ),
+            bias: self.config.bias,
+            gate: self
+                .config
+                .gate
+                .crossover(rng, fit, &other.config.gate, other_fit),
+        };
         Self {
             node_id: self.node_id,
-            level: self.level,
-            config: Config {
-                aggregation: self.config.aggregation.crossover(
-                    rng,
-                    fit,
-                    &other.config.aggregation,
-                    other_fit,
-                ),
-                clamp: self
-                    .config
-                    .clamp
-                    .crossover(rng, fit, &other.config.clamp, other_fit),
-                activation: self.config.activation.crossover(
-                    rng,
-                    fit,
-                    &other.config.activation,
-                    other_fit,
-                ),
-            },
+            level,
+            config: config
+                .from_floats(
+                    self.config
+                        .to_floats()
+                        .into_iter()
+                        .zip_eq(other.config.to_floats())
+                        .map(|(a, b)| {
+                            CrossoverMisc::default().f32_crossover(rng, a, fit, b, other_fit)
+                        }),
+                )
+                .expect("Configs should match"),
         }
     }
 }
 
+// `from_floats` rebuilds a value of the same variant as `self`
+#[allow(clippy::wrong_self_convention)]
 pub trait FloatList {
     type SelfItem;
     type Item;
@@ -79,6 +105,46 @@ impl FloatList for Clamp {
     }
 }
 
+/// The evolvable floats of a config besides its clamp and activation.
+impl FloatList for Config {
+    type Item = f32;
+    type SelfItem = Self;
+
+    fn to_floats(&self) -> Vec<f32> {
+        vec![self.bias]
+    }
+
+    fn from_floats_inner(&self, mut chromes: impl Iterator<Item = f32>) -> Option<Self> {
+        Some(Self {
+            bias: chromes.next()?,
+            ..*self
+        })
+    }
+}
+
+impl FloatList for Gate {
+    type Item = f32;
+    type SelfItem = Self;
+
+    fn to_floats(&self) -> Vec<f32> {
+        vec![
+            self.input_weight,
+            self.input_bias,
+            self.forget_weight,
+            self.forget_bias,
+        ]
+    }
+
+    fn from_floats_inner(&self, mut chromes: impl Iterator<Item = f32>) -> Option<Self> {
+        Some(Self {
+            input_weight: chromes.next()?,
+            input_bias: chromes.next()?,
+            forget_weight: chromes.next()?,
+            forget_bias: chromes.next()?,
+        })
+    }
+}
+
 impl FloatList for Activation {
     type Item = f32;
     type SelfItem = Self;
@@ -87,6 +153,7 @@ impl FloatList for Activation {
         match self {
             Activation::Softplus(a) => vec![*a],
             Activation::Periodic(a) => vec![*a],
+            Activation::NoisyRelu(sigma) => vec![*sigma],
             _ => vec![],
         }
     }
@@ -95,11 +162,15 @@ impl FloatList for Activation {
         &self,
         mut chromes: impl Iterator<Item = Self::Item>,
     ) -> Option<Self::SelfItem> {
-        Some(match self {
-            Activation::Softplus(_) => Activation::Softplus(chromes.next()?),
-            Activation::Periodic(_) => Activation::Periodic(chromes.next()?.abs()),
-            r => *r,
-        })
+        Some(
+            match self {
+                Activation::Softplus(_) => Activation::Softplus(chromes.next()?),
+                Activation::Periodic(_) => Activation::Periodic(chromes.next()?.abs()),
+                Activation::NoisyRelu(_) => Activation::NoisyRelu(chromes.next()?.abs()),
+                r => *r,
+            }
+            .bounded(),
+        )
     }
 }
 
@@ -119,7 +190,7 @@ impl Crossover for Clamp {
         self.from_floats(
             self.to_floats()
                 .into_iter()
-                .zip_eq(other.to_floats().into_iter())
+                .zip_eq(other.to_floats())
                 .map(|(a, b)| match (a, b) {
                     (None, None) => None,
                     (Some(a), None) => CrossoverMisc::default().bernoulli_crossover(
@@ -145,12 +216,34 @@ impl Crossover for Clamp {
     }
 }
 
+/// Gates blend when both parents have one, otherwise a parent is picked as
+/// for a clamp limit.
+impl Crossover for Option<Gate> {
+    fn crossover(&self, rng: &mut dyn RngCore, fit: f32, other: &Self, other_fit: f32) -> Self {
+        match (self, other) {
+            (None, None) => None,
+            (Some(a), Some(b)) => a.from_floats(
+                a.to_floats()
+                    .into_iter()
+                    .zip_eq(b.to_floats())
+                    .map(|(a, b)| CrossoverMisc::default().f32_crossover(rng, a, fit, b, other_fit)),
+            ),
+            (a, b) => CrossoverMisc::default().bernoulli_crossover(rng, *a, fit, *b, other_fit),
+        }
+    }
+}
+
 impl Crossover for Activation {
     fn crossover(&self, rng: &mut dyn RngCore, fit: f32, other: &Self, other_fit: f32) -> Self {
+        // Parameters only blend within a function
+        if std::mem::discriminant(self) != std::mem::discriminant(other) {
+            return CrossoverMisc::default()
+                .bernoulli_crossover::<Activation>(rng, *self, fit, *other, other_fit);
+        }
         self.from_floats(
             self.to_floats()
                 .into_iter()
-                .zip_eq(other.to_floats().into_iter())
+                .zip_eq(other.to_floats())
                 .map(|(a, b)| CrossoverMisc::default().f32_crossover(rng, a, fit, b, other_fit)),
         )
         .expect("Weights should match")
@@ -190,57 +283,154 @@ mod tests {
 
     use super::*;
 
-    mod clamp_crossover {
-
-        use approx::{Relative, RelativeEq};
+    #[test]
+    fn test_activation_crossover() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let (sigmoid, softplus) = (Activation::Sigmoid, Activation::Softplus(2.));
+        let children = (0..50)
+            .map(|_| sigmoid.crossover(&mut rng, 1., &softplus, 1.))
+            .collect::<Vec<_>>();
+        assert!(children.iter().all(|c| *c == sigmoid || *c == softplus));
+        assert!(children.contains(&sigmoid) && children.contains(&softplus));
+        let child = softplus.crossover(&mut rng, 1., &Activation::Softplus(4.), 1.);
+        assert!(matches!(child, Activation::Softplus(beta) if (2. ..=4.).contains(&beta)));
+        // Parents out of bounds, e.g. from older runs, breed children within
+        let (fst, snd) = (Activation::Softplus(0.), Activation::Softplus(-0.01));
+        let child = fst.crossover(&mut rng, 1., &snd, 1.);
+        assert_eq!(child.bounded(), child);
+    }
 
+    mod clamp_crossover {
         use super::*;
 
-        fn compare_relative(l : f32, h: f32, prod : f32) -> (f32,f32) {
-            let diff_1 = (prod - l).abs();
-            let diff_2 = (prod - h).abs();
-            (diff_1, diff_2)
+        /// Whether `child` sits at least as close to the fitter parent,
+        /// or between both when their fitnesses are close.
+        fn favours_fitter(a: f32, fit_a: f32, b: f32, fit_b: f32, child: f32) -> bool {
+            let (dist_a, dist_b) = ((child - a).abs(), (child - b).abs());
+            if (fit_a - fit_b).abs() < 2. {
+                // Close fitnesses barely favour either parent
+                a.min(b) - 1e-3 <= child && child <= a.max(b) + 1e-3
+            } else if fit_a > fit_b {
+                dist_a <= dist_b
+            } else {
+                dist_b <= dist_a
+            }
         }
 
         proptest! {
             #[test]
             fn test_node_clamp(
-                lims_1 in any::<(f32,f32)>().prop_filter("First limits", |a| a.0 < a.1),
-                lims_2 in any::<(f32,f32)>().prop_filter("Second limits", |a| a.0 < a.1),
-                perf_1 in any::<f32>(), perf_2 in any::<f32>(),
+                lims_1 in (-1e3f32..1e3, -1e3f32..1e3).prop_filter("First limits", |a| a.0 < a.1),
+                lims_2 in (-1e3f32..1e3, -1e3f32..1e3).prop_filter("Second limits", |a| a.0 < a.1),
+                perf_1 in -10f32..10., perf_2 in -10f32..10.,
             ) {
                 let mut rng = ChaCha8Rng::seed_from_u64(32);
                 let clamp_1 = Clamp::new(Some(lims_1.0), Some(lims_1.1)).unwrap();
                 let clamp_2 = Clamp::new(Some(lims_2.0), Some(lims_2.1)).unwrap();
-                let mut count_min = 0;
-                let mut count_max = 0;
+                let (mut count_min, mut count_max) = (0, 0);
                 for _ in 0..1_000 {
                     let res = clamp_1.crossover(&mut rng, perf_1, &clamp_2, perf_2);
-                    let res_min = compare_relative(clamp_1.min_limit.unwrap(), clamp_2.min_limit.unwrap(), res.min_limit.unwrap());
-                    let res_max = compare_relative(clamp_1.max_limit.unwrap(), clamp_2.max_limit.unwrap(), res.max_limit.unwrap());
-                    count_min += {
-                        if Relative::default().eq(&perf_1,  &perf_2) { // if they are equal
-                            (lims_1.0.min(lims_2.0) <= res.min_limit.unwrap() && lims_1.0.max(lims_2.0) >= res.min_limit.unwrap()) as u8
-                        } else {
-                            ((perf_1 <= perf_2) as u8 ^ (res_min.0 > res_min.1) as u8) | (res_min.0 == res_min.1) as u8
-                        }
-                    } as usize;
-                    count_max += {
-                        if Relative::default().eq(&perf_1,  &perf_2) { // if they are equal
-                            (lims_1.1.min(lims_2.1) <= res.max_limit.unwrap() && lims_1.1.max(lims_2.1) >= res.max_limit.unwrap()) as u8
-                        } else {
-                            ((perf_1 <= perf_2) as u8 ^ (res_max.0 > res_max.1) as u8) | (res_max.0 == res_max.1) as u8
-                        }
-                    } as usize;
+                    let min_limit = res.min_limit.unwrap();
+                    let max_limit = res.max_limit.unwrap();
+                    count_min += favours_fitter(lims_1.0, perf_1, lims_2.0, perf_2, min_limit) as usize;
+                    count_max += favours_fitter(lims_1.1, perf_1, lims_2.1, perf_2, max_limit) as usize;
                 }
-                assert!(dbg!(count_min) as f64 / 1_000f64 > 0.5_f64);
-                assert!(dbg!(count_max) as f64 / 1_000f64 > 0.5_f64);
+                prop_assert!(count_min > 500, "count_min {}", count_min);
+                prop_assert!(count_max > 500, "count_max {}", count_max);
+            }
+        }
+    }
+
+    #[test]
+    fn test_level_crossover() {
+        use num::rational::Ratio;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let node = |level| Node::new(3, Ratio::from_integer(level), None);
+        let (low, high) = (node(20), node(60));
+        assert_eq!(low.crossover(&mut rng, 2., &high, 1.).level, low.level);
+        assert_eq!(low.crossover(&mut rng, 1., &high, 2.).level, high.level);
+        let levels = (0..200)
+            .map(|_| low.crossover(&mut rng, 1., &high, 1.).level)
+            .counts();
+        assert_eq!(levels.len(), 2);
+        assert!((70..130).contains(&levels[&low.level]));
+    }
+
+    #[test]
+    fn test_gate_crossover() {
+        use num::rational::Ratio;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let node = |gate| {
+            let config = Config {
+                gate,
+                ..Default::default()
+            };
+            Node::new(3, Ratio::from_integer(50), Some(config))
+        };
+        let (plain, gated) = (node(None), node(Some(Gate::default())));
+        assert_eq!(plain.crossover(&mut rng, 1., &plain, 1.).config.gate, None);
+        let gates = (0..200)
+            .map(|_| plain.crossover(&mut rng, 1., &gated, 1.).config.gate.is_some())
+            .filter(|gated| *gated)
+            .count();
+        assert!((70..130).contains(&gates));
+
+        // Both gated, every parameter lies between the parents'
+        let other = node(Some(Gate {
+            input_weight: 1.,
+            input_bias: -1.,
+            forget_weight: 2.,
+            forget_bias: 3.,
+        }));
+        for _ in 0..50 {
+            let gate = gated.crossover(&mut rng, 1., &other, 2.).config.gate.unwrap();
+            let (a, b) = (Gate::default().to_floats(), other.config.gate.unwrap().to_floats());
+            for ((x, a), b) in gate.to_floats().into_iter().zip(a).zip(b) {
+                assert!(a.min(b) <= x && x <= a.max(b));
             }
         }
+    }
+
+    mod bias_crossover {
+        use num::rational::Ratio;
+
+        use super::*;
+
+        fn node(bias: f32) -> Node {
+            Node::new(
+                3,
+                Ratio::from_integer(50),
+                Some(Config {
+                    bias,
+                    ..Default::default()
+                }),
+            )
+        }
 
-        // #[test]
-        // fn test_node_clamp() {
-            
-        // }
+        proptest! {
+            #[test]
+            fn test_node_bias(
+                bias_1 in -1e3f32..1e3, bias_2 in -1e3f32..1e3,
+                perf_1 in -10f32..10., perf_2 in -10f32..10.,
+            ) {
+                let mut rng = ChaCha8Rng::seed_from_u64(32);
+                let (node_1, node_2) = (node(bias_1), node(bias_2));
+                let mut count = 0;
+                for _ in 0..1_000 {
+                    let res = node_1.crossover(&mut rng, perf_1, &node_2, perf_2).config.bias;
+                    let (diff_1, diff_2) = ((res - bias_1).abs(), (res - bias_2).abs());
+                    count += if (perf_1 - perf_2).abs() < 1. {
+                        true
+                    } else if perf_1 > perf_2 {
+                        diff_1 <= diff_2
+                    } else {
+                        diff_1 >= diff_2
+                    } as usize;
+                }
+                prop_assert!(count as f64 / 1_000f64 >= 0.49, "count {}", count);
+            }
+        }
     }
 }
@@ -0,0 +1,186 @@
+use itertools::Itertools;
+use rand::{Rng, RngCore};
+
+/// Conversion of network outputs into actions of an environment.
+pub trait ActionDecoder {
+    type Action;
+
+    /// Number of network outputs the decoder reads.
+    fn output_len(&self) -> usize;
+
+    fn decode(&self, rng: &mut dyn RngCore, outputs: &[f32]) -> Self::Action;
+}
+
+/// Index of the largest output, NaN outputs being the smallest. Ties go to
+/// the first index.
+pub fn argmax(outputs: &[f32]) -> usize {
+    outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| !value.is_nan())
+        .fold(None, |best, (index, &value)| match best {
+            Some((_, max)) if value <= max => best,
+            _ => Some((index, value)),
+        })
+        .map_or(0, |(index, _)| index)
+}
+
+fn epsilon_greedy(rng: &mut dyn RngCore, epsilon: f64, outputs: &[f32]) -> usize {
+    if rng.gen_bool(epsilon) {
+        rng.gen_range(0..outputs.len())
+    } else {
+        argmax(outputs)
+    }
+}
+
+/// One of `actions` choices, the one with the largest output, or a uniform
+/// one with probability `epsilon`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Discrete {
+    pub actions: usize,
+    pub epsilon: f64,
+}
+
+impl Discrete {
+    pub fn greedy(actions: usize) -> Self {
+        Self {
+            actions,
+            epsilon: 0.,
+        }
+    }
+}
+
+impl ActionDecoder for Discrete {
+    type Action = usize;
+
+    fn output_len(&self) -> usize {
+        self.actions
+    }
+
+    fn decode(&self, rng: &mut dyn RngCore, outputs: &[f32]) -> usize {
+        assert_eq!(outputs.len(), self.actions, "One output per action");
+        epsilon_greedy(rng, self.epsilon, outputs)
+    }
+}
+
+/// One value per output, squashed with tanh and scaled to its bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Continuous {
+    pub bounds: Vec<(f32, f32)>,
+}
+
+impl Continuous {
+    pub fn new(bounds: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        Self {
+            bounds: bounds.into_iter().collect(),
+        }
+    }
+
+    /// `dimension` values in `[-1, 1]`.
+    pub fn symmetric(dimension: usize) -> Self {
+        Self::new(vec![(-1., 1.); dimension])
+    }
+}
+
+impl ActionDecoder for Continuous {
+    type Action = Vec<f32>;
+
+    fn output_len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    fn decode(&self, _: &mut dyn RngCore, outputs: &[f32]) -> Vec<f32> {
+        assert_eq!(outputs.len(), self.bounds.len(), "One output per dimension");
+        outputs
+            .iter()
+            .zip_eq(self.bounds.iter())
+            .map(|(x, (low, high))| low + (x.tanh() + 1.) / 2. * (high - low))
+            .collect_vec()
+    }
+}
+
+/// Several independent discrete choices, each reading the next `sizes[i]`
+/// outputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiDiscrete {
+    pub sizes: Vec<usize>,
+    pub epsilon: f64,
+}
+
+impl MultiDiscrete {
+    pub fn greedy(sizes: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            sizes: sizes.into_iter().collect(),
+            epsilon: 0.,
+        }
+    }
+}
+
+impl ActionDecoder for MultiDiscrete {
+    type Action = Vec<usize>;
+
+    fn output_len(&self) -> usize {
+        self.sizes.iter().sum()
+    }
+
+    fn decode(&self, rng: &mut dyn RngCore, outputs: &[f32]) -> Vec<usize> {
+        assert_eq!(outputs.len(), self.output_len(), "One output per choice");
+        let mut rest = outputs;
+        self.sizes
+            .iter()
+            .map(|size| {
+                let (head, tail) = rest.split_at(*size);
+                rest = tail;
+                epsilon_greedy(rng, self.epsilon, head)
+            })
+            .collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_argmax() {
+        assert_eq!(argmax(&[0.1, 0.5, 0.5, -1.]), 1);
+        assert_eq!(argmax(&[f32::NAN, -3., f32::NAN]), 1);
+        assert_eq!(argmax(&[f32::NAN]), 0);
+    }
+
+    #[test]
+    fn test_discrete() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let greedy = Discrete::greedy(3);
+        assert_eq!(greedy.decode(&mut rng, &[0., 2., 1.]), 1);
+        let random = Discrete {
+            actions: 3,
+            epsilon: 1.,
+        };
+        let actions = (0..100)
+            .map(|_| random.decode(&mut rng, &[0., 2., 1.]))
+            .counts();
+        assert_eq!(actions.len(), 3);
+    }
+
+    #[test]
+    fn test_continuous() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let decoder = Continuous::new([(0., 10.), (-2., 2.)]);
+        let action = decoder.decode(&mut rng, &[0., 100.]);
+        assert_eq!(action, vec![5., 2.]);
+        let action = decoder.decode(&mut rng, &[-100., f32::NEG_INFINITY]);
+        assert_eq!(action, vec![0., -2.]);
+    }
+
+    #[test]
+    fn test_multi_discrete() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let decoder = MultiDiscrete::greedy([2, 3]);
+        assert_eq!(decoder.output_len(), 5);
+        assert_eq!(decoder.decode(&mut rng, &[1., 0., 0., 0., 3.]), vec![0, 2]);
+    }
+}
@@ -0,0 +1,113 @@
+use rand::{Rng, RngCore};
+
+use super::episode::{Environment, Step};
+
+/// Classic cart-pole balancing task (Barto, Sutton and Anderson 1983), with
+/// the constants of Gym's `CartPole-v1`. Observations are the cart position
+/// and velocity and the pole angle and angular velocity. Action 0 pushes the
+/// cart left, 1 right. Every step the pole stays up is rewarded with 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartPole {
+    pub gravity: f32,
+    pub cart_mass: f32,
+    pub pole_mass: f32,
+    /// Half the pole length.
+    pub pole_length: f32,
+    pub force: f32,
+    /// Seconds between steps.
+    pub tau: f32,
+    /// The episode ends once the pole leans further, in radians.
+    pub angle_limit: f32,
+    /// The episode ends once the cart is further from the centre.
+    pub position_limit: f32,
+    state: [f32; 4],
+}
+
+impl Default for CartPole {
+    fn default() -> Self {
+        Self {
+            gravity: 9.8,
+            cart_mass: 1.,
+            pole_mass: 0.1,
+            pole_length: 0.5,
+            force: 10.,
+            tau: 0.02,
+            angle_limit: 12f32.to_radians(),
+            position_limit: 2.4,
+            state: [0.; 4],
+        }
+    }
+}
+
+impl CartPole {
+    pub fn state(&self) -> [f32; 4] {
+        self.state
+    }
+}
+
+impl Environment for CartPole {
+    type Action = usize;
+
+    /// Every state variable starts uniformly in ±0.05.
+    fn reset(&mut self, rng: &mut dyn RngCore) -> Vec<f32> {
+        self.state = [(); 4].map(|_| rng.gen_range(-0.05..0.05));
+        self.state.to_vec()
+    }
+
+    fn step(&mut self, _: &mut dyn RngCore, action: &usize) -> Step {
+        let [x, x_dot, theta, theta_dot] = self.state;
+        let force = if *action == 1 { self.force } else { -self.force };
+        let (sin, cos) = theta.sin_cos();
+        let total_mass = self.cart_mass + self.pole_mass;
+        let pole_moment = self.pole_mass * self.pole_length;
+        let temp = (force + pole_moment * theta_dot * theta_dot * sin) / total_mass;
+        let theta_acc = (self.gravity * sin - cos * temp)
+            / (self.pole_length * (4. / 3. - self.pole_mass * cos * cos / total_mass));
+        let x_acc = temp - pole_moment * theta_acc * cos / total_mass;
+        self.state = [
+            x + self.tau * x_dot,
+            x_dot + self.tau * x_acc,
+            theta + self.tau * theta_dot,
+            theta_dot + self.tau * theta_acc,
+        ];
+        let done =
+            self.state[0].abs() > self.position_limit || self.state[2].abs() > self.angle_limit;
+        Step {
+            observation: self.state.to_vec(),
+            reward: 1.,
+            done,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    /// Steps until the pole falls, at most 500.
+    fn balance(policy: impl Fn(&[f32]) -> usize) -> usize {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut cart_pole = CartPole::default();
+        let mut observation = cart_pole.reset(&mut rng);
+        assert!(observation.iter().all(|x| x.abs() <= 0.05));
+        for steps in 1..=500 {
+            let step = cart_pole.step(&mut rng, &policy(&observation));
+            if step.done {
+                return steps;
+            }
+            observation = step.observation;
+        }
+        500
+    }
+
+    #[test]
+    fn test_cart_pole() {
+        // Pushing one way topples the pole within a second
+        assert!(balance(|_| 1) < 50);
+        // Pushing towards the lean, damped by the angular velocity, balances it
+        assert_eq!(balance(|o| usize::from(o[2] + 0.5 * o[3] > 0.)), 500);
+    }
+}
@@ -0,0 +1,94 @@
+use rand::RngCore;
+
+use super::{action::ActionDecoder, rollout::rollout};
+use crate::individual::genome::network::network::FFNetwork;
+
+/// Outcome of an environment step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    pub reward: f32,
+    pub done: bool,
+}
+
+/// Episodic reinforcement learning task.
+pub trait Environment {
+    type Action;
+
+    /// Start a new episode, returning the first observation.
+    fn reset(&mut self, rng: &mut dyn RngCore) -> Vec<f32>;
+
+    fn step(&mut self, rng: &mut dyn RngCore, action: &Self::Action) -> Step;
+}
+
+/// Run an episode of at most `max_steps` steps controlled by `network`,
+/// returning the total reward. See [`rollout`] for the episode statistics.
+pub fn run_episode<E, D>(
+    rng: &mut dyn RngCore,
+    environment: &mut E,
+    network: &mut FFNetwork,
+    decoder: &D,
+    max_steps: usize,
+) -> f32
+where
+    E: Environment,
+    D: ActionDecoder<Action = E::Action>,
+{
+    rollout(rng, environment, network, decoder, max_steps).reward
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        environment::action::Discrete,
+        individual::genome::{aggregation::Aggregation, genome::GenomeBuilder},
+    };
+
+    /// Rewards moving towards the sign of the observation, for 5 steps.
+    struct Corridor {
+        position: i32,
+    }
+
+    impl Environment for Corridor {
+        type Action = usize;
+
+        fn reset(&mut self, _: &mut dyn RngCore) -> Vec<f32> {
+            self.position = 0;
+            vec![1.]
+        }
+
+        fn step(&mut self, _: &mut dyn RngCore, action: &usize) -> Step {
+            self.position += if *action == 1 { 1 } else { -1 };
+            Step {
+                observation: vec![1.],
+                reward: self.position as f32,
+                done: self.position.abs() == 5,
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_episode() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(2)
+            .aggregation(1, Aggregation::Sum)
+            .aggregation(2, Aggregation::Sum)
+            .connect(0, 1, -1.)
+            .connect(0, 2, 1.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::from(&genome);
+        let mut corridor = Corridor { position: 0 };
+        let decoder = Discrete::greedy(2);
+        let reward = run_episode(&mut rng, &mut corridor, &mut network, &decoder, 100);
+        assert_eq!(reward, 15.);
+        let reward = run_episode(&mut rng, &mut corridor, &mut network, &decoder, 2);
+        assert_eq!(reward, 3.);
+    }
+}
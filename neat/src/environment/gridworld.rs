@@ -0,0 +1,304 @@
+use rand::RngCore;
+
+use super::episode::{Environment, Step};
+
+/// Content of a [`GridWorld`] cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Wall,
+    Food,
+    Poison,
+}
+
+impl Cell {
+    /// Wall, food and poison indicators.
+    fn channels(self) -> [f32; 3] {
+        match self {
+            Cell::Empty => [0., 0., 0.],
+            Cell::Wall => [1., 0., 0.],
+            Cell::Food => [0., 1., 0.],
+            Cell::Poison => [0., 0., 1.],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The layout has no rows.
+    Empty,
+    /// A row whose length differs from the first one.
+    Ragged(usize),
+    /// An unknown character, with its row and column.
+    UnknownCell(char, usize, usize),
+    /// The number of agents, which must be 1.
+    Agents(usize),
+}
+
+/// Small deterministic grid world. The agent moves up, right, down or left
+/// (actions 0 to 3), eating the food or poison it steps on; walls and the
+/// grid border block it. The episode ends once no food is left.
+///
+/// With full observation the agent sees the wall, food, poison and agent
+/// indicators of every cell, row by row. With partial observation it only
+/// sees the wall, food and poison indicators of its four neighbours, in
+/// action order, so remembering where it has been takes a recurrent network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridWorld {
+    pub food_reward: f32,
+    pub poison_reward: f32,
+    /// Reward of every step, usually a small cost.
+    pub step_reward: f32,
+    pub partial: bool,
+    width: usize,
+    layout: Vec<Cell>,
+    start: usize,
+    cells: Vec<Cell>,
+    position: usize,
+}
+
+impl GridWorld {
+    /// Parse a layout with one line per row: `#` is a wall, `.` empty, `F`
+    /// food, `P` poison and `A` the agent's start on an empty cell.
+    /// Surrounding whitespace of every line is ignored.
+    pub fn parse(layout: &str) -> Result<Self, LayoutError> {
+        let rows = layout
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+        let width = rows.first().ok_or(LayoutError::Empty)?.chars().count();
+        let mut cells = Vec::with_capacity(width * rows.len());
+        let mut starts = Vec::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(LayoutError::Ragged(row_index));
+            }
+            for (column, char) in row.chars().enumerate() {
+                cells.push(match char {
+                    '#' => Cell::Wall,
+                    '.' => Cell::Empty,
+                    'F' => Cell::Food,
+                    'P' => Cell::Poison,
+                    'A' => {
+                        starts.push(cells.len());
+                        Cell::Empty
+                    }
+                    _ => return Err(LayoutError::UnknownCell(char, row_index, column)),
+                });
+            }
+        }
+        if starts.len() != 1 {
+            return Err(LayoutError::Agents(starts.len()));
+        }
+        Ok(Self {
+            food_reward: 1.,
+            poison_reward: -1.,
+            step_reward: 0.,
+            partial: false,
+            width,
+            layout: cells.clone(),
+            start: starts[0],
+            cells,
+            position: starts[0],
+        })
+    }
+
+    pub fn with_rewards(self, food_reward: f32, poison_reward: f32, step_reward: f32) -> Self {
+        Self {
+            food_reward,
+            poison_reward,
+            step_reward,
+            ..self
+        }
+    }
+
+    pub fn with_partial(self, partial: bool) -> Self {
+        Self { partial, ..self }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.layout.len() / self.width
+    }
+
+    /// Row and column of the agent.
+    pub fn position(&self) -> (usize, usize) {
+        (self.position / self.width, self.position % self.width)
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Cell {
+        self.cells[row * self.width + column]
+    }
+
+    /// Number of network inputs an observation fills.
+    pub fn observation_len(&self) -> usize {
+        if self.partial {
+            4 * 3
+        } else {
+            4 * self.cells.len()
+        }
+    }
+
+    /// Index of the cell in direction `action`, if inside the grid.
+    fn neighbour(&self, action: usize) -> Option<usize> {
+        let (row, column) = self.position();
+        let (row, column) = match action {
+            0 => (row.checked_sub(1)?, column),
+            1 => (row, column + 1),
+            2 => (row + 1, column),
+            3 => (row, column.checked_sub(1)?),
+            _ => return None,
+        };
+        (row < self.height() && column < self.width).then_some(row * self.width + column)
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        if self.partial {
+            (0..4)
+                .flat_map(|action| {
+                    self.neighbour(action)
+                        .map_or(Cell::Wall, |index| self.cells[index])
+                        .channels()
+                })
+                .collect()
+        } else {
+            self.cells
+                .iter()
+                .enumerate()
+                .flat_map(|(index, cell)| {
+                    let [wall, food, poison] = cell.channels();
+                    [wall, food, poison, (index == self.position) as u8 as f32]
+                })
+                .collect()
+        }
+    }
+}
+
+impl Environment for GridWorld {
+    type Action = usize;
+
+    fn reset(&mut self, _: &mut dyn RngCore) -> Vec<f32> {
+        self.cells.clone_from(&self.layout);
+        self.position = self.start;
+        self.observation()
+    }
+
+    /// Unknown actions and moves into walls leave the agent in place.
+    fn step(&mut self, _: &mut dyn RngCore, action: &usize) -> Step {
+        let mut reward = self.step_reward;
+        if let Some(index) = self
+            .neighbour(*action)
+            .filter(|&index| self.cells[index] != Cell::Wall)
+        {
+            self.position = index;
+            match self.cells[index] {
+                Cell::Food => reward += self.food_reward,
+                Cell::Poison => reward += self.poison_reward,
+                _ => {}
+            }
+            self.cells[index] = Cell::Empty;
+        }
+        Step {
+            observation: self.observation(),
+            reward,
+            done: !self.cells.contains(&Cell::Food),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        environment::{action::Discrete, episode::run_episode},
+        individual::genome::{
+            activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+            network::network::FFNetwork,
+        },
+    };
+
+    const LAYOUT: &str = "
+        #.F
+        PA.
+        ..F
+    ";
+
+    #[test]
+    fn test_parse() {
+        let world = GridWorld::parse(LAYOUT).unwrap();
+        assert_eq!((world.width(), world.height()), (3, 3));
+        assert_eq!(world.position(), (1, 1));
+        assert_eq!(world.cell(0, 0), Cell::Wall);
+        assert_eq!(world.cell(1, 0), Cell::Poison);
+        assert_eq!(world.cell(1, 1), Cell::Empty);
+        assert_eq!(world.observation_len(), 36);
+        assert_eq!(world.with_partial(true).observation_len(), 12);
+        assert_eq!(GridWorld::parse(" \n "), Err(LayoutError::Empty));
+        assert_eq!(GridWorld::parse("A.\n..."), Err(LayoutError::Ragged(1)));
+        assert_eq!(
+            GridWorld::parse("A.\n.x"),
+            Err(LayoutError::UnknownCell('x', 1, 1))
+        );
+        assert_eq!(GridWorld::parse("AA"), Err(LayoutError::Agents(2)));
+        assert_eq!(GridWorld::parse(".F"), Err(LayoutError::Agents(0)));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut world = GridWorld::parse(LAYOUT)
+            .unwrap()
+            .with_rewards(2., -3., -0.5)
+            .with_partial(true);
+        let observation = world.reset(&mut rng);
+        assert_eq!(
+            observation,
+            vec![0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 1.]
+        );
+        let step = world.step(&mut rng, &3);
+        assert_eq!(step.reward, -3.5);
+        assert_eq!(world.position(), (1, 0));
+        // Border and wall block the agent.
+        assert_eq!(world.step(&mut rng, &3).reward, -0.5);
+        assert_eq!(world.step(&mut rng, &0).reward, -0.5);
+        assert_eq!(world.position(), (1, 0));
+        world.step(&mut rng, &1);
+        world.step(&mut rng, &1);
+        let step = world.step(&mut rng, &0);
+        assert_eq!((step.reward, step.done), (1.5, false));
+        world.step(&mut rng, &2);
+        let step = world.step(&mut rng, &2);
+        assert_eq!((step.reward, step.done), (1.5, true));
+        assert_eq!(world.cell(2, 2), Cell::Empty);
+        world.reset(&mut rng);
+        assert_eq!(world.cell(2, 2), Cell::Food);
+        assert_eq!(world.position(), (1, 1));
+    }
+
+    #[test]
+    fn test_run_episode() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        // Walk towards food seen in a neighbouring cell.
+        let mut builder = GenomeBuilder::new().input(12).output(4);
+        for action in 0..4 {
+            builder = builder
+                .aggregation(12 + action, Aggregation::Sum)
+                .activation(12 + action, Activation::Identity)
+                .connect(3 * action + 1, 12 + action, 1.);
+        }
+        let genome = builder.build().unwrap();
+        let mut network = FFNetwork::from(&genome);
+        let mut world = GridWorld::parse("A.\nFF").unwrap().with_partial(true);
+        let decoder = Discrete::greedy(4);
+        let reward = run_episode(&mut rng, &mut world, &mut network, &decoder, 10);
+        assert_eq!(reward, 2.);
+        assert_eq!(world.position(), (1, 1));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod action;
+pub mod cartpole;
+pub mod episode;
+pub mod gridworld;
+pub mod rollout;
+pub mod observation;
@@ -0,0 +1,193 @@
+/// Flattening of an observation into network inputs, so that environments
+/// and fitness functions can pass domain structs instead of hand-built
+/// vectors, see `FFNetwork::forward_encoded`. Structs and fieldless enums
+/// get an implementation with [`encode_fields!`](crate::encode_fields) and
+/// [`encode_one_hot!`](crate::encode_one_hot).
+pub trait Encode {
+    /// Append the inputs of `self` to `out`.
+    fn encode(&self, out: &mut Vec<f32>);
+
+    fn to_inputs(&self) -> Vec<f32> {
+        let mut out = vec![];
+        self.encode(&mut out);
+        out
+    }
+}
+
+/// Append `len` inputs, all 0 but the one at `index`.
+pub fn one_hot(index: usize, len: usize, out: &mut Vec<f32>) {
+    assert!(index < len, "One-hot index {index} out of {len}");
+    out.extend((0..len).map(|i| if i == index { 1. } else { 0. }));
+}
+
+macro_rules! encode_as_f32 {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode(&self, out: &mut Vec<f32>) {
+                    out.push(*self as f32);
+                }
+            }
+        )*
+    };
+}
+
+encode_as_f32!(f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// 1 if true, 0 otherwise.
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<f32>) {
+        out.push(if *self { 1. } else { 0. });
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, out: &mut Vec<f32>) {
+        for x in self {
+            x.encode(out);
+        }
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, out: &mut Vec<f32>) {
+        self.as_slice().encode(out);
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<f32>) {
+        self.as_slice().encode(out);
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, out: &mut Vec<f32>) {
+        (**self).encode(out);
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+    fn encode(&self, out: &mut Vec<f32>) {
+        (**self).encode(out);
+    }
+}
+
+macro_rules! encode_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Encode),+> Encode for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode(&self, out: &mut Vec<f32>) {
+                let ($($name,)+) = self;
+                $($name.encode(out);)+
+            }
+        }
+    };
+}
+
+encode_tuple!(A);
+encode_tuple!(A, B);
+encode_tuple!(A, B, C);
+encode_tuple!(A, B, C, D);
+encode_tuple!(A, B, C, D, E);
+encode_tuple!(A, B, C, D, E, G);
+
+/// Implement [`Encode`](crate::environment::observation::Encode) for a
+/// struct by encoding the listed fields in order.
+#[macro_export]
+macro_rules! encode_fields {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::environment::observation::Encode for $ty {
+            fn encode(&self, out: &mut Vec<f32>) {
+                $($crate::environment::observation::Encode::encode(&self.$field, out);)*
+            }
+        }
+    };
+}
+
+/// Implement [`Encode`](crate::environment::observation::Encode) for a
+/// fieldless enum as a one-hot vector over the listed variants, in order.
+#[macro_export]
+macro_rules! encode_one_hot {
+    ($ty:ident { $($variant:ident),+ $(,)? }) => {
+        impl $crate::environment::observation::Encode for $ty {
+            fn encode(&self, out: &mut Vec<f32>) {
+                let variants = [$($ty::$variant),+];
+                let this = ::std::mem::discriminant(self);
+                let index = variants
+                    .iter()
+                    .position(|variant| ::std::mem::discriminant(variant) == this)
+                    .expect("Every variant is listed");
+                $crate::environment::observation::one_hot(index, variants.len(), out);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{genome::GenomeBuilder, network::network::FFNetwork};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Heading {
+        North,
+        East,
+        South,
+        West,
+    }
+
+    encode_one_hot!(Heading {
+        North,
+        East,
+        South,
+        West
+    });
+
+    struct Agent {
+        position: (f32, f32),
+        heading: Heading,
+        alive: bool,
+        sensors: [u8; 2],
+    }
+
+    encode_fields!(Agent {
+        position,
+        heading,
+        alive,
+        sensors
+    });
+
+    #[test]
+    fn test_encode() {
+        let agent = Agent {
+            position: (0.5, -1.),
+            heading: Heading::South,
+            alive: true,
+            sensors: [3, 0],
+        };
+        assert_eq!(
+            agent.to_inputs(),
+            vec![0.5, -1., 0., 0., 1., 0., 1., 3., 0.]
+        );
+        assert_eq!(Heading::West.to_inputs(), vec![0., 0., 0., 1.]);
+        assert_eq!(
+            vec![(1u8, false), (2, true)].to_inputs(),
+            vec![1., 0., 2., 1.]
+        );
+
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 1.)
+            .connect(1, 2, 2.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert_eq!(
+            network.forward_encoded(&(0.25f64, true)),
+            network.forward(&[0.25, 1.])
+        );
+        assert_eq!(network.forward_encoded(&Heading::North), None);
+    }
+}
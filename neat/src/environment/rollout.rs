@@ -0,0 +1,395 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+use super::{
+    action::{argmax, ActionDecoder},
+    episode::{Environment, Step},
+};
+use crate::individual::genome::network::network::FFNetwork;
+
+/// Why an episode ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// The environment ended the episode.
+    Done,
+    /// The episode ran out of steps.
+    MaxSteps,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EpisodeStats {
+    pub reward: f32,
+    pub steps: usize,
+    pub termination: Termination,
+}
+
+/// Statistics of the episodes of a [`RolloutRunner::run`], in order.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct RolloutStats {
+    pub episodes: Vec<EpisodeStats>,
+    /// Behaviour descriptor of the episodes, `None` unless the runner
+    /// records one, see [`RolloutRunner::with_descriptor`].
+    pub behavior: Option<Vec<f32>>,
+}
+
+/// Part of the behaviour descriptor of a [`RolloutRunner`], e.g. for
+/// novelty search. Parts are recorded for every episode in turn, so
+/// descriptors of the same runner have the same length whatever the number
+/// of steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Descriptor {
+    /// Last observation of the episode, e.g. the final position.
+    FinalObservation,
+    /// `points` observations evenly spaced from the first to the last, some
+    /// repeated in episodes of fewer steps.
+    Trajectory { points: usize },
+    /// Share of the steps every output was the largest, the action of a
+    /// greedy discrete decoder.
+    ActionHistogram,
+}
+
+/// Observations of an episode, the first included, and the largest output
+/// of every step.
+#[derive(Debug, Clone, Default)]
+struct Trace {
+    observations: Vec<Vec<f32>>,
+    greedy: Vec<usize>,
+}
+
+impl Descriptor {
+    fn record(&self, trace: &Trace, outputs: usize, descriptor: &mut Vec<f32>) {
+        let observations = &trace.observations;
+        match *self {
+            Descriptor::FinalObservation => {
+                descriptor.extend_from_slice(observations.last().expect("Episodes start observed"))
+            }
+            Descriptor::Trajectory { points } => {
+                let last = observations.len() - 1;
+                for point in 0..points {
+                    let index = if points > 1 {
+                        point * last / (points - 1)
+                    } else {
+                        last
+                    };
+                    descriptor.extend_from_slice(&observations[index]);
+                }
+            }
+            Descriptor::ActionHistogram => {
+                let mut counts = vec![0.; outputs];
+                for action in trace.greedy.iter() {
+                    counts[*action] += 1.;
+                }
+                let steps = trace.greedy.len().max(1) as f32;
+                descriptor.extend(counts.into_iter().map(|count| count / steps));
+            }
+        }
+    }
+}
+
+impl RolloutStats {
+    pub fn total_reward(&self) -> f32 {
+        self.episodes.iter().map(|e| e.reward).sum()
+    }
+
+    /// Mean reward per episode, 0 without episodes.
+    pub fn mean_reward(&self) -> f32 {
+        if self.episodes.is_empty() {
+            0.
+        } else {
+            self.total_reward() / self.episodes.len() as f32
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.episodes.iter().map(|e| e.steps).sum()
+    }
+
+    /// Episodes that ended for `termination`.
+    pub fn count(&self, termination: Termination) -> usize {
+        self.episodes
+            .iter()
+            .filter(|e| e.termination == termination)
+            .count()
+    }
+}
+
+/// Run an episode of at most `max_steps` steps controlled by `network`.
+pub fn rollout<E, D>(
+    rng: &mut dyn RngCore,
+    environment: &mut E,
+    network: &mut FFNetwork,
+    decoder: &D,
+    max_steps: usize,
+) -> EpisodeStats
+where
+    E: Environment,
+    D: ActionDecoder<Action = E::Action>,
+{
+    play(rng, environment, network, decoder, max_steps, None)
+}
+
+/// Like [`rollout`], recording the episode in `trace` if any.
+fn play<E, D>(
+    rng: &mut dyn RngCore,
+    environment: &mut E,
+    network: &mut FFNetwork,
+    decoder: &D,
+    max_steps: usize,
+    mut trace: Option<&mut Trace>,
+) -> EpisodeStats
+where
+    E: Environment,
+    D: ActionDecoder<Action = E::Action>,
+{
+    network.reset();
+    let mut observation = environment.reset(rng);
+    if let Some(trace) = trace.as_mut() {
+        trace.observations.push(observation.clone());
+    }
+    let mut stats = EpisodeStats {
+        reward: 0.,
+        steps: 0,
+        termination: Termination::MaxSteps,
+    };
+    while stats.steps < max_steps {
+        let outputs = network
+            .forward(&observation)
+            .expect("Observations match the network inputs");
+        let action = decoder.decode(rng, &outputs);
+        let Step {
+            observation: next,
+            reward,
+            done,
+        } = environment.step(rng, &action);
+        if let Some(trace) = trace.as_mut() {
+            trace.greedy.push(argmax(&outputs));
+            trace.observations.push(next.clone());
+        }
+        stats.reward += reward;
+        stats.steps += 1;
+        if done {
+            stats.termination = Termination::Done;
+            break;
+        }
+        observation = next;
+    }
+    stats
+}
+
+/// Evaluation of a network over several episodes of an environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolloutRunner<D> {
+    pub decoder: D,
+    pub episodes: usize,
+    pub max_steps: usize,
+    /// Episode `i` draws from a generator seeded with `seed + i` instead of
+    /// the caller's, so every network faces the same episodes.
+    pub seed: Option<u64>,
+    /// Parts of the recorded behaviour descriptor, in order, none to record
+    /// nothing.
+    pub descriptors: Vec<Descriptor>,
+}
+
+impl<D: ActionDecoder> RolloutRunner<D> {
+    pub fn new(decoder: D, episodes: usize, max_steps: usize) -> Self {
+        Self {
+            decoder,
+            episodes,
+            max_steps,
+            seed: None,
+            descriptors: vec![],
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Record `descriptor` after the parts recorded so far.
+    pub fn with_descriptor(mut self, descriptor: Descriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    pub fn run<E>(
+        &self,
+        rng: &mut dyn RngCore,
+        environment: &mut E,
+        network: &mut FFNetwork,
+    ) -> RolloutStats
+    where
+        E: Environment<Action = D::Action>,
+    {
+        let recording = !self.descriptors.is_empty();
+        let mut behavior = recording.then(Vec::new);
+        let episodes = (0..self.episodes)
+            .map(|episode| {
+                let mut trace = Trace::default();
+                let trace_ref = recording.then_some(&mut trace);
+                let (decoder, steps) = (&self.decoder, self.max_steps);
+                let stats = match self.seed {
+                    Some(seed) => {
+                        let mut rng = ChaCha8Rng::seed_from_u64(seed.wrapping_add(episode as u64));
+                        play(&mut rng, environment, network, decoder, steps, trace_ref)
+                    }
+                    None => play(rng, environment, network, decoder, steps, trace_ref),
+                };
+                if let Some(behavior) = behavior.as_mut() {
+                    for descriptor in self.descriptors.iter() {
+                        descriptor.record(&trace, self.decoder.output_len(), behavior);
+                    }
+                }
+                stats
+            })
+            .collect();
+        RolloutStats { episodes, behavior }
+    }
+
+    /// Mean reward per episode, e.g. for `Population::evaluate_network`.
+    pub fn fitness<E>(
+        &self,
+        rng: &mut dyn RngCore,
+        environment: &mut E,
+        network: &mut FFNetwork,
+    ) -> f32
+    where
+        E: Environment<Action = D::Action>,
+    {
+        self.run(rng, environment, network).mean_reward()
+    }
+
+    /// Mean reward per episode and the recorded behaviour descriptor, e.g.
+    /// for `Population::evaluate_network_behavior`.
+    pub fn fitness_and_behavior<E>(
+        &self,
+        rng: &mut dyn RngCore,
+        environment: &mut E,
+        network: &mut FFNetwork,
+    ) -> (f32, Option<Vec<f32>>)
+    where
+        E: Environment<Action = D::Action>,
+    {
+        let stats = self.run(rng, environment, network);
+        (stats.mean_reward(), stats.behavior)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::{action::Discrete, cartpole::CartPole},
+        individual::genome::{aggregation::Aggregation, genome::GenomeBuilder},
+    };
+
+    /// Pushes towards the side the pole leans to.
+    fn network() -> FFNetwork {
+        let genome = GenomeBuilder::new()
+            .input(4)
+            .output(2)
+            .aggregation(4, Aggregation::Sum)
+            .aggregation(5, Aggregation::Sum)
+            .connect(2, 4, -1.)
+            .connect(3, 4, -1.)
+            .connect(2, 5, 1.)
+            .connect(3, 5, 1.)
+            .build()
+            .unwrap();
+        FFNetwork::from(&genome)
+    }
+
+    #[test]
+    fn test_run() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut network = network();
+        let runner = RolloutRunner::new(Discrete::greedy(2), 3, 50).with_seed(7);
+        let stats = runner.run(&mut rng, &mut CartPole::default(), &mut network);
+        assert_eq!(stats.episodes.len(), 3);
+        assert_eq!(stats.count(Termination::MaxSteps), 3);
+        assert_eq!(stats.steps(), 150);
+        assert_eq!(stats.total_reward(), 150.);
+        assert_eq!(
+            runner.fitness(&mut rng, &mut CartPole::default(), &mut network),
+            50.
+        );
+
+        // Ties go to the first action, always pushing left topples the pole
+        let mut network =
+            FFNetwork::from(&GenomeBuilder::new().input(4).output(2).build().unwrap());
+        let stats = runner.run(&mut rng, &mut CartPole::default(), &mut network);
+        assert_eq!(stats.count(Termination::Done), 3);
+        assert!(stats
+            .episodes
+            .iter()
+            .all(|e| e.steps < 50 && e.reward == e.steps as f32));
+    }
+
+    /// Walks along a line, observing the position, until 3 away.
+    struct Walk(i32);
+
+    impl Environment for Walk {
+        type Action = usize;
+
+        fn reset(&mut self, _: &mut dyn RngCore) -> Vec<f32> {
+            self.0 = 0;
+            vec![0.]
+        }
+
+        fn step(&mut self, _: &mut dyn RngCore, action: &usize) -> Step {
+            self.0 += if *action == 1 { 1 } else { -1 };
+            Step {
+                observation: vec![self.0 as f32],
+                reward: 1.,
+                done: self.0.abs() == 3,
+            }
+        }
+    }
+
+    #[test]
+    fn test_behavior() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        // Tied outputs, so always the first action
+        let mut network =
+            FFNetwork::from(&GenomeBuilder::new().input(1).output(2).build().unwrap());
+        let runner = RolloutRunner::new(Discrete::greedy(2), 2, 10);
+        let stats = runner.run(&mut rng, &mut Walk(0), &mut network);
+        assert_eq!(stats.behavior, None);
+
+        let runner = runner
+            .with_descriptor(Descriptor::FinalObservation)
+            .with_descriptor(Descriptor::Trajectory { points: 3 })
+            .with_descriptor(Descriptor::ActionHistogram);
+        let episode = [-3., 0., -1., -3., 1., 0.];
+        let (fitness, behavior) = runner.fitness_and_behavior(&mut rng, &mut Walk(0), &mut network);
+        assert_eq!(fitness, 3.);
+        assert_eq!(behavior, Some([episode, episode].concat()));
+
+        // Shorter episodes repeat observations
+        let runner = RolloutRunner {
+            max_steps: 1,
+            episodes: 1,
+            ..runner
+        };
+        let stats = runner.run(&mut rng, &mut Walk(0), &mut network);
+        assert_eq!(stats.behavior, Some(vec![-1., 0., 0., -1., 1., 0.]));
+    }
+
+    #[test]
+    fn test_seed() {
+        let mut network = network();
+        let runner = RolloutRunner::new(Discrete::greedy(2), 2, 500);
+        let mut environment = CartPole::default();
+        let mut run = |runner: &RolloutRunner<Discrete>, seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            runner.run(&mut rng, &mut environment, &mut network)
+        };
+        let seeded = runner.clone().with_seed(3);
+        assert_eq!(run(&seeded, 0), run(&seeded, 1));
+        assert_eq!(RolloutStats::default().mean_reward(), 0.);
+    }
+}
@@ -0,0 +1,100 @@
+//! Fixtures shared by the unit tests.
+
+use num::rational::Ratio;
+use rand::{Rng, RngCore};
+
+use crate::{
+    individual::genome::{
+        genome::{
+            Genome, GenomeBuilder, GenomeEdge, GenomeFactory, InnovationId, OrderedGenomeList,
+        },
+        node_list::{Node, NodeId},
+    },
+    mutation::{innovation_number::InnovNumber, mutation::MutationMethod},
+};
+
+/// Mutation leaving children as crossover made them.
+pub struct NoMutation;
+
+impl MutationMethod for NoMutation {
+    fn mutate(&self, _rng: &mut dyn RngCore, _child: &mut Genome, _innov: &mut InnovNumber) {}
+}
+
+/// Genome with `inputs` inputs, `outputs` outputs, the hidden nodes `hidden`
+/// at level 50 and the enabled edges `(innovation, in, out, weight)`. It
+/// keeps the given ids, so tests can line up the genes of several genomes.
+pub fn genome(
+    inputs: usize,
+    outputs: usize,
+    hidden: &[NodeId],
+    edges: &[(InnovationId, NodeId, NodeId, f32)],
+) -> Genome {
+    let mut genome = GenomeFactory::init(inputs, outputs)
+        .unwrap()
+        .generate_genome();
+    genome.node_list.hidden = hidden
+        .iter()
+        .map(|id| Node::new(*id, Ratio::from_integer(50), None))
+        .collect();
+    genome.genome_list = OrderedGenomeList::new(
+        edges
+            .iter()
+            .map(|&(innov_number, in_node, out_node, weight)| GenomeEdge {
+                innov_number,
+                in_node,
+                out_node,
+                weight,
+                enabled: true,
+            })
+            .collect(),
+    );
+    genome
+}
+
+/// Genome of a single input and output whose edges all connect them, for
+/// tests of gene alignment alone.
+pub fn genes(edges: &[(InnovationId, f32)]) -> Genome {
+    let edges = edges
+        .iter()
+        .map(|&(innov_number, weight)| (innov_number, 0, 1, weight))
+        .collect::<Vec<_>>();
+    genome(1, 1, &[], &edges)
+}
+
+/// Random genome with 3 inputs, 2 outputs, `hidden` hidden nodes of random
+/// configuration and at most `edges` distinct edges, recurrent ones only if
+/// `recurrent`.
+pub fn random_genome(rng: &mut impl Rng, hidden: usize, edges: usize, recurrent: bool) -> Genome {
+    let (inputs, outputs) = (3, 2);
+    let nodes = inputs + outputs + hidden;
+    let mut levels = vec![Ratio::from_integer(1); inputs];
+    levels.extend(vec![Ratio::from_integer(100); outputs]);
+    let mut builder = GenomeBuilder::new().input(inputs).output(outputs);
+    for _ in 0..hidden {
+        let level = Ratio::new(rng.gen_range(3..200), 2);
+        builder = builder.hidden(level);
+        levels.push(level);
+    }
+    for node in inputs as NodeId..nodes as NodeId {
+        builder = builder
+            .activation(node, rng.gen())
+            .aggregation(node, rng.gen())
+            .bias(node, rng.gen_range(-1. ..1.));
+    }
+    let mut pairs = vec![];
+    for _ in 0..edges {
+        let pair = (rng.gen_range(0..nodes), rng.gen_range(inputs..nodes));
+        let forward = levels[pair.0] < levels[pair.1];
+        if (recurrent || forward) && !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+    for (in_node, out_node) in pairs {
+        builder = builder.connect(
+            in_node as NodeId,
+            out_node as NodeId,
+            rng.gen_range(-2. ..2.),
+        );
+    }
+    builder.build().unwrap()
+}
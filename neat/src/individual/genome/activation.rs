@@ -1,10 +1,20 @@
 use errorfunctions::RealErrorFunctions;
-use num::{traits::real::Real, Float};
+use num::Float;
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
 use rand_derive2::RandGen;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use super::node_list::Activate;
+use super::node_list::{to_float, Activate};
 
-#[derive(Debug, Clone, Copy, PartialEq, Default, RandGen)]
+/// Range of the softplus beta, see [`Activation::param_bounds`].
+pub const SOFTPLUS_BOUNDS: (f32, f32) = (0.1, 10.);
+/// Range of the period of periodic, see [`Activation::param_bounds`].
+pub const PERIODIC_BOUNDS: (f32, f32) = (0.1, 10.);
+/// Range of the noise scale of noisy ReLU, see [`Activation::param_bounds`].
+pub const NOISE_BOUNDS: (f32, f32) = (0., 10.);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, RandGen, Serialize, Deserialize)]
 pub enum Activation {
     Abs,
     Exp,
@@ -24,38 +34,470 @@ pub enum Activation {
     Gelu,
     Root,
     Periodic(f32),
+    /// ReLU of the input plus Gaussian noise of the given scale when the
+    /// network samples noise, ReLU otherwise.
+    #[rand_derive(skip)]
+    NoisyRelu(f32),
+    /// 1 with probability `sigmoid(x)` when the network samples noise, the
+    /// step at 0 otherwise.
+    #[rand_derive(skip)]
+    StochasticBinary,
+}
+
+impl Activation {
+    /// Every function, parametrised ones with parameter 1.
+    pub const ALL: [Activation; 17] = [
+        Activation::Abs,
+        Activation::Exp,
+        Activation::Gauss,
+        Activation::Hat,
+        Activation::Identity,
+        Activation::Inv,
+        Activation::Log,
+        Activation::Relu,
+        Activation::Selu,
+        Activation::Sigmoid,
+        Activation::Sin,
+        Activation::Cos,
+        Activation::Tanh,
+        Activation::Softplus(1.),
+        Activation::Gelu,
+        Activation::Root,
+        Activation::Periodic(1.),
+    ];
+
+    /// Functions that sample noise, left out of [`Activation::ALL`] and of
+    /// [`ActivationSet::all`] so that only the sets asking for them use them.
+    pub const STOCHASTIC: [Activation; 2] =
+        [Activation::NoisyRelu(1.), Activation::StochasticBinary];
+
+    /// Lowercase name of the function, whatever its parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activation::Abs => "abs",
+            Activation::Exp => "exp",
+            Activation::Gauss => "gauss",
+            Activation::Hat => "hat",
+            Activation::Identity => "identity",
+            Activation::Inv => "inv",
+            Activation::Log => "log",
+            Activation::Relu => "relu",
+            Activation::Selu => "selu",
+            Activation::Sigmoid => "sigmoid",
+            Activation::Sin => "sin",
+            Activation::Cos => "cos",
+            Activation::Tanh => "tanh",
+            Activation::Softplus(_) => "softplus",
+            Activation::Gelu => "gelu",
+            Activation::Root => "root",
+            Activation::Periodic(_) => "periodic",
+            Activation::NoisyRelu(_) => "noisy_relu",
+            Activation::StochasticBinary => "stochastic_binary",
+        }
+    }
+
+    /// Function named `name`, with parameter 1 if it has one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .chain(Self::STOCHASTIC)
+            .find(|activation| activation.name() == name)
+    }
+
+    /// Whether the function samples noise, see [`Activation::STOCHASTIC`].
+    pub fn is_stochastic(&self) -> bool {
+        matches!(self, Activation::NoisyRelu(_) | Activation::StochasticBinary)
+    }
+
+    /// Like [`Activate::activate`], sampling the noise of the stochastic
+    /// functions from `rng`.
+    pub fn activate_noisy<F: Float>(&self, input: F, rng: &mut dyn RngCore) -> F {
+        match self {
+            Activation::NoisyRelu(sigma) => {
+                let noise: f32 = rng.sample(StandardNormal);
+                (input + to_float::<F>(sigma * noise)).max(F::zero())
+            }
+            Activation::StochasticBinary => {
+                let p = (F::one() + (-input).exp()).recip();
+                if to_float::<F>(rng.gen::<f32>()) < p {
+                    F::one()
+                } else {
+                    F::zero()
+                }
+            }
+            activation => activation.activate(input),
+        }
+    }
+
+    /// Range sampling, mutation and crossover keep the parameter in, `None`
+    /// without parameter. Softplus divides by its beta, which explodes near
+    /// 0, and periodic by its period.
+    pub fn param_bounds(&self) -> Option<(f32, f32)> {
+        match self {
+            Activation::Softplus(_) => Some(SOFTPLUS_BOUNDS),
+            Activation::Periodic(_) => Some(PERIODIC_BOUNDS),
+            Activation::NoisyRelu(_) => Some(NOISE_BOUNDS),
+            _ => None,
+        }
+    }
+
+    /// The function with its parameter clamped into
+    /// [`Activation::param_bounds`], a NaN parameter becoming 1.
+    pub fn bounded(self) -> Self {
+        let bound = |x: f32| match self.param_bounds() {
+            _ if x.is_nan() => 1.,
+            Some((min, max)) => x.clamp(min, max),
+            None => x,
+        };
+        match self {
+            Activation::Softplus(beta) => Activation::Softplus(bound(beta)),
+            Activation::Periodic(p) => Activation::Periodic(bound(p)),
+            Activation::NoisyRelu(sigma) => Activation::NoisyRelu(bound(sigma)),
+            activation => activation,
+        }
+    }
+
+    /// Derivative of [`Activate::activate`] at `x`. At kinks the subgradient
+    /// 0 is taken: for Abs, Hat and Log at 0, Relu at 0, Hat at ±1 and Exp
+    /// at its cap. Periodic has slope 1 between its jumps. Stochastic
+    /// functions take the derivative of their deterministic mode.
+    pub fn derivative(&self, x: f32) -> f32 {
+        // Sign with sign(0) = 0, picking the 0 subgradient at 0
+        let sign = if x == 0. { 0. } else { x.signum() };
+        match self {
+            Activation::Abs => sign,
+            Activation::Exp => {
+                if x < 5. {
+                    x.exp()
+                } else {
+                    0.
+                }
+            }
+            Activation::Gauss => -2. * x * (-(x * x)).exp(),
+            Activation::Hat => {
+                if x.abs() < 1. {
+                    -sign
+                } else {
+                    0.
+                }
+            }
+            Activation::Identity | Activation::Periodic(_) => 1.,
+            Activation::Inv => -x * (x * x + 1.).powf(-1.5),
+            Activation::Log => sign / (1. + x.abs()),
+            Activation::Relu | Activation::NoisyRelu(_) => {
+                if x > 0. {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Activation::StochasticBinary => 0.,
+            Activation::Selu => {
+                let lambda = 1.050701;
+                let alpha = 1.6732632;
+                if x >= 0. {
+                    lambda
+                } else {
+                    lambda * alpha * x.exp()
+                }
+            }
+            Activation::Sigmoid => {
+                let s = Activate::<f32>::activate(self, x);
+                s * (1. - s)
+            }
+            Activation::Sin => x.cos(),
+            Activation::Cos => -x.sin(),
+            Activation::Tanh => 1. - x.tanh() * x.tanh(),
+            Activation::Softplus(beta) => {
+                // d/dx (1 / beta) ln(1 + e^(-|beta x|))
+                let e = (-(beta * x).abs()).exp();
+                -sign * beta.signum() * e / (1. + e)
+            }
+            Activation::Gelu => {
+                let x = x as f64;
+                let cdf = (RealErrorFunctions::erf(x / 2.0_f64.sqrt()) + 1.) / 2.;
+                let pdf = (-x * x / 2.).exp() / (2. * std::f64::consts::PI).sqrt();
+                (cdf + x * pdf) as f32 // Phi(x) + x phi(x)
+            }
+            Activation::Root => x / (x * x + 1.).sqrt(),
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL
+            .iter()
+            .chain(Self::STOCHASTIC.iter())
+            .position(|activation| activation.name() == self.name())
+            .expect("Every function is listed")
+    }
+}
+
+/// Set of activation functions, whatever their parameter, e.g. the
+/// functions mutation may pick. Serialized as a list of names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivationSet(u32);
+
+impl ActivationSet {
+    pub fn all() -> Self {
+        Self((1 << Activation::ALL.len()) - 1)
+    }
+
+    pub fn contains(&self, activation: &Activation) -> bool {
+        self.0 & (1 << activation.index()) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Activation> + '_ {
+        Activation::ALL
+            .into_iter()
+            .chain(Activation::STOCHASTIC)
+            .filter(|activation| self.contains(activation))
+    }
+
+    /// Random function of the set, with a random parameter within bounds.
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Activation {
+        assert!(!self.is_empty(), "Sampling an empty activation set");
+        if self.iter().any(|activation| activation.is_stochastic()) {
+            // Random functions are never stochastic, pick among the set
+            let activations = self.iter().collect::<Vec<_>>();
+            return match activations[rng.gen_range(0..activations.len())] {
+                Activation::Softplus(_) => Activation::Softplus(rng.gen()),
+                Activation::Periodic(_) => Activation::Periodic(rng.gen()),
+                Activation::NoisyRelu(_) => Activation::NoisyRelu(rng.gen()),
+                activation => activation,
+            }
+            .bounded();
+        }
+        loop {
+            let activation = rng.gen::<Activation>();
+            if self.contains(&activation) {
+                return activation.bounded();
+            }
+        }
+    }
+}
+
+impl Default for ActivationSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl FromIterator<Activation> for ActivationSet {
+    fn from_iter<T: IntoIterator<Item = Activation>>(iter: T) -> Self {
+        Self(iter.into_iter().fold(0, |set, activation| set | 1 << activation.index()))
+    }
+}
+
+impl Serialize for ActivationSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Collected first, as length-prefixed formats need the length
+        let names = self.iter().map(|activation| activation.name()).collect::<Vec<_>>();
+        serializer.collect_seq(names)
+    }
+}
+
+impl<'de> Deserialize<'de> for ActivationSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        let set = names
+            .iter()
+            .map(|name| {
+                Activation::from_name(name)
+                    .ok_or_else(|| de::Error::custom(format!("unknown activation `{name}`")))
+            })
+            .collect::<Result<Self, _>>()?;
+        if set.is_empty() {
+            return Err(de::Error::custom("no activation allowed"));
+        }
+        Ok(set)
+    }
+}
+
+/// Serde of an optional function by name, e.g. `"tanh"`, for
+/// `#[serde(with = "...")]` fields.
+pub mod optional_name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        activation: &Option<Activation>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        activation.map(|activation| activation.name()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Activation>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| {
+                Activation::from_name(&name)
+                    .ok_or_else(|| de::Error::custom(format!("unknown activation `{name}`")))
+            })
+            .transpose()
+    }
 }
 
-impl Activate for Activation {
-    fn activate(&self, input: f32) -> f32 {
+impl<F: Float> Activate<F> for Activation {
+    fn activate(&self, input: F) -> F {
+        let one = F::one();
+        let zero = F::zero();
+        let half = to_float::<F>(0.5);
         match self {
             Activation::Abs => input.abs(),                      // |x|
-            Activation::Exp => input.min(5.).exp(),              // e^x // Avoid exploding
+            Activation::Exp => input.min(to_float(5.)).exp(),    // e^x // Avoid exploding
             Activation::Gauss => (-(input * input)).exp(),       // e^(-x^2)
-            Activation::Hat => (1. - input.abs()).max(0.),       // max(1 - |x|, 0)
+            Activation::Hat => (one - input.abs()).max(zero),    // max(1 - |x|, 0)
             Activation::Identity => input,                       // x
-            Activation::Inv => 1. / (input * input + 1.).sqrt(), // 1 / sqrt(x^2 + 1)
-            Activation::Log => input.abs().ln_1p(),                 // ln(|x| + 1)
-            Activation::Relu => input.max(0.),                   // max(x, 0)
+            Activation::Inv => (input * input + one).sqrt().recip(), // 1 / sqrt(x^2 + 1)
+            Activation::Log => input.abs().ln_1p(),              // ln(|x| + 1)
+            Activation::Relu => input.max(zero),                 // max(x, 0)
             Activation::Selu => {
-                let lambda = 1.0507009873554804934193349852946;
-                let alpha =  1.6732632423543772848170429916717;
-                if input >= 0. {
+                let lambda: F = to_float(1.0507009873554805);
+                let alpha: F = to_float(1.6732632423543772);
+                if input >= zero {
                     // lambda * x
                     lambda * input
                 } else {
-                    lambda * alpha * (input.exp() - 1.) // lambda * alpha * (e^x - 1)
+                    lambda * alpha * (input.exp() - one) // lambda * alpha * (e^x - 1)
                 }
             },
-            Activation::Sigmoid => (1. + (-input).exp()).recip(), // 1 / (1 + e^(-x))
+            Activation::Sigmoid => (one + (-input).exp()).recip(), // 1 / (1 + e^(-x))
             Activation::Sin => input.sin(),                    // sin x
             Activation::Cos => input.cos(),                    // cos x
             Activation::Tanh => input.tanh(),                  // tanh x
-            Activation::Softplus(beta) => beta.recip() * (-(beta * input).abs()).exp().ln_1p(), // (1 / beta) * ln(1. + exp(x * beta)) (Stable)
-            Activation::Gelu => ((input as f64 / 2.0_f64.sqrt()).erf() as f32 + 1.) * 0.5 * input, // x/2 (1 + erf(x / sqrt(2)))
-            Activation::Root => (input * input + 1.).sqrt(), // sqrt(x^2 + 1)
-            Activation::Periodic(p) => (input - p * (input / (p + f32::EPSILON)).floor()) - p / 2. // x - p * floor (x/(p + c)) - p/2
+            Activation::Softplus(beta) => {
+                let beta: F = to_float(*beta);
+                beta.recip() * (-(beta * input).abs()).exp().ln_1p() // (1 / beta) * ln(1. + exp(x * beta)) (Stable)
+            }
+            Activation::Gelu => {
+                let x = input.to_f64().expect("Float should convert to f64");
+                (to_float::<F>(RealErrorFunctions::erf(x / 2.0_f64.sqrt())) + one) * half * input // x/2 (1 + erf(x / sqrt(2)))
+            }
+            Activation::Root => (input * input + one).sqrt(), // sqrt(x^2 + 1)
+            Activation::Periodic(p) => {
+                let p: F = to_float(*p);
+                (input - p * (input / (p + F::epsilon())).floor()) - p * half // x - p * floor (x/(p + c)) - p/2
+            }
+            Activation::NoisyRelu(_) => input.max(zero),       // max(x, 0) without noise
+            Activation::StochasticBinary => {
+                if input > zero {
+                    one
+                } else {
+                    zero
+                }
+            }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_derivative() {
+        let points = [-2.3, -1.4, -0.7, -0.3, 0.2, 0.4, 0.9, 1.7, 3.1, 6.2];
+        let step = 1e-4;
+        for activation in Activation::ALL.into_iter().chain([Activation::Softplus(2.5)]) {
+            for x in points {
+                let at = |x: f64| Activate::<f64>::activate(&activation, x);
+                let numerical = (at(x + step) - at(x - step)) / (2. * step);
+                let derivative = activation.derivative(x as f32) as f64;
+                assert!(
+                    (derivative - numerical).abs() < 1e-3 * (1. + numerical.abs()),
+                    "{activation:?} at {x}: {derivative} vs {numerical}"
+                );
+            }
+        }
+        for activation in [Activation::Abs, Activation::Hat, Activation::Log, Activation::Relu] {
+            assert_eq!(activation.derivative(0.), 0., "{activation:?}");
+        }
+        assert_eq!(Activation::Relu.derivative(-1.), 0.);
+        assert_eq!(Activation::Hat.derivative(1.), 0.);
+        assert_eq!(Activation::Exp.derivative(5.), 0.);
+    }
+
+    #[test]
+    fn test_param_bounds() {
+        assert_eq!(Activation::Softplus(0.).bounded(), Activation::Softplus(0.1));
+        assert_eq!(Activation::Softplus(-3.).bounded(), Activation::Softplus(0.1));
+        assert_eq!(Activation::Periodic(50.).bounded(), Activation::Periodic(10.));
+        assert_eq!(Activation::Periodic(f32::NAN).bounded(), Activation::Periodic(1.));
+        assert_eq!(Activation::Tanh.bounded(), Activation::Tanh);
+        assert_eq!(Activation::Tanh.param_bounds(), None);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let set = [Activation::Softplus(1.), Activation::Periodic(1.)]
+            .into_iter()
+            .collect::<ActivationSet>();
+        for _ in 0..100 {
+            let activation = set.sample(&mut rng);
+            assert_eq!(activation.bounded(), activation);
+        }
+    }
+
+    #[test]
+    fn test_activation_set() {
+        assert!(Activation::ALL
+            .iter()
+            .all(|a| Activation::from_name(a.name()) == Some(*a)));
+        let set = [Activation::Tanh, Activation::Softplus(3.)]
+            .into_iter()
+            .collect::<ActivationSet>();
+        assert!(set.contains(&Activation::Softplus(0.5)) && !set.contains(&Activation::Relu));
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        assert!((0..50).all(|_| set.contains(&set.sample(&mut rng))));
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["tanh","softplus"]"#);
+        assert_eq!(serde_json::from_str::<ActivationSet>(&json).unwrap(), set);
+        assert!(serde_json::from_str::<ActivationSet>(r#"["tan"]"#).is_err());
+        assert!(serde_json::from_str::<ActivationSet>("[]").is_err());
+        assert_eq!(ActivationSet::default().iter().count(), Activation::ALL.len());
+    }
+
+    #[test]
+    fn test_stochastic() {
+        assert!(Activation::STOCHASTIC
+            .iter()
+            .all(|a| a.is_stochastic() && Activation::from_name(a.name()) == Some(*a)));
+        assert!(!ActivationSet::default().iter().any(|a| a.is_stochastic()));
+        // Without noise, the deterministic modes
+        assert_eq!(Activation::NoisyRelu(1.).activate(-0.5f32), 0.);
+        assert_eq!(Activation::NoisyRelu(1.).activate(0.5f32), 0.5);
+        assert_eq!(Activation::StochasticBinary.activate(0.5f32), 1.);
+        assert_eq!(Activation::StochasticBinary.activate(-0.5f32), 0.);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let noisy = (0..2000)
+            .map(|_| Activation::NoisyRelu(0.5).activate_noisy(3f64, &mut rng))
+            .collect::<Vec<_>>();
+        let mean = noisy.iter().sum::<f64>() / noisy.len() as f64;
+        let std = (noisy.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / noisy.len() as f64).sqrt();
+        assert!((mean - 3.).abs() < 0.05 && (std - 0.5).abs() < 0.05, "{mean} {std}");
+        assert!((0..100).all(|_| Activation::NoisyRelu(5.).activate_noisy(-1f32, &mut rng) >= 0.));
+        // Fires with probability sigmoid(x)
+        let fired = (0..2000)
+            .filter(|_| Activation::StochasticBinary.activate_noisy(1f32, &mut rng) == 1.)
+            .count();
+        let expected = 2000. / (1. + (-1f64).exp());
+        assert!((fired as f64 - expected).abs() < 60., "{fired} vs {expected}");
+        assert_eq!(Activation::Tanh.activate_noisy(0.3f32, &mut rng), Activation::Tanh.activate(0.3f32));
+
+        let set = [Activation::Relu, Activation::NoisyRelu(1.), Activation::StochasticBinary]
+            .into_iter()
+            .collect::<ActivationSet>();
+        let sampled = (0..100).map(|_| set.sample(&mut rng)).collect::<Vec<_>>();
+        assert!(sampled.iter().all(|a| set.contains(a) && a.bounded() == *a));
+        assert!(sampled.iter().any(|a| a.is_stochastic()));
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["relu","noisy_relu","stochastic_binary"]"#);
+        assert_eq!(serde_json::from_str::<ActivationSet>(&json).unwrap(), set);
+    }
+}
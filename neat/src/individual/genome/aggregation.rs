@@ -1,7 +1,17 @@
 use itertools::Itertools;
+use num::Float;
 use rand_derive2::RandGen;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, RandGen)]
+use super::node_list::to_float;
+
+/// How a node combines its weighted inputs. A node without inputs on a pass,
+/// e.g. when every edge into it is disabled, aggregates to 0 whatever the
+/// variant.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, RandGen, Serialize,
+    Deserialize,
+)]
 pub enum Aggregation {
     Sum,
     Max,
@@ -12,31 +22,34 @@ pub enum Aggregation {
 }
 
 impl Aggregation {
-    pub fn apply(&self, a: impl Iterator<Item = f32>) -> f32 {
+    pub fn apply<F: Float>(&self, a: impl Iterator<Item = F>) -> F {
+        let mut a = a.peekable();
+        if a.peek().is_none() {
+            return F::zero();
+        }
         match self {
-            Aggregation::Sum => a.sum(),
-            Aggregation::Max => a.reduce(|a, b| f32::max(a, b)).unwrap_or(0.),
+            Aggregation::Sum => a.fold(F::zero(), |acc, x| acc + x),
+            Aggregation::Max => a.reduce(F::max).unwrap_or(F::zero()),
             Aggregation::Mean => {
-                let x = a.fold((0., 0), |(acc, cnt), x| (acc + x, cnt + 1));
-                x.0 / x.1 as f32
+                let x = a.fold((F::zero(), 0), |(acc, cnt), x| (acc + x, cnt + 1));
+                x.0 / to_float(x.1)
             }
             Aggregation::L2NormAvg => {
                 let v = a.collect_vec();
-                let alpha = v
-                    .iter()
-                    .copied()
-                    .reduce(|a, b| f32::max(a.abs(), b.abs()))
-                    .expect("Should not contain NaN");
+                let alpha = v.iter().fold(F::zero(), |alpha, x| F::max(alpha, x.abs()));
+                if alpha.is_zero() {
+                    return F::zero();
+                }
                 v.iter()
                     .copied()
-                    .fold(0., |acc, x| acc + (x / alpha) * (x / alpha))
+                    .fold(F::zero(), |acc, x| acc + (x / alpha) * (x / alpha))
                     .sqrt()
                     * alpha
-                    / v.len() as f32
+                    / to_float(v.len())
             }
             Aggregation::L1NormAvg => {
-                let p = a.fold((0., 0), |(acc, cnt), x| (acc + x.abs(), cnt + 1));
-                p.0 / p.1 as f32
+                let p = a.fold((F::zero(), 0), |(acc, cnt), x| (acc + x.abs(), cnt + 1));
+                p.0 / to_float(p.1)
             }
         }
     }
@@ -57,4 +70,18 @@ mod tests {
         assert_relative_eq!(Aggregation::L2NormAvg.apply(DATA.iter().copied()), DATA.iter().map(|x| x * x).sum::<f32>().sqrt() / DATA.len() as f32);
         assert_relative_eq!(Aggregation::L1NormAvg.apply(DATA.iter().copied()), DATA.iter().map(|x| x.abs()).sum::<f32>() / DATA.len() as f32);
     }
+
+    #[test]
+    fn test_empty_inputs() {
+        for aggregation in [
+            Aggregation::Sum,
+            Aggregation::Max,
+            Aggregation::Mean,
+            Aggregation::L1NormAvg,
+            Aggregation::L2NormAvg,
+        ] {
+            assert_eq!(aggregation.apply(std::iter::empty::<f32>()), 0.);
+            assert_eq!(aggregation.apply([0f64, 0.].into_iter()), 0.);
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,9 @@
-use super::node_list::Activate;
+use num::Float;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use super::node_list::{to_float, Activate};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Clamp {
     pub min_limit: Option<f32>,
     pub max_limit: Option<f32>,
@@ -39,19 +42,30 @@ impl Clamp {
     }
 }
 
-impl Activate for Clamp {
-    fn activate(&self, input: f32) -> f32 {
+impl Clamp {
+    /// Clamp fitted to outputs observed in `[min, max]`, padded by `margin`
+    /// times the observed span and never looser than `self`. `None` if the
+    /// padded range is empty, e.g. for a node with a constant output.
+    pub fn tighten(&self, min: f32, max: f32, margin: f32) -> Option<Self> {
+        let pad = margin * (max - min);
+        let min = self.min_limit.map_or(min - pad, |m| m.max(min - pad));
+        let max = self.max_limit.map_or(max + pad, |m| m.min(max + pad));
+        Self::new(Some(min), Some(max))
+    }
+}
+
+impl<F: Float> Activate<F> for Clamp {
+    fn activate(&self, input: F) -> F {
         let input = if let Some(m) = self.max_limit {
-            input.min(m)
+            input.min(to_float(m))
         } else {
             input
         };
-        let input = if let Some(m) = self.min_limit {
-            input.max(m)
+        if let Some(m) = self.min_limit {
+            input.max(to_float(m))
         } else {
             input
-        };
-        return input;
+        }
     }
 }
 
@@ -65,7 +79,7 @@ mod tests {
       fn rand_number(a in any::<f32>()) {
         let clamp = Clamp::default();
         let res = clamp.activate(a);
-        prop_assert!(res >= -5. && res <= 5.);
+        prop_assert!((-5. ..=5.).contains(&res));
       }
 
       #[test]
@@ -82,6 +96,16 @@ mod tests {
         prop_assert!(res >= -2.);
       }
 
+      #[test]
+      fn tighten_within(a in -10.0f32..10.0f32, b in 0.0f32..10.0f32, margin in 0.0f32..1.) {
+        let clamp = Clamp::default();
+        if let Some(tight) = clamp.tighten(a, a + b, margin) {
+          prop_assert!(tight.min_limit.unwrap() >= -5. && tight.max_limit.unwrap() <= 5.);
+          prop_assert!(tight.min_limit.unwrap() <= a.clamp(-5., 5.));
+          prop_assert!(tight.max_limit.unwrap() >= (a + b).clamp(-5., 5.));
+        }
+      }
+
       #[test]
       fn check_clamp(a in -10.0f32..10.0f32, b in -10.0f32..10.0f32) {
         let clamp = Clamp::new(Some(a), Some(b));
@@ -0,0 +1,85 @@
+//! Graphviz export of genomes, e.g. `dot -Tsvg genome.dot > genome.svg`.
+
+use std::fmt::Write;
+
+use super::{
+    genome::Genome,
+    node_list::{Node, NodeRole},
+};
+
+impl Genome {
+    /// Graph of the genome in the DOT language. Nodes are labelled with
+    /// their name or id and activation, inputs and outputs are ranked at
+    /// the sides, and disabled edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let node_list = &self.node_list;
+        let label = |node: &Node| {
+            let name = node_list
+                .name(node.node_id)
+                .map_or_else(|| node.node_id.to_string(), str::to_string);
+            match node_list.role(node.node_id) {
+                NodeRole::Sensor => name,
+                _ => format!("{name}\\n{:?}", node.config.activation),
+            }
+        };
+        let mut dot = String::from("digraph genome {\n    rankdir=LR;\n");
+        for (rank, shape, nodes) in [
+            ("source", "box", &node_list.input[..]),
+            ("sink", "doublecircle", &node_list.output[..]),
+        ] {
+            let _ = writeln!(dot, "    {{\n        rank={rank};");
+            for node in nodes {
+                let _ = writeln!(
+                    dot,
+                    "        {} [label=\"{}\", shape={shape}];",
+                    node.node_id,
+                    label(node)
+                );
+            }
+            dot.push_str("    }\n");
+        }
+        for node in node_list.hidden.iter() {
+            let _ = writeln!(dot, "    {} [label=\"{}\"];", node.node_id, label(node));
+        }
+        for edge in self.genome_list.iter() {
+            let style = if edge.enabled { "solid" } else { "dashed" };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"{:.3}\", style={style}];",
+                edge.in_node,
+                edge.out_node,
+                self.weight(edge)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    #[test]
+    fn test_to_dot() {
+        let mut genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .name(0, "x")
+            .connect(0, 3, 0.5)
+            .connect(3, 2, -1.)
+            .connect(1, 2, 2.)
+            .build()
+            .unwrap();
+        genome.genome_list.edge_list[2].enabled = false;
+        let dot = genome.to_dot();
+        assert!(dot.starts_with("digraph genome {"));
+        assert!(dot.contains("0 [label=\"x\", shape=box];"));
+        assert!(dot.contains("1 [label=\"1\", shape=box];"));
+        assert!(dot.contains("3 [label=\"3\\n"));
+        assert!(dot.contains("0 -> 3 [label=\"0.500\", style=solid];"));
+        assert_eq!(dot.matches("style=dashed").count(), 1);
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
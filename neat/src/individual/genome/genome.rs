@@ -1,12 +1,30 @@
 use itertools::Itertools;
 use num::rational::Ratio;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
-use super::node_list::{Node, NodeList};
+use super::{
+    activation::Activation,
+    aggregation::Aggregation,
+    clamp::Clamp,
+    network::network::FFNetwork,
+    node_list::{Config, Gate, InputDefaults, Node, NodeId, NodeList, NodeNames},
+};
 
 const MIN_RATIO: usize = 1;
 const MAX_RATIO: usize = 100;
 
+/// Most inputs a genome may have, catching e.g. a miscomputed observation
+/// size before it allocates.
+pub const MAX_INPUTS: usize = 1 << 16;
+
+/// Resolution of the genome parameters compared by [`Genome`] equality.
+pub const PARAMETER_QUANTUM: f32 = 1e-6;
+
 // Consider using the following
 // * (Arc/Rc)<_>
 // * TinyVec::Vec<_>
@@ -18,10 +36,25 @@ const MAX_RATIO: usize = 100;
 pub struct GenomeFactory {
     input_list: Arc<[Node]>,
     output_list: Vec<Node>,
+    names: NodeNames,
+    input_defaults: InputDefaults,
+    input_scaling: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GenonomeError {
     ZeroIOVector,
+    /// More inputs than [`MAX_INPUTS`].
+    TooManyInputs(usize),
+    /// A node id that is not part of the genome.
+    UnknownNode(NodeId),
+    /// An innovation number that is not part of the genome.
+    UnknownEdge(InnovationId),
+    /// A node id carried by two nodes.
+    DuplicateNode(NodeId),
+    /// A hidden node, by id, not strictly between the input and output
+    /// levels.
+    LevelCollision(NodeId),
 }
 
 impl GenomeFactory {
@@ -29,7 +62,10 @@ impl GenomeFactory {
         if input == 0 || output == 0 {
             return Err(GenonomeError::ZeroIOVector);
         }
-        let mut id_generator = 0..input + output;
+        if input > MAX_INPUTS {
+            return Err(GenonomeError::TooManyInputs(input));
+        }
+        let mut id_generator = 0..(input + output) as NodeId;
         let input_list: Arc<_> = Arc::from_iter(
             (&mut id_generator)
                 .take(input)
@@ -41,28 +77,282 @@ impl GenomeFactory {
         Ok(Self {
             input_list,
             output_list,
+            names: NodeNames::default(),
+            input_defaults: InputDefaults::default(),
+            input_scaling: false,
         })
     }
+
+    /// Name the inputs in order, e.g. `["cart_position", "pole_angle"]`.
+    pub fn with_input_names<S: Into<String>>(self, names: impl IntoIterator<Item = S>) -> Self {
+        let ids = self.input_list.iter().map(|node| node.node_id).collect_vec();
+        self.with_names(ids, names)
+    }
+
+    /// Name the outputs in order, e.g. `["motor_left", "motor_right"]`.
+    pub fn with_output_names<S: Into<String>>(self, names: impl IntoIterator<Item = S>) -> Self {
+        let ids = self.output_list.iter().map(|node| node.node_id).collect_vec();
+        self.with_names(ids, names)
+    }
+
+    /// Declare the input at `index` optional: masked passes may omit it, in
+    /// which case it is held at `default`.
+    pub fn with_optional_input(mut self, index: usize, default: f32) -> Self {
+        let node_id = self.input_list.get(index).expect("Optional input exists").node_id;
+        Arc::make_mut(&mut self.input_defaults).insert(node_id, default);
+        self
+    }
+
+    /// Give every input an evolvable scale and offset, starting from the
+    /// identity, see [`InputScaling`].
+    pub fn with_input_scaling(self) -> Self {
+        Self {
+            input_scaling: true,
+            ..self
+        }
+    }
+
+    fn with_names<S: Into<String>>(
+        mut self,
+        ids: Vec<NodeId>,
+        names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let names = names.into_iter().map(Into::into).collect_vec();
+        assert!(names.len() <= ids.len(), "More names than nodes");
+        Arc::make_mut(&mut self.names).extend(ids.into_iter().zip(names));
+        self
+    }
+
     pub fn generate_genome(&self) -> Genome {
         let node_list = NodeList {
             input: Arc::clone(&self.input_list),
             output: Vec::clone(&self.output_list),
             hidden: vec![],
+            names: Arc::clone(&self.names),
+            input_defaults: Arc::clone(&self.input_defaults),
         };
-        Genome::new(node_list, vec![])
+        let mut genome =
+            Genome::new(node_list, vec![]).expect("The factory nodes were checked by `init`");
+        if self.input_scaling {
+            genome.input_scaling = InputScaling::identity(&self.input_list);
+        }
+        genome
+    }
+}
+
+/// Fluent construction of hand-crafted genomes. Node ids follow
+/// [`GenomeFactory`]: inputs first, then outputs, then hidden nodes in the
+/// order they were added. Connections are numbered from 0 in the order they
+/// were added.
+#[derive(Debug, Clone, Default)]
+pub struct GenomeBuilder {
+    inputs: usize,
+    outputs: usize,
+    hidden: Vec<Ratio<usize>>,
+    edges: Vec<(NodeId, NodeId, f32)>,
+    configs: Vec<(NodeId, Config)>,
+    names: Vec<(NodeId, String)>,
+}
+
+impl GenomeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input(mut self, count: usize) -> Self {
+        self.inputs = count;
+        self
+    }
+
+    pub fn output(mut self, count: usize) -> Self {
+        self.outputs = count;
+        self
+    }
+
+    /// Add a hidden node at `level`, between the input level 1 and the output
+    /// level 100.
+    pub fn hidden(mut self, level: impl Into<Ratio<usize>>) -> Self {
+        self.hidden.push(level.into());
+        self
+    }
+
+    pub fn connect(mut self, in_node: NodeId, out_node: NodeId, weight: f32) -> Self {
+        self.edges.push((in_node, out_node, weight));
+        self
+    }
+
+    pub fn config(mut self, node: NodeId, config: Config) -> Self {
+        self.configs.push((node, config));
+        self
+    }
+
+    pub fn name(mut self, node: NodeId, name: impl Into<String>) -> Self {
+        self.names.push((node, name.into()));
+        self
+    }
+
+    pub fn activation(self, node: NodeId, activation: Activation) -> Self {
+        self.update(node, |config| config.activation = activation)
+    }
+
+    pub fn aggregation(self, node: NodeId, aggregation: Aggregation) -> Self {
+        self.update(node, |config| config.aggregation = aggregation)
+    }
+
+    pub fn bias(self, node: NodeId, bias: f32) -> Self {
+        self.update(node, |config| config.bias = bias)
+    }
+
+    pub fn gate(self, node: NodeId, gate: Gate) -> Self {
+        self.update(node, |config| config.gate = Some(gate))
+    }
+
+    fn update(mut self, node: NodeId, f: impl FnOnce(&mut Config)) -> Self {
+        let mut config = self
+            .configs
+            .iter()
+            .rev()
+            .find(|(id, _)| *id == node)
+            .map_or_else(Config::default, |(_, config)| *config);
+        f(&mut config);
+        self.configs.push((node, config));
+        self
+    }
+
+    pub fn build(self) -> Result<Genome, GenonomeError> {
+        let mut genome = GenomeFactory::init(self.inputs, self.outputs)?.generate_genome();
+        let inputs = self.inputs as NodeId;
+        let first_hidden = (self.inputs + self.outputs) as NodeId;
+        genome.node_list.hidden = (first_hidden..)
+            .zip(self.hidden.iter())
+            .map(|(id, level)| Node::new(id, *level, None))
+            .collect_vec();
+        let node_count = first_hidden + self.hidden.len() as NodeId;
+        for (node, config) in self.configs {
+            let node = match node {
+                id if id < inputs => continue, // Inputs are not activated
+                id if id < first_hidden => &mut genome.node_list.output[(id - inputs) as usize],
+                id if id < node_count => &mut genome.node_list.hidden[(id - first_hidden) as usize],
+                id => return Err(GenonomeError::UnknownNode(id)),
+            };
+            node.config = config;
+        }
+        if let Some((id, _)) = self.names.iter().find(|(id, _)| *id >= node_count) {
+            return Err(GenonomeError::UnknownNode(*id));
+        }
+        genome.node_list.names = Arc::new(self.names.into_iter().collect());
+        let edges = self
+            .edges
+            .iter()
+            .zip(0..)
+            .map(|(&(in_node, out_node, weight), innov_number)| {
+                match [in_node, out_node].into_iter().find(|id| *id >= node_count) {
+                    Some(id) => Err(GenonomeError::UnknownNode(id)),
+                    None => Ok(GenomeEdge {
+                        innov_number,
+                        in_node,
+                        out_node,
+                        weight,
+                        enabled: true,
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        genome.genome_list = OrderedGenomeList::new(edges);
+        genome.validate()?;
+        Ok(genome)
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Genome {
     pub node_list: NodeList,
     pub genome_list: OrderedGenomeList,
+    #[serde(default)]
+    pub weight_table: WeightTable,
+    #[serde(default)]
+    pub input_scaling: InputScaling,
+}
+
+/// Shared weights of tied edges, e.g. mirrored legs of a walker. Every edge
+/// of a group uses the weight of the group.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeightTable {
+    /// Group of every tied edge, by innovation number.
+    pub groups: BTreeMap<InnovationId, usize>,
+    /// Weight of every group.
+    pub weights: BTreeMap<usize, f32>,
+}
+
+impl WeightTable {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Weight of the edge `innov_number`, `None` if it is not tied.
+    pub fn weight(&self, innov_number: InnovationId) -> Option<f32> {
+        self.groups
+            .get(&innov_number)
+            .and_then(|group| self.weights.get(group))
+            .copied()
+    }
+}
+
+/// Evolvable affine transform of the inputs, applied before propagation so
+/// networks adapt to unnormalized sensor ranges. Inputs without a gene pass
+/// through unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputScaling {
+    /// Gene of every scaled input, by node id.
+    pub genes: BTreeMap<NodeId, InputGene>,
+}
+
+/// An input `x` enters the network as `scale * x + offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InputGene {
+    pub scale: f32,
+    pub offset: f32,
+}
+
+impl Default for InputGene {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            offset: 0.,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl InputScaling {
+    /// Identity genes for every node of `inputs`.
+    pub fn identity(inputs: &[Node]) -> Self {
+        Self {
+            genes: inputs
+                .iter()
+                .map(|node| (node.node_id, InputGene::default()))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genes.is_empty()
+    }
+
+    /// Gene of the input `node_id`, the identity if it has none.
+    pub fn gene(&self, node_id: NodeId) -> InputGene {
+        self.genes.get(&node_id).copied().unwrap_or_default()
+    }
+}
+
+/// Historical marking of an edge, drawn from the innovation counter of
+/// `InnovNumber`, apart from the node ids.
+pub type InnovationId = u64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct GenomeEdge {
-    pub innov_number: usize,
-    pub in_node: usize,
-    pub out_node: usize,
+    pub innov_number: InnovationId,
+    pub in_node: NodeId,
+    pub out_node: NodeId,
     pub weight: f32,
     pub enabled: bool,
 }
@@ -77,7 +367,7 @@ impl Eq for GenomeEdge {}
 
 impl PartialOrd for GenomeEdge {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.innov_number.partial_cmp(&other.innov_number)
+        Some(self.cmp(other))
     }
 }
 
@@ -87,6 +377,7 @@ impl Ord for GenomeEdge {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderedGenomeList {
     pub edge_list: Vec<GenomeEdge>,
 }
@@ -116,10 +407,768 @@ impl OrderedGenomeList {
 }
 
 impl Genome {
-    fn new(node_list: NodeList, genome_list: Vec<GenomeEdge>) -> Self {
-        Self {
+    pub fn new(node_list: NodeList, genome_list: Vec<GenomeEdge>) -> Result<Self, GenonomeError> {
+        let genome = Self {
             node_list,
             genome_list: OrderedGenomeList::new(genome_list),
+            weight_table: WeightTable::default(),
+            input_scaling: InputScaling::default(),
+        };
+        genome.validate()?;
+        Ok(genome)
+    }
+
+    /// Check what compiling the network relies on: inputs and outputs
+    /// present, unique node ids, hidden levels strictly between the input
+    /// and output levels, and edges between nodes of the genome.
+    pub fn validate(&self) -> Result<(), GenonomeError> {
+        let node_list = &self.node_list;
+        if node_list.input.is_empty() || node_list.output.is_empty() {
+            return Err(GenonomeError::ZeroIOVector);
+        }
+        if node_list.input.len() > MAX_INPUTS {
+            return Err(GenonomeError::TooManyInputs(node_list.input.len()));
+        }
+        let mut ids = BTreeSet::new();
+        for node in node_list.input.iter().chain(&node_list.output).chain(&node_list.hidden) {
+            if !ids.insert(node.node_id) {
+                return Err(GenonomeError::DuplicateNode(node.node_id));
+            }
         }
+        let levels = Ratio::from_integer(MIN_RATIO)..Ratio::from_integer(MAX_RATIO);
+        if let Some(node) = node_list
+            .hidden
+            .iter()
+            .find(|node| node.level == levels.start || !levels.contains(&node.level))
+        {
+            return Err(GenonomeError::LevelCollision(node.node_id));
+        }
+        if let Some(id) = self
+            .genome_list
+            .iter()
+            .flat_map(|edge| [edge.in_node, edge.out_node])
+            .find(|id| !ids.contains(id))
+        {
+            return Err(GenonomeError::UnknownNode(id));
+        }
+        Ok(())
+    }
+
+    /// Remove the edges from or to a node the genome does not have, and
+    /// their tied weights, e.g. left by a crossover that did not inherit
+    /// every node. Returns how many edges were removed.
+    pub fn drop_dangling_edges(&mut self) -> usize {
+        let node_list = &self.node_list;
+        let ids = node_list
+            .input
+            .iter()
+            .chain(&node_list.output)
+            .chain(&node_list.hidden)
+            .map(|node| node.node_id)
+            .collect::<BTreeSet<_>>();
+        let edges = &mut self.genome_list.edge_list;
+        let before = edges.len();
+        edges.retain(|edge| ids.contains(&edge.in_node) && ids.contains(&edge.out_node));
+        let dropped = before - edges.len();
+        if dropped > 0 {
+            self.sync_weights();
+        }
+        dropped
+    }
+
+    /// Tie the weights of the edges `innov_numbers` to `group`. A new group
+    /// starts from the weight of its first edge.
+    pub fn tie_weights(
+        &mut self,
+        group: usize,
+        innov_numbers: impl IntoIterator<Item = InnovationId>,
+    ) -> Result<(), GenonomeError> {
+        for innov_number in innov_numbers {
+            let edge = self
+                .genome_list
+                .iter()
+                .find(|edge| edge.innov_number == innov_number)
+                .ok_or(GenonomeError::UnknownEdge(innov_number))?;
+            self.weight_table.weights.entry(group).or_insert(edge.weight);
+            self.weight_table.groups.insert(innov_number, group);
+        }
+        self.sync_weights();
+        Ok(())
+    }
+
+    /// Weight of `edge`, the weight of its group if it is tied.
+    pub fn weight(&self, edge: &GenomeEdge) -> f32 {
+        self.weight_table.weight(edge.innov_number).unwrap_or(edge.weight)
+    }
+
+    /// Copy the group weights into the tied edges and drop the groups whose
+    /// edges are gone, e.g. after crossover.
+    pub fn sync_weights(&mut self) {
+        if self.weight_table.is_empty() {
+            return;
+        }
+        let table = &mut self.weight_table;
+        let edges = self.genome_list.iter().map(|edge| edge.innov_number).collect::<BTreeSet<_>>();
+        table.groups.retain(|innov_number, _| edges.contains(innov_number));
+        let used = table.groups.values().copied().collect::<BTreeSet<_>>();
+        table.weights.retain(|group, _| used.contains(group));
+        for edge in self.genome_list.iter_mut() {
+            if let Some(weight) = table.weight(edge.innov_number) {
+                edge.weight = weight;
+            }
+        }
+    }
+}
+
+/// Parameter rounded to a multiple of [`PARAMETER_QUANTUM`]. NaN only
+/// equals itself.
+fn quantize(x: f32) -> i64 {
+    if x.is_nan() {
+        i64::MIN
+    } else {
+        (x / PARAMETER_QUANTUM).round() as i64
+    }
+}
+
+type ConfigKey = (
+    Aggregation,
+    (Option<i64>, Option<i64>),
+    &'static str,
+    Option<i64>,
+    i64,
+    Option<[i64; 4]>,
+);
+
+fn config_key(config: &Config) -> ConfigKey {
+    let param = match config.activation {
+        Activation::Softplus(param) | Activation::Periodic(param) | Activation::NoisyRelu(param) => {
+            Some(quantize(param))
+        }
+        _ => None,
+    };
+    (
+        config.aggregation,
+        (config.clamp.min_limit.map(quantize), config.clamp.max_limit.map(quantize)),
+        config.activation.name(),
+        param,
+        quantize(config.bias),
+        config.gate.map(|gate| {
+            [
+                gate.input_weight,
+                gate.input_bias,
+                gate.forget_weight,
+                gate.forget_bias,
+            ]
+            .map(quantize)
+        }),
+    )
+}
+
+/// Everything [`Genome`] equality compares.
+#[derive(PartialEq, Eq, Hash)]
+struct GenomeKey {
+    inputs: usize,
+    input_defaults: Vec<(NodeId, i64)>,
+    nodes: Vec<(NodeId, Ratio<usize>, ConfigKey)>,
+    edges: Vec<(InnovationId, NodeId, NodeId, i64, bool)>,
+    tied: Vec<(InnovationId, usize)>,
+    tied_weights: Vec<(usize, i64)>,
+    input_scaling: Vec<(NodeId, i64, i64)>,
+}
+
+impl Genome {
+    fn key(&self) -> GenomeKey {
+        let nodes = &self.node_list;
+        GenomeKey {
+            inputs: nodes.input.len(),
+            input_defaults: nodes
+                .input_defaults
+                .iter()
+                .map(|(id, value)| (*id, quantize(*value)))
+                .collect_vec(),
+            nodes: nodes
+                .output
+                .iter()
+                .chain(nodes.hidden.iter())
+                .map(|node| (node.node_id, node.level, config_key(&node.config)))
+                .collect_vec(),
+            edges: self
+                .genome_list
+                .iter()
+                .map(|e| (e.innov_number, e.in_node, e.out_node, quantize(e.weight), e.enabled))
+                .collect_vec(),
+            tied: self.weight_table.groups.iter().map(|(a, b)| (*a, *b)).collect_vec(),
+            tied_weights: self
+                .weight_table
+                .weights
+                .iter()
+                .map(|(group, weight)| (*group, quantize(*weight)))
+                .collect_vec(),
+            input_scaling: self
+                .input_scaling
+                .genes
+                .iter()
+                .map(|(id, gene)| (*id, quantize(gene.scale), quantize(gene.offset)))
+                .collect_vec(),
+        }
+    }
+}
+
+/// 64-bit FNV-1a over little-endian integers. Unlike the standard hasher
+/// it is fixed across Rust versions and platforms.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write_u64(&mut self, x: u64) {
+        for byte in x.to_le_bytes() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Genomes are equal when they have the same nodes, levels, node
+/// configurations, edges (innovation numbers, endpoints and enabled flags),
+/// tied weights and input scaling, with every parameter equal up to rounding
+/// to a multiple of [`PARAMETER_QUANTUM`]. Node names are not compared.
+/// Serialization round trips compare equal.
+impl PartialEq for Genome {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Genome {}
+
+impl Hash for Genome {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl Genome {
+    /// Whether `edge` feeds back, its source being at or past the level of
+    /// its target. Such edges read the previous pass of their source.
+    pub fn is_recurrent(&self, edge: &GenomeEdge) -> bool {
+        let level = |node_id| self.node_list.level(node_id);
+        match (level(edge.in_node), level(edge.out_node)) {
+            (Some(source), Some(target)) => source >= target,
+            _ => false,
+        }
+    }
+
+    /// Hash of the topology: hidden node ids and enabled connections,
+    /// ignoring weights, innovation numbers and node configuration. The
+    /// same on every platform and build, so it can be persisted.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        let hidden = &self.node_list.hidden;
+        hasher.write_u64(hidden.len() as u64);
+        for node in hidden.iter() {
+            hasher.write_u64(node.node_id);
+        }
+        let edges = self
+            .genome_list
+            .iter()
+            .filter(|edge| edge.enabled)
+            .map(|edge| (edge.in_node, edge.out_node))
+            .sorted()
+            .collect_vec();
+        hasher.write_u64(edges.len() as u64);
+        for (in_node, out_node) in edges {
+            hasher.write_u64(in_node);
+            hasher.write_u64(out_node);
+        }
+        hasher.0
+    }
+
+    /// Overwrite the clamps of the given nodes, e.g. with the result of
+    /// [`FFNetwork::calibrate_clamps`]. Unknown ids and inputs are skipped.
+    pub fn apply_clamps(&mut self, clamps: &BTreeMap<NodeId, Clamp>) {
+        for node in self
+            .node_list
+            .output
+            .iter_mut()
+            .chain(self.node_list.hidden.iter_mut())
+        {
+            if let Some(clamp) = clamps.get(&node.node_id) {
+                node.config.clamp = *clamp;
+            }
+        }
+    }
+
+    /// Greedily remove edges, weakest first, then hidden nodes, keeping each
+    /// removal whose fitness stays within `tolerance` of the original.
+    /// `eval` receives a freshly built network; clone it to run passes.
+    pub fn prune(&self, eval: impl Fn(&FFNetwork) -> f32, tolerance: f32) -> Genome {
+        let fitness = |genome: &Genome| eval(&FFNetwork::from(genome));
+        let target = fitness(self) - tolerance;
+        let mut pruned = self.clone();
+        pruned.genome_list.edge_list.retain(|edge| edge.enabled);
+        pruned.sync_weights();
+
+        let edges = pruned
+            .genome_list
+            .iter()
+            .sorted_by(|a, b| a.weight.abs().total_cmp(&b.weight.abs()))
+            .map(|edge| edge.innov_number)
+            .collect_vec();
+        for innov_number in edges {
+            let mut candidate = pruned.clone();
+            candidate
+                .genome_list
+                .edge_list
+                .retain(|edge| edge.innov_number != innov_number);
+            candidate.sync_weights();
+            if fitness(&candidate) >= target {
+                pruned = candidate;
+            }
+        }
+
+        let nodes = pruned.node_list.hidden.iter().map(|node| node.node_id).collect_vec();
+        for node_id in nodes {
+            let mut candidate = pruned.clone();
+            candidate.node_list.hidden.retain(|node| node.node_id != node_id);
+            candidate
+                .genome_list
+                .edge_list
+                .retain(|edge| edge.in_node != node_id && edge.out_node != node_id);
+            candidate.sync_weights();
+            if fitness(&candidate) >= target {
+                pruned = candidate;
+            }
+        }
+        pruned
+    }
+}
+
+/// Reassign hidden node levels to evenly spaced values between the input and
+/// output levels, preserving their order and ties. Splitting nodes averages
+/// levels, so denominators double with every nested split; this keeps them
+/// bounded by the number of distinct levels. The same mapping is applied to
+/// every genome so that shared nodes keep matching levels.
+pub fn renormalize_levels<'a>(genomes: impl IntoIterator<Item = &'a mut Genome>) {
+    let mut genomes = genomes.into_iter().collect_vec();
+    let levels = genomes
+        .iter()
+        .flat_map(|genome| genome.node_list.hidden.iter().map(|node| node.level))
+        .collect::<BTreeSet<_>>();
+    let slots = levels.len() + 1;
+    for genome in genomes.iter_mut() {
+        for node in genome.node_list.hidden.iter_mut() {
+            let rank = levels.range(..node.level).count() + 1;
+            node.level = Ratio::new(MIN_RATIO * slots + (MAX_RATIO - MIN_RATIO) * rank, slots);
+        }
+    }
+}
+
+/// Largest level denominator among the hidden nodes.
+pub fn max_level_denominator<'a>(genomes: impl IntoIterator<Item = &'a Genome>) -> usize {
+    genomes
+        .into_iter()
+        .flat_map(|genome| genome.node_list.hidden.iter().map(|node| *node.level.denom()))
+        .max()
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::individual::genome::node_list::NodeRole;
+
+    fn edge(innov_number: InnovationId, in_node: NodeId, out_node: NodeId, weight: f32) -> GenomeEdge {
+        GenomeEdge {
+            innov_number,
+            in_node,
+            out_node,
+            weight,
+            enabled: true,
+        }
+    }
+
+    /// Negative squared error of the single output against `2 * x0` for a
+    /// few positive inputs.
+    fn eval(network: &FFNetwork) -> f32 {
+        [0.5, 1., 2.]
+            .iter()
+            .map(|&x| {
+                let mut network = network.clone();
+                let out = network.forward(&[x, 1.]).unwrap()[0];
+                -(out - 2. * x) * (out - 2. * x)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_builder() {
+        // 2 inputs, 1 output, hidden node 3 computing x0 + x1
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .connect(0, 3, 1.)
+            .connect(1, 3, 1.)
+            .connect(3, 2, 2.)
+            .aggregation(3, Aggregation::Sum)
+            .activation(3, Activation::Identity)
+            .build()
+            .unwrap();
+        assert_eq!(
+            genome.node_list.hidden.iter().map(|n| n.node_id).collect_vec(),
+            vec![3]
+        );
+        assert_eq!(
+            genome.genome_list.iter().map(|e| e.innov_number).collect_vec(),
+            vec![0, 1, 2]
+        );
+        let hidden = genome.node_list.hidden[0].config;
+        assert_eq!(hidden.aggregation, Aggregation::Sum);
+        assert_eq!(hidden.activation, Activation::Identity);
+        let output = FFNetwork::<f32>::from(&genome).forward(&[0.5, 1.]).unwrap();
+        assert_relative_eq!(output[0], 3.);
+
+        assert_eq!(
+            GenomeBuilder::new().input(1).output(1).connect(0, 2, 1.).build().unwrap_err(),
+            GenonomeError::UnknownNode(2)
+        );
+        assert_eq!(
+            GenomeBuilder::new().input(1).build().unwrap_err(),
+            GenonomeError::ZeroIOVector
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            GenomeFactory::init(MAX_INPUTS + 1, 1).unwrap_err(),
+            GenonomeError::TooManyInputs(MAX_INPUTS + 1)
+        );
+        let builder = GenomeBuilder::new().input(1).output(1);
+        for level in [1, 100, 150] {
+            assert_eq!(
+                builder.clone().hidden(level).build().unwrap_err(),
+                GenonomeError::LevelCollision(2)
+            );
+        }
+
+        let mut genome = builder.hidden(50).connect(0, 2, 1.).build().unwrap();
+        assert_eq!(genome.validate(), Ok(()));
+        let node_list = genome.node_list.clone();
+        let edges = genome.genome_list.edge_list.clone();
+        assert!(Genome::new(node_list.clone(), edges.clone()).is_ok());
+        // A hidden node reusing an output id
+        genome.node_list.hidden[0].node_id = 1;
+        assert_eq!(genome.validate(), Err(GenonomeError::DuplicateNode(1)));
+        let mut dangling = edges.clone();
+        dangling[0].out_node = 7;
+        assert_eq!(
+            Genome::new(node_list, dangling).unwrap_err(),
+            GenonomeError::UnknownNode(7)
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let factory = GenomeFactory::init(2, 2)
+            .unwrap()
+            .with_input_names(["cart_position", "pole_angle"])
+            .with_output_names(["motor_left"]);
+        let genome = factory.generate_genome();
+        assert_eq!(genome.node_list.name(1), Some("pole_angle"));
+        assert_eq!(genome.node_list.role(1), NodeRole::Sensor);
+        assert_eq!(genome.node_list.role(3), NodeRole::Actuator);
+        assert_eq!(genome.node_list.role(4), NodeRole::Hidden);
+
+        let network = FFNetwork::<f32>::from(&genome);
+        assert_eq!(network.output_names(), vec![Some("motor_left"), None]);
+        assert_eq!(network.input_index("pole_angle"), Some(1));
+        assert_eq!(network.output_index("motor_right"), None);
+
+        let json = serde_json::to_string(&genome).unwrap();
+        let restored: Genome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.node_list.names, genome.node_list.names);
+
+        let built = GenomeBuilder::new().input(1).output(1).hidden(50).name(2, "memory");
+        assert_eq!(built.clone().build().unwrap().node_list.name(2), Some("memory"));
+        assert_eq!(
+            built.name(3, "missing").build().unwrap_err(),
+            GenonomeError::UnknownNode(3)
+        );
+    }
+
+    #[test]
+    fn test_optional_inputs() {
+        let factory = GenomeFactory::init(3, 1)
+            .unwrap()
+            .with_optional_input(1, 0.5);
+        let mut genome = factory.generate_genome();
+        genome.node_list.output[0].config.aggregation = Aggregation::Sum;
+        genome.genome_list =
+            OrderedGenomeList::new(vec![edge(0, 0, 3, 1.), edge(1, 1, 3, 2.), edge(2, 2, 3, 0.5)]);
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert_eq!(network.forward_masked(&[1., 2.], &[true, false, true]), Some(vec![3.]));
+        assert_eq!(network.forward_masked(&[1., 1., 2.], &[true; 3]), Some(vec![4.]));
+        // Required input missing, or too many values
+        assert_eq!(network.forward_masked(&[1., 2.], &[false, true, true]), None);
+        assert_eq!(network.forward_masked(&[1., 2., 3.], &[true, false, true]), None);
+        network.set_input_default(0, -1.);
+        assert_eq!(network.forward_masked(&[2.], &[false, false, true]), Some(vec![1.]));
+
+        let json = serde_json::to_string(&genome).unwrap();
+        let restored: Genome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.node_list.input_defaults, genome.node_list.input_defaults);
+    }
+
+    #[test]
+    fn test_tied_weights() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        use crate::{
+            crossover::crossover::{CrossoverMethod, Item, NeatCrossover},
+            mutation::{
+                innovation_number::InnovNumber,
+                mutation::{GaussianMutation, MutationMethod},
+            },
+        };
+
+        let untied = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .aggregation(2, Aggregation::Sum)
+            .connect(0, 2, 0.5)
+            .connect(1, 2, 2.)
+            .build()
+            .unwrap();
+        let mut tied = untied.clone();
+        assert_eq!(tied.tie_weights(0, [0, 7]), Err(GenonomeError::UnknownEdge(7)));
+        tied.tie_weights(0, [0, 1]).unwrap();
+        assert!(tied.genome_list.iter().all(|edge| edge.weight == 0.5));
+        tied.weight_table.weights.insert(0, 1.5);
+        let mut network = FFNetwork::<f32>::from(&tied);
+        assert_eq!(network.forward(&[1., 1.]), Some(vec![3.]));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(6);
+        let mut innov_number = InnovNumber::default();
+        let mutation = GaussianMutation::default();
+        let crossover = NeatCrossover::default();
+        for _ in 0..20 {
+            let mut child = crossover.crossover_method(
+                &mut rng,
+                &Item {
+                    item: Arc::new(tied.clone()),
+                    fitness: 2.,
+                },
+                &Item {
+                    item: Arc::new(untied.clone()),
+                    fitness: 1.,
+                },
+            );
+            assert_eq!(child.weight_table.groups.len(), 2);
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+            let weights = [0, 1].map(|innov| {
+                child.genome_list.iter().find(|e| e.innov_number == innov).unwrap().weight
+            });
+            assert_eq!(weights[0], weights[1]);
+        }
+
+        let json = serde_json::to_string(&tied).unwrap();
+        let restored: Genome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.weight_table, tied.weight_table);
+    }
+
+    #[test]
+    fn test_input_scaling() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        use crate::{
+            crossover::crossover::{CrossoverMethod, Item, NeatCrossover},
+            mutation::{
+                innovation_number::InnovNumber,
+                mutation::{GaussianMutation, MutationMethod},
+            },
+        };
+
+        let factory = GenomeFactory::init(2, 1).unwrap();
+        let mut plain = factory.generate_genome();
+        plain.node_list.output[0].config.aggregation = Aggregation::Sum;
+        plain.genome_list = OrderedGenomeList::new(vec![edge(0, 0, 2, 1.), edge(1, 1, 2, 1.)]);
+        assert!(plain.input_scaling.is_empty());
+        let mut scaled = plain.clone();
+        scaled.input_scaling = InputScaling::identity(&scaled.node_list.input);
+        let generated = factory.with_input_scaling().generate_genome();
+        assert_eq!(generated.input_scaling, scaled.input_scaling);
+        assert_ne!(plain, scaled);
+
+        let network = |genome: &Genome| FFNetwork::<f32>::from(genome);
+        assert_eq!(network(&scaled).forward(&[1., 2.]), network(&plain).forward(&[1., 2.]));
+        scaled.input_scaling.genes.insert(0, InputGene { scale: 0.5, offset: 1. });
+        assert_eq!(network(&scaled).forward(&[4., 2.]), Some(vec![5.]));
+        // Patched networks pick up the new genes
+        let recompiled = network(&plain).recompile(&plain, &scaled).forward(&[4., 2.]);
+        assert_eq!(recompiled, Some(vec![5.]));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(6);
+        let mut innov_number = InnovNumber::default();
+        let mutation = GaussianMutation::default().with_input_scaling(1., 0.1);
+        let crossover = NeatCrossover::default();
+        let item = |genome: &Genome, fitness| Item {
+            item: Arc::new(genome.clone()),
+            fitness,
+        };
+        let mut child =
+            crossover.crossover_method(&mut rng, &item(&plain, 2.), &item(&scaled, 1.));
+        assert_eq!(child.input_scaling, scaled.input_scaling);
+        mutation.mutate(&mut rng, &mut child, &mut innov_number);
+        assert!(child.input_scaling.genes.values().all(|gene| *gene != InputGene::default()));
+        let mut child = plain.clone();
+        mutation.mutate(&mut rng, &mut child, &mut innov_number);
+        assert!(child.input_scaling.is_empty());
+
+        let json = serde_json::to_string(&scaled).unwrap();
+        let restored: Genome = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.input_scaling, scaled.input_scaling);
+        // Genomes saved before input scaling existed
+        let mut value = serde_json::to_value(&scaled).unwrap();
+        value.as_object_mut().unwrap().remove("input_scaling");
+        let restored: Genome = serde_json::from_value(value).unwrap();
+        assert!(restored.input_scaling.is_empty());
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        let mut genome = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        genome.genome_list = OrderedGenomeList::new(vec![edge(0, 0, 2, 1.), edge(1, 1, 2, 1.)]);
+        let mut reweighted = genome.clone();
+        reweighted.genome_list.edge_list[0].weight = -3.;
+        reweighted.genome_list.edge_list[1].innov_number = 7;
+        assert_eq!(genome.structural_hash(), reweighted.structural_hash());
+
+        let mut disabled = genome.clone();
+        disabled.genome_list.edge_list[1].enabled = false;
+        assert_ne!(genome.structural_hash(), disabled.structural_hash());
+        // Persisted in hall of fame entries, so fixed across builds
+        assert_eq!(genome.structural_hash(), 9_186_722_847_017_644_902);
+    }
+
+    #[test]
+    fn test_equality() {
+        use std::collections::HashSet;
+
+        let mut genome = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        genome.genome_list = OrderedGenomeList::new(vec![edge(0, 0, 2, 0.1), edge(1, 1, 2, 1.)]);
+        let restored: Genome = serde_json::from_str(&serde_json::to_string(&genome).unwrap()).unwrap();
+        assert_eq!(restored, genome);
+
+        let mut close = genome.clone();
+        close.genome_list.edge_list[0].weight += PARAMETER_QUANTUM / 10.;
+        assert_eq!(close, genome);
+        let mut reweighted = genome.clone();
+        reweighted.genome_list.edge_list[0].weight += 10. * PARAMETER_QUANTUM;
+        assert_ne!(reweighted, genome);
+        let mut disabled = genome.clone();
+        disabled.genome_list.edge_list[1].enabled = false;
+        assert_ne!(disabled, genome);
+        let mut reactivated = genome.clone();
+        reactivated.node_list.output[0].config.activation = Activation::Softplus(2.);
+        assert_ne!(reactivated, genome);
+        let mut nan = genome.clone();
+        nan.genome_list.edge_list[0].weight = f32::NAN;
+        assert_eq!(nan, nan.clone());
+        assert_ne!(nan, genome);
+
+        let unique = [&genome, &restored, &close, &reweighted, &disabled]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(unique.len(), 3);
+    }
+
+    #[test]
+    fn test_renormalize_levels() {
+        let mut genome = GenomeFactory::init(1, 1).unwrap().generate_genome();
+        // Chain of nested splits towards the input: 1 < ... < 50.5 < 100
+        let mut level = Ratio::from_integer(MAX_RATIO);
+        let mut edges = vec![];
+        let mut prev = 1;
+        for id in 2..40 {
+            level = (Ratio::from_integer(MIN_RATIO) + level) / 2;
+            genome.node_list.hidden.push(Node::new(id, level, None));
+            edges.push(edge(id, id, prev, 1.));
+            prev = id;
+        }
+        edges.push(edge(0, 0, prev, 1.));
+        genome.genome_list = OrderedGenomeList::new(edges);
+        let mut other = genome.clone();
+        other.node_list.hidden.truncate(5);
+        assert!(max_level_denominator([&genome]) > 1 << 30);
+
+        let before = FFNetwork::<f64>::from(&genome).forward(&[0.7]);
+        renormalize_levels([&mut genome, &mut other]);
+        assert!(max_level_denominator([&genome, &other]) <= 39);
+        assert!(genome
+            .node_list
+            .hidden
+            .windows(2)
+            .all(|w| w[0].level > w[1].level));
+        assert!(other
+            .node_list
+            .hidden
+            .iter()
+            .zip(genome.node_list.hidden.iter())
+            .all(|(a, b)| a.level == b.level));
+        assert_eq!(FFNetwork::<f64>::from(&genome).forward(&[0.7]), before);
+    }
+
+    #[test]
+    fn test_prune() {
+        let sum = Config {
+            aggregation: Aggregation::Sum,
+            ..Default::default()
+        };
+        let mut genome = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        genome.node_list.output[0].config = sum;
+        genome
+            .node_list
+            .hidden
+            .push(Node::new(3, Ratio::from_integer(50), Some(sum)));
+        genome.genome_list = OrderedGenomeList::new(vec![
+            edge(0, 0, 2, 2.),
+            edge(1, 1, 2, 0.001),
+            edge(2, 1, 3, 0.5),
+            edge(3, 3, 2, 0.001),
+            GenomeEdge {
+                enabled: false,
+                ..edge(4, 0, 3, 1.)
+            },
+        ]);
+        genome.tie_weights(0, [1, 3]).unwrap();
+
+        let pruned = genome.prune(eval, 0.01);
+        assert_eq!(
+            pruned.genome_list.iter().map(|e| e.innov_number).collect_vec(),
+            vec![0]
+        );
+        assert!(pruned.node_list.hidden.is_empty());
+        // The groups of removed edges go with them
+        assert!(pruned.weight_table.is_empty());
+        assert_relative_eq!(
+            eval(&FFNetwork::from(&pruned)),
+            eval(&FFNetwork::from(&genome)),
+            epsilon = 0.01
+        );
+
+        // The vestigial edges only add error, but the main one is needed
+        let strict = genome.prune(eval, 0.);
+        assert_eq!(
+            strict.genome_list.iter().map(|e| e.innov_number).collect_vec(),
+            vec![0]
+        );
+        assert!(genome.prune(|_| 0., 0.).genome_list.edge_list.is_empty());
     }
 }
@@ -0,0 +1,123 @@
+//! Genomes as [`petgraph`] graphs, behind the `petgraph` feature, to run
+//! the graph algorithms of the ecosystem on evolved topologies, e.g.
+//! `petgraph::algo::tarjan_scc` to find the recurrent loops.
+
+use std::collections::BTreeMap;
+
+use num::rational::Ratio;
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use super::{
+    genome::{Genome, InnovationId},
+    node_list::{Config, NodeId, NodeRole},
+};
+
+/// Node of [`Genome::to_petgraph`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInfo {
+    pub node_id: NodeId,
+    pub role: NodeRole,
+    pub level: Ratio<usize>,
+    pub config: Config,
+    pub name: Option<String>,
+}
+
+/// Edge of [`Genome::to_petgraph`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeInfo {
+    pub innov_number: InnovationId,
+    /// Effective weight, the tied one for tied edges.
+    pub weight: f32,
+    pub enabled: bool,
+    /// See [`Genome::is_recurrent`].
+    pub recurrent: bool,
+}
+
+impl Genome {
+    /// Graph of the genome with a node per node of the genome and an edge
+    /// per edge, disabled ones included. Node indices follow the node ids:
+    /// inputs, outputs, then hidden nodes by id. Drop the disabled edges
+    /// with `retain_edges` to get the graph the network evaluates.
+    pub fn to_petgraph(&self) -> DiGraph<NodeInfo, EdgeInfo> {
+        let node_list = &self.node_list;
+        let nodes = node_list
+            .input
+            .iter()
+            .chain(node_list.output.iter())
+            .chain(node_list.hidden.iter());
+        let mut graph = DiGraph::with_capacity(
+            node_list.input.len() + node_list.output.len() + node_list.hidden.len(),
+            self.genome_list.edge_list.len(),
+        );
+        let mut indices = BTreeMap::new();
+        for node in nodes {
+            let index = graph.add_node(NodeInfo {
+                node_id: node.node_id,
+                role: node_list.role(node.node_id),
+                level: node.level,
+                config: node.config,
+                name: node_list.name(node.node_id).map(str::to_string),
+            });
+            indices.insert(node.node_id, index);
+        }
+        let index = |node_id: NodeId| -> NodeIndex { indices[&node_id] };
+        for edge in self.genome_list.iter() {
+            graph.add_edge(
+                index(edge.in_node),
+                index(edge.out_node),
+                EdgeInfo {
+                    innov_number: edge.innov_number,
+                    weight: self.weight(edge),
+                    enabled: edge.enabled,
+                    recurrent: self.is_recurrent(edge),
+                },
+            );
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::{algo, Direction};
+
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    #[test]
+    fn test_to_petgraph() {
+        let mut genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .name(0, "x")
+            .connect(0, 3, 0.5)
+            .connect(3, 2, -1.)
+            .connect(1, 2, 2.)
+            .connect(2, 3, 0.25)
+            .build()
+            .unwrap();
+        genome.genome_list.edge_list[2].enabled = false;
+        let mut graph = genome.to_petgraph();
+        assert_eq!((graph.node_count(), graph.edge_count()), (4, 4));
+        for (index, node) in graph.node_indices().zip(0..) {
+            assert_eq!(graph[index].node_id, node);
+        }
+        let x = NodeIndex::new(0);
+        assert_eq!(graph[x].name.as_deref(), Some("x"));
+        assert_eq!(graph[x].role, NodeRole::Sensor);
+        assert_eq!(graph[NodeIndex::new(2)].role, NodeRole::Actuator);
+        assert_eq!(graph[NodeIndex::new(3)].level, Ratio::from_integer(50));
+
+        let feedback = graph.edge_weights().filter(|edge| edge.recurrent).collect::<Vec<_>>();
+        assert_eq!(feedback.len(), 1);
+        assert_eq!(feedback[0].weight, 0.25);
+        // The hidden node and the output form the only loop
+        let loops = algo::tarjan_scc(&graph).into_iter().filter(|scc| scc.len() > 1).count();
+        assert_eq!(loops, 1);
+
+        graph.retain_edges(|graph, edge| graph[edge].enabled);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.neighbors_directed(NodeIndex::new(1), Direction::Outgoing).count(), 0);
+    }
+}
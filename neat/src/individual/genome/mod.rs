@@ -1,6 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod genome;
 pub mod node_list;
 pub mod network;
 pub mod clamp;
 pub mod aggregation;
-pub mod activation;
\ No newline at end of file
+pub mod activation;
+pub mod neat_python;
+pub mod dot;
+pub mod svg;
+#[cfg(feature = "petgraph")]
+pub mod graph;
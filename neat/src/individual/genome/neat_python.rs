@@ -0,0 +1,453 @@
+//! Genome exchange format mirroring NEAT-Python's `DefaultGenome`.
+//!
+//! ```json
+//! {
+//!   "key": 0,
+//!   "fitness": 1.5,
+//!   "num_inputs": 2,
+//!   "num_outputs": 1,
+//!   "nodes": [
+//!     { "key": 0, "bias": 0.0, "response": 1.0, "activation": "sigmoid", "aggregation": "sum" }
+//!   ],
+//!   "connections": [
+//!     { "key": [-1, 0], "innovation": 3, "weight": 0.5, "enabled": true }
+//!   ]
+//! }
+//! ```
+//!
+//! Keys follow NEAT-Python: inputs are `-1, -2, ...`, outputs `0..num_outputs`
+//! and hidden nodes any key above. Only output and hidden nodes have a node
+//! gene. Activation and aggregation use NEAT-Python's names where one
+//! exists, the functions themselves are this crate's, e.g. `sigmoid` is not
+//! scaled by 5. Optional fields carry what NEAT-Python has no notion of:
+//! `activation_param` (softplus beta, periodic period), `clamp` (unclamped
+//! when missing), `gate` (a gated memory cell, plain when missing), `level`
+//! (`[numerator, denominator]`, inferred from the topology when missing) and
+//! `innovation` (numbered in order when missing).
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use num::rational::Ratio;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    activation::Activation,
+    aggregation::Aggregation,
+    clamp::Clamp,
+    genome::{Genome, GenomeEdge, GenomeFactory, GenonomeError, InnovationId, OrderedGenomeList},
+    node_list::{Config, Gate, Node, NodeId},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeGene {
+    pub key: i64,
+    pub bias: f32,
+    pub response: f32,
+    pub activation: String,
+    pub aggregation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activation_param: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clamp: Option<Clamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gate: Option<Gate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionGene {
+    pub key: (i64, i64),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub innovation: Option<InnovationId>,
+    pub weight: f32,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeatPythonGenome {
+    pub key: usize,
+    #[serde(default)]
+    pub fitness: Option<f32>,
+    pub num_inputs: usize,
+    pub num_outputs: usize,
+    pub nodes: Vec<NodeGene>,
+    pub connections: Vec<ConnectionGene>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NeatPythonError {
+    Genome(GenonomeError),
+    UnknownActivation(String),
+    UnknownAggregation(String),
+    /// Node responses are not supported, the response must be 1.
+    Response(i64),
+    /// A connection endpoint without node gene, or an input node gene.
+    UnknownNode(i64),
+    /// A node level with a zero denominator.
+    Level(i64),
+}
+
+impl NeatPythonGenome {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Genome contains only plain data")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    fn id(&self, key: i64) -> NodeId {
+        if key < 0 {
+            (-key - 1) as NodeId
+        } else {
+            key as NodeId + self.num_inputs as NodeId
+        }
+    }
+}
+
+fn key(genome: &Genome, node_id: NodeId) -> i64 {
+    let inputs = genome.node_list.input.len() as NodeId;
+    if node_id < inputs {
+        -(node_id as i64) - 1
+    } else {
+        (node_id - inputs) as i64
+    }
+}
+
+fn activation_name(activation: Activation) -> (&'static str, Option<f32>) {
+    let param = match activation {
+        Activation::Softplus(param) | Activation::Periodic(param) | Activation::NoisyRelu(param) => {
+            Some(param)
+        }
+        _ => None,
+    };
+    (activation.name(), param)
+}
+
+fn parse_activation(name: &str, param: Option<f32>) -> Result<Activation, NeatPythonError> {
+    let activation = Activation::from_name(name)
+        .ok_or_else(|| NeatPythonError::UnknownActivation(name.to_string()))?;
+    Ok(match activation {
+        Activation::Softplus(_) => Activation::Softplus(param.unwrap_or(1.)),
+        Activation::Periodic(_) => Activation::Periodic(param.unwrap_or(1.)),
+        Activation::NoisyRelu(_) => Activation::NoisyRelu(param.unwrap_or(1.)),
+        activation => activation,
+    })
+}
+
+fn aggregation_name(aggregation: Aggregation) -> &'static str {
+    match aggregation {
+        Aggregation::Sum => "sum",
+        Aggregation::Max => "max",
+        Aggregation::Mean => "mean",
+        Aggregation::L1NormAvg => "l1_norm_avg",
+        Aggregation::L2NormAvg => "l2_norm_avg",
+    }
+}
+
+fn parse_aggregation(name: &str) -> Result<Aggregation, NeatPythonError> {
+    Ok(match name {
+        "sum" => Aggregation::Sum,
+        "max" => Aggregation::Max,
+        "mean" => Aggregation::Mean,
+        "l1_norm_avg" => Aggregation::L1NormAvg,
+        "l2_norm_avg" => Aggregation::L2NormAvg,
+        _ => return Err(NeatPythonError::UnknownAggregation(name.to_string())),
+    })
+}
+
+impl From<&Genome> for NeatPythonGenome {
+    fn from(genome: &Genome) -> Self {
+        let node_list = &genome.node_list;
+        let nodes = node_list
+            .output
+            .iter()
+            .chain(node_list.hidden.iter())
+            .map(|node| {
+                let (activation, activation_param) = activation_name(node.config.activation);
+                NodeGene {
+                    key: key(genome, node.node_id),
+                    bias: node.config.bias,
+                    response: 1.,
+                    activation: activation.to_string(),
+                    aggregation: aggregation_name(node.config.aggregation).to_string(),
+                    activation_param,
+                    clamp: Some(node.config.clamp),
+                    gate: node.config.gate,
+                    level: Some((*node.level.numer(), *node.level.denom())),
+                }
+            })
+            .collect_vec();
+        let connections = genome
+            .genome_list
+            .iter()
+            .map(|edge| ConnectionGene {
+                key: (key(genome, edge.in_node), key(genome, edge.out_node)),
+                innovation: Some(edge.innov_number),
+                weight: edge.weight,
+                enabled: edge.enabled,
+            })
+            .collect_vec();
+        Self {
+            key: 0,
+            fitness: None,
+            num_inputs: node_list.input.len(),
+            num_outputs: node_list.output.len(),
+            nodes,
+            connections,
+        }
+    }
+}
+
+impl TryFrom<&NeatPythonGenome> for Genome {
+    type Error = NeatPythonError;
+
+    fn try_from(source: &NeatPythonGenome) -> Result<Self, Self::Error> {
+        let mut genome = GenomeFactory::init(source.num_inputs, source.num_outputs)
+            .map_err(NeatPythonError::Genome)?
+            .generate_genome();
+        let mut hidden = BTreeMap::new();
+        for gene in source.nodes.iter() {
+            if gene.key < 0 {
+                return Err(NeatPythonError::UnknownNode(gene.key));
+            }
+            if gene.response != 1. {
+                return Err(NeatPythonError::Response(gene.key));
+            }
+            let config = Config {
+                activation: parse_activation(&gene.activation, gene.activation_param)?,
+                aggregation: parse_aggregation(&gene.aggregation)?,
+                clamp: gene.clamp.unwrap_or(Clamp {
+                    min_limit: None,
+                    max_limit: None,
+                }),
+                bias: gene.bias,
+                gate: gene.gate,
+            };
+            match genome.node_list.output.get_mut(gene.key as usize) {
+                Some(output) => output.config = config,
+                None => {
+                    if gene.level.is_some_and(|(_, denom)| denom == 0) {
+                        return Err(NeatPythonError::Level(gene.key));
+                    }
+                    let level = gene.level.map(|(numer, denom)| Ratio::new(numer, denom));
+                    hidden.insert(source.id(gene.key), (config, level));
+                }
+            }
+        }
+        let edges = source
+            .connections
+            .iter()
+            .zip(0..)
+            .map(|(gene, index)| {
+                let (in_key, out_key) = gene.key;
+                let known = |key: i64| {
+                    if key < 0 {
+                        key.unsigned_abs() <= source.num_inputs as u64
+                    } else {
+                        key < source.num_outputs as i64 || hidden.contains_key(&source.id(key))
+                    }
+                };
+                match [in_key, out_key].into_iter().find(|key| !known(*key)) {
+                    Some(key) => Err(NeatPythonError::UnknownNode(key)),
+                    None => Ok(GenomeEdge {
+                        innov_number: gene.innovation.unwrap_or(index),
+                        in_node: source.id(in_key),
+                        out_node: source.id(out_key),
+                        weight: gene.weight,
+                        enabled: gene.enabled,
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let hidden_ids = hidden.keys().copied().collect_vec();
+        let inferred = infer_levels(source.num_inputs, &hidden_ids, &edges);
+        genome.node_list.hidden = hidden
+            .into_iter()
+            .map(|(id, (config, level))| {
+                Node::new(id, level.unwrap_or(inferred[&id]), Some(config))
+            })
+            .collect_vec();
+        genome.genome_list = OrderedGenomeList::new(edges);
+        genome.validate().map_err(NeatPythonError::Genome)?;
+        Ok(genome)
+    }
+}
+
+/// Levels of hidden nodes spread by their longest path from the inputs.
+/// Edges closing a cycle, in depth-first order from the inputs, are left out
+/// and become recurrent.
+fn infer_levels(
+    inputs: usize,
+    hidden: &[NodeId],
+    edges: &[GenomeEdge],
+) -> BTreeMap<NodeId, Ratio<usize>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        node: NodeId,
+        children: &BTreeMap<NodeId, Vec<NodeId>>,
+        states: &mut BTreeMap<NodeId, State>,
+        forward: &mut Vec<(NodeId, NodeId)>,
+    ) {
+        states.insert(node, State::Visiting);
+        for child in children.get(&node).into_iter().flatten().copied() {
+            match states.get(&child) {
+                Some(State::Visiting) => {}
+                Some(State::Done) => forward.push((node, child)),
+                None => {
+                    forward.push((node, child));
+                    visit(child, children, states, forward);
+                }
+            }
+        }
+        states.insert(node, State::Done);
+    }
+
+    let inputs = inputs as NodeId;
+    let mut children: BTreeMap<NodeId, Vec<NodeId>> = BTreeMap::new();
+    for edge in edges.iter().filter(|edge| hidden.contains(&edge.out_node)) {
+        if edge.in_node < inputs || hidden.contains(&edge.in_node) {
+            children
+                .entry(edge.in_node)
+                .or_default()
+                .push(edge.out_node);
+        }
+    }
+    let (mut states, mut forward) = (BTreeMap::new(), vec![]);
+    for node in (0..inputs).chain(hidden.iter().copied()) {
+        if !states.contains_key(&node) {
+            visit(node, &children, &mut states, &mut forward);
+        }
+    }
+    // Longest path, relaxing the acyclic forward edges until stable
+    let mut depths: BTreeMap<NodeId, usize> = hidden.iter().map(|node| (*node, 1)).collect();
+    for _ in 0..hidden.len() {
+        for (parent, child) in forward.iter() {
+            let depth = depths.get(parent).map_or(1, |depth| depth + 1);
+            if depths[child] < depth {
+                depths.insert(*child, depth);
+            }
+        }
+    }
+    let steps = depths.values().max().map_or(1, |max| max + 1);
+    depths
+        .into_iter()
+        .map(|(node, depth)| (node, Ratio::new(steps + 99 * depth, steps)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{genome::GenomeBuilder, network::network::FFNetwork};
+
+    #[test]
+    fn test_round_trip() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(Ratio::new(101, 2))
+            .connect(0, 3, 0.5)
+            .connect(1, 3, -1.)
+            .connect(3, 2, 2.)
+            .connect(2, 3, 0.25)
+            .activation(3, Activation::Softplus(2.))
+            .aggregation(2, Aggregation::Sum)
+            .build()
+            .unwrap();
+        let exported = NeatPythonGenome::from(&genome);
+        assert_eq!(
+            exported.nodes.iter().map(|n| n.key).collect_vec(),
+            vec![0, 1]
+        );
+        assert_eq!(exported.connections[0].key, (-1, 1));
+        let restored =
+            Genome::try_from(&NeatPythonGenome::from_json(&exported.to_json()).unwrap()).unwrap();
+        assert_eq!(NeatPythonGenome::from(&restored), exported);
+        let mut fst = FFNetwork::<f32>::from(&genome);
+        let mut snd = FFNetwork::<f32>::from(&restored);
+        for input in [[1., 2.], [0.5, -1.]] {
+            assert_eq!(fst.forward(&input), snd.forward(&input));
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let json = r#"{
+            "key": 7,
+            "fitness": 3.5,
+            "num_inputs": 1,
+            "num_outputs": 1,
+            "nodes": [
+                { "key": 0, "bias": 0.0, "response": 1.0, "activation": "identity", "aggregation": "sum" },
+                { "key": 4, "bias": 0.0, "response": 1.0, "activation": "relu", "aggregation": "sum" },
+                { "key": 9, "bias": 0.0, "response": 1.0, "activation": "relu", "aggregation": "sum" }
+            ],
+            "connections": [
+                { "key": [-1, 4], "weight": 1.0, "enabled": true },
+                { "key": [4, 9], "weight": 1.0, "enabled": true },
+                { "key": [9, 0], "weight": 3.0, "enabled": true },
+                { "key": [9, 4], "weight": 1.0, "enabled": false }
+            ]
+        }"#;
+        let source = NeatPythonGenome::from_json(json).unwrap();
+        assert_eq!((source.key, source.fitness), (7, Some(3.5)));
+        let genome = Genome::try_from(&source).unwrap();
+        let hidden = &genome.node_list.hidden;
+        assert_eq!(hidden.iter().map(|n| n.node_id).collect_vec(), vec![5, 10]);
+        assert!(hidden[0].level < hidden[1].level);
+        assert_eq!(
+            genome
+                .genome_list
+                .iter()
+                .map(|e| e.innov_number)
+                .collect_vec(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            FFNetwork::<f32>::from(&genome).forward(&[2.]),
+            Some(vec![6.])
+        );
+
+        let mut biased = source.clone();
+        biased.nodes[1].bias = 0.5;
+        let genome = Genome::try_from(&biased).unwrap();
+        assert_eq!(genome.node_list.hidden[0].config.bias, 0.5);
+        assert_eq!(NeatPythonGenome::from(&genome).nodes[1].bias, 0.5);
+        assert_eq!(
+            FFNetwork::<f32>::from(&genome).forward(&[2.]),
+            Some(vec![7.5])
+        );
+        let mut unknown = source.clone();
+        unknown.connections[0].key = (-1, 5);
+        assert_eq!(
+            Genome::try_from(&unknown).unwrap_err(),
+            NeatPythonError::UnknownNode(5)
+        );
+        // The second input of a genome with one
+        unknown.connections[0].key = (-2, 0);
+        assert_eq!(
+            Genome::try_from(&unknown).unwrap_err(),
+            NeatPythonError::UnknownNode(-2)
+        );
+        let mut level = source.clone();
+        level.nodes[1].level = Some((1, 0));
+        assert_eq!(
+            Genome::try_from(&level).unwrap_err(),
+            NeatPythonError::Level(4)
+        );
+        let mut clamped = source;
+        clamped.nodes[0].activation = "clamped".to_string();
+        assert_eq!(
+            Genome::try_from(&clamped).unwrap_err(),
+            NeatPythonError::UnknownActivation("clamped".to_string())
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Forward passes on [`ndarray`] arrays, behind the `ndarray` feature, so
+//! samples go in and out of array pipelines without a `Vec` per sample.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use num::Float;
+
+use super::network::FFNetwork;
+
+impl<F: Float> FFNetwork<F> {
+    /// [`FFNetwork::forward`] on an array. Contiguous inputs are read in
+    /// place, others copied first. `None` if the length is not the input
+    /// length.
+    pub fn forward_ndarray(&mut self, input: ArrayView1<F>) -> Option<Array1<F>> {
+        let outputs = match input.as_slice() {
+            Some(input) => self.forward(input),
+            None => self.forward(&input.to_vec()),
+        }?;
+        Some(Array1::from_vec(outputs))
+    }
+
+    /// Outputs of every row of `inputs`, one row per sample. Rows are
+    /// evaluated in order, so a recurrent network carries its state from one
+    /// row to the next as in a sequence. `None` if the rows are not of the
+    /// input length.
+    pub fn forward_batch_ndarray(&mut self, inputs: ArrayView2<F>) -> Option<Array2<F>> {
+        if inputs.ncols() != self.input_len() {
+            return None;
+        }
+        let mut outputs = Array2::zeros((inputs.nrows(), self.output_len()));
+        for (input, mut output) in inputs.rows().into_iter().zip(outputs.rows_mut()) {
+            output.assign(&self.forward_ndarray(input)?);
+        }
+        Some(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, s, Array2};
+
+    use crate::individual::genome::{
+        activation::Activation,
+        genome::GenomeBuilder,
+        network::network::FFNetwork,
+    };
+
+    fn network() -> FFNetwork<f32> {
+        // The output feeds back into itself
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 1.)
+            .connect(1, 2, -0.5)
+            .connect(2, 2, 0.5)
+            .activation(2, Activation::Tanh)
+            .build()
+            .unwrap();
+        FFNetwork::from(&genome)
+    }
+
+    #[test]
+    fn test_forward_ndarray() {
+        let inputs = array![[0.5f32, 1.], [-1., 2.], [0.3, 0.3]];
+        let mut expected = network();
+        let expected = inputs
+            .rows()
+            .into_iter()
+            .flat_map(|row| expected.forward(&row.to_vec()).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut network = network();
+        let outputs = network.forward_batch_ndarray(inputs.view()).unwrap();
+        assert_eq!(outputs.shape(), [3, 1]);
+        assert_eq!(outputs.iter().copied().collect::<Vec<_>>(), expected);
+        // Columns of the transpose are not contiguous
+        let transposed = inputs.t().as_standard_layout().into_owned();
+        network.reset();
+        for (column, expected) in transposed.columns().into_iter().zip(expected.iter()) {
+            assert!(column.as_slice().is_none());
+            assert_eq!(network.forward_ndarray(column).unwrap(), array![*expected]);
+        }
+        assert_eq!(network.forward_ndarray(inputs.slice(s![0, ..1])), None);
+        assert_eq!(network.forward_batch_ndarray(Array2::zeros((2, 3)).view()), None);
+        let empty = network.forward_batch_ndarray(Array2::zeros((0, 2)).view());
+        assert_eq!(empty.unwrap().shape(), [0, 1]);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use num::Float;
+
+/// Lookups of an [`OutputCache`] since it was created or cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Cached input vectors.
+    pub len: usize,
+}
+
+/// Outputs of a feed-forward network by quantized input vector, holding at
+/// most `capacity` of them and evicting the least recently used. Inputs
+/// closer than `resolution` may share an entry. See
+/// `FFNetwork::enable_cache`.
+#[derive(Debug, Clone)]
+pub struct OutputCache<F> {
+    capacity: usize,
+    resolution: f64,
+    /// Outputs and last use of every key.
+    entries: BTreeMap<Vec<i64>, (Vec<F>, u64)>,
+    /// Key of every last use.
+    recency: BTreeMap<u64, Vec<i64>>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl<F: Float> OutputCache<F> {
+    pub fn new(capacity: usize, resolution: f64) -> Self {
+        assert!(capacity > 0, "The cache needs room for an entry");
+        assert!(resolution > 0., "The resolution must be positive");
+        Self {
+            capacity,
+            resolution,
+            entries: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Key of `input`, every value rounded to a multiple of the resolution.
+    /// NaN inputs share a key.
+    pub fn key(&self, input: &[F]) -> Vec<i64> {
+        input
+            .iter()
+            .map(|x| match x.to_f64() {
+                Some(x) if !x.is_nan() => (x / self.resolution).round() as i64,
+                _ => i64::MIN,
+            })
+            .collect()
+    }
+
+    /// Cached outputs of `key`, now the most recently used.
+    pub fn get(&mut self, key: &[i64]) -> Option<Vec<F>> {
+        let Some((outputs, last_use)) = self.entries.get_mut(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.tick += 1;
+        let key = self
+            .recency
+            .remove(last_use)
+            .expect("Entries have a last use");
+        self.recency.insert(self.tick, key);
+        *last_use = self.tick;
+        self.stats.hits += 1;
+        Some(outputs.clone())
+    }
+
+    pub fn insert(&mut self, key: Vec<i64>, outputs: Vec<F>) {
+        self.tick += 1;
+        if let Some((_, last_use)) = self.entries.insert(key.clone(), (outputs, self.tick)) {
+            self.recency.remove(&last_use);
+        }
+        self.recency.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let (_, oldest) = self.recency.pop_first().expect("Entries have a last use");
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Forget the entries and the statistics.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.capacity, self.resolution);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, genome::GenomeBuilder, network::network::FFNetwork,
+    };
+
+    #[test]
+    fn test_lru() {
+        let mut cache = OutputCache::<f32>::new(2, 0.1);
+        assert_eq!(cache.key(&[0.12, -0.26, f32::NAN]), vec![1, -3, i64::MIN]);
+        let (a, b, c) = (cache.key(&[0.]), cache.key(&[1.]), cache.key(&[2.]));
+        cache.insert(a.clone(), vec![0.]);
+        cache.insert(b.clone(), vec![1.]);
+        assert_eq!(cache.get(&a), Some(vec![0.]));
+        // `b` is now the least recently used
+        cache.insert(c.clone(), vec![2.]);
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&c), Some(vec![2.]));
+        assert_eq!(cache.get(&a), Some(vec![0.]));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                len: 2,
+            }
+        );
+        cache.clear();
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_network_cache() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .activation(2, Activation::Tanh)
+            .connect(0, 3, 0.5)
+            .connect(3, 2, 2.)
+            .connect(1, 2, -1.)
+            .build()
+            .unwrap();
+        let mut uncached = FFNetwork::<f32>::from(&genome);
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert!(network.enable_cache(16, 1e-6));
+        for input in [[0.5, 1.], [0.25, 0.], [0.5, 1.], [0.5, 1.]] {
+            assert_eq!(network.forward(&input), uncached.forward(&input));
+        }
+        let stats = network.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.len), (2, 2, 2));
+        // Dropout passes bypass the cache
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        network.forward_with_dropout(&mut rng, 1., &[0.5, 1.]);
+        assert_eq!(network.cache_stats().unwrap().hits, 2);
+
+        let recurrent = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(0, 1, 1.)
+            .connect(1, 1, 0.5)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&recurrent);
+        assert!(!network.enable_cache(16, 1e-6));
+        assert_eq!(network.cache_stats(), None);
+    }
+}
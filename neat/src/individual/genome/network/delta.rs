@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use num::Float;
+
+use super::{
+    feed_forward::FeedForward,
+    mem_cell::MemoryCellType,
+    network::{get_mem_location, Edge, FFNetwork},
+};
+use crate::individual::genome::{
+    genome::{Genome, InputScaling},
+    node_list::{to_float, Node, NodeId, NodeNames},
+};
+
+/// Enabled connection by endpoints, with its effective weight.
+pub type Connection = (NodeId, NodeId, f32);
+
+/// Difference between a parent genome and a child of it, enough to patch the
+/// network compiled from the parent into the one of the child, see
+/// [`FFNetwork::apply_delta`].
+#[derive(Debug, Clone, Default)]
+pub struct GenomeDelta {
+    /// Connections of both whose weight changed, with the parent's and the
+    /// child's weight.
+    pub weights: Vec<(NodeId, NodeId, f32, f32)>,
+    /// Connections only the child has enabled.
+    pub added_edges: Vec<Connection>,
+    /// Connections only the parent has enabled.
+    pub removed_edges: Vec<Connection>,
+    /// Non-input nodes only the child has, or whose level or config changed.
+    pub nodes: Vec<Node>,
+    /// Ids of the hidden nodes only the parent has.
+    pub removed_nodes: Vec<NodeId>,
+    /// Input scaling of the child, if it differs from the parent's.
+    pub input_scaling: Option<InputScaling>,
+    names: NodeNames,
+}
+
+fn same_bits(a: f32, b: f32) -> bool {
+    a.to_bits() == b.to_bits()
+}
+
+impl GenomeDelta {
+    /// `None` if the inputs, their defaults or the outputs differ, which
+    /// needs a full compilation.
+    pub fn between(parent: &Genome, child: &Genome) -> Option<Self> {
+        let (old, new) = (&parent.node_list, &child.node_list);
+        let levels = |nodes: &[Node]| nodes.iter().map(|n| (n.node_id, n.level)).collect_vec();
+        let ids = |nodes: &[Node]| nodes.iter().map(|n| n.node_id).collect_vec();
+        if levels(&old.input) != levels(&new.input)
+            || old.input_defaults != new.input_defaults
+            || ids(&old.output) != ids(&new.output)
+        {
+            return None;
+        }
+        let mut delta = Self {
+            names: new.names.clone(),
+            input_scaling: (parent.input_scaling != child.input_scaling)
+                .then(|| child.input_scaling.clone()),
+            ..Default::default()
+        };
+        let before = old
+            .output
+            .iter()
+            .chain(old.hidden.iter())
+            .map(|node| (node.node_id, node))
+            .collect::<BTreeMap<_, _>>();
+        // Nodes whose level changed, their edges may change direction
+        let mut moved = BTreeSet::new();
+        for node in new.output.iter().chain(new.hidden.iter()) {
+            match before.get(&node.node_id) {
+                Some(prev) if prev.level == node.level && prev.config == node.config => {}
+                Some(prev) => {
+                    if prev.level != node.level {
+                        moved.insert(node.node_id);
+                    }
+                    delta.nodes.push(*node);
+                }
+                None => delta.nodes.push(*node),
+            }
+        }
+        let hidden = new
+            .hidden
+            .iter()
+            .map(|node| node.node_id)
+            .collect::<BTreeSet<_>>();
+        delta.removed_nodes = old
+            .hidden
+            .iter()
+            .map(|node| node.node_id)
+            .filter(|id| !hidden.contains(id))
+            .collect();
+
+        let connections = |genome: &Genome| {
+            genome
+                .genome_list
+                .iter()
+                .filter(|edge| edge.enabled)
+                .map(|edge| {
+                    (
+                        edge.innov_number,
+                        (edge.in_node, edge.out_node, genome.weight(edge)),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (before, mut after) = (connections(parent), connections(child));
+        for (innov_number, (in_node, out_node, weight)) in before {
+            let still = after.remove(&innov_number);
+            match still {
+                Some((i, o, w))
+                    if (i, o) == (in_node, out_node)
+                        && !moved.contains(&in_node)
+                        && !moved.contains(&out_node) =>
+                {
+                    if !same_bits(weight, w) {
+                        delta.weights.push((in_node, out_node, weight, w));
+                    }
+                }
+                _ => {
+                    delta.removed_edges.push((in_node, out_node, weight));
+                    delta.added_edges.extend(still);
+                }
+            }
+        }
+        delta.added_edges.extend(after.into_values());
+        Some(delta)
+    }
+
+    /// Whether the networks of the parent and the child are the same.
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.input_scaling.is_none()
+    }
+}
+
+impl<F: Float> FFNetwork<F> {
+    /// Network of `child`, patched from this network of `parent` when
+    /// possible and compiled from scratch otherwise.
+    pub fn recompile(&self, parent: &Genome, child: &Genome) -> Self {
+        match GenomeDelta::between(parent, child) {
+            Some(delta) => {
+                let mut network = self.clone();
+                network.apply_delta(&delta);
+                network
+            }
+            None => Self::from(child),
+        }
+    }
+
+    /// Patch the network compiled from the parent of `delta` into the one
+    /// compiled from the child, in its fresh state. Panics if the network
+    /// does not match the parent.
+    pub fn apply_delta(&mut self, delta: &GenomeDelta) {
+        let inputs = self.lengths.input;
+        for (in_node, out_node, weight) in delta.removed_edges.iter().copied() {
+            let (edges, position) = self.find_edge(in_node, out_node, weight);
+            edges.remove(position);
+        }
+        for id in delta.removed_nodes.iter() {
+            let index = get_mem_location(&self.memory, *id);
+            self.memory.remove(index);
+            self.edge_map.remove(index);
+            self.back_map.remove(index - inputs);
+            self.lengths.hidden -= 1;
+        }
+        for node in delta.nodes.iter() {
+            let cell = MemoryCellType::activated(*node);
+            match self
+                .memory
+                .binary_search_by_key(&node.node_id, |c| c.get_node().node_id)
+            {
+                Ok(index) => self.memory[index] = cell,
+                Err(index) => {
+                    self.memory.insert(index, cell);
+                    self.edge_map.insert(index, vec![]);
+                    self.back_map.insert(index - inputs, vec![]);
+                    self.lengths.hidden += 1;
+                }
+            }
+        }
+        for (in_node, out_node, before, after) in delta.weights.iter().copied() {
+            let (edges, position) = self.find_edge(in_node, out_node, before);
+            edges[position].weight = to_float(after);
+        }
+        for (in_node, out_node, weight) in delta.added_edges.iter().copied() {
+            let (edges, dest) = self.edges_of(in_node, out_node);
+            edges.push(Edge {
+                dest,
+                weight: to_float(weight),
+            });
+        }
+        if let Some(scaling) = delta.input_scaling.as_ref() {
+            self.set_input_scaling(scaling);
+        }
+        self.names = delta.names.clone();
+        self.feed_forward =
+            FeedForward::new(&self.memory, &self.edge_map, &self.back_map, inputs);
+        self.clear_dropout();
+        self.clear_saturation_stats();
+        self.reset();
+        if self.cache.is_some() {
+            let recurrent = self.back_map.iter().any(|edges| !edges.is_empty());
+            self.cache = self.cache.take().filter(|_| !recurrent).map(|mut cache| {
+                cache.clear();
+                cache
+            });
+        }
+    }
+
+    /// List `in_node -> out_node` is stored in, and the node it points to.
+    fn edges_of(&mut self, in_node: NodeId, out_node: NodeId) -> (&mut Vec<Edge<F>>, NodeId) {
+        let in_index = get_mem_location(&self.memory, in_node);
+        let out_index = get_mem_location(&self.memory, out_node);
+        if self.memory[in_index].get_node().level >= self.memory[out_index].get_node().level {
+            (&mut self.back_map[out_index - self.lengths.input], in_node)
+        } else {
+            (&mut self.edge_map[in_index], out_node)
+        }
+    }
+
+    fn find_edge(
+        &mut self,
+        in_node: NodeId,
+        out_node: NodeId,
+        weight: f32,
+    ) -> (&mut Vec<Edge<F>>, usize) {
+        let weight = to_float::<F>(weight);
+        let (edges, dest) = self.edges_of(in_node, out_node);
+        let position = edges
+            .iter()
+            .position(|edge| {
+                edge.dest == dest
+                    && (edge.weight == weight || (edge.weight.is_nan() && weight.is_nan()))
+            })
+            .expect("The network should have the edges of the parent");
+        (edges, position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation,
+        genome::{GenomeBuilder, GenomeFactory},
+    };
+
+    fn assert_same_outputs(patched: &mut FFNetwork, child: &Genome) {
+        let mut compiled = FFNetwork::from(child);
+        for input in [[0.5, -1.], [1., 2.], [-0.3, 0.7]] {
+            assert_eq!(patched.forward(&input), compiled.forward(&input));
+        }
+    }
+
+    #[test]
+    fn test_recompile() {
+        let parent = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .hidden(60)
+            .activation(3, Activation::Tanh)
+            .activation(4, Activation::Sigmoid)
+            .connect(0, 3, 0.5)
+            .connect(3, 2, 1.5)
+            .connect(1, 4, -1.)
+            .connect(4, 2, 0.8)
+            .connect(0, 2, 0.2)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&parent);
+        network.forward(&[1., 1.]);
+
+        // Weight change, removed node, new node splitting an edge, a
+        // recurrent edge and a new output activation
+        let child = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .hidden(70)
+            .activation(3, Activation::Tanh)
+            .activation(4, Activation::Relu)
+            .activation(2, Activation::Gauss)
+            .connect(0, 3, -0.25)
+            .connect(3, 2, 1.5)
+            .connect(0, 4, 0.3)
+            .connect(4, 2, 0.6)
+            .connect(2, 3, 0.4)
+            .connect(0, 2, 0.2)
+            .build()
+            .unwrap();
+        let delta = GenomeDelta::between(&parent, &child).unwrap();
+        assert_eq!(delta.weights, vec![(0, 3, 0.5, -0.25)]);
+        assert!(delta.removed_nodes.is_empty());
+        assert_eq!(delta.nodes.len(), 2);
+        let mut patched = network.recompile(&parent, &child);
+        assert_same_outputs(&mut patched, &child);
+
+        let mut pruned = child.clone();
+        pruned.node_list.hidden.retain(|node| node.node_id != 4);
+        pruned
+            .genome_list
+            .edge_list
+            .retain(|edge| ![edge.in_node, edge.out_node].contains(&4));
+        let delta = GenomeDelta::between(&child, &pruned).unwrap();
+        assert_eq!(delta.removed_nodes, vec![4]);
+        let mut patched = patched.recompile(&child, &pruned);
+        assert_same_outputs(&mut patched, &pruned);
+        assert!(GenomeDelta::between(&pruned, &pruned).unwrap().is_empty());
+
+        // Other inputs need a full compilation
+        let other = GenomeFactory::init(3, 1).unwrap().generate_genome();
+        assert!(GenomeDelta::between(&parent, &other).is_none());
+        assert_eq!(network.recompile(&parent, &other).input_len(), 3);
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    fmt::Write,
+};
+
+use num::Float;
+
+use super::network::{get_mem_location, FFNetwork};
+use crate::individual::genome::{
+    activation::Activation,
+    aggregation::Aggregation,
+    node_list::{to_float, LevelNode},
+};
+
+/// Helpers of the generated function, indexed by [`activation_kind`] and
+/// [`aggregation_kind`]. GELU uses an approximation of erf (Abramowitz and
+/// Stegun 7.1.26) whose absolute error is below 1.5e-7.
+const HELPERS: &str = "    fn activate(kind: u8, param: f32, x: f32) -> f32 {
+        match kind {
+            0 => x.abs(),
+            1 => x.min(5.0).exp(),
+            2 => (-(x * x)).exp(),
+            3 => (1.0 - x.abs()).max(0.0),
+            4 => x,
+            5 => (x * x + 1.0).sqrt().recip(),
+            6 => x.abs().ln_1p(),
+            7 => x.max(0.0),
+            8 => {
+                let (lambda, alpha) = (1.0507009873554805, 1.6732632423543772);
+                if x >= 0.0 {
+                    lambda * x
+                } else {
+                    lambda * alpha * (x.exp() - 1.0)
+                }
+            }
+            9 => (1.0 + (-x).exp()).recip(),
+            10 => x.sin(),
+            11 => x.cos(),
+            12 => x.tanh(),
+            13 => param.recip() * (-(param * x).abs()).exp().ln_1p(),
+            14 => {
+                let z = x as f64 / 2.0f64.sqrt();
+                let t = 1.0 / (1.0 + 0.3275911 * z.abs());
+                let poly = t * (0.254829592
+                    + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+                let erf = (1.0 - poly * (-z * z).exp()).copysign(z);
+                (erf as f32 + 1.0) * 0.5 * x
+            }
+            15 => (x * x + 1.0).sqrt(),
+            16 => (x - param * (x / (param + f32::EPSILON)).floor()) - param * 0.5,
+            17 => if x > 0.0 { 1.0 } else { 0.0 },
+            _ => unreachable!(),
+        }
+    }
+
+    fn aggregate(kind: u8, xs: &[f32]) -> f32 {
+        if xs.is_empty() {
+            return 0.0;
+        }
+        let len = xs.len() as f32;
+        match kind {
+            0 => xs.iter().fold(0.0, |acc, x| acc + x),
+            1 => xs.iter().copied().reduce(f32::max).unwrap_or(0.0),
+            2 => xs.iter().fold(0.0, |acc, x| acc + x) / len,
+            3 => xs.iter().fold(0.0, |acc, x| acc + x.abs()) / len,
+            4 => {
+                let alpha = xs.iter().fold(0.0f32, |alpha, x| alpha.max(x.abs()));
+                if alpha == 0.0 {
+                    return 0.0;
+                }
+                xs.iter().fold(0.0, |acc, x| acc + (x / alpha) * (x / alpha)).sqrt() * alpha / len
+            }
+            _ => unreachable!(),
+        }
+    }
+";
+
+fn activation_kind(activation: Activation) -> (u8, f32) {
+    match activation {
+        Activation::Abs => (0, 0.),
+        Activation::Exp => (1, 0.),
+        Activation::Gauss => (2, 0.),
+        Activation::Hat => (3, 0.),
+        Activation::Identity => (4, 0.),
+        Activation::Inv => (5, 0.),
+        Activation::Log => (6, 0.),
+        Activation::Relu => (7, 0.),
+        Activation::Selu => (8, 0.),
+        Activation::Sigmoid => (9, 0.),
+        Activation::Sin => (10, 0.),
+        Activation::Cos => (11, 0.),
+        Activation::Tanh => (12, 0.),
+        Activation::Softplus(beta) => (13, beta),
+        Activation::Gelu => (14, 0.),
+        Activation::Root => (15, 0.),
+        Activation::Periodic(p) => (16, p),
+        // Exported networks run without noise
+        Activation::NoisyRelu(_) => (7, 0.),
+        Activation::StochasticBinary => (17, 0.),
+    }
+}
+
+fn aggregation_kind(aggregation: Aggregation) -> u8 {
+    match aggregation {
+        Aggregation::Sum => 0,
+        Aggregation::Max => 1,
+        Aggregation::Mean => 2,
+        Aggregation::L1NormAvg => 3,
+        Aggregation::L2NormAvg => 4,
+    }
+}
+
+/// Rust literal of `x`.
+fn literal(x: f32) -> String {
+    match x {
+        x if x.is_nan() => "f32::NAN".to_string(),
+        f32::INFINITY => "f32::INFINITY".to_string(),
+        f32::NEG_INFINITY => "f32::NEG_INFINITY".to_string(),
+        x => format!("{x:?}"),
+    }
+}
+
+impl<F: Float> FFNetwork<F> {
+    /// Source of a dependency-free Rust function `name` computing the same
+    /// forward pass in `f32`, with hard-coded weights. Networks with
+    /// recurrent edges or gated cells take a `state` array too, all zeros
+    /// for a fresh or reset network. Input scaling is applied, dropout is
+    /// not exported.
+    pub fn export_rust_fn(&self, name: &str) -> String {
+        let inputs = self.lengths.input;
+        let weight = |w: F| literal(to_float(w));
+        // Nodes in the order `forward` activates them, every node once
+        let mut queue = self.memory[..inputs]
+            .iter()
+            .map(|cell| Reverse(cell.get_node().into_level()))
+            .collect::<BinaryHeap<_>>();
+        let mut pushed = BTreeSet::new();
+        let mut order = vec![];
+        while let Some(Reverse(LevelNode(node))) = queue.pop() {
+            let index = get_mem_location(&self.memory, node.node_id);
+            order.push(index);
+            for edge in self.edge_map[index].iter() {
+                let dest = get_mem_location(&self.memory, edge.dest);
+                if dest >= inputs && pushed.insert(dest) {
+                    queue.push(Reverse(self.memory[dest].get_node().into_level()));
+                }
+            }
+        }
+        let activated = order.iter().copied().collect::<BTreeSet<_>>();
+        // Slot in `state` of every activated node a recurrent edge reads,
+        // and of every gated cell, whose state is its last output
+        let slots = self
+            .back_map
+            .iter()
+            .flatten()
+            .map(|edge| get_mem_location(&self.memory, edge.dest))
+            .chain(
+                (inputs..self.memory.len()).filter(|index| self.memory[*index].is_stateful()),
+            )
+            .filter(|source| *source >= inputs && activated.contains(source))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .enumerate()
+            .map(|(slot, source)| (source, slot))
+            .collect::<BTreeMap<_, _>>();
+        let value = |index: usize| {
+            if index < inputs {
+                match self.input_scaling.get(index) {
+                    Some((scale, offset)) => format!(
+                        "({} * input[{index}] + {})",
+                        weight(*scale),
+                        weight(*offset)
+                    ),
+                    None => format!("input[{index}]"),
+                }
+            } else {
+                format!("n{}", self.memory[index].get_node().node_id)
+            }
+        };
+
+        let mut code = String::new();
+        let outputs = self.lengths.output;
+        let state = match slots.len() {
+            0 => String::new(),
+            len => format!(", state: &mut [f32; {len}]"),
+        };
+        writeln!(code, "/// Forward pass of an evolved network.").unwrap();
+        if !slots.is_empty() {
+            let doc = "/// `state` holds the recurrent memory, all zeros before the first pass.";
+            writeln!(code, "{doc}").unwrap();
+        }
+        writeln!(
+            code,
+            "#[allow(unused_variables, clippy::excessive_precision)]"
+        )
+        .unwrap();
+        writeln!(
+            code,
+            "pub fn {name}(input: &[f32; {inputs}]{state}) -> [f32; {outputs}] {{"
+        )
+        .unwrap();
+        code.push_str(HELPERS);
+        code.push('\n');
+        let mut terms = vec![vec![]; self.memory.len()];
+        for index in order {
+            if index >= inputs {
+                for edge in self.back_map[index - inputs].iter() {
+                    let source = get_mem_location(&self.memory, edge.dest);
+                    let read = match slots.get(&source) {
+                        Some(slot) => format!("state[{slot}]"),
+                        None if source < inputs => value(source),
+                        None => "0.0".to_string(),
+                    };
+                    terms[index].push(format!("{read} * {}", weight(edge.weight)));
+                }
+                let config = self.memory[index].get_node().config;
+                let (kind, param) = activation_kind(config.activation);
+                let var = value(index);
+                let sum = terms[index].join(", ");
+                let aggregation = aggregation_kind(config.aggregation);
+                let bias = if config.bias == 0. {
+                    String::new()
+                } else {
+                    format!(" + {}", literal(config.bias))
+                };
+                let aggregate = format!("aggregate({aggregation}, &[{sum}]){bias}");
+                writeln!(code, "    let {var} = {aggregate};").unwrap();
+                let mut activation = format!("activate({kind}, {}, {var})", literal(param));
+                if let Some(gate) = config.gate {
+                    let (sigmoid, _) = activation_kind(Activation::Sigmoid);
+                    let open = |weight: f32, bias: f32| {
+                        let x = format!("{} * {var} + {}", literal(weight), literal(bias));
+                        format!("activate({sigmoid}, 0.0, {x})")
+                    };
+                    activation = format!(
+                        "({} * state[{}] + {} * {activation})",
+                        open(gate.forget_weight, gate.forget_bias),
+                        slots[&index],
+                        open(gate.input_weight, gate.input_bias),
+                    );
+                }
+                if let Some(max) = config.clamp.max_limit {
+                    write!(activation, ".min({})", literal(max)).unwrap();
+                }
+                if let Some(min) = config.clamp.min_limit {
+                    write!(activation, ".max({})", literal(min)).unwrap();
+                }
+                writeln!(code, "    let {var} = {activation};").unwrap();
+            }
+            for edge in self.edge_map[index].iter() {
+                let dest = get_mem_location(&self.memory, edge.dest);
+                terms[dest].push(format!("{} * {}", value(index), weight(edge.weight)));
+            }
+        }
+        for (source, slot) in slots.iter() {
+            writeln!(code, "    state[{slot}] = {};", value(*source)).unwrap();
+        }
+        let results = (inputs..inputs + outputs)
+            .map(|index| {
+                if activated.contains(&index) {
+                    value(index)
+                } else {
+                    "0.0".to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(code, "    [{}]", results.join(", ")).unwrap();
+        code.push_str("}\n");
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        process::Command,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::individual::genome::{
+        genome::{Genome, GenomeBuilder, InputGene},
+        network::sparse::SparseNetwork,
+        node_list::Gate,
+    };
+
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Outputs of the exported function on `inputs`, fed in order, compiled
+    /// with rustc.
+    fn run_exported(network: &FFNetwork, inputs: &[[f32; 2]]) -> Vec<Vec<f32>> {
+        let code = network.export_rust_fn("controller");
+        let (state, call) = if code.contains("state:") {
+            (
+                "let mut state = Default::default();",
+                "controller(input, &mut state)",
+            )
+        } else {
+            ("", "controller(input)")
+        };
+        let main = format!(
+            "fn main() {{
+                let inputs: [[f32; 2]; {}] = {inputs:?};
+                {state}
+                for input in inputs.iter() {{
+                    let outputs: Vec<String> = {call}.iter().map(|o| o.to_string()).collect();
+                    println!(\"{{}}\", outputs.join(\" \"));
+                }}
+            }}",
+            inputs.len()
+        );
+        let run = RUNS.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("neat-export-{}-{run}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (source, binary) = (dir.join("main.rs"), dir.join("main"));
+        fs::write(&source, format!("{code}\n{main}\n")).unwrap();
+        let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let status = Command::new(rustc)
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .status()
+            .unwrap();
+        assert!(status.success(), "The exported code compiles");
+        let output = Command::new(&binary).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(' ').map(|o| o.parse().unwrap()).collect())
+            .collect()
+    }
+
+    fn assert_exported(genome: &Genome, inputs: &[[f32; 2]]) {
+        let mut network = FFNetwork::from(genome);
+        let exported = run_exported(&network, inputs);
+        assert_eq!(exported.len(), inputs.len());
+        for (input, outputs) in inputs.iter().zip(exported) {
+            let expected = network.forward(input).unwrap();
+            assert_eq!(outputs.len(), expected.len());
+            for (output, expected) in outputs.iter().zip(expected) {
+                assert_relative_eq!(*output, expected, epsilon = 1e-5, max_relative = 1e-5);
+            }
+        }
+    }
+
+    const INPUTS: [[f32; 2]; 4] = [[0.5, -1.], [1.5, 0.25], [-2., 3.], [0., 0.]];
+
+    #[test]
+    fn test_feed_forward() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(2)
+            .hidden(50)
+            .hidden(70)
+            .activation(4, Activation::Gelu)
+            .aggregation(4, Aggregation::L2NormAvg)
+            .activation(5, Activation::Softplus(2.))
+            .bias(5, -0.3)
+            .activation(2, Activation::Tanh)
+            .aggregation(2, Aggregation::Sum)
+            .activation(3, Activation::Periodic(1.5))
+            .aggregation(3, Aggregation::Max)
+            .connect(0, 4, 0.7)
+            .connect(1, 4, -1.3)
+            .connect(4, 5, 2.)
+            .connect(1, 5, 0.4)
+            .connect(5, 2, 1.1)
+            .connect(0, 2, -0.6)
+            .connect(4, 3, 0.9)
+            .build()
+            .unwrap();
+        let network = FFNetwork::<f32>::from(&genome);
+        assert!(!network.export_rust_fn("controller").contains("state"));
+        assert_exported(&genome, &INPUTS);
+    }
+
+    #[test]
+    fn test_recurrent() {
+        // A self-loop, an output feeding back into a hidden node, and an
+        // output nothing reaches
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(2)
+            .hidden(50)
+            .activation(4, Activation::Sigmoid)
+            .aggregation(4, Aggregation::Mean)
+            .activation(2, Activation::Selu)
+            .connect(0, 4, 1.2)
+            .connect(4, 4, 0.5)
+            .connect(2, 4, -0.8)
+            .connect(4, 2, 1.5)
+            .connect(1, 2, 0.3)
+            .connect(3, 4, 0.2)
+            .build()
+            .unwrap();
+        let code = FFNetwork::<f32>::from(&genome).export_rust_fn("controller");
+        assert!(code.contains("state: &mut [f32; 2]"));
+        assert_exported(&genome, &INPUTS);
+    }
+
+    #[test]
+    fn test_gated() {
+        // A gated hidden node and a gated output fed back into it
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .activation(3, Activation::Tanh)
+            .gate(3, Gate::default())
+            .gate(
+                2,
+                Gate {
+                    input_weight: 0.5,
+                    input_bias: 0.2,
+                    forget_weight: -0.3,
+                    forget_bias: 1.,
+                },
+            )
+            .connect(0, 3, 1.2)
+            .connect(1, 3, -0.4)
+            .connect(3, 2, 0.9)
+            .connect(2, 3, 0.3)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert!(!network.is_feed_forward());
+        assert!(!network.enable_cache(16, 0.1));
+        let code = network.export_rust_fn("controller");
+        assert!(code.contains("state: &mut [f32; 2]"));
+        assert_exported(&genome, &INPUTS);
+    }
+
+    #[test]
+    fn test_input_scaling() {
+        let mut genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .activation(3, Activation::Tanh)
+            .aggregation(2, Aggregation::Sum)
+            .aggregation(3, Aggregation::Sum)
+            .connect(0, 3, 0.8)
+            .connect(1, 3, -0.5)
+            .connect(3, 2, 1.2)
+            .connect(0, 2, 1.)
+            .connect(2, 3, 0.4)
+            .build()
+            .unwrap();
+        let gene = |scale, offset| InputGene { scale, offset };
+        genome.input_scaling.genes.insert(0, gene(0.5, 1.));
+        genome.input_scaling.genes.insert(1, gene(-2., 0.25));
+        let mut graph = FFNetwork::<f32>::from(&genome);
+        let mut sparse = SparseNetwork::<f32>::new(&genome);
+        for input in INPUTS.iter() {
+            let expected = graph.forward(input).unwrap();
+            let outputs = sparse.forward(input).unwrap();
+            for (output, expected) in outputs.iter().zip(&expected) {
+                assert_relative_eq!(*output, *expected, epsilon = 1e-5, max_relative = 1e-5);
+            }
+        }
+        assert!(FFNetwork::<f32>::from(&genome)
+            .export_rust_fn("controller")
+            .contains("(0.5 * input[0] + 1.0)"));
+        assert_exported(&genome, &INPUTS);
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use num::{rational::Ratio, Float};
+use rand::RngCore;
+
+use super::{
+    mem_cell::MemoryCellType,
+    network::{get_mem_location, Edge, Lengths},
+};
+
+/// Evaluation order of a network without recurrent edges: the nodes the
+/// inputs reach in level order, each with its incoming edges. Depends only
+/// on the structure, so networks of the same structure share it, see
+/// `Schedule`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Plan {
+    /// Memory indices of the reached outputs and hidden nodes.
+    order: Vec<usize>,
+    /// Incoming edges of `order[i]` are `sources[starts[i]..starts[i + 1]]`.
+    starts: Vec<usize>,
+    /// Memory index of the source of every edge, and the position of the
+    /// edge among the forward edges of the source.
+    sources: Vec<(usize, usize)>,
+    reached: Vec<bool>,
+}
+
+impl Plan {
+    /// Plan of the cells at `levels`, whose forward edges go to the memory
+    /// indices of `targets`, by source. `None` if there are recurrent edges.
+    pub(super) fn new(
+        levels: &[Ratio<usize>],
+        targets: &[Vec<usize>],
+        inputs: usize,
+        recurrent: bool,
+    ) -> Option<Self> {
+        if recurrent {
+            return None;
+        }
+        // Forward edges climb levels, so sources come before their targets
+        let by_level = (0..levels.len()).sorted_by_key(|index| levels[*index]).collect_vec();
+        let mut incoming = vec![vec![]; levels.len()];
+        let mut reached = vec![false; levels.len()];
+        reached[..inputs].fill(true);
+        for index in by_level.iter().copied() {
+            if !reached[index] {
+                continue;
+            }
+            for (position, dest) in targets[index].iter().copied().enumerate() {
+                incoming[dest].push((index, position));
+                reached[dest] = true;
+            }
+        }
+        let order = by_level
+            .into_iter()
+            .filter(|index| *index >= inputs && reached[*index])
+            .collect_vec();
+        let mut starts = vec![0];
+        let mut sources = vec![];
+        for index in order.iter() {
+            sources.extend(incoming[*index].iter().copied());
+            starts.push(sources.len());
+        }
+        Some(Self {
+            order,
+            starts,
+            sources,
+            reached,
+        })
+    }
+}
+
+/// [`Plan`] of a network with its weights, evaluated over a flat buffer of
+/// node values. Skips the queue and the pass flags of the general
+/// evaluation, see `FFNetwork::is_feed_forward`.
+#[derive(Debug, Clone)]
+pub(super) struct FeedForward<F> {
+    plan: Arc<Plan>,
+    /// Weight of every edge of the plan.
+    weights: Vec<F>,
+    /// Value of every cell in the last pass, 0 for unreached ones.
+    values: Vec<F>,
+}
+
+impl<F: Float> FeedForward<F> {
+    /// Plan the network from scratch, `None` if it has recurrent edges or
+    /// gated cells.
+    pub(super) fn new(
+        memory: &[MemoryCellType<F>],
+        edge_map: &[Vec<Edge<F>>],
+        back_map: &[Vec<Edge<F>>],
+        inputs: usize,
+    ) -> Option<Self> {
+        let levels = memory.iter().map(|cell| cell.get_node().level).collect_vec();
+        let targets = edge_map
+            .iter()
+            .map(|edges| {
+                edges
+                    .iter()
+                    .map(|edge| get_mem_location(memory, edge.dest))
+                    .collect_vec()
+            })
+            .collect_vec();
+        let recurrent = back_map.iter().any(|edges| !edges.is_empty())
+            || memory.iter().any(MemoryCellType::is_stateful);
+        let plan = Plan::new(&levels, &targets, inputs, recurrent)?;
+        Some(Self::with_plan(Arc::new(plan), edge_map))
+    }
+
+    /// Evaluate `edge_map` in the order of `plan`, planned for its structure.
+    pub(super) fn with_plan(plan: Arc<Plan>, edge_map: &[Vec<Edge<F>>]) -> Self {
+        let mut feed_forward = Self {
+            weights: vec![],
+            values: vec![F::zero(); plan.reached.len()],
+            plan,
+        };
+        feed_forward.reweight(edge_map);
+        feed_forward
+    }
+
+    /// Copy the weights of `edge_map`, after they were edited in place.
+    pub(super) fn reweight(&mut self, edge_map: &[Vec<Edge<F>>]) {
+        self.weights.clear();
+        self.weights.extend(
+            self.plan
+                .sources
+                .iter()
+                .map(|(source, position)| edge_map[*source][*position].weight),
+        );
+    }
+
+    /// Outputs for `input`, of the input length. Dropped hidden nodes, by
+    /// hidden index, output 0.
+    pub(super) fn propagate(
+        &mut self,
+        memory: &mut [MemoryCellType<F>],
+        lengths: Lengths,
+        dropped: &[bool],
+        input: &[F],
+        mut noise: Option<&mut dyn RngCore>,
+    ) -> Vec<F> {
+        self.values[..lengths.input].copy_from_slice(input);
+        let first_hidden = lengths.input + lengths.output;
+        let plan = &*self.plan;
+        for (position, index) in plan.order.iter().copied().enumerate() {
+            let edges = plan.starts[position]..plan.starts[position + 1];
+            let inputs = plan.sources[edges.clone()]
+                .iter()
+                .zip(self.weights[edges].iter())
+                .map(|((source, _), weight)| self.values[*source] * *weight);
+            let MemoryCellType::Activation(cell) = &mut memory[index] else {
+                unreachable!("Inputs are not evaluated");
+            };
+            let noise = noise.as_mut().map(|rng| &mut **rng as &mut dyn RngCore);
+            let value = cell.evaluate(inputs, noise);
+            let is_dropped = index
+                .checked_sub(first_hidden)
+                .and_then(|hidden| dropped.get(hidden))
+                .is_some_and(|dropped| *dropped);
+            self.values[index] = if is_dropped { F::zero() } else { value };
+        }
+        self.values[lengths.input..first_hidden].to_vec()
+    }
+
+    /// Value of the cell at `index` in the last pass, `None` if the inputs
+    /// do not reach it.
+    pub(super) fn output(&self, index: usize) -> Option<F> {
+        self.plan.reached[index].then(|| self.values[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::relative_eq;
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        fixtures::random_genome,
+        individual::genome::{
+            activation::Activation, genome::GenomeBuilder, network::network::FFNetwork,
+        },
+    };
+
+    proptest! {
+        #[test]
+        fn test_matches_general(seed in any::<u64>(), hidden in 0usize..8, edges in 0usize..40) {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let genome = random_genome(&mut rng, hidden, edges, false);
+            let mut fast = FFNetwork::<f64>::from(&genome);
+            prop_assert!(fast.is_feed_forward());
+            let mut general = fast.clone();
+            general.feed_forward = None;
+            for step in 0..3 {
+                let input = [step as f64 * 0.5, 1. - step as f64, 0.25];
+                let fst = fast.forward(&input).unwrap();
+                let snd = general.forward(&input).unwrap();
+                for (a, b) in fst.iter().zip(snd.iter()) {
+                    prop_assert!(
+                        relative_eq!(a, b, epsilon = 1e-9, max_relative = 1e-9)
+                            || (a.is_nan() && b.is_nan()),
+                        "{fst:?} != {snd:?}"
+                    );
+                }
+            }
+            prop_assert_eq!(fast.saturation_stats(), general.saturation_stats());
+        }
+    }
+
+    #[test]
+    fn test_feed_forward() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(2)
+            .hidden(50)
+            .activation(4, Activation::Tanh)
+            .connect(0, 4, 0.5)
+            .connect(1, 4, -1.)
+            .connect(4, 2, 2.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert!(network.is_feed_forward());
+        let mut general = network.clone();
+        general.feed_forward = None;
+        // The unreached output stays at 0
+        assert_eq!(network.forward(&[1., 0.25]), general.forward(&[1., 0.25]));
+        assert_eq!(network.forward(&[1., 0.25]).unwrap()[1], 0.);
+
+        let samples = vec![vec![1., 0.], vec![-1., 1.], vec![0.5, 0.5]];
+        assert_eq!(
+            network.calibrate_clamps(&samples, 0.1),
+            general.calibrate_clamps(&samples, 0.1)
+        );
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert_eq!(
+            network.forward_with_dropout(&mut rng, 1., &[1., 1.]),
+            Some(vec![0., 0.])
+        );
+
+        let recurrent = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(0, 1, 1.)
+            .connect(1, 1, 0.5)
+            .build()
+            .unwrap();
+        assert!(!FFNetwork::<f32>::from(&recurrent).is_feed_forward());
+    }
+}
@@ -1,50 +1,93 @@
-use crate::individual::genome::node_list::{Activate, Node};
+use num::Float;
+use rand::RngCore;
 
-#[derive(Debug)]
-pub struct MemoryCell {
+use crate::individual::genome::{
+    activation::Activation,
+    clamp::Clamp,
+    node_list::{to_float, Activate, Gate, Node},
+};
+
+use super::saturation::SaturationStats;
+
+#[derive(Debug, Clone)]
+pub struct MemoryCell<F = f32> {
     node: Node,
-    current: f32,
-    prev: f32,
-    bias: f32,
-    current_data: Vec<f32>,
+    current: F,
+    prev: F,
+    bias: F,
+    current_data: Vec<F>,
     activated: bool,
     pub passed: bool,
+    saturation: SaturationStats,
 }
 
-impl MemoryCell {
+impl<F: Float> MemoryCell<F> {
+    /// Cell of `node`, with the bias of its config.
     pub fn default(node: Node) -> Self {
-        Self::new(node, 0.)
+        Self::new(node, to_float(node.config.bias))
     }
 
-    pub fn new(node: Node, bias: f32) -> Self {
+    pub fn new(node: Node, bias: F) -> Self {
         MemoryCell {
             node,
-            current: 0.,
-            prev: 0.,
-            bias: bias,
+            current: F::zero(),
+            prev: F::zero(),
+            bias,
             current_data: Vec::new(),
             activated: false,
             passed: false,
+            saturation: SaturationStats::default(),
         }
     }
-    pub fn get_node(&self) -> Node {
-        self.node
+    pub fn activate(&mut self, pass_flag: bool, noise: Option<&mut dyn RngCore>) {
+        self.activate_with(pass_flag, |cell, data| {
+            cell.evaluate(data.iter().copied(), noise)
+        });
     }
 
-    pub fn activate(&mut self, pass_flag: bool) {
+    /// Activate with the output `evaluate` computes from the inputs.
+    fn activate_with(&mut self, pass_flag: bool, evaluate: impl FnOnce(&mut Self, &[F]) -> F) {
         if self.activated == pass_flag {
             return; // If already activated, do not activate again
         }
-        let config = self.node.config;
-        let agg_data = config.aggregation.apply(self.current_data.iter().copied()) + self.bias;
-        let current = config.clamp.activate(config.activation.activate(agg_data));
+        let mut data = std::mem::take(&mut self.current_data);
+        let current = evaluate(self, &data);
         self.prev = self.current;
         self.current = current;
         self.activated = pass_flag;
-        self.current_data.clear();
+        data.clear();
+        self.current_data = data;
+    }
+
+    /// Output for `inputs`, leaving the stored outputs untouched. The
+    /// saturation is recorded as in `activate`. Stochastic activations sample
+    /// from `noise`, and take their deterministic mode without it.
+    pub fn evaluate(&mut self, inputs: impl Iterator<Item = F>, noise: Option<&mut dyn RngCore>) -> F {
+        let activated = self.activation(self.aggregate(inputs), noise);
+        self.clamp(activated)
+    }
+
+    /// Aggregated inputs plus the bias.
+    fn aggregate(&self, inputs: impl Iterator<Item = F>) -> F {
+        self.node.config.aggregation.apply(inputs) + self.bias
+    }
+
+    fn activation(&self, x: F, noise: Option<&mut dyn RngCore>) -> F {
+        let activation = self.node.config.activation;
+        match noise {
+            Some(rng) => activation.activate_noisy(x, rng),
+            None => activation.activate(x),
+        }
     }
 
-    pub fn get_current_output(&self, pass_flag: bool) -> Option<f32> {
+    /// Clamped `x`, recording the saturation.
+    fn clamp(&mut self, x: F) -> F {
+        let clamp = self.node.config.clamp;
+        self.saturation.record(&clamp, x);
+        clamp.activate(x)
+    }
+
+    pub fn get_current_output(&self, pass_flag: bool) -> Option<F> {
         if self.activated == pass_flag {
             Some(self.current)
         } else {
@@ -52,7 +95,7 @@ impl MemoryCell {
         }
     }
 
-    pub fn get_previous_output(&self, pass_flag: bool) -> f32 {
+    pub fn get_previous_output(&self, pass_flag: bool) -> F {
         if self.activated == pass_flag {
             self.prev
         } else {
@@ -60,34 +103,101 @@ impl MemoryCell {
         }
     }
 
-    pub fn append_input(&mut self, input: f32) {
+    pub fn append_input(&mut self, input: F) {
         self.current_data.push(input);
     }
+
+    /// Forget the stored outputs, as if the cell was never activated. The
+    /// saturation statistics are kept.
+    pub fn reset(&mut self) {
+        let saturation = self.saturation;
+        *self = Self::new(self.node, self.bias);
+        self.saturation = saturation;
+    }
+
+    pub fn saturation(&self) -> SaturationStats {
+        self.saturation
+    }
+
+    pub fn clear_saturation(&mut self) {
+        self.saturation = SaturationStats::default();
+    }
+
+    /// Force the output of the current activation to zero.
+    pub fn silence(&mut self) {
+        self.current = F::zero();
+    }
+
+    pub fn set_clamp(&mut self, clamp: Clamp) {
+        self.node.config.clamp = clamp;
+    }
+}
+
+/// Cell of a node with a [`Gate`]. Its output is a state carried across
+/// passes: the input gate scales the activation added to it, the forget
+/// gate how much of it is kept.
+#[derive(Debug, Clone)]
+pub struct GatedCell<F = f32> {
+    cell: MemoryCell<F>,
+    gate: Gate,
+    state: F,
+}
+
+impl<F: Float> GatedCell<F> {
+    /// Cell of `node`, with the bias and gate of its config, ungated if it
+    /// has none.
+    pub fn default(node: Node) -> Self {
+        Self {
+            cell: MemoryCell::default(node),
+            gate: node.config.gate.unwrap_or_default(),
+            state: F::zero(),
+        }
+    }
+
+    pub fn activate(&mut self, pass_flag: bool, noise: Option<&mut dyn RngCore>) {
+        let (gate, state) = (self.gate, &mut self.state);
+        self.cell.activate_with(pass_flag, |cell, data| {
+            let aggregate = cell.aggregate(data.iter().copied());
+            let activated = cell.activation(aggregate, noise);
+            let open = |weight: f32, bias: f32| {
+                Activation::Sigmoid.activate(to_float::<F>(weight) * aggregate + to_float(bias))
+            };
+            let input = open(gate.input_weight, gate.input_bias);
+            let forget = open(gate.forget_weight, gate.forget_bias);
+            *state = cell.clamp(forget * *state + input * activated);
+            *state
+        });
+    }
+
+    /// Forget the state and the stored outputs.
+    pub fn reset(&mut self) {
+        self.cell.reset();
+        self.state = F::zero();
+    }
 }
 
-#[derive(Debug)]
-pub enum MemoryCellType {
-    Input { node: Node, cell_value: f32 },
-    Activation(MemoryCell),
+#[derive(Debug, Clone)]
+pub enum MemoryCellType<F = f32> {
+    Input { node: Node, cell_value: F },
+    Activation(MemoryCell<F>),
+    Gated(GatedCell<F>),
 }
 
-impl PartialEq for MemoryCellType {
+impl<F> PartialEq for MemoryCellType<F> {
     fn eq(&self, other: &Self) -> bool {
         self.get_node().into_level() == other.get_node().into_level()
     }
 }
 
-impl Eq for MemoryCellType {}
+impl<F> Eq for MemoryCellType<F> {}
 
-impl PartialOrd for MemoryCellType {
+impl<F> PartialOrd for MemoryCellType<F> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.get_node()
-            .into_level()
-            .partial_cmp(&other.get_node().into_level())
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for MemoryCellType {
+impl<F> Ord for MemoryCellType<F> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.get_node()
             .into_level()
@@ -95,18 +205,23 @@ impl Ord for MemoryCellType {
     }
 }
 
-impl MemoryCellType {
+impl<F> MemoryCellType<F> {
     pub fn get_node(&self) -> Node {
         match self {
             MemoryCellType::Input { node, .. } => *node,
             MemoryCellType::Activation(MemoryCell { node, .. }) => *node,
+            MemoryCellType::Gated(GatedCell { cell, .. }) => cell.node,
         }
     }
 
     pub fn was_not_passed_set(&mut self, pass_flag: bool) -> bool {
         match self {
             MemoryCellType::Input { .. } => true,
-            MemoryCellType::Activation(MemoryCell { passed, .. }) => {
+            MemoryCellType::Activation(MemoryCell { passed, .. })
+            | MemoryCellType::Gated(GatedCell {
+                cell: MemoryCell { passed, .. },
+                ..
+            }) => {
                 let prev = *passed != pass_flag;
                 *passed = pass_flag;
                 prev
@@ -114,31 +229,100 @@ impl MemoryCellType {
         }
     }
 
-    pub fn propagate_input(&mut self, input: f32) {
+    /// Whether the output depends on past passes through the cell's own
+    /// state, beside any recurrent edge.
+    pub fn is_stateful(&self) -> bool {
+        matches!(self, MemoryCellType::Gated(_))
+    }
+}
+
+impl<F: Float> MemoryCellType<F> {
+    /// Cell of an output or hidden node, gated if its config has a gate.
+    pub fn activated(node: Node) -> Self {
+        match node.config.gate {
+            Some(_) => MemoryCellType::Gated(GatedCell::default(node)),
+            None => MemoryCellType::Activation(MemoryCell::default(node)),
+        }
+    }
+
+    /// The activated cell underneath, `None` for inputs.
+    fn cell(&self) -> Option<&MemoryCell<F>> {
+        match self {
+            MemoryCellType::Input { .. } => None,
+            MemoryCellType::Activation(c) | MemoryCellType::Gated(GatedCell { cell: c, .. }) => {
+                Some(c)
+            }
+        }
+    }
+
+    fn cell_mut(&mut self) -> Option<&mut MemoryCell<F>> {
+        match self {
+            MemoryCellType::Input { .. } => None,
+            MemoryCellType::Activation(c) | MemoryCellType::Gated(GatedCell { cell: c, .. }) => {
+                Some(c)
+            }
+        }
+    }
+
+    pub fn propagate_input(&mut self, input: F) {
         match self {
             MemoryCellType::Input { cell_value, .. } => *cell_value = input,
-            MemoryCellType::Activation(c) => c.append_input(input),
+            c => c.cell_mut().unwrap().append_input(input),
         }
     }
 
-    pub fn activate(&mut self, pass_flag: bool) {
+    pub fn activate(&mut self, pass_flag: bool, noise: Option<&mut dyn RngCore>) {
         match self {
-            MemoryCellType::Activation(c) => c.activate(pass_flag),
+            MemoryCellType::Activation(c) => c.activate(pass_flag, noise),
+            MemoryCellType::Gated(c) => c.activate(pass_flag, noise),
             _ => (),
         }
     }
 
-    pub fn get_previous_output(&self, pass_flag: bool) -> f32 {
+    pub fn get_previous_output(&self, pass_flag: bool) -> F {
         match self {
             MemoryCellType::Input { cell_value, .. } => *cell_value, // should never occur
-            MemoryCellType::Activation(c) => c.get_previous_output(pass_flag),
+            c => c.cell().unwrap().get_previous_output(pass_flag),
         }
     }
 
-    pub fn get_current_output(&self, pass_flag: bool) -> Option<f32> {
+    pub fn get_current_output(&self, pass_flag: bool) -> Option<F> {
         match self {
             MemoryCellType::Input { cell_value, .. } => Some(*cell_value), // should never occur
-            MemoryCellType::Activation(c) => c.get_current_output(pass_flag),
+            c => c.cell().unwrap().get_current_output(pass_flag),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match self {
+            MemoryCellType::Input { cell_value, .. } => *cell_value = F::zero(),
+            MemoryCellType::Activation(c) => c.reset(),
+            MemoryCellType::Gated(c) => c.reset(),
+        }
+    }
+
+    pub fn silence(&mut self) {
+        match self {
+            MemoryCellType::Input { cell_value, .. } => *cell_value = F::zero(),
+            c => c.cell_mut().unwrap().silence(),
+        }
+    }
+
+    /// Inputs are not activated, so they ignore the clamp.
+    pub fn set_clamp(&mut self, clamp: Clamp) {
+        if let Some(c) = self.cell_mut() {
+            c.set_clamp(clamp);
+        }
+    }
+
+    /// `None` for inputs, which are not activated.
+    pub fn saturation(&self) -> Option<SaturationStats> {
+        self.cell().map(MemoryCell::saturation)
+    }
+
+    pub fn clear_saturation(&mut self) {
+        if let Some(c) = self.cell_mut() {
+            c.clear_saturation();
         }
     }
 }
@@ -167,6 +351,8 @@ mod tests {
                         min_limit: Some(-10.),
                         max_limit: Some(10.),
                     },
+                    bias: 0.,
+                    gate: None,
                 },
                 level: Ratio::new(0, 1),
             }
@@ -179,7 +365,7 @@ mod tests {
                 for el in a.iter().copied() {
                     mem_cell.append_input(el);
                 }
-                mem_cell.activate(true);
+                mem_cell.activate(true, None);
                 let curr_val = mem_cell.get_current_output(true).unwrap();
                 let exp = Activation::Gauss.activate(Aggregation::L2NormAvg.apply(a.iter().copied()) + 1.).clamp(-10., 10.);
                 assert_relative_eq!(curr_val, exp);
@@ -192,7 +378,7 @@ mod tests {
                 for el in a.iter().copied() {
                     mem_cell.append_input(el);
                 }
-                mem_cell.activate(pass);
+                mem_cell.activate(pass, None);
                 let curr_val = mem_cell.get_current_output(pass).unwrap();
                 let exp_a = Activation::Gauss.activate(Aggregation::L2NormAvg.apply(a.iter().copied()) + 1.).clamp(-10., 10.);
                 assert_relative_eq!(curr_val, exp_a);
@@ -200,13 +386,67 @@ mod tests {
                 for el in b.iter().copied() {
                     mem_cell.append_input(el);
                 }
-                mem_cell.activate(pass);
+                mem_cell.activate(pass, None);
                 let curr_val = mem_cell.get_current_output(pass).unwrap();
                 let prev_val = mem_cell.get_previous_output(pass);
                 let exp = Activation::Gauss.activate(Aggregation::L2NormAvg.apply(b.iter().copied()) + 1.).clamp(-10., 10.);
                 assert_relative_eq!(curr_val, exp);
                 assert_relative_eq!(prev_val, exp_a);
             }
+
+            #[test]
+            fn test_clamp_composition(a in -100.0f32..100.0, activation in 0..3usize) {
+                let activation = [Activation::Identity, Activation::Relu, Activation::Tanh][activation];
+                let mut node = generate_default_node();
+                node.config.activation = activation;
+                node.config.aggregation = Aggregation::Sum;
+                node.config.clamp = Clamp::new(Some(-0.5), Some(2.)).unwrap();
+                let mut mem_cell = MemoryCell::new(node, 0.);
+                mem_cell.append_input(a);
+                mem_cell.activate(true, None);
+                let activated = activation.activate(a);
+                prop_assert_eq!(mem_cell.get_current_output(true).unwrap(), activated.clamp(-0.5, 2.));
+                let saturation = mem_cell.saturation();
+                prop_assert_eq!(saturation.activations, 1);
+                prop_assert_eq!(saturation.at_min, usize::from(activated <= -0.5));
+                prop_assert_eq!(saturation.at_max, usize::from(activated >= 2.));
+                mem_cell.reset();
+                prop_assert_eq!(mem_cell.saturation(), saturation);
+            }
+        }
+
+        #[test]
+        fn test_gated() {
+            let mut node = generate_default_node();
+            node.config.activation = Activation::Identity;
+            node.config.aggregation = Aggregation::Sum;
+            let pass = |cell: &mut MemoryCellType<f32>, input: f32, pass_flag: bool| {
+                cell.propagate_input(input);
+                cell.activate(pass_flag, None);
+                cell.get_current_output(pass_flag).unwrap()
+            };
+            // The default gate passes the activation through, nearly
+            node.config.gate = Some(Gate::default());
+            let mut cell = MemoryCellType::activated(node);
+            assert!(cell.is_stateful());
+            assert_relative_eq!(pass(&mut cell, 2., true), 2., epsilon = 0.02);
+            assert_relative_eq!(pass(&mut cell, -1., false), -1., epsilon = 0.02);
+
+            // Both gates open, the cell sums its inputs
+            node.config.gate = Some(Gate {
+                input_bias: 20.,
+                forget_bias: 20.,
+                ..Gate::default()
+            });
+            let mut cell = MemoryCellType::activated(node);
+            let outputs = [(1., true), (2., false), (3., true)]
+                .map(|(input, pass_flag)| pass(&mut cell, input, pass_flag));
+            assert_relative_eq!(outputs.as_slice(), [1., 3., 6.].as_slice(), epsilon = 1e-5);
+            // The sum saturates at the clamp
+            assert_eq!(pass(&mut cell, 5., false), 10.);
+            assert_eq!(cell.saturation().unwrap().at_max, 1);
+            cell.reset();
+            assert_relative_eq!(pass(&mut cell, 1., true), 1., epsilon = 1e-5);
         }
     }
 }
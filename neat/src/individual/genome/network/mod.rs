@@ -1,2 +1,16 @@
 mod mem_cell;
-mod network;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod network;
+pub mod readout;
+pub mod sequence;
+pub mod sparse;
+pub mod saturation;
+pub mod output_groups;
+pub mod export;
+pub mod cache;
+pub mod delta;
+pub mod weights;
+pub mod schedule;
+mod feed_forward;
+#[cfg(feature = "ndarray")]
+pub mod array;
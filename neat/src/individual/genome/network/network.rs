@@ -1,89 +1,156 @@
-use super::mem_cell::MemoryCellType;
-use crate::individual::genome::{
-    genome::GenomeEdge, network::mem_cell::MemoryCell, node_list::{LevelNode, Node, NodeList},
+use super::{
+    cache::{CacheStats, OutputCache},
+    feed_forward::FeedForward,
+    mem_cell::MemoryCellType,
+    saturation::SaturationStats,
+    schedule::{self, EdgeSlot, Schedule},
+};
+use crate::{
+    environment::observation::Encode,
+    individual::genome::{
+        clamp::Clamp,
+        genome::{Genome, GenomeEdge, InputScaling},
+        node_list::{to_float, LevelNode, NodeId, NodeList, NodeNames},
+    },
 };
 use itertools::Itertools;
-use std::{cmp::Reverse, collections::BinaryHeap};
+use num::Float;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    sync::Arc,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Lengths {
-    input: usize,
-    output: usize,
-    hidden: usize,
+    pub(super) input: usize,
+    pub(super) output: usize,
+    pub(super) hidden: usize,
 }
 
-pub struct FFNetwork {
-    memory: Vec<MemoryCellType>,
+/// Compiled network. Evaluation runs in `f32` by default; use `FFNetwork<f64>`
+/// when accumulation error over long recurrent rollouts matters.
+#[derive(Debug, Clone)]
+pub struct FFNetwork<F = f32> {
+    pub(super) memory: Vec<MemoryCellType<F>>,
     pass: bool,
-    edge_map: Vec2D<Edge>,
-    back_map: Vec2D<Edge>,
-    lengths: Lengths,
+    pub(super) edge_map: Vec2D<Edge<F>>,
+    pub(super) back_map: Vec2D<Edge<F>>,
+    pub(super) lengths: Lengths,
+    diverged: bool,
+    /// Hidden nodes whose output is forced to zero, by hidden index.
+    dropped: Vec<bool>,
+    pub(super) names: NodeNames,
+    /// Value of every input missing from a masked pass, `None` for required
+    /// inputs.
+    input_defaults: Vec<Option<F>>,
+    /// Scale and offset of every input, empty when the genome scales none.
+    pub(super) input_scaling: Vec<(F, F)>,
+    pub(super) cache: Option<OutputCache<F>>,
+    /// Evaluation order when the network has no recurrent edges.
+    pub(super) feed_forward: Option<FeedForward<F>>,
+    /// Whether the weights changed since `feed_forward` copied them.
+    pub(super) stale_schedule: bool,
+    /// Source the stochastic activations sample from, `None` in the
+    /// deterministic evaluation mode. Boxed, as most networks never sample.
+    noise: Option<Box<ChaCha8Rng>>,
 }
 
 type Vec2D<T> = Vec<Vec<T>>;
 
 #[derive(Debug, Clone, Copy)]
-struct Edge {
-    dest: usize,
-    weight: f32,
+pub(super) struct Edge<F> {
+    pub(super) dest: NodeId,
+    pub(super) weight: F,
 }
 
 #[inline]
-fn get_mem_location(memory: &[MemoryCellType], item: usize) -> usize {
+pub(super) fn get_mem_location<F>(memory: &[MemoryCellType<F>], item: NodeId) -> usize {
     memory
         .binary_search_by_key(&item, |cell| cell.get_node().node_id)
-        .expect(format!("Id {item:?} should be in list").as_str())
+        .unwrap_or_else(|_| panic!("Id {item:?} should be in list"))
 }
 
-impl FFNetwork {
+impl<F: Float> FFNetwork<F> {
+    #[cfg(test)]
     fn new(node_list: NodeList, genome_list: Vec<GenomeEdge>) -> Self {
-        let memory = node_list
-            .input
+        let schedule = Schedule::new(&node_list, &genome_list);
+        Self::scheduled(node_list, &genome_list, &schedule)
+    }
+
+    /// Network of `genome` on `schedule`, the schedule of its structure.
+    pub(super) fn with_schedule(genome: &Genome, schedule: &Schedule) -> Self {
+        let edges = genome
+            .genome_list
             .iter()
-            .map(|cell| MemoryCellType::Input {
-                node: *cell,
-                cell_value: 0.,
+            .map(|edge| GenomeEdge {
+                weight: genome.weight(edge),
+                ..*edge
+            })
+            .collect_vec();
+        let mut network = Self::scheduled(genome.node_list.clone(), &edges, schedule);
+        network.set_input_scaling(&genome.input_scaling);
+        network
+    }
+
+    fn scheduled(node_list: NodeList, genome_list: &[GenomeEdge], schedule: &Schedule) -> Self {
+        let nodes = schedule::nodes(&node_list).collect_vec();
+        let inputs = node_list.input.len();
+        let memory = schedule
+            .cells
+            .iter()
+            .map(|index| match *nodes[*index] {
+                node if *index < inputs => MemoryCellType::Input {
+                    node,
+                    cell_value: F::zero(),
+                },
+                node => MemoryCellType::activated(node),
             })
-            .chain(
-                node_list
-                    .output
-                    .iter()
-                    .chain(node_list.hidden.iter())
-                    .map(|cell| MemoryCellType::Activation(MemoryCell::default(*cell))),
-            )
-            .sorted_by_key(|cell| cell.get_node().node_id)
             .collect_vec();
-        let mut edge_map = memory.iter().map(|_| Vec::new()).collect_vec();
-        let mut back_map = vec![vec![]; node_list.hidden.len()];
-        for GenomeEdge {
-            in_node,
-            out_node,
-            weight,
-            ..
-        } in genome_list.iter().filter(|edge| edge.enabled).copied()
+        let mut edge_map = vec![vec![]; memory.len()];
+        let mut back_map = vec![vec![]; memory.len() - inputs];
+        for (edge, slot) in genome_list
+            .iter()
+            .filter(|edge| edge.enabled)
+            .zip_eq(schedule.edges.iter())
         {
-            let in_index = get_mem_location(&memory, in_node);
-            let out_index = get_mem_location(&memory, out_node);
-            let in_node_el = memory[in_index].get_node();
-            let out_node_el = memory[out_index].get_node();
-            if in_node_el.level >= out_node_el.level {
-                back_map[out_index - (node_list.input.len() + node_list.output.len())].push(Edge {
-                    dest: in_node,
+            let weight = to_float(edge.weight);
+            match slot {
+                EdgeSlot::Forward(source) => edge_map[*source].push(Edge {
+                    dest: edge.out_node,
                     weight,
-                });
-            } else {
-                edge_map[in_index].push(Edge {
-                    dest: out_node,
+                }),
+                EdgeSlot::Recurrent(target) => back_map[*target].push(Edge {
+                    dest: edge.in_node,
                     weight,
-                });
+                }),
             }
         }
+        let feed_forward = schedule
+            .plan
+            .as_ref()
+            .map(|plan| FeedForward::with_plan(Arc::clone(plan), &edge_map));
 
         Self {
             memory,
             pass: false,
             edge_map,
             back_map,
+            diverged: false,
+            dropped: vec![],
+            names: node_list.names.clone(),
+            input_defaults: node_list
+                .input
+                .iter()
+                .map(|node| node_list.input_defaults.get(&node.node_id).map(|default| to_float(*default)))
+                .collect_vec(),
+            input_scaling: vec![],
+            cache: None,
+            feed_forward,
+            stale_schedule: false,
+            noise: None,
             lengths: Lengths {
                 input: node_list.input.len(),
                 output: node_list.output.len(),
@@ -92,23 +159,297 @@ impl FFNetwork {
         }
     }
 
-    #[inline]
-    fn is_hidden(&self, node_id: usize) -> bool {
-        self.lengths.input + self.lengths.output <= node_id
+    /// Clear the recurrent state, so the next pass behaves like the first
+    /// pass of a freshly built network.
+    pub fn reset(&mut self) {
+        self.pass = false;
+        self.diverged = false;
+        for cell in self.memory.iter_mut() {
+            cell.reset();
+        }
+    }
+
+    /// Whether a pass since the last reset produced a NaN or infinite output.
+    pub fn diverged(&self) -> bool {
+        self.diverged
+    }
+
+    /// Whether the network has no recurrent edges nor gated cells, and is
+    /// evaluated in a precomputed order without the recurrent state.
+    pub fn is_feed_forward(&self) -> bool {
+        self.feed_forward.is_some()
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.lengths.input
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.lengths.output
+    }
+
+    /// Run `samples` as consecutive passes from a reset state and tighten
+    /// the clamp of every activated node to its observed output range, see
+    /// [`Clamp::tighten`]. Returns the new clamps by node id, to be written
+    /// back with [`Genome::apply_clamps`]. The network is reset afterwards.
+    pub fn calibrate_clamps(&mut self, samples: &[Vec<F>], margin: f32) -> BTreeMap<NodeId, Clamp> {
+        // Calibration reads the cells of every pass, and changes the outputs
+        let mut cache = self.cache.take();
+        if let Some(cache) = cache.as_mut() {
+            cache.clear();
+        }
+        self.reset();
+        let mut ranges = BTreeMap::new();
+        for sample in samples.iter() {
+            if self.forward(sample).is_none() {
+                continue;
+            }
+            for index in self.lengths.input..self.memory.len() {
+                let cell = &self.memory[index];
+                let output = match self.feed_forward.as_ref() {
+                    Some(feed_forward) => feed_forward.output(index),
+                    None => cell.get_current_output(self.pass),
+                };
+                let Some(output) = output.and_then(|o| o.to_f32()) else {
+                    continue;
+                };
+                let (min, max) = ranges
+                    .entry(cell.get_node().node_id)
+                    .or_insert((output, output));
+                *min = output.min(*min);
+                *max = output.max(*max);
+            }
+        }
+        self.reset();
+        let mut clamps = BTreeMap::new();
+        for cell in self.memory.iter_mut() {
+            let node = cell.get_node();
+            let Some(clamp) = ranges
+                .get(&node.node_id)
+                .and_then(|(min, max)| node.config.clamp.tighten(*min, *max, margin))
+            else {
+                continue;
+            };
+            cell.set_clamp(clamp);
+            clamps.insert(node.node_id, clamp);
+        }
+        self.cache = cache;
+        clamps
+    }
+
+    /// Cache the outputs of up to `capacity` input vectors, quantized to
+    /// multiples of `resolution`, for fitness functions that query the same
+    /// inputs over and over, e.g. CPPN grids. Cached passes skip the
+    /// saturation statistics, and dropout passes bypass the cache. `false`,
+    /// leaving the network uncached, if it has recurrent edges or gated
+    /// cells, whose outputs depend on the past inputs.
+    pub fn enable_cache(&mut self, capacity: usize, resolution: f64) -> bool {
+        if self.back_map.iter().any(|edges| !edges.is_empty())
+            || self.memory.iter().any(MemoryCellType::is_stateful)
+        {
+            return false;
+        }
+        self.cache = Some(OutputCache::new(capacity, resolution));
+        true
+    }
+
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// `None` unless the cache is enabled.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(OutputCache::stats)
+    }
+
+    /// How often the output of every activated node hit its clamp since the
+    /// network was built, by node id. Resets keep the statistics.
+    pub fn saturation_stats(&self) -> BTreeMap<NodeId, SaturationStats> {
+        self.memory
+            .iter()
+            .filter_map(|cell| Some((cell.get_node().node_id, cell.saturation()?)))
+            .collect()
+    }
+
+    pub fn clear_saturation_stats(&mut self) {
+        for cell in self.memory.iter_mut() {
+            cell.clear_saturation();
+        }
+    }
+
+    /// Names of the inputs in input order, `None` for unnamed ones.
+    pub fn input_names(&self) -> Vec<Option<&str>> {
+        self.names_of(0..self.lengths.input)
+    }
+
+    /// Names of the outputs in output order, `None` for unnamed ones.
+    pub fn output_names(&self) -> Vec<Option<&str>> {
+        self.names_of(self.lengths.input..self.lengths.input + self.lengths.output)
+    }
+
+    /// Position of the input called `name` in the input vector.
+    pub fn input_index(&self, name: &str) -> Option<usize> {
+        self.input_names().iter().position(|n| *n == Some(name))
+    }
+
+    /// Position of the output called `name` in the output vector.
+    pub fn output_index(&self, name: &str) -> Option<usize> {
+        self.output_names().iter().position(|n| *n == Some(name))
+    }
+
+    fn names_of(&self, cells: std::ops::Range<usize>) -> Vec<Option<&str>> {
+        self.memory[cells]
+            .iter()
+            .map(|cell| self.names.get(&cell.get_node().node_id).map(String::as_str))
+            .collect_vec()
+    }
+
+    /// Drop every hidden node with probability `p`: its output is forced to 0
+    /// on every pass until the next call or [`FFNetwork::clear_dropout`].
+    pub fn drop_hidden(&mut self, rng: &mut dyn RngCore, p: f64) {
+        self.dropped = (0..self.lengths.hidden).map(|_| rng.gen_bool(p)).collect_vec();
+    }
+
+    /// Sample the stochastic activations from a noise source seeded with
+    /// `seed` on every pass, until [`FFNetwork::disable_noise`]. The output
+    /// cache is bypassed while the noise is on.
+    pub fn enable_noise(&mut self, seed: u64) {
+        self.noise = Some(Box::new(ChaCha8Rng::seed_from_u64(seed)));
+    }
+
+    /// Evaluate the stochastic activations in their deterministic mode: noisy
+    /// ReLU as ReLU, stochastic binary as a step at 0.
+    pub fn disable_noise(&mut self) {
+        self.noise = None;
+    }
+
+    pub fn is_noisy(&self) -> bool {
+        self.noise.is_some()
+    }
+
+    pub fn clear_dropout(&mut self) {
+        self.dropped.clear();
+    }
+
+    /// Number of hidden nodes currently dropped.
+    pub fn dropped_len(&self) -> usize {
+        self.dropped.iter().filter(|d| **d).count()
+    }
+
+    /// Forward pass with a fresh dropout sample, see [`FFNetwork::drop_hidden`].
+    pub fn forward_with_dropout(
+        &mut self,
+        rng: &mut dyn RngCore,
+        p: f64,
+        input_vector: &[F],
+    ) -> Option<Vec<F>> {
+        self.drop_hidden(rng, p);
+        self.forward(input_vector)
     }
 
     #[inline]
-    fn translate_hidden(&self, node_id: usize) -> usize {
-        let index = get_mem_location(&self.memory, node_id);
-        index - (self.lengths.input + self.lengths.output)
+    fn is_dropped(&self, index: usize) -> bool {
+        index
+            .checked_sub(self.lengths.input + self.lengths.output)
+            .and_then(|hidden| self.dropped.get(hidden))
+            .is_some_and(|dropped| *dropped)
+    }
+
+    /// Scale the inputs by the genes of `scaling` before every pass.
+    pub(super) fn set_input_scaling(&mut self, scaling: &InputScaling) {
+        self.input_scaling = if scaling.is_empty() {
+            vec![]
+        } else {
+            self.memory[..self.lengths.input]
+                .iter()
+                .map(|cell| {
+                    let gene = scaling.gene(cell.get_node().node_id);
+                    (to_float(gene.scale), to_float(gene.offset))
+                })
+                .collect_vec()
+        };
+    }
+
+    /// Make the input at `index` optional, held at `default` when missing.
+    pub fn set_input_default(&mut self, index: usize, default: F) {
+        self.input_defaults[index] = Some(default);
+    }
+
+    /// Forward pass where only the inputs set in `mask` are given, in order.
+    /// Missing inputs are held at their default; `None` if a missing input is
+    /// required or the lengths do not match.
+    pub fn forward_masked(&mut self, inputs: &[F], mask: &[bool]) -> Option<Vec<F>> {
+        if mask.len() != self.lengths.input {
+            return None;
+        }
+        let mut given = inputs.iter().copied();
+        let input_vector = mask
+            .iter()
+            .zip(self.input_defaults.iter())
+            .map(|(present, default)| if *present { given.next() } else { *default })
+            .collect::<Option<Vec<_>>>()?;
+        if given.next().is_some() {
+            return None;
+        }
+        self.forward(&input_vector)
+    }
+
+    /// Forward pass on the inputs `observation` encodes.
+    pub fn forward_encoded(&mut self, observation: &(impl Encode + ?Sized)) -> Option<Vec<F>> {
+        let inputs = observation.to_inputs().into_iter().map(to_float).collect_vec();
+        self.forward(&inputs)
     }
 
     // Assumption of memory
-    pub fn forward(&mut self, input_vector: &[f32]) -> Option<Vec<f32>> {
-        self.pass = !self.pass;
+    pub fn forward(&mut self, input_vector: &[F]) -> Option<Vec<F>> {
         if input_vector.len() != self.lengths.input {
+            self.pass = !self.pass;
             return None;
         }
+        let cacheable = self.dropped_len() == 0 && self.noise.is_none();
+        let key = match self.cache.as_mut() {
+            Some(cache) if cacheable => {
+                let key = cache.key(input_vector);
+                if let Some(outputs) = cache.get(&key) {
+                    self.diverged |= outputs.iter().any(|x| !x.is_finite());
+                    return Some(outputs);
+                }
+                Some(key)
+            }
+            _ => None,
+        };
+        let outputs = self.propagate(input_vector);
+        if let (Some(cache), Some(key)) = (self.cache.as_mut(), key) {
+            cache.insert(key, outputs.clone());
+        }
+        Some(outputs)
+    }
+
+    fn propagate(&mut self, input_vector: &[F]) -> Vec<F> {
+        let scaled;
+        let input_vector = if self.input_scaling.is_empty() {
+            input_vector
+        } else {
+            scaled = input_vector
+                .iter()
+                .zip(self.input_scaling.iter())
+                .map(|(x, (scale, offset))| *scale * *x + *offset)
+                .collect_vec();
+            &scaled
+        };
+        if self.stale_schedule {
+            if let Some(feed_forward) = self.feed_forward.as_mut() {
+                feed_forward.reweight(&self.edge_map);
+            }
+            self.stale_schedule = false;
+        }
+        if let Some(feed_forward) = self.feed_forward.as_mut() {
+            let noise = self.noise.as_deref_mut().map(|rng| rng as &mut dyn RngCore);
+            let outputs = feed_forward.propagate(&mut self.memory, self.lengths, &self.dropped, input_vector, noise);
+            self.diverged |= outputs.iter().any(|x| !x.is_finite());
+            return outputs;
+        }
+        self.pass = !self.pass;
         for (cell, val) in (0..self.lengths.input).zip_eq(input_vector.iter().copied()) {
             self.memory[cell].propagate_input(val);
         }
@@ -123,18 +464,20 @@ impl FFNetwork {
         while let Some(Reverse(LevelNode(head))) = queue.pop() {
             let head_id = head;
             let head_idx = get_mem_location(&self.memory, head_id.node_id);
-            if self.is_hidden(head_id.node_id) {
-                for v in self.back_map[self.translate_hidden(head.node_id)]
-                    .iter()
-                    .copied()
-                {
+            // Recurrent edges into outputs and hidden nodes
+            if let Some(back) = head_idx.checked_sub(self.lengths.input) {
+                for v in self.back_map[back].iter().copied() {
                     let index = get_mem_location(&self.memory, v.dest);
                     let inp = self.memory[index].get_previous_output(self.pass);
                     self.memory[head_idx].propagate_input(inp * v.weight);
                 }
             }
 
-            self.memory[head_idx].activate(self.pass);
+            let noise = self.noise.as_deref_mut().map(|rng| rng as &mut dyn RngCore);
+            self.memory[head_idx].activate(self.pass, noise);
+            if self.is_dropped(head_idx) {
+                self.memory[head_idx].silence();
+            }
             for Edge { dest, weight } in self.edge_map[head_idx].iter().copied() {
                 let index = get_mem_location(&self.memory, dest);
                 let input = self.memory[head_idx]
@@ -147,12 +490,18 @@ impl FFNetwork {
             }
         }
         // Extract output memory cells
-        Some(
-            self.memory[self.lengths.input..self.lengths.input + self.lengths.output]
-                .iter()
-                .map(|cell| cell.get_current_output(self.pass).unwrap_or(0.))
-                .collect_vec(),
-        )
+        let outputs = self.memory[self.lengths.input..self.lengths.input + self.lengths.output]
+            .iter()
+            .map(|cell| cell.get_current_output(self.pass).unwrap_or(F::zero()))
+            .collect_vec();
+        self.diverged |= outputs.iter().any(|x| !x.is_finite());
+        outputs
+    }
+}
+
+impl<F: Float> From<&Genome> for FFNetwork<F> {
+    fn from(genome: &Genome) -> Self {
+        Self::with_schedule(genome, &Schedule::new(&genome.node_list, &genome.genome_list.edge_list))
     }
 }
 
@@ -234,8 +583,7 @@ mod tests {
                         node_id: c,
                         level: Ratio::from_integer(1),
                         config: Default::default(),
-                    })
-                    .into_iter(),
+                    }),
             ),
             output: Vec::from_iter(
                 [2, 3, 4, 5]
@@ -243,10 +591,11 @@ mod tests {
                         node_id: c,
                         level: Ratio::from_integer(100),
                         config: Default::default(),
-                    })
-                    .into_iter(),
+                    }),
             ),
             hidden: vec![],
+            names: Default::default(),
+            input_defaults: Default::default(),
         };
         let (x1, x2) = (0.1, 0.5);
         let mut genome = FFNetwork::new(node_list, edges);
@@ -256,7 +605,7 @@ mod tests {
             Activation::Relu.activate((x1 * weights[4] + x2 * weights[5]) / 2.),
             Activation::Relu.activate((x1 * weights[6] + x2 * weights[7]) / 2.),
         ];
-        let output_genome = genome.forward(&vec![x1, x2]);
+        let output_genome = genome.forward(&[x1, x2]);
         assert!(dbg!(outputs)
             .iter()
             .copied()
@@ -324,8 +673,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(1),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 output: Vec::from_iter(
                     [2, 3]
@@ -333,8 +681,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(100),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 hidden: [4, 5]
                     .map(|c| Node {
@@ -343,11 +690,13 @@ mod tests {
                         config: Default::default(),
                     })
                     .into(),
+                names: Default::default(),
+                input_defaults: Default::default(),
             };
             let (x1, x2) = (0.1, 0.5);
             let mut genome = FFNetwork::new(node_list, edges);
-            let outputs = vec![0.3, 1.5];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let outputs = [0.3, 1.5];
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(outputs
                 .iter()
                 .copied()
@@ -411,8 +760,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(1),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 output: Vec::from_iter(
                     [2, 3]
@@ -420,8 +768,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(100),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 hidden: [4]
                     .map(|c| Node {
@@ -430,11 +777,13 @@ mod tests {
                         config: Default::default(),
                     })
                     .into(),
+                names: Default::default(),
+                input_defaults: Default::default(),
             };
             let mut genome = FFNetwork::new(node_list, edges);
             let (x1, x2) = (0.3, 0.3);
             let outputs = vec![0.8, 0.8];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(dbg!(outputs)
                 .iter()
                 .copied()
@@ -447,7 +796,7 @@ mod tests {
 
             let (x1, x2) = (0.1, 0.1);
             let outputs = vec![0., 0.];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(dbg!(outputs)
                 .iter()
                 .copied()
@@ -525,16 +874,14 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(1),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 output: Vec::from_iter(
                     [2].map(|c| Node {
                         node_id: c,
                         level: Ratio::from_integer(100),
                         config: Default::default(),
-                    })
-                    .into_iter(),
+                    }),
                 ),
                 hidden: [
                     Node {
@@ -554,11 +901,13 @@ mod tests {
                     },
                 ]
                 .into(),
+                names: Default::default(),
+                input_defaults: Default::default(),
             };
             let mut genome = FFNetwork::new(node_list, edges);
             let (x1, x2) = (1., 1.);
             let outputs = vec![0.171875];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(dbg!(outputs)
                 .iter()
                 .copied()
@@ -571,7 +920,7 @@ mod tests {
 
             let (x1, x2) = (2., 2.);
             let outputs = vec![0.3466796875];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(dbg!(outputs)
                 .iter()
                 .copied()
@@ -637,8 +986,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(1),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 output: Vec::from_iter(
                     [2, 3]
@@ -646,8 +994,7 @@ mod tests {
                             node_id: c,
                             level: Ratio::from_integer(100),
                             config: Default::default(),
-                        })
-                        .into_iter(),
+                        }),
                 ),
                 hidden: [6, 7]
                     .map(|c| Node {
@@ -656,11 +1003,13 @@ mod tests {
                         config: Default::default(),
                     })
                     .into(),
+                names: Default::default(),
+                input_defaults: Default::default(),
             };
             let (x1, x2) = (0.1, 0.5);
             let mut genome = FFNetwork::new(node_list, edges);
             let outputs = vec![0.3, 1.5];
-            let output_genome = genome.forward(&vec![x1, x2]);
+            let output_genome = genome.forward(&[x1, x2]);
             assert!(dbg!(outputs)
                 .iter()
                 .copied()
@@ -673,6 +1022,204 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_f64_matches_f32() {
+        let edges = vec![
+            GenomeEdge {
+                innov_number: 0,
+                in_node: 0,
+                out_node: 3,
+                weight: 0.5,
+                enabled: true,
+            },
+            GenomeEdge {
+                innov_number: 1,
+                in_node: 1,
+                out_node: 3,
+                weight: -0.25,
+                enabled: true,
+            },
+            GenomeEdge {
+                innov_number: 2,
+                in_node: 3,
+                out_node: 2,
+                weight: 1.5,
+                enabled: true,
+            },
+            GenomeEdge {
+                innov_number: 3,
+                in_node: 2,
+                out_node: 3,
+                weight: 0.75,
+                enabled: true,
+            },
+        ];
+        let node_list = NodeList {
+            input: Arc::from_iter([0, 1].map(|c| Node {
+                node_id: c,
+                level: Ratio::from_integer(1),
+                config: Default::default(),
+            })),
+            output: vec![Node {
+                node_id: 2,
+                level: Ratio::from_integer(100),
+                config: Default::default(),
+            }],
+            hidden: vec![Node {
+                node_id: 3,
+                level: Ratio::from_integer(50),
+                config: Default::default(),
+            }],
+            names: Default::default(),
+            input_defaults: Default::default(),
+        };
+        let mut single = FFNetwork::<f32>::new(node_list.clone(), edges.clone());
+        let mut double = FFNetwork::<f64>::new(node_list, edges);
+        for step in 0..10 {
+            let (x1, x2) = (step as f64 * 0.3, 1. - step as f64 * 0.1);
+            let out_single = single.forward(&[x1 as f32, x2 as f32]).expect("Should be legal input");
+            let out_double = double.forward(&[x1, x2]).expect("Should be legal input");
+            for (a, b) in out_single.iter().zip_eq(out_double.iter()) {
+                assert_relative_eq!(*a as f64, *b, epsilon = 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_recurrent_outputs() {
+        use crate::individual::genome::{aggregation::Aggregation, genome::GenomeBuilder};
+
+        // Output 1 feeds back on itself and reads output 2 of the last pass
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(2)
+            .connect(0, 1, 1.)
+            .connect(0, 2, 1.)
+            .connect(1, 1, 0.5)
+            .connect(2, 1, 2.)
+            .aggregation(1, Aggregation::Sum)
+            .activation(1, Activation::Identity)
+            .aggregation(2, Aggregation::Sum)
+            .activation(2, Activation::Identity)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert_eq!(network.forward(&[1.]), Some(vec![1., 1.]));
+        assert_eq!(network.forward(&[1.]), Some(vec![3.5, 1.]));
+        assert_eq!(network.forward(&[0.]), Some(vec![3.75, 0.]));
+    }
+
+    #[test]
+    fn test_dropout() {
+        use crate::individual::genome::{aggregation::Aggregation, genome::GenomeBuilder};
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        // Two redundant hidden paths, the output sums them
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(50)
+            .hidden(50)
+            .connect(0, 2, 1.)
+            .connect(0, 3, 1.)
+            .connect(2, 1, 1.)
+            .connect(3, 1, 1.)
+            .aggregation(1, Aggregation::Sum)
+            .activation(1, Activation::Identity)
+            .build()
+            .unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert_eq!(network.forward_with_dropout(&mut rng, 0., &[1.]), Some(vec![2.]));
+        assert_eq!(network.forward_with_dropout(&mut rng, 1., &[1.]), Some(vec![0.]));
+        // The sample of the last call is kept
+        assert_eq!(network.dropped_len(), 2);
+        assert_eq!(network.forward(&[1.]), Some(vec![0.]));
+        network.clear_dropout();
+        assert_eq!(network.forward(&[1.]), Some(vec![2.]));
+        for _ in 0..20 {
+            let output = network.forward_with_dropout(&mut rng, 0.5, &[1.]).unwrap();
+            assert_eq!(output[0], (2 - network.dropped_len()) as f32);
+        }
+    }
+
+    #[test]
+    fn test_noise() {
+        use crate::individual::genome::genome::GenomeBuilder;
+
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(0, 1, 1.)
+            .activation(1, Activation::NoisyRelu(1.))
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert!(!network.is_noisy());
+        assert_eq!(network.forward(&[2.]), Some(vec![2.]));
+        network.enable_cache(16, 1e-3);
+        network.enable_noise(3);
+        let outputs = (0..10).map(|_| network.forward(&[2.]).unwrap()[0]).collect::<Vec<_>>();
+        assert!(outputs.iter().all(|x| *x >= 0.));
+        assert!(outputs.iter().any(|x| *x != outputs[0]));
+        // Same seed, same samples
+        network.enable_noise(3);
+        assert!(outputs.iter().all(|x| network.forward(&[2.]).unwrap()[0] == *x));
+        network.disable_noise();
+        assert_eq!(network.forward(&[2.]), Some(vec![2.]));
+    }
+
+    #[test]
+    fn test_calibrate_clamps() {
+        use crate::individual::genome::genome::GenomeBuilder;
+
+        let mut genome = GenomeBuilder::new()
+            .input(1)
+            .output(2)
+            .connect(0, 1, 1.)
+            .activation(1, Activation::Identity)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        let samples = [vec![0.], vec![1.], vec![2.]];
+        let clamps = network.calibrate_clamps(&samples, 0.5);
+        // The unconnected output is constant, so it keeps its clamp
+        assert_eq!(clamps.keys().collect_vec(), vec![&1]);
+        assert_eq!(clamps[&1], Clamp::new(Some(-1.), Some(3.)).unwrap());
+        assert_eq!(network.forward(&[10.]), Some(vec![3., 0.]));
+
+        genome.apply_clamps(&clamps);
+        assert_eq!(genome.node_list.output[0].config.clamp, clamps[&1]);
+        assert_eq!(genome.node_list.output[1].config.clamp, Clamp::default());
+        assert_eq!(FFNetwork::<f32>::from(&genome).forward(&[-10.]), Some(vec![-1., 0.]));
+    }
+
+    #[test]
+    fn test_saturation_stats() {
+        use crate::individual::genome::genome::GenomeBuilder;
+
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(2)
+            .connect(0, 1, 1.)
+            .activation(1, Activation::Identity)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        for input in [0., 10., -10., 1.] {
+            network.forward(&[input]).unwrap();
+        }
+        network.reset();
+        let stats = network.saturation_stats();
+        // Inputs are not activated
+        assert_eq!(stats.keys().collect_vec(), vec![&1, &2]);
+        assert_eq!((stats[&1].activations, stats[&1].at_min, stats[&1].at_max), (4, 1, 1));
+        assert_eq!(stats[&2].saturated(), 0);
+        network.clear_saturation_stats();
+        assert_eq!(network.saturation_stats()[&1], SaturationStats::default());
+    }
+
     #[test]
     fn rational_test() {
         let a = rational::Ratio::new(3usize, 2);
@@ -0,0 +1,129 @@
+use num::Float;
+use serde::{Deserialize, Serialize};
+
+use super::network::FFNetwork;
+
+/// Outputs forming independent softmax groups, e.g. a categorical action
+/// head next to continuous outputs. Each group is a half-open `(start, end)`
+/// range of output positions; outputs outside every group are left as is.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OutputGroups {
+    groups: Vec<(usize, usize)>,
+}
+
+impl OutputGroups {
+    /// `None` if a group is empty or overlaps another.
+    pub fn new(groups: Vec<(usize, usize)>) -> Option<Self> {
+        let groups = Self { groups };
+        groups.is_valid().then_some(groups)
+    }
+
+    pub fn groups(&self) -> &[(usize, usize)] {
+        &self.groups
+    }
+
+    /// Whether the groups are non-empty and disjoint.
+    pub fn is_valid(&self) -> bool {
+        let mut sorted = self.groups.clone();
+        sorted.sort_unstable();
+        sorted.iter().all(|(start, end)| start < end)
+            && sorted.windows(2).all(|pair| pair[0].1 <= pair[1].0)
+    }
+
+    /// Number of outputs the groups need.
+    pub fn min_outputs(&self) -> usize {
+        self.groups.iter().map(|(_, end)| *end).max().unwrap_or(0)
+    }
+
+    /// Softmax every group of `outputs` in place. `false`, leaving `outputs`
+    /// untouched, if a group lies past the outputs.
+    pub fn apply<F: Float>(&self, outputs: &mut [F]) -> bool {
+        if self.min_outputs() > outputs.len() {
+            return false;
+        }
+        for (start, end) in self.groups.iter().copied() {
+            let group = &mut outputs[start..end];
+            let max = group.iter().copied().fold(F::neg_infinity(), F::max);
+            for output in group.iter_mut() {
+                *output = (*output - max).exp();
+            }
+            let sum = group.iter().fold(F::zero(), |acc, &o| acc + o);
+            for output in group.iter_mut() {
+                *output = *output / sum;
+            }
+        }
+        true
+    }
+
+    /// Forward `input` through `network` and apply the groups.
+    pub fn forward<F: Float>(&self, network: &mut FFNetwork<F>, input: &[F]) -> Option<Vec<F>> {
+        let mut outputs = network.forward(input)?;
+        self.apply(&mut outputs).then_some(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+    };
+
+    #[test]
+    fn test_new() {
+        assert!(OutputGroups::new(vec![(0, 4), (4, 6)]).is_some());
+        assert!(OutputGroups::new(vec![(4, 6), (0, 4)]).is_some());
+        assert!(OutputGroups::new(vec![(0, 4), (3, 6)]).is_none());
+        assert!(OutputGroups::new(vec![(2, 2)]).is_none());
+        assert_eq!(OutputGroups::default().min_outputs(), 0);
+    }
+
+    #[test]
+    fn test_apply() {
+        let groups = OutputGroups::new(vec![(0, 2), (3, 5)]).unwrap();
+        let mut outputs = [0., 0., 7., 1000., 1000. + 2f32.ln()];
+        assert!(groups.apply(&mut outputs));
+        assert_eq!(outputs[..3], [0.5, 0.5, 7.]);
+        assert_relative_eq!(outputs[3], 1. / 3., epsilon = 1e-4);
+        assert_relative_eq!(outputs[4], 2. / 3., epsilon = 1e-4);
+
+        let mut short = [1., 2., 3.];
+        assert!(!groups.apply(&mut short));
+        assert_eq!(short, [1., 2., 3.]);
+    }
+
+    #[test]
+    fn test_forward() {
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(3)
+            .aggregation(1, Aggregation::Sum)
+            .aggregation(2, Aggregation::Sum)
+            .activation(1, Activation::Identity)
+            .activation(2, Activation::Identity)
+            .activation(3, Activation::Identity)
+            .connect(0, 1, 1.)
+            .connect(0, 3, 2.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::from(&genome);
+        let groups = OutputGroups::new(vec![(0, 2)]).unwrap();
+        let outputs = groups.forward(&mut network, &[0.]).unwrap();
+        assert_eq!(outputs, vec![0.5, 0.5, 0.]);
+        assert_eq!(
+            OutputGroups::new(vec![(0, 4)])
+                .unwrap()
+                .forward(&mut network, &[0.]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let groups: OutputGroups = serde_json::from_str("[[0, 4], [4, 6]]").unwrap();
+        assert_eq!(groups.groups(), &[(0, 4), (4, 6)]);
+    }
+}
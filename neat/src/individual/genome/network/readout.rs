@@ -0,0 +1,112 @@
+use num::Float;
+use serde::{Deserialize, Serialize};
+
+use super::network::FFNetwork;
+
+/// Reduction of the network outputs to scalar decisions after the forward
+/// pass, e.g. for regression with several evolved outputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Readout {
+    /// Mean of the outputs.
+    Mean,
+    /// Weighted vote of the outputs: their weighted sum over the sum of the
+    /// absolute weights, one weight per output.
+    Weighted(Vec<f32>),
+    /// Fixed projection matrix, one row of output weights per scalar.
+    Projection(Vec<Vec<f32>>),
+}
+
+impl Readout {
+    /// Number of scalars produced.
+    pub fn len(&self) -> usize {
+        match self {
+            Readout::Mean | Readout::Weighted(_) => 1,
+            Readout::Projection(rows) => rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `None` if the outputs do not match the weights, or are empty.
+    pub fn apply<F: Float>(&self, outputs: &[F]) -> Option<Vec<F>> {
+        let float = |x: f32| F::from(x).unwrap();
+        let dot = |weights: &[f32]| {
+            (weights.len() == outputs.len()).then(|| {
+                weights
+                    .iter()
+                    .zip(outputs.iter())
+                    .fold(F::zero(), |acc, (&w, &o)| acc + float(w) * o)
+            })
+        };
+        match self {
+            Readout::Mean => (!outputs.is_empty()).then(|| {
+                outputs.iter().fold(F::zero(), |acc, &o| acc + o) / F::from(outputs.len()).unwrap()
+            }),
+            Readout::Weighted(weights) => {
+                let total = weights.iter().map(|w| w.abs()).sum::<f32>();
+                dot(weights)
+                    .filter(|_| total > 0.)
+                    .map(|sum| sum / float(total))
+            }
+            Readout::Projection(rows) => return rows.iter().map(|row| dot(row)).collect(),
+        }
+        .map(|scalar| vec![scalar])
+    }
+
+    /// Forward `input` through `network` and read the outputs out.
+    pub fn forward<F: Float>(&self, network: &mut FFNetwork<F>, input: &[F]) -> Option<Vec<F>> {
+        self.apply(&network.forward(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+    };
+
+    #[test]
+    fn test_apply() {
+        let outputs = [1., 2., 6.];
+        assert_eq!(Readout::Mean.apply(&outputs), Some(vec![3.]));
+        assert_eq!(Readout::Mean.apply::<f32>(&[]), None);
+        let vote = Readout::Weighted(vec![1., -1., 0.]);
+        assert_eq!(vote.apply(&outputs), Some(vec![-0.5]));
+        assert_eq!(vote.apply(&outputs[..2]), None);
+        assert_eq!(Readout::Weighted(vec![0.; 3]).apply(&outputs), None);
+        let projection = Readout::Projection(vec![vec![1., 0., 0.], vec![0., 0.5, 0.5]]);
+        assert_eq!(projection.len(), 2);
+        assert_eq!(projection.apply(&outputs), Some(vec![1., 4.]));
+        assert_eq!(projection.apply(&[1., 2.]), None);
+    }
+
+    #[test]
+    fn test_forward() {
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(2)
+            .aggregation(1, Aggregation::Sum)
+            .aggregation(2, Aggregation::Sum)
+            .activation(1, Activation::Identity)
+            .activation(2, Activation::Identity)
+            .connect(0, 1, 1.)
+            .connect(0, 2, 3.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::from(&genome);
+        assert_eq!(Readout::Mean.forward(&mut network, &[0.5]), Some(vec![1.]));
+        assert_eq!(Readout::Mean.forward(&mut network, &[0.5, 1.]), None);
+    }
+
+    #[test]
+    fn test_serde() {
+        let readout: Readout = serde_json::from_str(r#"{"weighted": [1.0, 2.0]}"#).unwrap();
+        assert_eq!(readout, Readout::Weighted(vec![1., 2.]));
+        let readout: Readout = serde_json::from_str(r#""mean""#).unwrap();
+        assert_eq!(readout, Readout::Mean);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use num::Float;
+use serde::{Deserialize, Serialize};
+
+use crate::individual::genome::{
+    clamp::Clamp,
+    node_list::{to_float, NodeId},
+};
+
+/// How often the activated output of a node hit its clamp. A saturated
+/// node barely reacts to its inputs, so mutating them stops paying off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SaturationStats {
+    pub activations: usize,
+    pub at_min: usize,
+    pub at_max: usize,
+}
+
+impl SaturationStats {
+    /// Activations at either bound.
+    pub fn saturated(&self) -> usize {
+        self.at_min + self.at_max
+    }
+
+    /// Share of the activations at either bound, 0 before any activation.
+    pub fn fraction(&self) -> f32 {
+        if self.activations == 0 {
+            0.
+        } else {
+            self.saturated() as f32 / self.activations as f32
+        }
+    }
+
+    /// Add the counts of `other`, e.g. of another network of the same genome.
+    pub fn merge(&mut self, other: &Self) {
+        self.activations += other.activations;
+        self.at_min += other.at_min;
+        self.at_max += other.at_max;
+    }
+
+    /// Count an activation, `output` being the activated value before `clamp`.
+    pub fn record<F: Float>(&mut self, clamp: &Clamp, output: F) {
+        self.activations += 1;
+        if clamp.min_limit.is_some_and(|m| output <= to_float(m)) {
+            self.at_min += 1;
+        } else if clamp.max_limit.is_some_and(|m| output >= to_float(m)) {
+            self.at_max += 1;
+        }
+    }
+}
+
+/// Warn when at least a `network` share of the activated nodes are
+/// saturated, a node being saturated when at least a `node` share of its
+/// activations hit the clamp.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SaturationWarning {
+    pub node: f32,
+    pub network: f32,
+}
+
+impl Default for SaturationWarning {
+    fn default() -> Self {
+        Self {
+            node: 0.9,
+            network: 0.5,
+        }
+    }
+}
+
+impl SaturationWarning {
+    /// Number of saturated nodes and of activated nodes if `stats`, as
+    /// returned by `FFNetwork::saturation_stats`, deserve a warning.
+    pub fn check(&self, stats: &BTreeMap<NodeId, SaturationStats>) -> Option<(usize, usize)> {
+        let nodes = stats.values().filter(|s| s.activations > 0).count();
+        let saturated = stats
+            .values()
+            .filter(|s| s.activations > 0 && s.fraction() >= self.node)
+            .count();
+        (nodes > 0 && saturated as f32 >= self.network * nodes as f32).then_some((saturated, nodes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let clamp = Clamp::new(Some(-1.), Some(1.)).unwrap();
+        let mut stats = SaturationStats::default();
+        assert_eq!(stats.fraction(), 0.);
+        for output in [-2., -1., 0., 0.5, 1., f32::NAN] {
+            stats.record(&clamp, output);
+        }
+        assert_eq!(
+            stats,
+            SaturationStats {
+                activations: 6,
+                at_min: 2,
+                at_max: 1,
+            }
+        );
+        assert_eq!(stats.fraction(), 0.5);
+
+        let mut unbounded = SaturationStats::default();
+        unbounded.record(&Clamp::new(None, None).unwrap(), 1e30);
+        assert_eq!(unbounded.saturated(), 0);
+    }
+
+    #[test]
+    fn test_warning() {
+        let saturated = SaturationStats {
+            activations: 10,
+            at_min: 0,
+            at_max: 9,
+        };
+        let unsaturated = SaturationStats {
+            activations: 10,
+            ..Default::default()
+        };
+        let mut stats = BTreeMap::from([(1, saturated), (2, SaturationStats::default())]);
+        let warning = SaturationWarning::default();
+        // Nodes never activated are ignored
+        assert_eq!(warning.check(&stats), Some((1, 1)));
+        stats.insert(3, unsaturated);
+        assert_eq!(warning.check(&stats), Some((1, 2)));
+        stats.insert(4, unsaturated);
+        assert_eq!(warning.check(&stats), None);
+        assert_eq!(warning.check(&BTreeMap::new()), None);
+    }
+}
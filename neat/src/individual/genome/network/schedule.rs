@@ -0,0 +1,229 @@
+//! Layout of a network structure, computed once and shared by the networks
+//! of every genome with that structure, which in a converged population
+//! differ in weights and node configuration only.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use itertools::Itertools;
+use num::{rational::Ratio, Float};
+
+use super::{cache::CacheStats, feed_forward::Plan, network::FFNetwork};
+use crate::individual::genome::{
+    genome::{Genome, GenomeEdge},
+    node_list::{Node, NodeId, NodeList},
+};
+
+/// Where the cells and edges of a compiled network go, and the order a
+/// network without recurrent edges is evaluated in. Weight and
+/// configuration free, see [`ScheduleCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// Position of every memory cell, in id order, among the input, output
+    /// then hidden nodes.
+    pub(super) cells: Vec<usize>,
+    /// Slot of every enabled edge, in genome order.
+    pub(super) edges: Vec<EdgeSlot>,
+    pub(super) plan: Option<Arc<Plan>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum EdgeSlot {
+    /// In the forward edges of the cell at this memory index.
+    Forward(usize),
+    /// In the recurrent edges into this output or hidden cell, by index
+    /// after the inputs.
+    Recurrent(usize),
+}
+
+impl Schedule {
+    /// Schedule of the nodes of `node_list` joined by `edges`.
+    pub fn new(node_list: &NodeList, edges: &[GenomeEdge]) -> Self {
+        let nodes = nodes(node_list).collect_vec();
+        let cells = (0..nodes.len())
+            .sorted_by_key(|index| nodes[*index].node_id)
+            .collect_vec();
+        let ids = cells
+            .iter()
+            .map(|index| nodes[*index].node_id)
+            .collect_vec();
+        let levels = cells.iter().map(|index| nodes[*index].level).collect_vec();
+        let location = |id: NodeId| {
+            ids.binary_search(&id)
+                .unwrap_or_else(|_| panic!("Id {id:?} should be in list"))
+        };
+        let inputs = node_list.input.len();
+        let mut targets = vec![vec![]; cells.len()];
+        let edges = edges
+            .iter()
+            .filter(|edge| edge.enabled)
+            .map(|edge| {
+                let (source, target) = (location(edge.in_node), location(edge.out_node));
+                if levels[source] >= levels[target] {
+                    EdgeSlot::Recurrent(target - inputs)
+                } else {
+                    targets[source].push(target);
+                    EdgeSlot::Forward(source)
+                }
+            })
+            .collect_vec();
+        // Gated cells carry a state across passes like recurrent edges
+        let recurrent = edges
+            .iter()
+            .any(|slot| matches!(slot, EdgeSlot::Recurrent(_)))
+            || nodes.iter().any(|node| node.config.gate.is_some());
+        let plan = Plan::new(&levels, &targets, inputs, recurrent).map(Arc::new);
+        Self { cells, edges, plan }
+    }
+}
+
+/// Input, output then hidden nodes.
+pub(super) fn nodes(node_list: &NodeList) -> impl Iterator<Item = &Node> {
+    node_list
+        .input
+        .iter()
+        .chain(node_list.output.iter())
+        .chain(node_list.hidden.iter())
+}
+
+/// What a [`Schedule`] depends on: the id, level and gatedness of every
+/// node, in node list order, and the endpoints of the enabled edges, in
+/// genome order.
+type StructureKey = (Vec<(NodeId, Ratio<usize>, bool)>, Vec<(NodeId, NodeId)>);
+
+/// Schedules by structure, so compiling the genomes of a generation plans
+/// every distinct structure once. Clear it between generations, or keep it
+/// while the structures it holds are still around.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleCache {
+    schedules: BTreeMap<StructureKey, Arc<Schedule>>,
+    stats: CacheStats,
+}
+
+impl ScheduleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Network of `genome`, as `FFNetwork::from` compiles it, on the cached
+    /// schedule of its structure.
+    pub fn compile<F: Float>(&mut self, genome: &Genome) -> FFNetwork<F> {
+        let key = (
+            nodes(&genome.node_list)
+                .map(|node| (node.node_id, node.level, node.config.gate.is_some()))
+                .collect_vec(),
+            genome
+                .genome_list
+                .iter()
+                .filter(|edge| edge.enabled)
+                .map(|edge| (edge.in_node, edge.out_node))
+                .collect_vec(),
+        );
+        let schedule = match self.schedules.get(&key) {
+            Some(schedule) => {
+                self.stats.hits += 1;
+                Arc::clone(schedule)
+            }
+            None => {
+                self.stats.misses += 1;
+                let schedule = Arc::new(Schedule::new(
+                    &genome.node_list,
+                    &genome.genome_list.edge_list,
+                ));
+                self.schedules.insert(key, Arc::clone(&schedule));
+                schedule
+            }
+        };
+        FFNetwork::with_schedule(genome, &schedule)
+    }
+
+    /// Lookups since the cache was created or cleared, and the structures
+    /// it holds.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.schedules.len(),
+            ..self.stats
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.schedules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schedules.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.schedules.clear();
+        self.stats = CacheStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation,
+        genome::GenomeBuilder,
+        node_list::Gate,
+    };
+
+    fn builder(level: usize, weight: f32, activation: Activation) -> GenomeBuilder {
+        GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(level)
+            .connect(0, 3, weight)
+            .connect(1, 3, -0.5)
+            .connect(3, 2, 1.5)
+            .connect(0, 2, weight * 2.)
+            .activation(3, activation)
+            .bias(2, weight)
+    }
+
+    fn outputs(network: &mut FFNetwork<f32>) -> Vec<Vec<f32>> {
+        [[0.5, 1.], [-1., 2.], [0.3, -0.7]]
+            .iter()
+            .map(|input| network.forward(input).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_compile() {
+        let mut cache = ScheduleCache::new();
+        let genomes = [
+            builder(50, 0.3, Activation::Tanh),
+            builder(50, -1.2, Activation::Relu),
+            builder(75, 0.3, Activation::Tanh),
+            builder(50, 0.3, Activation::Tanh).connect(2, 3, 0.8),
+            builder(50, 0.3, Activation::Tanh).connect(3, 3, 0.8),
+            builder(50, 0.3, Activation::Tanh).gate(3, Gate::default()),
+        ]
+        .map(|builder| builder.build().unwrap());
+        for genome in genomes.iter() {
+            let mut scheduled = cache.compile::<f32>(genome);
+            let mut compiled = FFNetwork::<f32>::from(genome);
+            assert_eq!(scheduled.is_feed_forward(), compiled.is_feed_forward());
+            assert_eq!(outputs(&mut scheduled), outputs(&mut compiled));
+        }
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 5,
+                len: 5,
+            }
+        );
+        // Weights edited in place reach the shared schedule's evaluation
+        let mut scheduled = cache.compile::<f32>(&genomes[0]);
+        let mut compiled = FFNetwork::<f32>::from(&genomes[0]);
+        for network in [&mut scheduled, &mut compiled] {
+            network.set_weight(0, 3, 4.).unwrap();
+        }
+        assert_eq!(outputs(&mut scheduled), outputs(&mut compiled));
+        assert_eq!(cache.stats().hits, 2);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+}
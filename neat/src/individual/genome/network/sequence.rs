@@ -0,0 +1,244 @@
+use itertools::Itertools;
+use num::Float;
+
+use super::network::FFNetwork;
+
+/// Loss between the network outputs and the targets of one step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Loss {
+    /// Mean squared error over the outputs.
+    #[default]
+    Mse,
+    /// Categorical cross-entropy. Outputs are passed through a softmax, so
+    /// they do not need to be normalised.
+    CrossEntropy,
+}
+
+impl Loss {
+    pub fn apply<F: Float>(&self, output: &[F], target: &[F]) -> F {
+        assert_eq!(output.len(), target.len(), "Output and target should have the same length");
+        match self {
+            Loss::Mse => {
+                output
+                    .iter()
+                    .zip(target.iter())
+                    .map(|(&o, &t)| (o - t) * (o - t))
+                    .fold(F::zero(), |acc, x| acc + x)
+                    / F::from(output.len()).unwrap()
+            }
+            Loss::CrossEntropy => {
+                let max = output.iter().copied().fold(F::neg_infinity(), F::max);
+                let log_sum = output
+                    .iter()
+                    .map(|&o| (o - max).exp())
+                    .fold(F::zero(), |acc, x| acc + x)
+                    .ln();
+                output
+                    .iter()
+                    .zip(target.iter())
+                    .map(|(&o, &t)| -t * (o - max - log_sum))
+                    .fold(F::zero(), |acc, x| acc + x)
+            }
+        }
+    }
+}
+
+/// What is appended to the input of each step, besides the sequence input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Feedback {
+    /// Only the sequence input is fed.
+    #[default]
+    None,
+    /// The target of the previous step is appended (zeros on the first step).
+    TeacherForcing,
+    /// The output of the previous step is appended (zeros on the first step).
+    FreeRunning,
+}
+
+/// Inputs and targets of a sequence, one vector per step.
+pub type Sequence<F = f32> = (Vec<Vec<F>>, Vec<Vec<F>>);
+
+/// Outputs of a sequence and their mean loss.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceResult<F = f32> {
+    pub outputs: Vec<Vec<F>>,
+    pub loss: F,
+}
+
+/// Feeds input sequences through a recurrent network and scores the outputs
+/// against the target sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceEvaluator {
+    pub loss: Loss,
+    pub feedback: Feedback,
+    /// Reset the network state before every sequence.
+    pub reset_state: bool,
+}
+
+impl Default for SequenceEvaluator {
+    fn default() -> Self {
+        Self {
+            loss: Loss::default(),
+            feedback: Feedback::default(),
+            reset_state: true,
+        }
+    }
+}
+
+impl SequenceEvaluator {
+    pub fn new(loss: Loss) -> Self {
+        Self {
+            loss,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_feedback(mut self, feedback: Feedback) -> Self {
+        self.feedback = feedback;
+        self
+    }
+
+    pub fn with_reset_state(mut self, reset_state: bool) -> Self {
+        self.reset_state = reset_state;
+        self
+    }
+
+    /// Run a single sequence. Returns `None` if the sequences have different
+    /// lengths or a step does not match the network input size.
+    pub fn evaluate<F: Float>(
+        &self,
+        network: &mut FFNetwork<F>,
+        inputs: &[Vec<F>],
+        targets: &[Vec<F>],
+    ) -> Option<SequenceResult<F>> {
+        if inputs.len() != targets.len() {
+            return None;
+        }
+        if self.reset_state {
+            network.reset();
+        }
+        let mut outputs: Vec<Vec<F>> = Vec::with_capacity(inputs.len());
+        let mut loss = F::zero();
+        for (step, (input, target)) in inputs.iter().zip(targets.iter()).enumerate() {
+            let previous = match self.feedback {
+                Feedback::None => None,
+                Feedback::TeacherForcing => Some(step.checked_sub(1).map(|s| &targets[s])),
+                Feedback::FreeRunning => Some(step.checked_sub(1).map(|s| &outputs[s])),
+            };
+            let output = match previous {
+                None => network.forward(input)?,
+                Some(previous) => {
+                    let previous = previous
+                        .cloned()
+                        .unwrap_or_else(|| vec![F::zero(); network.output_len()]);
+                    network.forward(&input.iter().copied().chain(previous).collect_vec())?
+                }
+            };
+            if output.len() != target.len() {
+                return None;
+            }
+            loss = loss + self.loss.apply(&output, target);
+            outputs.push(output);
+        }
+        if !outputs.is_empty() {
+            loss = loss / F::from(outputs.len()).unwrap();
+        }
+        Some(SequenceResult { outputs, loss })
+    }
+
+    /// Mean loss over several `(inputs, targets)` sequences.
+    pub fn evaluate_all<F: Float>(
+        &self,
+        network: &mut FFNetwork<F>,
+        sequences: &[Sequence<F>],
+    ) -> Option<F> {
+        let mut total = F::zero();
+        for (inputs, targets) in sequences.iter() {
+            total = total + self.evaluate(network, inputs, targets)?.loss;
+        }
+        Some(total / F::from(sequences.len().max(1)).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::individual::genome::genome::{GenomeEdge, GenomeFactory};
+
+    /// Identity-like network: output `i` is `relu(x_i)`.
+    fn network(inputs: usize, outputs: usize) -> FFNetwork {
+        let mut genome = GenomeFactory::init(inputs, outputs).unwrap().generate_genome();
+        genome.genome_list.edge_list = (0..outputs as u64)
+            .map(|i| GenomeEdge {
+                innov_number: i,
+                in_node: i,
+                out_node: inputs as u64 + i,
+                weight: 1.,
+                enabled: true,
+            })
+            .collect();
+        FFNetwork::from(&genome)
+    }
+
+    #[test]
+    fn test_loss() {
+        assert_relative_eq!(Loss::Mse.apply(&[1f32, 3.], &[0., 1.]), 2.5);
+        let uniform = Loss::CrossEntropy.apply(&[0f32, 0.], &[1., 0.]);
+        assert_relative_eq!(uniform, 2f32.ln());
+        assert!(Loss::CrossEntropy.apply(&[5f32, 0.], &[1., 0.]) < uniform);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut net = network(1, 1);
+        let inputs = [vec![1.], vec![2.]];
+        let targets = [vec![1.], vec![0.]];
+        let result = SequenceEvaluator::default()
+            .evaluate(&mut net, &inputs, &targets)
+            .unwrap();
+        assert_eq!(result.outputs, vec![vec![1.], vec![2.]]);
+        assert_relative_eq!(result.loss, 2.);
+        assert!(SequenceEvaluator::default()
+            .evaluate(&mut net, &inputs, &targets[..1])
+            .is_none());
+    }
+
+    #[test]
+    fn test_feedback() {
+        // The second input is wired to the output, so it echoes the feedback.
+        let mut genome = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        genome.genome_list.edge_list = vec![GenomeEdge {
+            innov_number: 0,
+            in_node: 1,
+            out_node: 2,
+            weight: 1.,
+            enabled: true,
+        }];
+        let mut net = FFNetwork::from(&genome);
+        let inputs = [vec![0.], vec![0.], vec![0.]];
+        let targets = [vec![3.], vec![4.], vec![5.]];
+
+        let forced = SequenceEvaluator::default().with_feedback(Feedback::TeacherForcing);
+        let result = forced.evaluate(&mut net, &inputs, &targets).unwrap();
+        assert_eq!(result.outputs, vec![vec![0.], vec![3.], vec![4.]]);
+
+        let free = SequenceEvaluator::default().with_feedback(Feedback::FreeRunning);
+        let result = free.evaluate(&mut net, &inputs, &targets).unwrap();
+        assert_eq!(result.outputs, vec![vec![0.], vec![0.], vec![0.]]);
+    }
+
+    #[test]
+    fn test_evaluate_all() {
+        let mut net = network(1, 1);
+        let sequences = vec![
+            (vec![vec![1.]], vec![vec![1.]]),
+            (vec![vec![1.]], vec![vec![3.]]),
+        ];
+        let loss = SequenceEvaluator::default()
+            .evaluate_all(&mut net, &sequences)
+            .unwrap();
+        assert_relative_eq!(loss, 2.);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use num::Float;
+use serde::{Deserialize, Serialize};
+
+use super::network::FFNetwork;
+use crate::individual::genome::{
+    genome::Genome,
+    node_list::{to_float, Activate, Config},
+};
+
+/// Enabled edge count above which [`Backend::Auto`] compiles to a
+/// [`SparseNetwork`]. In `benches/network.rs` compiling and running a pass
+/// is over twice as fast on the sparse backend from 16 edges on, up to
+/// about 1000, while repeated passes on a compiled network stay faster on
+/// [`FFNetwork`], which also supports dropout and clamp calibration.
+pub const SPARSE_EDGE_THRESHOLD: usize = 16;
+
+/// Incoming edges of every node in compressed sparse row form.
+#[derive(Debug, Clone, Default)]
+struct Csr<F> {
+    /// Row `i` spans `offsets[i]..offsets[i + 1]`.
+    offsets: Vec<usize>,
+    sources: Vec<usize>,
+    weights: Vec<F>,
+}
+
+impl<F: Copy> Csr<F> {
+    fn new(rows: usize, mut edges: Vec<(usize, usize, F)>) -> Self {
+        edges.sort_by_key(|(row, source, _)| (*row, *source));
+        let mut offsets = vec![0; rows + 1];
+        for (row, _, _) in edges.iter() {
+            offsets[row + 1] += 1;
+        }
+        for row in 0..rows {
+            offsets[row + 1] += offsets[row];
+        }
+        Self {
+            offsets,
+            sources: edges.iter().map(|(_, source, _)| *source).collect_vec(),
+            weights: edges.iter().map(|(_, _, weight)| *weight).collect_vec(),
+        }
+    }
+
+    fn row(&self, row: usize) -> impl Iterator<Item = (usize, F)> + '_ {
+        let range = self.offsets[row]..self.offsets[row + 1];
+        self.sources[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.weights[range].iter().copied())
+    }
+}
+
+/// Network compiled to sparse weight matrices, one block of rows per level.
+/// Computes the same outputs as [`FFNetwork`], without its per-edge lookups
+/// and priority queue, which pays off on genomes with many edges. Gated
+/// cells are not supported.
+#[derive(Debug, Clone)]
+pub struct SparseNetwork<F = f32> {
+    inputs: usize,
+    /// Scale and offset applied to each input row, empty when unscaled.
+    input_scaling: Vec<(F, F)>,
+    /// Non-input nodes ordered by level.
+    configs: Vec<Config>,
+    /// Edges from a lower level, read in the current pass.
+    forward: Csr<F>,
+    /// Edges from the same or a higher level, read from the previous pass.
+    recurrent: Csr<F>,
+    /// Row of every output, in output order.
+    outputs: Vec<usize>,
+    /// Values of the current pass, inputs first.
+    values: Vec<F>,
+    active: Vec<bool>,
+    /// Last activation of every node, read by recurrent edges.
+    last: Vec<F>,
+    previous: Vec<F>,
+    buffer: Vec<F>,
+    diverged: bool,
+}
+
+impl<F: Float> SparseNetwork<F> {
+    /// Panics if the genome has gated cells, see [`Network::new`].
+    pub fn new(genome: &Genome) -> Self {
+        let node_list = &genome.node_list;
+        assert!(!node_list.is_gated(), "Gated cells need the graph backend");
+        let inputs = node_list.input.len();
+        let nodes = node_list
+            .output
+            .iter()
+            .chain(node_list.hidden.iter())
+            .sorted_by(|a, b| a.level.cmp(&b.level).then(a.node_id.cmp(&b.node_id)))
+            .collect_vec();
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(row, node)| (node.node_id, (inputs + row, node.level)))
+            .chain(
+                node_list
+                    .input
+                    .iter()
+                    .enumerate()
+                    .map(|(row, node)| (node.node_id, (row, node.level))),
+            )
+            .collect::<BTreeMap<_, _>>();
+        let (mut forward, mut recurrent) = (vec![], vec![]);
+        for edge in genome.genome_list.iter().filter(|edge| edge.enabled) {
+            let (source, source_level) = index[&edge.in_node];
+            let (target, target_level) = index[&edge.out_node];
+            let entry = (target - inputs, source, to_float(genome.weight(edge)));
+            if source_level < target_level {
+                forward.push(entry);
+            } else {
+                recurrent.push(entry);
+            }
+        }
+        let outputs = node_list
+            .output
+            .iter()
+            .map(|node| index[&node.node_id].0 - inputs)
+            .collect_vec();
+        let len = inputs + nodes.len();
+        let input_scaling = if genome.input_scaling.is_empty() {
+            vec![]
+        } else {
+            node_list
+                .input
+                .iter()
+                .map(|node| {
+                    let gene = genome.input_scaling.gene(node.node_id);
+                    (to_float(gene.scale), to_float(gene.offset))
+                })
+                .collect_vec()
+        };
+        Self {
+            inputs,
+            input_scaling,
+            configs: nodes.iter().map(|node| node.config).collect_vec(),
+            forward: Csr::new(nodes.len(), forward),
+            recurrent: Csr::new(nodes.len(), recurrent),
+            outputs,
+            values: vec![F::zero(); len],
+            active: vec![false; len],
+            last: vec![F::zero(); len],
+            previous: vec![F::zero(); len],
+            buffer: vec![],
+            diverged: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last.fill(F::zero());
+        self.diverged = false;
+    }
+
+    pub fn diverged(&self) -> bool {
+        self.diverged
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn forward(&mut self, input_vector: &[F]) -> Option<Vec<F>> {
+        if input_vector.len() != self.inputs {
+            return None;
+        }
+        self.previous.copy_from_slice(&self.last);
+        self.values[..self.inputs].copy_from_slice(input_vector);
+        for (value, (scale, offset)) in self.values.iter_mut().zip(self.input_scaling.iter()) {
+            *value = *scale * *value + *offset;
+        }
+        self.active.fill(false);
+        self.active[..self.inputs].fill(true);
+        for (row, config) in self.configs.iter().enumerate() {
+            self.buffer.clear();
+            for (source, weight) in self.forward.row(row) {
+                if self.active[source] {
+                    self.buffer.push(self.values[source] * weight);
+                }
+            }
+            // Nodes are only activated when reached from the inputs
+            if self.buffer.is_empty() {
+                continue;
+            }
+            for (source, weight) in self.recurrent.row(row) {
+                self.buffer.push(self.previous[source] * weight);
+            }
+            let aggregated = config.aggregation.apply(self.buffer.iter().copied())
+                + to_float::<F>(config.bias);
+            let value = config
+                .clamp
+                .activate(config.activation.activate(aggregated));
+            let node = self.inputs + row;
+            self.values[node] = value;
+            self.active[node] = true;
+            self.last[node] = value;
+        }
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|row| self.inputs + row)
+            .map(|node| {
+                if self.active[node] {
+                    self.values[node]
+                } else {
+                    F::zero()
+                }
+            })
+            .collect_vec();
+        self.diverged |= outputs.iter().any(|x| !x.is_finite());
+        Some(outputs)
+    }
+}
+
+impl<F: Float> From<&Genome> for SparseNetwork<F> {
+    fn from(genome: &Genome) -> Self {
+        Self::new(genome)
+    }
+}
+
+/// Evaluation backend of a [`Network`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Per-edge traversal, [`FFNetwork`].
+    Graph,
+    /// Sparse matrices, [`SparseNetwork`].
+    Sparse,
+    /// Sparse once the genome has more than `edges` enabled edges.
+    Auto { edges: usize },
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Auto {
+            edges: SPARSE_EDGE_THRESHOLD,
+        }
+    }
+}
+
+/// Network compiled with either backend.
+#[derive(Debug, Clone)]
+pub enum Network<F = f32> {
+    Graph(FFNetwork<F>),
+    Sparse(SparseNetwork<F>),
+}
+
+impl<F: Float> Network<F> {
+    /// Genomes with gated cells compile to the graph backend whatever
+    /// `backend` is.
+    pub fn new(genome: &Genome, backend: Backend) -> Self {
+        let sparse = !genome.node_list.is_gated()
+            && match backend {
+                Backend::Graph => false,
+                Backend::Sparse => true,
+                Backend::Auto { edges } => {
+                    genome.genome_list.iter().filter(|e| e.enabled).count() > edges
+                }
+            };
+        if sparse {
+            Network::Sparse(SparseNetwork::new(genome))
+        } else {
+            Network::Graph(FFNetwork::from(genome))
+        }
+    }
+
+    pub fn forward(&mut self, input_vector: &[F]) -> Option<Vec<F>> {
+        match self {
+            Network::Graph(network) => network.forward(input_vector),
+            Network::Sparse(network) => network.forward(input_vector),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match self {
+            Network::Graph(network) => network.reset(),
+            Network::Sparse(network) => network.reset(),
+        }
+    }
+
+    pub fn diverged(&self) -> bool {
+        match self {
+            Network::Graph(network) => network.diverged(),
+            Network::Sparse(network) => network.diverged(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::relative_eq;
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        fixtures::random_genome,
+        individual::genome::genome::{GenomeEdge, GenomeFactory},
+    };
+
+    proptest! {
+        #[test]
+        fn test_matches_graph(seed in any::<u64>(), hidden in 0usize..8, edges in 0usize..40) {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let genome = random_genome(&mut rng, hidden, edges, true);
+            let mut graph = FFNetwork::<f64>::from(&genome);
+            let mut sparse = SparseNetwork::<f64>::from(&genome);
+            for step in 0..5 {
+                let input = [step as f64 * 0.5, 1. - step as f64, 0.25];
+                let (fst, snd) = (graph.forward(&input).unwrap(), sparse.forward(&input).unwrap());
+                for (a, b) in fst.iter().zip_eq(snd.iter()) {
+                    prop_assert!(
+                        relative_eq!(a, b, epsilon = 1e-9, max_relative = 1e-9) || (a.is_nan() && b.is_nan()),
+                        "{fst:?} != {snd:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_backend() {
+        let mut genome = GenomeFactory::init(1, 1).unwrap().generate_genome();
+        assert!(matches!(
+            Network::<f32>::new(&genome, Backend::default()),
+            Network::Graph(_)
+        ));
+        assert!(matches!(
+            Network::<f32>::new(&genome, Backend::Sparse),
+            Network::Sparse(_)
+        ));
+        genome.genome_list.edge_list.push(GenomeEdge {
+            innov_number: 0,
+            in_node: 0,
+            out_node: 1,
+            weight: 1.,
+            enabled: true,
+        });
+        let mut network = Network::<f32>::new(&genome, Backend::Auto { edges: 0 });
+        assert!(matches!(network, Network::Sparse(_)));
+        assert_eq!(network.forward(&[2.]), Some(vec![2.]));
+        assert_eq!(network.forward(&[1., 2.]), None);
+    }
+}
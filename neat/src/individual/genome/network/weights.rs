@@ -0,0 +1,242 @@
+//! Editing the weights of a compiled network in place, e.g. for optimizers
+//! tuning the weights of a fixed topology without recompiling it from a
+//! genome at every step.
+
+use itertools::Itertools;
+use num::Float;
+
+use super::network::FFNetwork;
+use crate::individual::genome::{
+    genome::Genome,
+    node_list::{to_float, NodeId},
+};
+
+/// Why weights could not be edited or written back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightError {
+    /// No enabled edge `in_node -> out_node`, in the network or the genome
+    /// written back to.
+    UnknownEdge { in_node: NodeId, out_node: NodeId },
+}
+
+impl<F: Float> FFNetwork<F> {
+    /// Every edge as `(in_node, out_node, weight)`: the forward edges by
+    /// source, then the recurrent edges by target. The order is the one of
+    /// [`FFNetwork::weights_mut`] and stays fixed until the network is
+    /// patched.
+    pub fn weights(&self) -> Vec<(NodeId, NodeId, F)> {
+        let inputs = self.lengths.input;
+        let ids = self
+            .memory
+            .iter()
+            .map(|cell| cell.get_node().node_id)
+            .collect_vec();
+        let forward = self
+            .edge_map
+            .iter()
+            .zip(ids.iter())
+            .flat_map(|(edges, source)| edges.iter().map(|edge| (*source, edge.dest, edge.weight)));
+        let recurrent = self
+            .back_map
+            .iter()
+            .zip(ids[inputs..].iter())
+            .flat_map(|(edges, target)| edges.iter().map(|edge| (edge.dest, *target, edge.weight)));
+        forward.chain(recurrent).collect_vec()
+    }
+
+    /// Weights in the order of [`FFNetwork::weights`], to overwrite in place.
+    /// Cached outputs are dropped.
+    pub fn weights_mut(&mut self) -> impl Iterator<Item = &mut F> {
+        self.weights_changed();
+        self.edge_map
+            .iter_mut()
+            .chain(self.back_map.iter_mut())
+            .flatten()
+            .map(|edge| &mut edge.weight)
+    }
+
+    /// Set the weight of the edge `in_node -> out_node`, of every such edge
+    /// if there are several. Cached outputs are dropped.
+    pub fn set_weight(
+        &mut self,
+        in_node: NodeId,
+        out_node: NodeId,
+        weight: F,
+    ) -> Result<(), WeightError> {
+        let inputs = self.lengths.input;
+        let position = |id| {
+            self.memory
+                .binary_search_by_key(&id, |cell| cell.get_node().node_id)
+                .ok()
+        };
+        let unknown = WeightError::UnknownEdge { in_node, out_node };
+        let (Some(source), Some(target)) = (position(in_node), position(out_node)) else {
+            return Err(unknown);
+        };
+        let mut found = false;
+        for edge in self.edge_map[source]
+            .iter_mut()
+            .filter(|edge| edge.dest == out_node)
+        {
+            edge.weight = weight;
+            found = true;
+        }
+        if let Some(back) = target.checked_sub(inputs) {
+            for edge in self.back_map[back]
+                .iter_mut()
+                .filter(|edge| edge.dest == in_node)
+            {
+                edge.weight = weight;
+                found = true;
+            }
+        }
+        if !found {
+            return Err(unknown);
+        }
+        self.weights_changed();
+        Ok(())
+    }
+
+    /// Write the weights back to `genome`, the genome the network was
+    /// compiled from: every enabled edge gets the weight of its network edge,
+    /// tied edges through their group. Fails, leaving `genome` untouched, if
+    /// the genome lacks an edge of the network.
+    pub fn write_back_to_genome(&self, genome: &mut Genome) -> Result<(), WeightError> {
+        let weights = self.weights();
+        let mut edited = genome.clone();
+        for (in_node, out_node, weight) in weights {
+            let weight = to_float::<f32>(weight);
+            let Genome {
+                genome_list,
+                weight_table,
+                ..
+            } = &mut edited;
+            let mut found = false;
+            for edge in genome_list
+                .iter_mut()
+                .filter(|edge| edge.enabled && edge.in_node == in_node && edge.out_node == out_node)
+            {
+                edge.weight = weight;
+                if let Some(group) = weight_table.groups.get(&edge.innov_number) {
+                    weight_table.weights.insert(*group, weight);
+                }
+                found = true;
+            }
+            if !found {
+                return Err(WeightError::UnknownEdge { in_node, out_node });
+            }
+        }
+        edited.sync_weights();
+        *genome = edited;
+        Ok(())
+    }
+
+    /// Drop what depends on the weights: the cached outputs now, the
+    /// feed-forward schedule at the next pass.
+    fn weights_changed(&mut self) {
+        self.stale_schedule = self.feed_forward.is_some();
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+    };
+
+    fn genome() -> Genome {
+        GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .activation(2, Activation::Identity)
+            .activation(3, Activation::Identity)
+            .aggregation(3, Aggregation::Sum)
+            .connect(0, 3, 0.5)
+            .connect(1, 3, 1.)
+            .connect(3, 2, -1.)
+            .connect(2, 3, 0.25)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_set_weight() {
+        let genome = genome();
+        let mut network = FFNetwork::<f64>::from(&genome);
+        assert_eq!(
+            network.weights(),
+            vec![(0, 3, 0.5), (1, 3, 1.), (3, 2, -1.), (2, 3, 0.25)]
+        );
+        network.set_weight(1, 3, 2.).unwrap();
+        network.set_weight(2, 3, 0.).unwrap();
+        assert_eq!(
+            network.set_weight(0, 2, 1.),
+            Err(WeightError::UnknownEdge {
+                in_node: 0,
+                out_node: 2
+            })
+        );
+        assert!(network.set_weight(9, 2, 1.).is_err());
+
+        let mut edited = genome.clone();
+        network.write_back_to_genome(&mut edited).unwrap();
+        let mut recompiled = FFNetwork::<f64>::from(&edited);
+        assert_eq!(recompiled.weights(), network.weights());
+        assert_eq!(network.forward(&[1., 1.]), recompiled.forward(&[1., 1.]));
+        assert_eq!(network.forward(&[1., 1.]), Some(vec![-2.5]));
+
+        // Edges the genome lacks are not written
+        let mut other = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 1.)
+            .build()
+            .unwrap();
+        let before = format!("{other:?}");
+        assert!(network.write_back_to_genome(&mut other).is_err());
+        assert_eq!(format!("{other:?}"), before);
+    }
+
+    #[test]
+    fn test_weights_mut() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .activation(2, Activation::Identity)
+            .aggregation(2, Aggregation::Sum)
+            .connect(0, 2, 1.)
+            .connect(1, 2, 1.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        assert!(network.is_feed_forward());
+        assert!(network.enable_cache(16, 0.01));
+        assert_eq!(network.forward(&[1., 2.]), Some(vec![3.]));
+        for (weight, value) in network.weights_mut().zip([2., -1.]) {
+            *weight = value;
+        }
+        assert_eq!(network.forward(&[1., 2.]), Some(vec![0.]));
+        assert_eq!(network.cache_stats().unwrap().hits, 0);
+
+        let mut tied = genome.clone();
+        tied.tie_weights(
+            0,
+            tied.genome_list
+                .iter()
+                .map(|edge| edge.innov_number)
+                .collect_vec(),
+        )
+        .unwrap();
+        for weight in network.weights_mut() {
+            *weight = 3.;
+        }
+        network.write_back_to_genome(&mut tied).unwrap();
+        assert_eq!(tied.weight_table.weights[&0], 3.);
+        assert!(tied.genome_list.iter().all(|edge| tied.weight(edge) == 3.));
+    }
+}
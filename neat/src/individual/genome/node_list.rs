@@ -1,22 +1,69 @@
-use num::rational::Ratio;
-use std::sync::Arc;
+use num::{rational::Ratio, Float, ToPrimitive};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 use super::{activation::Activation, aggregation::Aggregation, clamp::Clamp};
 
-pub trait Activate {
-    fn activate(&self, x: f32) -> f32;
+/// Activation over the numeric type used for evaluation. Genome parameters are
+/// stored as `f32` and converted to `F` when applied.
+pub trait Activate<F = f32> {
+    fn activate(&self, x: F) -> F;
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Convert a genome parameter or constant into the evaluation type.
+#[inline]
+pub fn to_float<F: Float>(x: impl ToPrimitive) -> F {
+    F::from(x).expect("Value should be representable as a float")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub aggregation: Aggregation,
     pub clamp: Clamp,
     pub activation: Activation,
+    /// Added to the aggregated inputs before activation.
+    pub bias: f32,
+    /// Gates of a gated memory cell, `None` for a plain node.
+    pub gate: Option<Gate>,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Input and forget gates of a gated memory cell, each a sigmoid of the
+/// biased aggregate scaled by its own weight. The cell keeps a state,
+/// `forget * state + input * activation`, and outputs it, so a closed forget
+/// gate holds on to values over many passes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gate {
+    pub input_weight: f32,
+    pub input_bias: f32,
+    pub forget_weight: f32,
+    pub forget_bias: f32,
+}
+
+/// Input gate open and forget gate shut, so a new gate barely changes the
+/// node's output until its parameters evolve.
+impl Default for Gate {
+    fn default() -> Self {
+        Self {
+            input_weight: 0.,
+            input_bias: 5.,
+            forget_weight: 0.,
+            forget_bias: -5.,
+        }
+    }
+}
+
+/// Id of a node, unique within a genome. Factories number their nodes from
+/// 0, add-node mutation draws the ids of new ones from the node counter of
+/// `InnovNumber`.
+pub type NodeId = u64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Node {
-    pub node_id: usize,
+    pub node_id: NodeId,
     pub config: Config,
     pub level: Ratio<usize>,
 }
@@ -27,12 +74,14 @@ impl Default for Config {
             aggregation: Default::default(),
             clamp: Default::default(),
             activation: Default::default(),
+            bias: 0.,
+            gate: None,
         }
     }
 }
 
 impl Node {
-    pub fn new(node_id: usize, level: Ratio<usize>, config: Option<Config>) -> Self {
+    pub fn new(node_id: NodeId, level: Ratio<usize>, config: Option<Config>) -> Self {
         Self {
             node_id,
             level,
@@ -58,14 +107,13 @@ impl Eq for LevelNode {}
 
 impl PartialOrd for LevelNode {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.level.partial_cmp(&other.0.level)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for LevelNode {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other)
-            .expect("Rational numbers are well ordered")
+        self.0.level.cmp(&other.0.level)
     }
 }
 
@@ -80,7 +128,7 @@ impl Eq for Node {}
 
 impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.node_id.partial_cmp(&other.node_id)
+        Some(self.cmp(other))
     }
 }
 
@@ -90,11 +138,33 @@ impl Ord for Node {
     }
 }
 
-#[derive(Debug, Clone)]
+/// What a node is wired to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeRole {
+    /// Input node, fed by the environment.
+    Sensor,
+    /// Output node, driving the environment.
+    Actuator,
+    Hidden,
+}
+
+/// Optional node names keyed by node id, e.g. `"cart_position"` for a sensor
+/// or `"motor_left"` for an actuator. Shared by the genomes of a factory.
+pub type NodeNames = Arc<BTreeMap<NodeId, String>>;
+
+/// Value of every optional input keyed by node id, used when the input is
+/// missing from a masked pass.
+pub type InputDefaults = Arc<BTreeMap<NodeId, f32>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeList {
     pub input: Arc<[Node]>,
     pub output: Vec<Node>, // Due to mutation, output cells also get mutated
     pub hidden: Vec<Node>,
+    #[serde(default)]
+    pub names: NodeNames,
+    #[serde(default)]
+    pub input_defaults: InputDefaults,
 }
 
 impl NodeList {
@@ -105,6 +175,46 @@ impl NodeList {
             input : input.clone(),
             output: output.clone(),
             hidden,
+            names: NodeNames::default(),
+            input_defaults: InputDefaults::default(),
         }
     }
+
+    pub fn role(&self, node_id: NodeId) -> NodeRole {
+        if node_id < self.input.len() as NodeId {
+            NodeRole::Sensor
+        } else if node_id < (self.input.len() + self.output.len()) as NodeId {
+            NodeRole::Actuator
+        } else {
+            NodeRole::Hidden
+        }
+    }
+
+    pub fn name(&self, node_id: NodeId) -> Option<&str> {
+        self.names.get(&node_id).map(String::as_str)
+    }
+
+    /// Level of node `node_id`, `None` if it is not part of the list.
+    pub fn level(&self, node_id: NodeId) -> Option<Ratio<usize>> {
+        self.input
+            .iter()
+            .chain(self.output.iter())
+            .chain(self.hidden.iter())
+            .find(|node| node.node_id == node_id)
+            .map(|node| node.level)
+    }
+
+    /// Distinct hidden levels, i.e. hidden layers a signal from the inputs
+    /// may cross on its way to the outputs.
+    pub fn depth(&self) -> usize {
+        self.hidden.iter().map(|node| node.level).collect::<BTreeSet<_>>().len()
+    }
+
+    /// Whether some output or hidden node is a gated memory cell.
+    pub fn is_gated(&self) -> bool {
+        self.output
+            .iter()
+            .chain(self.hidden.iter())
+            .any(|node| node.config.gate.is_some())
+    }
 }
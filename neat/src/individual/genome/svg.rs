@@ -0,0 +1,175 @@
+//! Self-contained SVG rendering of genomes, for viewing topologies without
+//! Graphviz.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use itertools::Itertools;
+
+use super::{
+    genome::Genome,
+    node_list::{Node, NodeRole},
+};
+
+const WIDTH: f32 = 600.;
+const MARGIN: f32 = 40.;
+/// Vertical distance between the nodes of a level.
+const SPACING: f32 = 48.;
+const RADIUS: f32 = 14.;
+const POSITIVE: &str = "#2b6cb0";
+const NEGATIVE: &str = "#c53030";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Genome {
+    /// Drawing of the genome in SVG. Nodes sit on the x-axis by level and
+    /// are labelled with their name or id, edges are as thick as their
+    /// absolute weight relative to the heaviest, blue if positive and red if
+    /// negative, and disabled edges are dashed.
+    pub fn to_svg(&self) -> String {
+        let node_list = &self.node_list;
+        let nodes = node_list
+            .input
+            .iter()
+            .chain(node_list.output.iter())
+            .chain(node_list.hidden.iter())
+            .collect_vec();
+        let columns = nodes
+            .iter()
+            .map(|node| (node.level, *node))
+            .into_group_map()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let to_f32 = |node: &Node| *node.level.numer() as f32 / *node.level.denom() as f32;
+        let (min, max) = nodes
+            .iter()
+            .map(|node| to_f32(node))
+            .minmax()
+            .into_option()
+            .unwrap_or((0., 0.));
+        let rows = columns.values().map(Vec::len).max().unwrap_or(0);
+        let height = 2. * MARGIN + rows.saturating_sub(1) as f32 * SPACING;
+        let mut positions = BTreeMap::new();
+        for column in columns.values() {
+            let top = (height - (column.len() - 1) as f32 * SPACING) / 2.;
+            for (row, node) in column.iter().enumerate() {
+                let x = if max > min {
+                    MARGIN + (to_f32(node) - min) / (max - min) * WIDTH
+                } else {
+                    MARGIN + WIDTH / 2.
+                };
+                positions.insert(node.node_id, (x, top + row as f32 * SPACING));
+            }
+        }
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\">",
+            WIDTH + 2. * MARGIN,
+            height
+        );
+        let heaviest = self
+            .genome_list
+            .iter()
+            .map(|edge| self.weight(edge).abs())
+            .fold(0., f32::max);
+        for edge in self.genome_list.iter() {
+            let weight = self.weight(edge);
+            let (Some((x1, y1)), Some((x2, y2))) =
+                (positions.get(&edge.in_node), positions.get(&edge.out_node))
+            else {
+                continue;
+            };
+            let width = if heaviest > 0. {
+                0.5 + 3.5 * weight.abs() / heaviest
+            } else {
+                0.5
+            };
+            let colour = if weight < 0. { NEGATIVE } else { POSITIVE };
+            let dash = if edge.enabled {
+                ""
+            } else {
+                " stroke-dasharray=\"4 3\" opacity=\"0.5\""
+            };
+            let _ = writeln!(
+                svg,
+                "  <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"{colour}\" \
+                 stroke-width=\"{width:.2}\"{dash}><title>{} -> {}: {weight:.3}</title></line>",
+                edge.in_node, edge.out_node
+            );
+        }
+        for node in nodes {
+            let (x, y) = positions[&node.node_id];
+            let label = node_list
+                .name(node.node_id)
+                .map_or_else(|| node.node_id.to_string(), escape);
+            let (fill, title) = match node_list.role(node.node_id) {
+                NodeRole::Sensor => ("#e2e8f0", label.clone()),
+                role => (
+                    if role == NodeRole::Actuator {
+                        "#fefcbf"
+                    } else {
+                        "#ffffff"
+                    },
+                    format!("{label}: {:?}", node.config.activation),
+                ),
+            };
+            let _ = writeln!(
+                svg,
+                "  <circle cx=\"{x}\" cy=\"{y}\" r=\"{RADIUS}\" fill=\"{fill}\" stroke=\"#2d3748\">\
+                 <title>{title}</title></circle>"
+            );
+            let _ = writeln!(
+                svg,
+                "  <text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" dominant-baseline=\"central\" \
+                 font-size=\"10\">{label}</text>"
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_to_svg() {
+        let mut genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .name(0, "x<y")
+            .connect(0, 3, 0.5)
+            .connect(3, 2, -1.)
+            .connect(1, 2, 2.)
+            .build()
+            .unwrap();
+        genome.genome_list.edge_list[2].enabled = false;
+        let svg = genome.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(svg.matches("<line").count(), 3);
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+        assert_eq!(svg.matches(NEGATIVE).count(), 1);
+        assert!(svg.contains(">x&lt;y</text>"));
+        // The heaviest edge is the thickest
+        assert!(svg.contains("stroke-width=\"4.00\" stroke-dasharray"));
+        assert!(svg.contains("stroke-width=\"1.38\""));
+        // Inputs on the left, the output on the right, the hidden node between
+        assert!(svg.contains("<circle cx=\"40\" cy=\"40\""));
+        assert!(svg.contains("<circle cx=\"640\" cy=\"64\""));
+        assert!(svg.contains("<circle cx=\"336.9697\" cy=\"64\""));
+        assert!(svg.contains("<title>1</title>"));
+    }
+}
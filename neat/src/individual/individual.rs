@@ -1,7 +1,79 @@
+use std::sync::Arc;
+
 use super::genome::genome::Genome;
+use crate::speciation::speciation::Comparable;
 
-    ///
+/// Member of a population that has a fitness.
 pub trait Individual {
     fn fitness(&self) -> f32;
     fn to_genome(&self) -> Genome;
 }
+
+/// Genome with a stable id, so components can refer to it across
+/// generations, along with what its evaluation produced. The genome is
+/// shared, so species representatives and archives hold it without copies.
+#[derive(Debug, Clone)]
+pub struct EvaluatedIndividual {
+    pub id: usize,
+    pub genome: Arc<Genome>,
+    /// `None` until the individual is evaluated.
+    pub fitness: Option<f32>,
+    /// Behaviour descriptor, e.g. for novelty search.
+    pub behavior: Option<Vec<f32>>,
+}
+
+impl EvaluatedIndividual {
+    pub fn new(id: usize, genome: Genome) -> Self {
+        Self {
+            id,
+            genome: Arc::new(genome),
+            fitness: None,
+            behavior: None,
+        }
+    }
+
+    pub fn with_fitness(mut self, fitness: f32) -> Self {
+        self.fitness = Some(fitness);
+        self
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        self.fitness.is_some()
+    }
+
+    /// Genome to edit in place, copied first if it is shared.
+    pub fn genome_mut(&mut self) -> &mut Genome {
+        Arc::make_mut(&mut self.genome)
+    }
+}
+
+/// Unevaluated individuals report NaN, which selection ranks last.
+/// Where a child comes from: the ids of its parents, the same twice for a
+/// self-crossover, and the species that bred it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Origin {
+    pub parents: [usize; 2],
+    pub species: usize,
+}
+
+impl Individual for EvaluatedIndividual {
+    fn fitness(&self) -> f32 {
+        self.fitness.unwrap_or(f32::NAN)
+    }
+
+    fn to_genome(&self) -> Genome {
+        (*self.genome).clone()
+    }
+}
+
+impl AsRef<Genome> for EvaluatedIndividual {
+    fn as_ref(&self) -> &Genome {
+        &self.genome
+    }
+}
+
+impl Comparable for EvaluatedIndividual {
+    fn compare(&self, other: &Self) -> f32 {
+        self.genome.compare(&other.genome)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod genome;
+#[allow(clippy::module_inception)]
 pub mod individual;
\ No newline at end of file
@@ -1,61 +1,200 @@
+use std::sync::Arc;
+
 use crossover::crossover::CrossoverMethod;
-use individual::{genome::genome::Genome, individual::Individual};
+use individual::{
+    genome::genome::Genome,
+    individual::{EvaluatedIndividual, Individual, Origin},
+};
+use mutation::{innovation_number::InnovNumber, mutation::MutationMethod};
 use rand::RngCore;
 use selection::selection_trait::SelectionMethod;
-use speciation::speciation::{Comparable, SpeciationMethod};
+use speciation::speciation::SpeciationMethod;
+
+use crate::{
+    crossover::crossover::Item,
+    population::replay::{Breeding, MutationRecord},
+};
 
-use crate::crossover::crossover::Item;
+#[macro_use]
+mod trace;
+#[cfg(test)]
+mod fixtures;
 
-mod crossover;
-mod individual;
-mod selection;
-mod speciation;
-mod mutation;
+pub mod config;
+pub mod crossover;
+pub mod environment;
+pub mod individual;
+pub mod population;
+pub mod selection;
+pub mod speciation;
+pub mod mutation;
 
 pub struct GeneticAlgortihm<Spe, Sel> {
     speciation: Spe,
     selection: Sel,
     crossover: Box<dyn CrossoverMethod>,
+    mutation: Box<dyn MutationMethod>,
 }
 
 impl<Spe, Sel> GeneticAlgortihm<Spe, Sel>
 where
-    Spe: SpeciationMethod,
     Sel: SelectionMethod,
 {
-    pub fn new(spec_method: Spe, sel_method: Sel, cross_method: Box<dyn CrossoverMethod>) -> Self {
+    pub fn new(
+        spec_method: Spe,
+        sel_method: Sel,
+        cross_method: Box<dyn CrossoverMethod>,
+        mut_method: Box<dyn MutationMethod>,
+    ) -> Self {
         Self {
             speciation: spec_method,
             selection: sel_method,
             crossover: cross_method,
+            mutation: mut_method,
         }
     }
 
-    fn evolve<I>(&self, rng: &mut dyn RngCore, population: &[I]) -> Vec<Genome>
+    pub fn speciation(&self) -> &Spe {
+        &self.speciation
+    }
+
+    pub fn selection_mut(&mut self) -> &mut Sel {
+        &mut self.selection
+    }
+
+    pub fn mutation(&self) -> &dyn MutationMethod {
+        &*self.mutation
+    }
+
+    pub fn mutation_mut(&mut self) -> &mut dyn MutationMethod {
+        &mut *self.mutation
+    }
+
+    pub fn evolve(
+        &self,
+        rng: &mut dyn RngCore,
+        population: &[EvaluatedIndividual],
+        innov_number: &mut InnovNumber,
+    ) -> Vec<Genome>
+    where
+        Spe: SpeciationMethod<EvaluatedIndividual>,
+    {
+        self.evolve_with_origins(rng, population, innov_number)
+            .into_iter()
+            .map(|(child, _)| child)
+            .collect()
+    }
+
+    /// Like [`GeneticAlgortihm::evolve`], with the origin of every child.
+    /// Species are numbered by their index in this speciation.
+    pub fn evolve_with_origins(
+        &self,
+        rng: &mut dyn RngCore,
+        population: &[EvaluatedIndividual],
+        innov_number: &mut InnovNumber,
+    ) -> Vec<(Genome, Origin)>
     where
-        I: Individual + Comparable,
+        Spe: SpeciationMethod<EvaluatedIndividual>,
     {
         assert!(!population.is_empty());
-        let s = self.speciation.speciate(population.iter());
+        let s = {
+            phase!("speciation");
+            let s = self.speciation.speciate(population.iter());
+            event!(DEBUG, species = s.len());
+            s
+        };
         let mut ret = Vec::with_capacity(population.len());
-        for sub_pop in s {
-            for _ in 0..sub_pop.len() {
-                let parent_a = self.selection.select(rng, &sub_pop);
-                let parent_b = self.selection.select(rng, &sub_pop);
-                let child = self.crossover.crossover_method(
+        for (species, sub_pop) in s.into_iter().enumerate() {
+            let count = sub_pop.len();
+            let children =
+                self.reproduce_with_origins(rng, &sub_pop, count, innov_number, species, None);
+            ret.extend(children);
+        }
+        ret
+    }
+
+    /// Breed `count` children from a single species.
+    pub fn reproduce(
+        &self,
+        rng: &mut dyn RngCore,
+        sub_pop: &[&EvaluatedIndividual],
+        count: usize,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<Genome> {
+        self.reproduce_observed(rng, sub_pop, count, innov_number, None)
+    }
+
+    /// Like [`GeneticAlgortihm::reproduce`], reporting the parents and
+    /// mutations of every child to `observer`.
+    pub fn reproduce_observed(
+        &self,
+        rng: &mut dyn RngCore,
+        sub_pop: &[&EvaluatedIndividual],
+        count: usize,
+        innov_number: &mut InnovNumber,
+        observer: Option<&mut dyn FnMut(Breeding)>,
+    ) -> Vec<Genome> {
+        self.reproduce_with_origins(rng, sub_pop, count, innov_number, 0, observer)
+            .into_iter()
+            .map(|(child, _)| child)
+            .collect()
+    }
+
+    /// Like [`GeneticAlgortihm::reproduce_observed`], with the origin of
+    /// every child, bred by the species with id `species`.
+    pub fn reproduce_with_origins(
+        &self,
+        rng: &mut dyn RngCore,
+        sub_pop: &[&EvaluatedIndividual],
+        count: usize,
+        innov_number: &mut InnovNumber,
+        species: usize,
+        mut observer: Option<&mut dyn FnMut(Breeding)>,
+    ) -> Vec<(Genome, Origin)> {
+        phase!("reproduction", species_size = sub_pop.len(), count);
+        let mut ret = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (parent_a, parent_b) = {
+                span!(TRACE, "selection");
+                self.selection.select_pair(rng, sub_pop)
+            };
+            let mut child = {
+                span!(TRACE, "crossover");
+                self.crossover.crossover_method(
                     rng,
                     &Item {
-                        item: parent_a.to_genome(),
+                        item: Arc::clone(&parent_a.genome),
                         fitness: parent_a.fitness(),
                     },
                     &Item {
-                        item: parent_b.to_genome(),
-                        fitness: parent_a.fitness(),
+                        item: Arc::clone(&parent_b.genome),
+                        fitness: parent_b.fitness(),
                     },
-                );
-                todo!("Mutation");
-                ret.push(child);
+                )
+            };
+            // Custom crossovers may leave edges to nodes they did not inherit
+            let dangling = child.drop_dangling_edges();
+            if dangling > 0 {
+                event!(DEBUG, dangling);
+            }
+            span!(TRACE, "mutation");
+            match observer.as_mut() {
+                Some(observer) => {
+                    let crossed = child.clone();
+                    let steps = self.mutation.mutate_recorded(rng, &mut child, innov_number);
+                    observer(Breeding {
+                        parents: [parent_a.id, parent_b.id],
+                        mutation: MutationRecord::between(&crossed, &child),
+                        steps,
+                    });
+                }
+                None => self.mutation.mutate(rng, &mut child, innov_number),
             }
+            let origin = Origin {
+                parents: [parent_a.id, parent_b.id],
+                species,
+            };
+            ret.push((child, origin));
         }
         ret
     }
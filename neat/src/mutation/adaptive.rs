@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    innovation_number::InnovNumber,
+    mutation::{GaussianMutation, MutationMethod, ProbabilityMatrix},
+};
+use crate::{
+    individual::genome::genome::Genome,
+    population::{generation::OperatorStats, replay::MutationStep},
+};
+
+/// Operators of [`GaussianMutation::pipeline`], by name.
+const OPERATORS: [&str; 5] = [
+    "NodeConfigMutation",
+    "ToggleEnabled",
+    "WeightMutation",
+    "AddNode",
+    "AddEdge",
+];
+
+/// How an [`OperatorBandit`] shares out the mutation effort, rewarding an
+/// operator with the share of its children that improved on their parents.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "rule")]
+pub enum AdaptationRule {
+    /// Adaptive pursuit (Thierens 2005): qualities follow the rewards at
+    /// rate `alpha` and the shares pursue the best operator at rate `beta`,
+    /// every share staying at least `p_min`.
+    Pursuit { alpha: f64, beta: f64, p_min: f64 },
+    /// UCB1: shares proportional to the mean reward plus `c` times the
+    /// exploration bonus. Every operator starts with an improving child.
+    Ucb { c: f64 },
+}
+
+impl Default for AdaptationRule {
+    fn default() -> Self {
+        AdaptationRule::Pursuit {
+            alpha: 0.3,
+            beta: 0.3,
+            p_min: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Arm {
+    pub name: String,
+    /// Share of the mutation effort, the shares summing to 1.
+    pub share: f64,
+    /// Estimated reward, of adaptive pursuit.
+    pub quality: f64,
+    pub children: usize,
+    pub improved: usize,
+}
+
+/// Multi-armed bandit over named mutation operators, updated with the
+/// operator statistics of every generation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorBandit {
+    rule: AdaptationRule,
+    arms: Vec<Arm>,
+}
+
+impl OperatorBandit {
+    /// Bandit over `names`, sharing equally.
+    pub fn new<S: Into<String>>(rule: AdaptationRule, names: impl IntoIterator<Item = S>) -> Self {
+        let mut arms = names
+            .into_iter()
+            .map(|name| Arm {
+                name: name.into(),
+                share: 0.,
+                quality: 0.,
+                children: 0,
+                improved: 0,
+            })
+            .collect::<Vec<_>>();
+        let share = 1. / arms.len().max(1) as f64;
+        arms.iter_mut().for_each(|arm| arm.share = share);
+        Self { rule, arms }
+    }
+
+    pub fn arms(&self) -> &[Arm] {
+        &self.arms
+    }
+
+    /// Share of `name` relative to an equal share, 1 for unknown operators.
+    pub fn scale(&self, name: &str) -> f64 {
+        self.arms
+            .iter()
+            .find(|arm| arm.name == name)
+            .map_or(1., |arm| arm.share * self.arms.len() as f64)
+    }
+
+    /// Reward the operators of `stats`, the others keep their estimates.
+    pub fn update(&mut self, stats: &BTreeMap<String, OperatorStats>) {
+        if self.arms.is_empty() {
+            return;
+        }
+        for arm in self.arms.iter_mut() {
+            if let Some(stats) = stats.get(&arm.name) {
+                arm.children += stats.children;
+                arm.improved += stats.improved;
+                if let AdaptationRule::Pursuit { alpha, .. } = self.rule {
+                    let reward = f64::from(stats.success_rate());
+                    arm.quality += alpha * (reward - arm.quality);
+                }
+            }
+        }
+        match self.rule {
+            AdaptationRule::Pursuit { beta, p_min, .. } => {
+                let p_max = 1. - (self.arms.len() - 1) as f64 * p_min;
+                let best = (0..self.arms.len())
+                    .max_by(|a, b| self.arms[*a].quality.total_cmp(&self.arms[*b].quality))
+                    .expect("Some arm");
+                for (index, arm) in self.arms.iter_mut().enumerate() {
+                    let target = if index == best { p_max } else { p_min };
+                    arm.share += beta * (target - arm.share);
+                }
+            }
+            AdaptationRule::Ucb { c } => {
+                let pulls = self.arms.iter().map(|arm| arm.children + 1).sum::<usize>() as f64;
+                let scores = self
+                    .arms
+                    .iter()
+                    .map(|arm| {
+                        let n = (arm.children + 1) as f64;
+                        (arm.improved + 1) as f64 / n + c * (pulls.ln() / n).sqrt()
+                    })
+                    .collect::<Vec<_>>();
+                let total = scores.iter().sum::<f64>();
+                for (arm, score) in self.arms.iter_mut().zip(scores) {
+                    arm.share = score / total;
+                }
+            }
+        }
+    }
+}
+
+/// [`GaussianMutation`] whose rates follow an [`OperatorBandit`]: every
+/// rate of an operator is its base rate times the operator's
+/// [`OperatorBandit::scale`], at most 1.
+#[derive(Debug, Clone)]
+pub struct AdaptiveMutation {
+    base: GaussianMutation,
+    bandit: OperatorBandit,
+}
+
+impl AdaptiveMutation {
+    pub fn new(base: GaussianMutation, rule: AdaptationRule) -> Self {
+        Self {
+            base,
+            bandit: OperatorBandit::new(rule, OPERATORS),
+        }
+    }
+
+    pub fn bandit(&self) -> &OperatorBandit {
+        &self.bandit
+    }
+
+    /// Current rates.
+    pub fn probabilities(&self) -> ProbabilityMatrix {
+        let scale = |name: &str, prob: f64| (prob * self.bandit.scale(name)).min(1.);
+        let mut prob = self.base.prob;
+        let node = &mut prob.node_probs;
+        node.prob_clamp = scale("NodeConfigMutation", node.prob_clamp);
+        node.prob_activation = scale("NodeConfigMutation", node.prob_activation);
+        node.prob_aggregation = scale("NodeConfigMutation", node.prob_aggregation);
+        let edge = &mut prob.prob_edge;
+        edge.prob_enabled = scale("ToggleEnabled", edge.prob_enabled);
+        edge.prob_weight_perturb = scale("WeightMutation", edge.prob_weight_perturb);
+        edge.prob_weight_replace = scale("WeightMutation", edge.prob_weight_replace);
+        edge.prob_new_node = scale("AddNode", edge.prob_new_node);
+        edge.prob_new_edge = scale("AddEdge", edge.prob_new_edge);
+        prob
+    }
+
+    /// Mutation at the current rates.
+    pub fn mutation(&self) -> GaussianMutation {
+        GaussianMutation {
+            prob: self.probabilities(),
+            ..self.base
+        }
+    }
+}
+
+impl MutationMethod for AdaptiveMutation {
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome, innov_number: &mut InnovNumber) {
+        self.mutation().mutate(rng, child, innov_number);
+    }
+
+    fn mutate_named(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<&'static str> {
+        self.mutation().mutate_named(rng, child, innov_number)
+    }
+
+    fn mutate_recorded(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<MutationStep> {
+        self.mutation().mutate_recorded(rng, child, innov_number)
+    }
+
+    fn adapts(&self) -> bool {
+        true
+    }
+
+    fn adapt(&mut self, stats: &BTreeMap<String, OperatorStats>) {
+        self.bandit.update(stats);
+    }
+
+    /// Scales the base rates, the bandit keeping its shares.
+    fn boost(&mut self, factor: f64) {
+        self.base.prob = self.base.prob.scaled(factor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn stats(entries: &[(&str, usize, usize)]) -> BTreeMap<String, OperatorStats> {
+        entries
+            .iter()
+            .map(|(name, children, improved)| {
+                let stats = OperatorStats {
+                    children: *children,
+                    improved: *improved,
+                };
+                (name.to_string(), stats)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pursuit() {
+        let rule = AdaptationRule::Pursuit {
+            alpha: 0.5,
+            beta: 0.5,
+            p_min: 0.1,
+        };
+        let mut bandit = OperatorBandit::new(rule, ["a", "b", "c"]);
+        assert_relative_eq!(bandit.scale("a"), 1.);
+        for _ in 0..20 {
+            bandit.update(&stats(&[("a", 10, 1), ("b", 10, 8)]));
+        }
+        let shares = bandit
+            .arms()
+            .iter()
+            .map(|arm| arm.share)
+            .collect::<Vec<_>>();
+        assert_relative_eq!(shares[0], 0.1, epsilon = 1e-4);
+        assert_relative_eq!(shares[1], 0.8, epsilon = 1e-4);
+        assert_relative_eq!(shares.iter().sum::<f64>(), 1., epsilon = 1e-9);
+        assert_relative_eq!(bandit.arms()[1].quality, 0.8, epsilon = 1e-4);
+        assert_eq!(bandit.arms()[2].children, 0);
+        assert_eq!(bandit.scale("unknown"), 1.);
+    }
+
+    #[test]
+    fn test_ucb() {
+        let mut bandit = OperatorBandit::new(AdaptationRule::Ucb { c: 0.1 }, ["a", "b"]);
+        bandit.update(&stats(&[("a", 100, 90), ("b", 100, 10)]));
+        let arms = bandit.arms();
+        assert!(arms[0].share > 0.8);
+        assert_relative_eq!(arms[0].share + arms[1].share, 1.);
+        // An untried operator is optimistic
+        let mut bandit = OperatorBandit::new(AdaptationRule::Ucb { c: 0.1 }, ["a", "b"]);
+        bandit.update(&stats(&[("a", 100, 10)]));
+        assert!(bandit.arms()[1].share > bandit.arms()[0].share);
+    }
+
+    #[test]
+    fn test_probabilities() {
+        // Rates of the NEAT paper
+        let base = GaussianMutation {
+            prob: ProbabilityMatrix::default(),
+            ..Default::default()
+        };
+        let mut mutation = AdaptiveMutation::new(base, AdaptationRule::default());
+        assert_eq!(mutation.probabilities(), base.prob);
+        assert!(mutation.adapts());
+        for _ in 0..10 {
+            mutation.adapt(&stats(&[("AddNode", 10, 5), ("WeightMutation", 10, 0)]));
+        }
+        let prob = mutation.probabilities().prob_edge;
+        assert!(prob.prob_new_node > 3. * base.prob.prob_edge.prob_new_node);
+        assert!(prob.prob_weight_perturb < base.prob.prob_edge.prob_weight_perturb);
+        assert!(prob.prob_weight_perturb <= 1.);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::individual::genome::genome::{Genome, InnovationId, OrderedGenomeList};
+
+/// Outcome of merging equivalent innovations, see
+/// `Population::merge_innovations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct MergeStats {
+    /// Innovation numbers mapped onto an older one.
+    pub merged_ids: usize,
+    /// Edges renumbered over all genomes.
+    pub remapped_edges: usize,
+    /// Genomes with at least one renumbered edge.
+    pub genomes: usize,
+}
+
+/// Maps the innovation numbers of edges with the same endpoints, created
+/// independently e.g. in different generations, onto the oldest of them, so
+/// that compatibility distance matches them instead of counting them as
+/// disjoint.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InnovationRemap {
+    /// Canonical innovation number of every merged one.
+    map: BTreeMap<InnovationId, InnovationId>,
+}
+
+impl InnovationRemap {
+    pub fn new<'a>(genomes: impl IntoIterator<Item = &'a Genome>) -> Self {
+        let mut ids = BTreeMap::<_, BTreeSet<InnovationId>>::new();
+        for edge in genomes
+            .into_iter()
+            .flat_map(|genome| genome.genome_list.iter())
+        {
+            ids.entry((edge.in_node, edge.out_node))
+                .or_default()
+                .insert(edge.innov_number);
+        }
+        let map = ids
+            .into_values()
+            .flat_map(|ids| {
+                let canonical = *ids.first().expect("Endpoints have an edge");
+                ids.into_iter().skip(1).map(move |id| (id, canonical))
+            })
+            .collect();
+        Self { map }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Canonical innovation number of `innov_number`.
+    pub fn get(&self, innov_number: InnovationId) -> InnovationId {
+        self.map.get(&innov_number).copied().unwrap_or(innov_number)
+    }
+
+    /// Renumber the edges of `genome`, tied weights included, and return how
+    /// many changed. An edge keeps its number if the genome already carries
+    /// the canonical one.
+    pub fn apply(&self, genome: &mut Genome) -> usize {
+        let mut carried = genome
+            .genome_list
+            .iter()
+            .map(|edge| edge.innov_number)
+            .collect::<BTreeSet<_>>();
+        let mut remapped = 0;
+        for edge in genome.genome_list.iter_mut() {
+            let canonical = self.get(edge.innov_number);
+            if canonical == edge.innov_number || !carried.insert(canonical) {
+                continue;
+            }
+            if let Some(group) = genome.weight_table.groups.remove(&edge.innov_number) {
+                genome.weight_table.groups.insert(canonical, group);
+            }
+            edge.innov_number = canonical;
+            remapped += 1;
+        }
+        if remapped > 0 {
+            let edges = std::mem::take(&mut genome.genome_list.edge_list);
+            genome.genome_list = OrderedGenomeList::new(edges);
+        }
+        remapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::genome, speciation::compatibility::Compatibility};
+
+    #[test]
+    fn test_remap() {
+        // 0 -> 2 appeared as 3 and 7, 1 -> 2 as 5 and 4
+        let mut genomes = vec![
+            genome(2, 1, &[], &[(3, 0, 2, 1.), (5, 1, 2, 1.)]),
+            genome(2, 1, &[], &[(7, 0, 2, 1.), (4, 1, 2, 1.)]),
+            genome(2, 1, &[], &[(3, 0, 2, 1.), (7, 0, 2, 1.)]),
+        ];
+        genomes[1].tie_weights(0, [7]).unwrap();
+        let compatibility = Compatibility::default();
+        assert!(compatibility.distance(&genomes[0], &genomes[1]) > 0.);
+
+        let remap = InnovationRemap::new(&genomes);
+        assert_eq!(remap.len(), 2);
+        assert_eq!((remap.get(7), remap.get(5), remap.get(3)), (3, 4, 3));
+        let remapped = genomes
+            .iter_mut()
+            .map(|g| remap.apply(g))
+            .collect::<Vec<_>>();
+        // The third genome already carries 3
+        assert_eq!(remapped, vec![1, 1, 0]);
+        let ids = |genome: &Genome| {
+            genome
+                .genome_list
+                .iter()
+                .map(|edge| edge.innov_number)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&genomes[0]), vec![3, 4]);
+        assert_eq!(ids(&genomes[1]), vec![3, 4]);
+        assert_eq!(ids(&genomes[2]), vec![3, 7]);
+        assert_eq!(genomes[1].weight_table.groups, BTreeMap::from([(3, 0)]));
+        assert_eq!(compatibility.distance(&genomes[0], &genomes[1]), 0.);
+        assert!(InnovationRemap::new(&genomes[..2]).is_empty());
+    }
+}
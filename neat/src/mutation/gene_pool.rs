@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::individual::genome::{
+    genome::{Genome, InnovationId},
+    node_list::NodeId,
+};
+
+/// Population statistics of a single edge innovation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GeneStats {
+    pub in_node: NodeId,
+    pub out_node: NodeId,
+    /// Fraction of the population carrying the edge.
+    pub frequency: f32,
+    /// Mean weight among carriers.
+    pub mean_weight: f32,
+    /// Correlation between carrying the edge and fitness, smoothed over
+    /// generations.
+    pub fitness_correlation: f32,
+}
+
+/// How add-edge mutation uses the gene pool to pick a new connection.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeBias {
+    /// Connections are picked uniformly.
+    #[default]
+    None,
+    /// Prefer connections that correlated with fitness in other genomes,
+    /// weighting candidates by `exp(strength * correlation)`.
+    Helpful { strength: f32 },
+    /// Skip connections already carried by at least `max_frequency` of the
+    /// population.
+    AvoidSaturated { max_frequency: f32 },
+}
+
+/// Per-innovation statistics updated once per generation.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GenePool {
+    /// Weight of the previous correlation when a generation is added.
+    decay: f32,
+    stats: BTreeMap<InnovationId, GeneStats>,
+}
+
+impl GenePool {
+    /// `decay` in `[0, 1)`: `0` only keeps the latest generation.
+    pub fn new(decay: f32) -> Self {
+        Self {
+            decay: decay.clamp(0., 1.),
+            stats: BTreeMap::new(),
+        }
+    }
+
+    /// Update the statistics from an evaluated generation. Individuals with
+    /// NaN fitness count towards frequency but not correlation.
+    pub fn update<'a>(&mut self, population: impl Iterator<Item = (&'a Genome, f32)>) {
+        let population = population.collect_vec();
+        if population.is_empty() {
+            return;
+        }
+        let evaluated = population.iter().filter(|(_, f)| !f.is_nan()).collect_vec();
+        let mean_fitness =
+            evaluated.iter().map(|(_, f)| f).sum::<f32>() / evaluated.len().max(1) as f32;
+
+        // (weight, fitness) of each carrier and the endpoints of each innovation
+        let mut carriers: BTreeMap<InnovationId, Vec<(f32, f32)>> = BTreeMap::new();
+        let mut endpoints = BTreeMap::new();
+        for (genome, fitness) in population.iter() {
+            for edge in genome.genome_list.iter() {
+                carriers
+                    .entry(edge.innov_number)
+                    .or_default()
+                    .push((edge.weight, *fitness));
+                endpoints.insert(edge.innov_number, (edge.in_node, edge.out_node));
+            }
+        }
+        for stats in self.stats.values_mut() {
+            stats.frequency = 0.;
+            stats.fitness_correlation *= self.decay;
+        }
+        for (innov, carried) in carriers {
+            let (in_node, out_node) = endpoints[&innov];
+            let correlation = point_biserial(
+                carried.iter().map(|(_, f)| *f).filter(|f| !f.is_nan()),
+                evaluated.iter().map(|(_, f)| *f),
+                mean_fitness,
+            );
+            let entry = self.stats.entry(innov).or_insert(GeneStats {
+                in_node,
+                out_node,
+                ..Default::default()
+            });
+            entry.frequency = carried.len() as f32 / population.len() as f32;
+            entry.mean_weight =
+                carried.iter().map(|(w, _)| w).sum::<f32>() / carried.len() as f32;
+            entry.fitness_correlation += (1. - self.decay) * correlation;
+        }
+    }
+
+    pub fn get(&self, innov_number: InnovationId) -> Option<&GeneStats> {
+        self.stats.get(&innov_number)
+    }
+
+    /// Statistics of the connection `in_node -> out_node`. If several
+    /// innovations share the endpoints, the most frequent is returned.
+    pub fn by_edge(&self, in_node: NodeId, out_node: NodeId) -> Option<&GeneStats> {
+        self.stats
+            .values()
+            .filter(|s| s.in_node == in_node && s.out_node == out_node)
+            .max_by(|a, b| a.frequency.total_cmp(&b.frequency))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&InnovationId, &GeneStats)> {
+        self.stats.iter()
+    }
+
+    /// Selection weight of a candidate connection under `bias`. Zero means
+    /// the candidate should not be picked.
+    pub fn edge_weight(&self, bias: EdgeBias, in_node: NodeId, out_node: NodeId) -> f64 {
+        let stats = self.by_edge(in_node, out_node);
+        match (bias, stats) {
+            (EdgeBias::None, _) | (_, None) => 1.,
+            (EdgeBias::Helpful { strength }, Some(s)) => {
+                (strength * s.fitness_correlation).exp() as f64
+            }
+            (EdgeBias::AvoidSaturated { max_frequency }, Some(s)) => {
+                if s.frequency >= max_frequency {
+                    0.
+                } else {
+                    1.
+                }
+            }
+        }
+    }
+}
+
+/// Correlation between carrying a gene and fitness, where `carried` are the
+/// carriers' fitness values and `all` the fitness of the whole population.
+fn point_biserial(
+    carried: impl Iterator<Item = f32>,
+    all: impl Iterator<Item = f32> + Clone,
+    mean: f32,
+) -> f32 {
+    let n = all.clone().count() as f32;
+    let carried = carried.collect_vec();
+    let n1 = carried.len() as f32;
+    let n0 = n - n1;
+    let std = (all.map(|f| (f - mean) * (f - mean)).sum::<f32>() / n).sqrt();
+    if n1 == 0. || n0 == 0. || std == 0. || !std.is_finite() {
+        return 0.;
+    }
+    let mean_carried = carried.iter().sum::<f32>() / n1;
+    let mean_rest = (mean * n - mean_carried * n1) / n0;
+    (mean_carried - mean_rest) / std * (n1 * n0 / (n * n)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::fixtures::genome;
+
+    #[test]
+    fn test_update() {
+        let population = [
+            (genome(2, 2, &[], &[(1, 0, 2, 1.), (2, 1, 2, 0.)]), 3.),
+            (genome(2, 2, &[], &[(1, 0, 2, 3.)]), 1.),
+            (genome(2, 2, &[], &[(2, 1, 2, 0.)]), 3.),
+            (genome(2, 2, &[], &[]), 1.),
+        ];
+        let mut pool = GenePool::new(0.);
+        pool.update(population.iter().map(|(g, f)| (g, *f)));
+
+        let first = pool.get(1).unwrap();
+        assert_relative_eq!(first.frequency, 0.5);
+        assert_relative_eq!(first.mean_weight, 2.);
+        assert_relative_eq!(first.fitness_correlation, 0.);
+        // Edge 2 is carried by exactly the fittest half
+        let second = pool.by_edge(1, 2).unwrap();
+        assert_relative_eq!(second.fitness_correlation, 1.);
+        assert!(pool.get(3).is_none());
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut pool = GenePool::new(0.5);
+        let carried = [
+            (genome(2, 2, &[], &[(1, 0, 2, 1.)]), 2.),
+            (genome(2, 2, &[], &[]), 0.),
+        ];
+        pool.update(carried.iter().map(|(g, f)| (g, *f)));
+        assert_relative_eq!(pool.get(1).unwrap().fitness_correlation, 0.5);
+        let lost = [(genome(2, 2, &[], &[]), 2.), (genome(2, 2, &[], &[]), 0.)];
+        pool.update(lost.iter().map(|(g, f)| (g, *f)));
+        let stats = pool.get(1).unwrap();
+        assert_relative_eq!(stats.fitness_correlation, 0.25);
+        assert_relative_eq!(stats.frequency, 0.);
+    }
+
+    #[test]
+    fn test_edge_weight() {
+        let population = [
+            (genome(2, 2, &[], &[(1, 0, 2, 1.)]), 2.),
+            (genome(2, 2, &[], &[]), 0.),
+        ];
+        let mut pool = GenePool::new(0.);
+        pool.update(population.iter().map(|(g, f)| (g, *f)));
+        let saturated = EdgeBias::AvoidSaturated { max_frequency: 0.5 };
+        assert_eq!(pool.edge_weight(saturated, 0, 2), 0.);
+        assert_eq!(pool.edge_weight(saturated, 1, 2), 1.);
+        let helpful = EdgeBias::Helpful { strength: 2. };
+        assert_relative_eq!(pool.edge_weight(helpful, 0, 2), 2f64.exp(), epsilon = 1e-5);
+        assert_eq!(pool.edge_weight(EdgeBias::None, 0, 2), 1.);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::individual::genome::genome::Genome;
+
+use super::innovation_number::{InnovNumber, InnovationKind, InnovationRecord};
+
+/// Innovation record together with the genomes currently carrying it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InnovationHistoryEntry {
+    #[serde(flatten)]
+    pub record: InnovationRecord,
+    /// Indices into the exported population.
+    pub carriers: Vec<usize>,
+}
+
+/// Snapshot of the innovation registry against a population, exportable as
+/// CSV or JSON for offline analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InnovationHistory {
+    pub entries: Vec<InnovationHistoryEntry>,
+}
+
+impl InnovationHistory {
+    /// A genome carries an edge innovation if its edge list contains the
+    /// innovation number, and a node innovation if it has the hidden node.
+    pub fn new(registry: &InnovNumber, population: &[Genome]) -> Self {
+        // Edge innovation numbers and node ids are counted apart
+        let mut carriers: BTreeMap<(bool, u64), Vec<usize>> = BTreeMap::new();
+        for (index, genome) in population.iter().enumerate() {
+            genome
+                .genome_list
+                .iter()
+                .map(|edge| (false, edge.innov_number))
+                .chain(
+                    genome
+                        .node_list
+                        .hidden
+                        .iter()
+                        .map(|node| (true, node.node_id)),
+                )
+                .unique()
+                .for_each(|innov| carriers.entry(innov).or_default().push(index));
+        }
+        let entries = registry
+            .history()
+            .iter()
+            .map(|record| {
+                let node = !matches!(record.kind, InnovationKind::Edge { .. });
+                InnovationHistoryEntry {
+                    record: *record,
+                    carriers: carriers
+                        .remove(&(node, record.innov_number))
+                        .unwrap_or_default(),
+                }
+            })
+            .collect_vec();
+        Self { entries }
+    }
+
+    /// One row per innovation. Carriers are separated by `;`, and duplicated
+    /// nodes have the node they copy as `in_node` and no `out_node`.
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::from("innov_number,generation,type,in_node,out_node,carriers\n");
+        for InnovationHistoryEntry { record, carriers } in self.entries.iter() {
+            let (kind, in_node, out_node) = match record.kind {
+                InnovationKind::Node { in_node, out_node } => ("node", in_node, Some(out_node)),
+                InnovationKind::Edge { in_node, out_node } => ("edge", in_node, Some(out_node)),
+                InnovationKind::Duplicate { node } => ("duplicate", node, None),
+            };
+            ret.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                record.innov_number,
+                record.generation,
+                kind,
+                in_node,
+                out_node.map(|id| id.to_string()).unwrap_or_default(),
+                carriers.iter().join(";")
+            ));
+        }
+        ret
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("History contains only plain data")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::genome;
+
+    #[test]
+    fn test_history_export() {
+        let mut registry = InnovNumber::from_population(&[genome(1, 1, &[], &[])]);
+        let edge = registry.next_edge(0, 1);
+        registry.advance_generation();
+        let node = registry.next_node(0, 1);
+        let fst = registry.next_edge(0, node);
+        let snd = registry.next_edge(node, 1);
+        let population = vec![
+            genome(1, 1, &[], &[(edge, 0, 1, 1.)]),
+            genome(
+                1,
+                1,
+                &[node],
+                &[(edge, 0, 1, 1.), (fst, 0, node, 1.), (snd, node, 1, 1.)],
+            ),
+        ];
+
+        let history = InnovationHistory::new(&registry, &population);
+        assert_eq!(history.entries.len(), 4);
+        assert_eq!(history.entries[0].carriers, vec![0, 1]);
+        assert_eq!(history.entries[1].record.generation, 1);
+        assert_eq!(history.entries[1].carriers, vec![1]);
+
+        let csv = history.to_csv();
+        let lines = csv.lines().collect_vec();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "1,0,edge,0,1,0;1");
+        assert_eq!(lines[2], "2,1,node,0,1,1");
+
+        let json: serde_json::Value = serde_json::from_str(&history.to_json()).unwrap();
+        assert_eq!(json["entries"][1]["type"], "node");
+        assert_eq!(json["entries"][1]["generation"], 1);
+        assert_eq!(json["entries"][3]["carriers"], serde_json::json!([1]));
+
+        // Copies of a duplicated module name the node they copy
+        assert_eq!(registry.next_copy(node), 3);
+        let csv = InnovationHistory::new(&registry, &population).to_csv();
+        assert_eq!(csv.lines().last(), Some("3,1,duplicate,2,,"));
+    }
+}
@@ -1,11 +1,280 @@
-#[derive(Debug, Clone ,Copy, Default)]
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::gene_pool::GenePool;
+use crate::individual::genome::{
+    genome::{Genome, InnovationId},
+    node_list::NodeId,
+};
+
+/// Structure an innovation number was assigned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InnovationKind {
+    /// Hidden node created by splitting the edge `in_node -> out_node`.
+    Node { in_node: NodeId, out_node: NodeId },
+    /// Connection `in_node -> out_node`.
+    Edge { in_node: NodeId, out_node: NodeId },
+    /// Hidden node copying `node` in a duplicated module.
+    Duplicate { node: NodeId },
+}
+
+/// Registry entry describing when and where an innovation appeared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct InnovationRecord {
+    /// Innovation number of an edge, id of a node.
+    pub innov_number: u64,
+    pub generation: usize,
+    #[serde(flatten)]
+    pub kind: InnovationKind,
+}
+
+/// Structural mutations skipped over the run because the genome had
+/// reached a size limit, see `SizeLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct OversizedMutations {
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// Innovation registry. Hands out edge innovation numbers and node ids,
+/// from separate counters, and records the generation and structure of each
+/// of them, together with the population statistics of the edge
+/// innovations.
+#[derive(Debug, Clone, Default)]
 pub struct InnovNumber {
-    curr_innov: usize
+    curr_innov: InnovationId,
+    curr_node: NodeId,
+    generation: usize,
+    history: Vec<InnovationRecord>,
+    gene_pool: GenePool,
+    oversized: OversizedMutations,
 }
 
 impl InnovNumber {
-    pub fn next(&mut self) -> usize {
-        self.curr_innov += 1;
+    pub fn with_gene_pool(gene_pool: GenePool) -> Self {
+        Self {
+            gene_pool,
+            ..Default::default()
+        }
+    }
+
+    /// Registry of a resumed run whose last innovation number was `last`,
+    /// at `generation`. The history of the earlier generations is lost, and
+    /// node ids start past those of the genomes it is primed with.
+    pub fn resume(last: InnovationId, generation: usize) -> Self {
+        Self {
+            curr_innov: last,
+            generation,
+            ..Default::default()
+        }
+    }
+
+    /// Registry of genomes numbered elsewhere, e.g. imported or seeded from
+    /// files, see [`InnovNumber::prime`].
+    pub fn from_population(population: &[Genome]) -> Self {
+        let mut registry = Self::default();
+        registry.prime(population);
+        registry
+    }
+
+    /// Hand out innovation numbers and node ids past those of `genomes`, and
+    /// record their edges missing from the history at the current
+    /// generation. The nodes are not recorded: the edge they split is not
+    /// known.
+    pub fn prime<'a>(&mut self, genomes: impl IntoIterator<Item = &'a Genome>) {
+        let known = self
+            .history
+            .iter()
+            .filter(|record| matches!(record.kind, InnovationKind::Edge { .. }))
+            .map(|record| record.innov_number)
+            .collect::<BTreeSet<_>>();
+        let mut edges = BTreeMap::new();
+        for genome in genomes {
+            let nodes = &genome.node_list;
+            let ids = nodes
+                .input
+                .iter()
+                .chain(nodes.output.iter())
+                .chain(nodes.hidden.iter());
+            self.reserve_nodes(ids.map(|node| node.node_id));
+            for edge in genome.genome_list.iter() {
+                self.curr_innov = self.curr_innov.max(edge.innov_number);
+                if !known.contains(&edge.innov_number) {
+                    edges
+                        .entry(edge.innov_number)
+                        .or_insert(InnovationKind::Edge {
+                            in_node: edge.in_node,
+                            out_node: edge.out_node,
+                        });
+                }
+            }
+        }
+        self.history.extend(
+            edges
+                .into_iter()
+                .map(|(innov_number, kind)| InnovationRecord {
+                    innov_number,
+                    generation: self.generation,
+                    kind,
+                }),
+        );
+    }
+
+    /// Hand out node ids past `ids`, e.g. those of a genome the registry was
+    /// not primed with, so new nodes never collide with them.
+    pub fn reserve_nodes(&mut self, ids: impl IntoIterator<Item = NodeId>) {
+        self.curr_node = ids.into_iter().fold(self.curr_node, NodeId::max);
+    }
+
+    /// Last innovation number handed out.
+    pub fn last(&self) -> InnovationId {
         self.curr_innov
     }
-}
\ No newline at end of file
+
+    /// Last node id handed out, or the largest one primed.
+    pub fn last_node(&self) -> NodeId {
+        self.curr_node
+    }
+
+    /// Register a new hidden node splitting the edge `in_node -> out_node`.
+    pub fn next_node(&mut self, in_node: NodeId, out_node: NodeId) -> NodeId {
+        let node_id = next(&mut self.curr_node, "node ids");
+        self.record(node_id, InnovationKind::Node { in_node, out_node })
+    }
+
+    /// Register a new hidden node copying `node`.
+    pub fn next_copy(&mut self, node: NodeId) -> NodeId {
+        let node_id = next(&mut self.curr_node, "node ids");
+        self.record(node_id, InnovationKind::Duplicate { node })
+    }
+
+    /// Register a new connection `in_node -> out_node`.
+    pub fn next_edge(&mut self, in_node: NodeId, out_node: NodeId) -> InnovationId {
+        let innov_number = next(&mut self.curr_innov, "innovation numbers");
+        self.record(innov_number, InnovationKind::Edge { in_node, out_node })
+    }
+
+    fn record(&mut self, innov_number: u64, kind: InnovationKind) -> u64 {
+        self.history.push(InnovationRecord {
+            innov_number,
+            generation: self.generation,
+            kind,
+        });
+        innov_number
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Innovations registered from now on are attributed to the next generation.
+    pub fn advance_generation(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Registered innovations in registration order, the edges of a primed
+    /// population by innovation number.
+    pub fn history(&self) -> &[InnovationRecord] {
+        &self.history
+    }
+
+    pub fn gene_pool(&self) -> &GenePool {
+        &self.gene_pool
+    }
+
+    pub fn gene_pool_mut(&mut self) -> &mut GenePool {
+        &mut self.gene_pool
+    }
+
+    pub fn oversized(&self) -> OversizedMutations {
+        self.oversized
+    }
+
+    pub fn oversized_mut(&mut self) -> &mut OversizedMutations {
+        &mut self.oversized
+    }
+}
+
+/// Number after `counter`, which it becomes. Panics rather than wrap
+/// around and reuse numbers.
+fn next(counter: &mut u64, what: &str) -> u64 {
+    *counter = counter
+        .checked_add(1)
+        .unwrap_or_else(|| panic!("Ran out of {what}"));
+    *counter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    #[test]
+    fn test_from_population() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .connect(0, 3, 1.)
+            .connect(3, 2, 1.)
+            .build()
+            .unwrap();
+        let mut imported = genome.clone();
+        imported.genome_list.edge_list[1].innov_number = 12;
+        let mut registry = InnovNumber::from_population(&[genome.clone(), imported]);
+        assert_eq!(registry.last(), 12);
+        assert_eq!(
+            registry
+                .history()
+                .iter()
+                .map(|record| record.innov_number)
+                .collect::<Vec<_>>(),
+            [0, 1, 12]
+        );
+        assert_eq!(
+            registry.history()[2].kind,
+            InnovationKind::Edge {
+                in_node: 3,
+                out_node: 2
+            }
+        );
+        assert_eq!(registry.next_edge(0, 2), 13);
+
+        // Resumed counters only move forward, and known numbers stay put
+        let mut resumed = InnovNumber::resume(20, 4);
+        resumed.prime([&genome]);
+        assert_eq!(resumed.last(), 20);
+        resumed.prime([&genome]);
+        assert_eq!(resumed.history().len(), 2);
+        assert_eq!(resumed.history()[0].generation, 4);
+    }
+
+    #[test]
+    fn test_separate_counters() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .connect(0, 3, 1.)
+            .build()
+            .unwrap();
+        let mut registry = InnovNumber::from_population(&[genome]);
+        assert_eq!((registry.last(), registry.last_node()), (0, 3));
+        assert_eq!(registry.next_node(0, 3), 4);
+        assert_eq!(registry.next_edge(0, 4), 1);
+        assert_eq!(registry.next_node(0, 4), 5);
+
+        // Ids of genomes it was not primed with are skipped
+        registry.reserve_nodes([2, 9]);
+        assert_eq!(registry.next_node(0, 2), 10);
+        assert_eq!(registry.last(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ran out of innovation numbers")]
+    fn test_overflow() {
+        InnovNumber::resume(u64::MAX, 0).next_edge(0, 1);
+    }
+}
@@ -1,2 +1,8 @@
+#[allow(clippy::module_inception)]
 pub mod mutation;
-pub mod innovation_number;
\ No newline at end of file
+pub mod innovation_number;
+pub mod innovation_history;
+pub mod gene_pool;
+pub mod operator;
+pub mod adaptive;
+pub mod canonical;
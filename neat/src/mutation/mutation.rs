@@ -1,44 +1,287 @@
-use std::collections::BTreeSet as TreeSet;
-use itertools::Itertools;
-use rand::prelude::*;
-use crate::individual::genome::{genome::{Genome, GenomeEdge}, node_list::{Node, Config}, clamp::Clamp, aggregation::Aggregation, activation::Activation};
-use super::innovation_number::InnovNumber;
+use std::collections::BTreeMap;
 
-// TODO: Consider different mutation methods
+use rand::prelude::*;
+use rand_distr::{Cauchy, Exp1, Normal};
+use serde::{Deserialize, Serialize};
+use crate::population::{generation::OperatorStats, replay::MutationStep};
+use crate::individual::genome::{genome::Genome, clamp::Clamp, aggregation::Aggregation, activation::{Activation, ActivationSet}};
+use super::{
+    gene_pool::EdgeBias,
+    innovation_number::InnovNumber,
+    operator::{
+        AddEdge, AddGate, AddNode, DuplicateModule, InputScalingMutation, MutationPipeline,
+        NodeConfigMutation, ToggleEnabled, WeightMutation,
+    },
+};
 
 pub trait MutationMethod {
     fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome , innov_number: &mut InnovNumber);
+
+    /// Like `mutate`, returning the names of the operators that changed the
+    /// child, none for methods without named operators.
+    fn mutate_named(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<&'static str> {
+        self.mutate(rng, child, innov_number);
+        vec![]
+    }
+
+    /// Like `mutate_named`, also recording the change of every operator.
+    /// Draws the same numbers as `mutate`, so recording does not change a
+    /// run. Methods that cannot tell their operators apart leave the
+    /// records out.
+    fn mutate_recorded(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<MutationStep> {
+        self.mutate_named(rng, child, innov_number)
+            .into_iter()
+            .map(|operator| MutationStep {
+                operator: operator.to_string(),
+                record: None,
+            })
+            .collect()
+    }
+
+    /// Whether the method adapts to the operator statistics, which the
+    /// population then tracks.
+    fn adapts(&self) -> bool {
+        false
+    }
+
+    /// Adjust to the success of the operators that bred the last evaluated
+    /// generation.
+    fn adapt(&mut self, _stats: &BTreeMap<String, OperatorStats>) {}
+
+    /// Scale the mutation rates by `factor`, e.g. to escape stagnation. A
+    /// no-op for methods without rates.
+    fn boost(&mut self, _factor: f64) {}
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ProbabilityMatrixNode {
-    prob_clamp : f64,
-    prob_activation : f64,
-    prob_aggregation : f64
+    pub prob_clamp : f64,
+    pub prob_activation : f64,
+    pub prob_aggregation : f64,
+    /// Probability of adding Gaussian noise to the bias
+    pub prob_bias : f64,
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ProbabilityMatrixEdge {
-    prob_enabled : f64,
-    prob_weight : f64,
-    prob_new_node : f64,
-    prob_new_edge : f64,
+    pub prob_enabled : f64,
+    /// Probability of adding Gaussian noise to a weight
+    pub prob_weight_perturb : f64,
+    /// Probability of drawing a new weight, checked before perturbation
+    pub prob_weight_replace : f64,
+    pub prob_new_node : f64,
+    pub prob_new_edge : f64,
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
 pub struct ProbabilityMatrix {
-    node_probs: ProbabilityMatrixNode,
-    prob_edge : ProbabilityMatrixEdge,
+    pub node_probs: ProbabilityMatrixNode,
+    pub prob_edge : ProbabilityMatrixEdge,
+}
+
+impl ProbabilityMatrix {
+    /// Every rate times `factor`, at most 1.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let scale = |prob: f64| (prob * factor).min(1.);
+        let (node, edge) = (self.node_probs, self.prob_edge);
+        Self {
+            node_probs: ProbabilityMatrixNode {
+                prob_clamp: scale(node.prob_clamp),
+                prob_activation: scale(node.prob_activation),
+                prob_aggregation: scale(node.prob_aggregation),
+                prob_bias: scale(node.prob_bias),
+            },
+            prob_edge: ProbabilityMatrixEdge {
+                prob_enabled: scale(edge.prob_enabled),
+                prob_weight_perturb: scale(edge.prob_weight_perturb),
+                prob_weight_replace: scale(edge.prob_weight_replace),
+                prob_new_node: scale(edge.prob_new_node),
+                prob_new_edge: scale(edge.prob_new_edge),
+            },
+        }
+    }
+}
+
+/// Rates the NEAT paper has no notion of, kept small.
+impl Default for ProbabilityMatrixNode {
+    fn default() -> Self {
+        Self {
+            prob_clamp: 0.1,
+            prob_activation: 0.1,
+            prob_aggregation: 0.1,
+            prob_bias: 0.1,
+        }
+    }
+}
+
+/// Rates of the NEAT paper, with rare toggling.
+impl Default for ProbabilityMatrixEdge {
+    fn default() -> Self {
+        Self {
+            prob_enabled: 0.01,
+            prob_weight_perturb: 0.8,
+            prob_weight_replace: 0.1,
+            prob_new_node: 0.03,
+            prob_new_edge: 0.05,
+        }
+    }
+}
+
+/// Parts of the genome the mutation operators leave untouched.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Frozen {
+    /// Keep the output activations, e.g. sigmoids producing probabilities
+    pub output_activation : bool,
+    pub output_aggregation : bool,
+    pub output_clamp : bool,
+    /// Never add, toggle or split edges leaving an input node. Their weights
+    /// still mutate
+    pub input_edges : bool,
+    pub output_bias : bool,
+}
+
+/// Largest genome structural mutations may grow. Node counts include the
+/// inputs and outputs, edge counts the disabled edges. Unlimited by default.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub max_nodes: Option<usize>,
+    pub max_edges: Option<usize>,
+    /// Distinct hidden levels, see `NodeList::depth`.
+    pub max_depth: Option<usize>,
 }
 
+impl SizeLimits {
+    /// Whether `genome` may grow by `nodes` nodes and `edges` edges.
+    pub fn allows(&self, genome: &Genome, nodes: usize, edges: usize) -> bool {
+        let node_list = &genome.node_list;
+        let node_count = node_list.input.len() + node_list.output.len() + node_list.hidden.len();
+        let edge_count = genome.genome_list.edge_list.len();
+        self.max_nodes.is_none_or(|max| node_count + nodes <= max)
+            && self.max_edges.is_none_or(|max| edge_count + edges <= max)
+    }
+
+    /// Whether `genome` may get a hidden node at a new level.
+    pub fn allows_level(&self, genome: &Genome) -> bool {
+        self.max_depth.is_none_or(|max| genome.node_list.depth() < max)
+    }
+}
+
+/// Which enabled edge add-node mutation splits. Weighted policies fall back
+/// to a uniform pick when every candidate weighs 0.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitPolicy {
+    /// Any edge, as in the original NEAT.
+    #[default]
+    Uniform,
+    /// Edges weighted by the level gap between their endpoints, so long
+    /// edges are split first.
+    Long,
+    /// Edges weighted by the rank of their innovation number, so recently
+    /// added edges are split first.
+    Recent,
+    /// Edges weighted by their absolute weight.
+    Weight,
+}
+
+/// Distribution of the noise added to weights and biases, centred on 0. Its
+/// scale is the deviation of the Gaussian, the half-width of the uniform and
+/// the scale parameter of the Cauchy and Laplace distributions. The heavy
+/// tails of the Cauchy and Laplace distributions mix rare large jumps into
+/// small steps, which helps escape local optima.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Perturbation {
+    #[default]
+    Gaussian,
+    Uniform,
+    Cauchy,
+    Laplace,
+}
+
+impl Perturbation {
+    /// Noise of scale `scale`, always 0 for a scale of 0.
+    pub fn sample(self, rng: &mut dyn RngCore, scale: f32) -> f32 {
+        let scale = scale.abs();
+        match self {
+            Perturbation::Gaussian => Normal::new(0., scale)
+                .expect("Standard deviation is finite")
+                .sample(rng),
+            Perturbation::Uniform => (rng.gen::<f32>() * 2. - 1.) * scale,
+            // The Cauchy distribution rejects a scale of 0
+            Perturbation::Cauchy => Cauchy::new(0., scale).map_or(0., |cauchy| cauchy.sample(rng)),
+            Perturbation::Laplace => {
+                let magnitude = scale * rng.sample::<f32, _>(Exp1);
+                if rng.gen_bool(0.5) {
+                    magnitude
+                } else {
+                    -magnitude
+                }
+            }
+        }
+    }
+}
+
+/// Default deviation of recurrent weight perturbations, a fifth of the
+/// forward one: recurrent edges feed back outputs of up to the default clamp
+/// of 5 in magnitude, and their changes compound over the passes.
+pub const RECURRENT_COEFF: f32 = 0.2;
+
+/// Default deviation of bias perturbations, half the weight one: a bias
+/// shifts the node for every input at once.
+pub const BIAS_COEFF: f32 = 0.5;
+
 #[derive(Clone, Debug, Copy)]
 pub struct GaussianMutation {
     /// Probability of a changing gene
     pub prob: ProbabilityMatrix,
-    /// Coefficient for the mutation
+    /// Standard deviation of weight perturbation
     pub coeff : f32,
+    /// Standard deviation of the perturbation of recurrent edges
+    pub recurrent_coeff : f32,
+    /// Standard deviation of bias perturbation
+    pub bias_coeff : f32,
+    /// Range new weights are drawn from on replacement
+    pub replace_range : (f32, f32),
     /// Iteration loopa
-    pub max_iteration : usize
+    pub max_iteration : usize,
+    /// Gene pool bias of add-edge mutation
+    pub edge_bias : EdgeBias,
+    pub frozen : Frozen,
+    /// Functions new and mutated nodes may use
+    pub activations : ActivationSet,
+    pub limits : SizeLimits,
+    pub split_policy : SplitPolicy,
+    /// Function of the nodes add-node mutation creates, drawn from
+    /// `activations` if none
+    pub node_activation : Option<Activation>,
+    /// Distribution of weight perturbations, of scale `coeff` or
+    /// `recurrent_coeff`
+    pub weight_perturbation : Perturbation,
+    /// Distribution of bias perturbations, of scale `bias_coeff`
+    pub bias_perturbation : Perturbation,
+    /// Probability of perturbing each input scale and offset
+    pub prob_input_scaling : f64,
+    /// Scale of input scale and offset perturbations, of distribution
+    /// `weight_perturbation`
+    pub input_scaling_coeff : f32,
+    /// Probability of turning a hidden node into a gated memory cell
+    pub prob_new_gate : f64,
+    /// Probability of duplicating a module of hidden nodes
+    pub prob_duplicate_module : f64,
 }
 
 impl Default for GaussianMutation {
@@ -49,28 +292,103 @@ impl Default for GaussianMutation {
                     prob_clamp: 0.5,
                     prob_activation: 0.5,
                     prob_aggregation: 0.5,
+                    prob_bias: 0.5,
                 },
                 prob_edge: ProbabilityMatrixEdge {
-                    prob_weight: 0.5,
+                    prob_weight_perturb: 0.5,
+                    prob_weight_replace: 0.1,
                     prob_enabled: 0.5,
                     prob_new_node: 0.5,
                     prob_new_edge: 0.5,
                 }
             },
             coeff: 1.,
+            recurrent_coeff: RECURRENT_COEFF,
+            bias_coeff: BIAS_COEFF,
+            replace_range: (-1., 1.),
             max_iteration: 10,
+            edge_bias: EdgeBias::None,
+            frozen: Frozen::default(),
+            activations: ActivationSet::all(),
+            limits: SizeLimits::default(),
+            split_policy: SplitPolicy::default(),
+            node_activation: None,
+            weight_perturbation: Perturbation::default(),
+            bias_perturbation: Perturbation::default(),
+            prob_input_scaling: 0.2,
+            input_scaling_coeff: 0.1,
+            prob_new_gate: 0.,
+            prob_duplicate_module: 0.,
         }
     }
 }
 
 impl GaussianMutation {
     pub fn new(prob: ProbabilityMatrix, coeff : f32, max_iteration : usize) -> Self {
-        Self { prob, coeff, max_iteration }
+        Self { prob, coeff, max_iteration, ..Default::default() }
+    }
+
+    pub fn with_replace_range(self, min: f32, max: f32) -> Self {
+        assert!(min <= max);
+        Self { replace_range: (min, max), ..self }
+    }
+
+    pub fn with_recurrent_coeff(self, recurrent_coeff: f32) -> Self {
+        Self { recurrent_coeff, ..self }
+    }
+
+    pub fn with_bias_coeff(self, bias_coeff: f32) -> Self {
+        Self { bias_coeff, ..self }
+    }
+
+    pub fn with_edge_bias(self, edge_bias: EdgeBias) -> Self {
+        Self { edge_bias, ..self }
+    }
+
+    pub fn with_frozen(self, frozen: Frozen) -> Self {
+        Self { frozen, ..self }
+    }
+
+    pub fn with_activations(self, activations: ActivationSet) -> Self {
+        assert!(!activations.is_empty());
+        Self { activations, ..self }
+    }
+
+    pub fn with_limits(self, limits: SizeLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    pub fn with_split_policy(self, split_policy: SplitPolicy) -> Self {
+        Self { split_policy, ..self }
+    }
+
+    pub fn with_node_activation(self, node_activation: Option<Activation>) -> Self {
+        Self { node_activation, ..self }
+    }
+
+    pub fn with_weight_perturbation(self, weight_perturbation: Perturbation) -> Self {
+        Self { weight_perturbation, ..self }
+    }
+
+    pub fn with_bias_perturbation(self, bias_perturbation: Perturbation) -> Self {
+        Self { bias_perturbation, ..self }
+    }
+
+    pub fn with_input_scaling(self, prob_input_scaling: f64, input_scaling_coeff: f32) -> Self {
+        Self { prob_input_scaling, input_scaling_coeff, ..self }
+    }
+
+    pub fn with_gates(self, prob_new_gate: f64) -> Self {
+        Self { prob_new_gate, ..self }
+    }
+
+    pub fn with_module_duplication(self, prob_duplicate_module: f64) -> Self {
+        Self { prob_duplicate_module, ..self }
     }
 }
 
 fn weight_mutation(rng: &mut dyn RngCore, coeff: f32) -> f32 {
-    (rng.gen::<f32>() * 4. - 2.) * coeff
+    Perturbation::Uniform.sample(rng, 2. * coeff)
 }
 
 pub trait Mutation {
@@ -92,123 +410,493 @@ impl Mutation for Aggregation {
 
 impl Mutation for Activation {
     fn mutate(&mut self, rng: &mut dyn RngCore) {
-        *self = match rng.gen::<Activation>() {
-            Activation::Softplus(x) => Activation::Softplus(x + weight_mutation(rng, 1.)),
-            Activation::Periodic(a) => Activation::Periodic(a + weight_mutation(rng, 1.)),
-            v => v
-        }
+        mutate_activation(self, rng, ActivationSet::all());
     }
 }
 
-impl GaussianMutation {
+/// Replace `activation` by a function of `activations`.
+pub(super) fn mutate_activation(activation: &mut Activation, rng: &mut dyn RngCore, activations: ActivationSet) {
+    *activation = match activations.sample(rng) {
+        Activation::Softplus(x) => Activation::Softplus(x + weight_mutation(rng, 1.)),
+        Activation::Periodic(a) => Activation::Periodic(a + weight_mutation(rng, 1.)),
+        Activation::NoisyRelu(sigma) => Activation::NoisyRelu(sigma + weight_mutation(rng, 1.)),
+        v => v
+    }
+    .bounded()
+}
 
-  fn mutate_nodes<'a>(&self, rng : &mut dyn RngCore, node_list : impl Iterator<Item = &'a mut Node>) {
-    let prob_node = self.prob.node_probs;
-    for Node {config, ..} in node_list {
-      // Mutate 
-      if rng.gen_bool(prob_node.prob_clamp) {
-        config.clamp.mutate(rng)
-      }
-      if rng.gen_bool(prob_node.prob_aggregation) {
-          config.aggregation.mutate(rng);
-      }
-      if rng.gen_bool(prob_node.prob_activation) {
-          config.activation.mutate(rng);
-      }
-    }
-  }
-
-  fn mutate_edges<'a>(&self, rng : &mut dyn RngCore, edge_list : impl Iterator<Item = &'a mut GenomeEdge>) {
-    let prob_edge = self.prob.prob_edge;
-    // Weight mutation
-    for v in edge_list {
-      if rng.gen_bool(prob_edge.prob_enabled) {
-        v.enabled = !v.enabled;
-      }
-      
-        if rng.gen_bool(prob_edge.prob_weight) {
-            v.weight += weight_mutation(rng, self.coeff);
+impl GaussianMutation {
+    /// The operators this mutation applies, in order. Extend it with
+    /// [`MutationPipeline::with_operator`] to add custom operators.
+    /// Gates and module duplication are added last, and only with a
+    /// positive probability, so runs without them draw the same numbers as
+    /// before.
+    pub fn pipeline(&self) -> MutationPipeline {
+        let prob_edge = self.prob.prob_edge;
+        let frozen = self.frozen;
+        let add_edge = AddEdge {
+            max_iteration: self.max_iteration,
+            edge_bias: self.edge_bias,
+            frozen,
+            limits: self.limits,
+        };
+        let pipeline = MutationPipeline::new()
+            .with_operator(1., NodeConfigMutation {
+                prob: self.prob.node_probs,
+                frozen,
+                activations: self.activations,
+                bias_sigma: self.bias_coeff,
+                bias_perturbation: self.bias_perturbation,
+            })
+            .with_operator(1., ToggleEnabled { prob: prob_edge.prob_enabled, frozen })
+            .with_operator(1., WeightMutation {
+                prob_perturb: prob_edge.prob_weight_perturb,
+                prob_replace: prob_edge.prob_weight_replace,
+                sigma: self.coeff,
+                recurrent_sigma: self.recurrent_coeff,
+                replace_range: self.replace_range,
+                perturbation: self.weight_perturbation,
+            })
+            .with_operator(1., InputScalingMutation {
+                prob: self.prob_input_scaling,
+                sigma: self.input_scaling_coeff,
+                perturbation: self.weight_perturbation,
+            })
+            // A genome without enabled edges has nothing to split, so it
+            // grows an edge instead
+            .with_operator(prob_edge.prob_new_node, AddNode {
+                fallback: Some(add_edge),
+                frozen,
+                activations: self.activations,
+                limits: self.limits,
+                split_policy: self.split_policy,
+                activation: self.node_activation,
+            })
+            .with_operator(prob_edge.prob_new_edge, add_edge);
+        let pipeline = if self.prob_new_gate > 0. {
+            pipeline.with_operator(self.prob_new_gate, AddGate)
+        } else {
+            pipeline
+        };
+        if self.prob_duplicate_module > 0. {
+            pipeline.with_operator(self.prob_duplicate_module, DuplicateModule {
+                frozen: self.frozen,
+                limits: self.limits,
+                ..Default::default()
+            })
+        } else {
+            pipeline
         }
     }
-  }
 }
 
 impl MutationMethod for GaussianMutation {
-    fn mutate(&self, rng: &mut dyn RngCore, Genome {genome_list, node_list}: &mut Genome, innov_number : &mut InnovNumber) {
-        self.mutate_nodes(rng, node_list.hidden.iter_mut().chain(node_list.output.iter_mut().chain(node_list.input.iter_mut())));
-        self.mutate_edges(rng, genome_list.edge_list.iter_mut());
-        
-        let concated_list = [node_list.input.iter(),node_list.output.iter(), node_list.hidden.iter()].into_iter().flatten().collect_vec();
-        // Topological mutations
-        // Clean up and test
-        if rng.gen_bool(self.prob.prob_edge.prob_new_node) {
-            let edge = genome_list
-                        .iter_mut()
-                        .choose(rng)
-                        .unwrap();
-            let node_start = concated_list[concated_list.binary_search_by(|a| a.node_id.cmp(&edge.in_node)).unwrap()];
-            let node_end = concated_list[concated_list.binary_search_by(|a| a.node_id.cmp(&edge.out_node)).unwrap()];
-            edge.enabled = false;
-            let number = innov_number.next();
-            let new_node = Node { 
-                node_id: number,
-                level: (node_start.level + node_end.level) / 2,
-                config: Config {
-                    aggregation: rng.gen(),
-                    clamp: Clamp::default(),
-                    activation: rng.gen(),
-                },
-            };
-            let number = innov_number.next();
-            let edge1 = GenomeEdge {
-                in_node: node_start.node_id,
-                out_node: new_node.node_id,
-                innov_number: number,
-                weight: 2. * rng.gen::<f32>() - 1.,
-                enabled: true,
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome, innov_number : &mut InnovNumber) {
+        self.pipeline().mutate(rng, child, innov_number);
+    }
+
+    fn mutate_named(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<&'static str> {
+        self.pipeline().mutate_named(rng, child, innov_number)
+    }
+
+    fn mutate_recorded(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<MutationStep> {
+        self.pipeline().mutate_recorded(rng, child, innov_number)
+    }
+
+    fn boost(&mut self, factor: f64) {
+        self.prob = self.prob.scaled(factor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use num::rational::Ratio;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        individual::genome::{
+            genome::{GenomeEdge, GenomeFactory},
+            node_list::Node,
+        },
+        mutation::{gene_pool::GenePool, operator::MutationOperator},
+    };
+
+    fn add_edge_only() -> GaussianMutation {
+        let mut mutation = GaussianMutation::default();
+        mutation.prob.node_probs = ProbabilityMatrixNode {
+            prob_clamp: 0.,
+            prob_activation: 0.,
+            prob_aggregation: 0.,
+            prob_bias: 0.,
+        };
+        mutation.prob.prob_edge = ProbabilityMatrixEdge {
+            prob_enabled: 0.,
+            prob_weight_perturb: 0.,
+            prob_weight_replace: 0.,
+            prob_new_node: 0.,
+            prob_new_edge: 1.,
+        };
+        mutation
+    }
+
+    fn weights_only(prob_weight_perturb: f64, prob_weight_replace: f64) -> GaussianMutation {
+        let mut mutation = add_edge_only();
+        mutation.prob.prob_edge = ProbabilityMatrixEdge {
+            prob_weight_perturb,
+            prob_weight_replace,
+            prob_new_edge: 0.,
+            ..mutation.prob.prob_edge
+        };
+        mutation
+    }
+
+    fn mutated_weights(mutation: GaussianMutation, weight: f32) -> Vec<f32> {
+        let factory = GenomeFactory::init(1, 1).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut innov_number = InnovNumber::default();
+        (0..2000)
+            .map(|_| {
+                let mut child = factory.generate_genome();
+                child.genome_list.edge_list.push(GenomeEdge {
+                    innov_number: 0,
+                    in_node: 0,
+                    out_node: 1,
+                    weight,
+                    enabled: true,
+                });
+                mutation.mutate(&mut rng, &mut child, &mut innov_number);
+                child.genome_list.edge_list[0].weight
+            })
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_weight_perturbation_is_gaussian() {
+        let mutation = GaussianMutation {
+            coeff: 0.5,
+            ..weights_only(1., 0.)
+        };
+        let deltas = mutated_weights(mutation, 10.).iter().map(|w| w - 10.).collect_vec();
+        let mean = deltas.iter().sum::<f32>() / deltas.len() as f32;
+        let variance =
+            deltas.iter().map(|d| (d - mean) * (d - mean)).sum::<f32>() / deltas.len() as f32;
+        let std = variance.sqrt();
+        assert!(mean.abs() < 0.05);
+        assert!((std - 0.5).abs() < 0.05);
+        // Noise is unbounded, unlike the former uniform noise of width 2
+        assert!(deltas.iter().any(|d| d.abs() > 1.));
+    }
+
+    #[test]
+    fn test_weight_perturbation_distributions() {
+        let deltas = |weight_perturbation| {
+            let mutation = GaussianMutation {
+                coeff: 0.5,
+                weight_perturbation,
+                ..weights_only(1., 0.)
             };
-            let number = innov_number.next();
-            let edge2 = GenomeEdge {
-                in_node: new_node.node_id,
-                out_node: node_end.node_id,
-                innov_number: number,
-                weight: 2. * rng.gen::<f32>() - 1.,
-                enabled: true,
-            }; 
-            genome_list.edge_list.push(edge1);
-            genome_list.edge_list.push(edge2);
-            node_list.hidden.push(new_node);
+            mutated_weights(mutation, 10.)
+                .iter()
+                .map(|w| (w - 10.).abs())
+                .sorted_by(f32::total_cmp)
+                .collect_vec()
+        };
+        let uniform = deltas(Perturbation::Uniform);
+        assert!(uniform.iter().all(|d| *d <= 0.5));
+        assert!((uniform[1000] - 0.25).abs() < 0.03);
+        // The absolute value of a Laplace draw is exponential, of mean the scale
+        let laplace = deltas(Perturbation::Laplace);
+        assert!((laplace.iter().sum::<f32>() / 2000. - 0.5).abs() < 0.05);
+        // Half the Cauchy draws are within the scale, and the tail is heavy
+        let cauchy = deltas(Perturbation::Cauchy);
+        assert!((cauchy[1000] - 0.5).abs() < 0.05);
+        assert!(cauchy[1999] > 50.);
+        let gaussian = deltas(Perturbation::Gaussian);
+        assert!(gaussian[1999] < 3.);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for perturbation in [Perturbation::Gaussian, Perturbation::Uniform, Perturbation::Cauchy, Perturbation::Laplace] {
+            assert_eq!(perturbation.sample(&mut rng, 0.), 0.);
         }
-        if rng.gen_bool(prob_edge.prob_new_edge) {
-            let n = node_list.input.len();
-            let p = node_list.hidden.len() + node_list.output.len();
-            let total = n * p + p * (p - 1);
-            if genome_list.edge_list.len() != total {
-                let ratio = genome_list.edge_list.len() as f64 / total as f64;
-                let attempt = (0.01f64.log(ratio).ceil().min(100.) as usize + 2).min(self.max_iteration);
-                let map = TreeSet::from_iter(genome_list.iter().map(|el| (el.in_node,el.out_node)));
-                for _ in 0..attempt {
-                    let start = [
-                        node_list.input.iter(),
-                        node_list.hidden.iter(),
-                        node_list.output.iter(),
-                    ].into_iter().flatten().choose(rng).unwrap();
-                    let end = [
-                        node_list.hidden.iter(),
-                        node_list.output.iter(),
-                    ].into_iter().flatten().choose(rng).unwrap();
-                    if !map.contains(&(start.node_id,end.node_id)) {
-                        genome_list.edge_list.push(GenomeEdge {
-                            innov_number: innov_number.next(),
-                            in_node: start.node_id,
-                            out_node: end.node_id,
-                            weight: 2. * rng.gen::<f32>() - 1.,
-                            enabled: rng.gen_bool(0.9),
-                        });
-                        break
-                    }
+    }
+
+    #[test]
+    fn test_recurrent_perturbation() {
+        use crate::individual::genome::genome::GenomeBuilder;
+
+        let mutation = GaussianMutation {
+            coeff: 0.5,
+            recurrent_coeff: 0.05,
+            ..weights_only(1., 0.)
+        };
+        // A forward edge and a self-loop of the output
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(0, 1, 0.)
+            .connect(1, 1, 0.)
+            .build()
+            .unwrap();
+        let edges = &genome.genome_list.edge_list;
+        assert!(!genome.is_recurrent(&edges[0]) && genome.is_recurrent(&edges[1]));
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut innov_number = InnovNumber::default();
+        let (mut forward, mut recurrent) = (0f32, 0f32);
+        for _ in 0..2000 {
+            let mut child = genome.clone();
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+            forward = forward.max(child.genome_list.edge_list[0].weight.abs());
+            recurrent = recurrent.max(child.genome_list.edge_list[1].weight.abs());
+        }
+        assert!(forward > 1. && recurrent < 0.5);
+        assert_eq!(GaussianMutation::default().recurrent_coeff, RECURRENT_COEFF);
+    }
+
+    #[test]
+    fn test_weight_replacement() {
+        let mutation = weights_only(1., 1.).with_replace_range(2., 3.);
+        let weights = mutated_weights(mutation, 10.);
+        assert!(weights.iter().all(|w| (2. ..=3.).contains(w)));
+
+        let mutation = weights_only(0., 0.5).with_replace_range(2., 3.);
+        let replaced = mutated_weights(mutation, 10.).iter().filter(|w| **w != 10.).count();
+        assert!((800..1200).contains(&replaced));
+    }
+
+    #[test]
+    fn test_add_node_on_empty_genome() {
+        let mut mutation = add_edge_only();
+        mutation.prob.prob_edge.prob_new_node = 1.;
+        mutation.prob.prob_edge.prob_new_edge = 0.;
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let mut child = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        // Falls back to add-edge, then splits the new edge once enabled
+        while child.genome_list.iter().all(|e| !e.enabled) {
+            assert!(child.node_list.hidden.is_empty());
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+        }
+        mutation.mutate(&mut rng, &mut child, &mut innov_number);
+        assert_eq!(child.node_list.hidden.len(), 1);
+    }
+
+    #[test]
+    fn test_mutate_fresh_genomes() {
+        let mut rng = ChaCha8Rng::seed_from_u64(9);
+        let mut innov_number = InnovNumber::default();
+        let mutations = [GaussianMutation::default(), {
+            let mut structural = add_edge_only();
+            structural.prob.prob_edge.prob_new_node = 1.;
+            structural
+        }];
+        for (inputs, outputs) in [(1, 1), (2, 1), (3, 4)] {
+            let factory = GenomeFactory::init(inputs, outputs).unwrap();
+            for mutation in mutations.iter() {
+                for _ in 0..1000 {
+                    let mut child = factory.generate_genome();
+                    mutation.mutate(&mut rng, &mut child, &mut innov_number);
+                }
+                let mut child = factory.generate_genome();
+                for _ in 0..20 {
+                    mutation.mutate(&mut rng, &mut child, &mut innov_number);
                 }
             }
         }
     }
+
+    #[test]
+    fn test_avoid_saturated_edges() {
+        let factory = GenomeFactory::init(2, 2).unwrap();
+        let mut saturated = factory.generate_genome();
+        saturated.genome_list.edge_list.push(GenomeEdge {
+            innov_number: 0,
+            in_node: 0,
+            out_node: 2,
+            weight: 1.,
+            enabled: true,
+        });
+        let mut pool = GenePool::new(0.);
+        pool.update([(&saturated, 1.)].into_iter());
+        let mut innov_number = InnovNumber::with_gene_pool(pool);
+
+        let mutation = add_edge_only().with_edge_bias(EdgeBias::AvoidSaturated { max_frequency: 0.5 });
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut added = 0;
+        for _ in 0..500 {
+            let mut child = factory.generate_genome();
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+            added += child.genome_list.edge_list.len();
+            assert!(child.genome_list.iter().all(|e| (e.in_node, e.out_node) != (0, 2)));
+        }
+        assert!(added > 0);
+    }
+
+    #[test]
+    fn test_frozen() {
+        let frozen = Frozen {
+            output_activation: true,
+            output_aggregation: true,
+            output_clamp: true,
+            output_bias: true,
+            input_edges: true,
+        };
+        let mutation = GaussianMutation::default().with_frozen(frozen);
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let mut innov_number = InnovNumber::default();
+        // Keep new node ids clear of the factory's
+        (0..10).for_each(|_| {
+            innov_number.next_edge(0, 0);
+        });
+        let mut child = GenomeFactory::init(2, 2).unwrap().generate_genome();
+        child.genome_list.edge_list.push(GenomeEdge {
+            innov_number: 0,
+            in_node: 0,
+            out_node: 2,
+            weight: 1.,
+            enabled: true,
+        });
+        let outputs = format!("{:?}", child.node_list.output);
+        for _ in 0..50 {
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+        }
+        assert_eq!(format!("{:?}", child.node_list.output), outputs);
+        let input_edges = child.genome_list.iter().filter(|e| e.in_node < 2).collect_vec();
+        assert_eq!(input_edges.len(), 1);
+        assert!(input_edges[0].enabled);
+        // Only edges between outputs are left to split, which have no level
+        // for a hidden node
+        assert!(child.node_list.hidden.is_empty());
+        assert_eq!(child.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_activation_whitelist() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let activations = [Activation::Tanh, Activation::Gauss]
+            .into_iter()
+            .collect::<ActivationSet>();
+        let operator = NodeConfigMutation {
+            prob: ProbabilityMatrixNode {
+                prob_clamp: 0.,
+                prob_activation: 1.,
+                prob_aggregation: 0.,
+                prob_bias: 0.,
+            },
+            frozen: Frozen::default(),
+            activations,
+            bias_sigma: BIAS_COEFF,
+            bias_perturbation: Perturbation::Gaussian,
+        };
+        let mut child = GenomeFactory::init(1, 3).unwrap().generate_genome();
+        for _ in 0..10 {
+            operator.apply(&mut rng, &mut child, &mut InnovNumber::default());
+            assert!(child
+                .node_list
+                .output
+                .iter()
+                .all(|node| activations.contains(&node.config.activation)));
+        }
+    }
+
+    #[test]
+    fn test_bias_mutation() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let operator = NodeConfigMutation {
+            prob: ProbabilityMatrixNode {
+                prob_clamp: 0.,
+                prob_activation: 0.,
+                prob_aggregation: 0.,
+                prob_bias: 1.,
+            },
+            frozen: Frozen {
+                output_bias: true,
+                ..Default::default()
+            },
+            activations: ActivationSet::all(),
+            bias_sigma: 0.1,
+            bias_perturbation: Perturbation::Gaussian,
+        };
+        let mut child = GenomeFactory::init(1, 1).unwrap().generate_genome();
+        child
+            .node_list
+            .hidden
+            .push(Node::new(3, Ratio::from_integer(50), None));
+        let biases = (0..2000)
+            .map(|_| {
+                let mut child = child.clone();
+                assert!(operator.apply(&mut rng, &mut child, &mut InnovNumber::default()));
+                assert_eq!(child.node_list.output[0].config.bias, 0.);
+                child.node_list.hidden[0].config.bias
+            })
+            .collect_vec();
+        let mean = biases.iter().sum::<f32>() / biases.len() as f32;
+        let deviation = (biases.iter().map(|b| (b - mean).powi(2)).sum::<f32>()
+            / biases.len() as f32)
+            .sqrt();
+        assert!(mean.abs() < 0.01, "mean {mean}");
+        assert!((deviation - 0.1).abs() < 0.01, "deviation {deviation}");
+    }
+
+    #[test]
+    fn test_bounded_activation_params() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let activations = [Activation::Softplus(1.), Activation::Periodic(1.)]
+            .into_iter()
+            .collect::<ActivationSet>();
+        let mut activation = Activation::Softplus(1.);
+        for _ in 0..200 {
+            mutate_activation(&mut activation, &mut rng, activations);
+            assert_eq!(activation.bounded(), activation);
+        }
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let mut mutation = GaussianMutation::default().with_limits(SizeLimits {
+            max_nodes: Some(5),
+            max_edges: Some(6),
+            max_depth: None,
+        });
+        mutation.prob.prob_edge.prob_new_node = 1.;
+        mutation.prob.prob_edge.prob_new_edge = 1.;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut innov_number = InnovNumber::default();
+        (0..10).for_each(|_| {
+            innov_number.next_edge(0, 0);
+        });
+        let mut child = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        for _ in 0..50 {
+            mutation.mutate(&mut rng, &mut child, &mut innov_number);
+            assert!(mutation.limits.allows(&child, 0, 0));
+        }
+        assert!(!child.node_list.hidden.is_empty());
+        assert_eq!(child.genome_list.edge_list.len(), 6);
+        let oversized = innov_number.oversized();
+        assert!(oversized.nodes > 0 && oversized.edges > 0);
+    }
+
+    #[test]
+    fn test_boost() {
+        let mut mutation = GaussianMutation {
+            prob: ProbabilityMatrix::default(),
+            ..Default::default()
+        };
+        mutation.boost(2.);
+        assert_eq!(mutation.prob.prob_edge.prob_new_node, 0.06);
+        assert_eq!(mutation.prob.prob_edge.prob_weight_perturb, 1.);
+        assert_eq!(mutation.prob.node_probs.prob_clamp, 0.2);
+    }
 }
@@ -0,0 +1,1233 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound::Excluded,
+};
+
+use itertools::Itertools;
+use num::ToPrimitive;
+use rand::prelude::*;
+
+use super::{
+    gene_pool::EdgeBias,
+    innovation_number::InnovNumber,
+    mutation::{
+        mutate_activation, Frozen, Mutation, MutationMethod, Perturbation, ProbabilityMatrixNode,
+        SizeLimits, SplitPolicy,
+    },
+};
+use crate::{
+    individual::genome::{
+        activation::{Activation, ActivationSet},
+        clamp::Clamp,
+        genome::{Genome, GenomeEdge},
+        node_list::{Config, Gate, Node, NodeId, NodeList, NodeRole},
+    },
+    population::{
+        generation::OperatorStats,
+        replay::{MutationRecord, MutationStep},
+    },
+};
+
+/// Whether `edge` is input-adjacent structure frozen by `frozen`.
+fn is_frozen(frozen: Frozen, node_list: &NodeList, edge: &GenomeEdge) -> bool {
+    frozen.input_edges && node_list.role(edge.in_node) == NodeRole::Sensor
+}
+
+/// Single, independent mutation of a genome. Returns whether the genome
+/// changed.
+pub trait MutationOperator {
+    fn apply(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> bool;
+}
+
+/// Mutation applying a list of operators in order, each with its own
+/// probability.
+#[derive(Default)]
+pub struct MutationPipeline {
+    operators: Vec<(f64, &'static str, Box<dyn MutationOperator>)>,
+}
+
+impl MutationPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `operator`, applied with probability `probability`.
+    pub fn with_operator(
+        mut self,
+        probability: f64,
+        operator: impl MutationOperator + 'static,
+    ) -> Self {
+        self.register(probability, operator);
+        self
+    }
+
+    pub fn register<O: MutationOperator + 'static>(&mut self, probability: f64, operator: O) {
+        let name = std::any::type_name::<O>().rsplit("::").next().unwrap_or_default();
+        self.register_named(name, probability, operator);
+    }
+
+    /// Append `operator` under `name` rather than its type name, e.g. to
+    /// tell instances of a type apart in the operator statistics.
+    pub fn with_named_operator(
+        mut self,
+        name: &'static str,
+        probability: f64,
+        operator: impl MutationOperator + 'static,
+    ) -> Self {
+        self.register_named(name, probability, operator);
+        self
+    }
+
+    pub fn register_named(
+        &mut self,
+        name: &'static str,
+        probability: f64,
+        operator: impl MutationOperator + 'static,
+    ) {
+        assert!((0. ..=1.).contains(&probability));
+        self.operators.push((probability, name, Box::new(operator)));
+    }
+
+    /// Apply the operators named `name` with probability `probability`.
+    /// Returns whether there was any.
+    pub fn set_probability(&mut self, name: &str, probability: f64) -> bool {
+        assert!((0. ..=1.).contains(&probability));
+        let mut found = false;
+        for (prob, _, _) in self.operators.iter_mut().filter(|(_, n, _)| *n == name) {
+            *prob = probability;
+            found = true;
+        }
+        found
+    }
+
+    /// Type name and probability of every operator, in order.
+    pub fn operators(&self) -> impl Iterator<Item = (&'static str, f64)> + '_ {
+        self.operators.iter().map(|(probability, name, _)| (*name, *probability))
+    }
+
+    pub fn len(&self) -> usize {
+        self.operators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+}
+
+impl MutationMethod for MutationPipeline {
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome, innov_number: &mut InnovNumber) {
+        self.mutate_named(rng, child, innov_number);
+    }
+
+    fn mutate_named(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<&'static str> {
+        let mut applied = vec![];
+        for (probability, name, operator) in self.operators.iter() {
+            if rng.gen_bool(*probability) {
+                let changed = operator.apply(rng, child, innov_number);
+                event!(TRACE, operator = name, changed);
+                if changed {
+                    applied.push(*name);
+                }
+            }
+        }
+        applied
+    }
+
+    fn mutate_recorded(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<MutationStep> {
+        let mut steps = vec![];
+        for (probability, name, operator) in self.operators.iter() {
+            if rng.gen_bool(*probability) {
+                let before = child.clone();
+                let changed = operator.apply(rng, child, innov_number);
+                event!(TRACE, operator = name, changed);
+                if changed {
+                    steps.push(MutationStep {
+                        operator: name.to_string(),
+                        record: Some(MutationRecord::between(&before, child)),
+                    });
+                }
+            }
+        }
+        steps
+    }
+
+    /// Every probability times `factor`, at most 1.
+    fn boost(&mut self, factor: f64) {
+        for (probability, _, _) in self.operators.iter_mut() {
+            *probability = (*probability * factor).min(1.);
+        }
+    }
+}
+
+/// `base` followed by the operators of a pipeline, the extension point of
+/// user-defined structural mutations, e.g. adding a fully connected module
+/// or duplicating a subgraph. The operators take part like the built-in
+/// ones: they get the innovation registry, follow the probabilities of
+/// [`NeatConfig::mutation_with`] and `boost`, and count in the operator
+/// statistics under their names. Adaptation is left to `base`.
+///
+/// [`NeatConfig::mutation_with`]: crate::config::neat_config::NeatConfig::mutation_with
+pub struct ExtendedMutation<M> {
+    base: M,
+    operators: MutationPipeline,
+}
+
+impl<M: MutationMethod> ExtendedMutation<M> {
+    pub fn new(base: M, operators: MutationPipeline) -> Self {
+        Self { base, operators }
+    }
+
+    pub fn base(&self) -> &M {
+        &self.base
+    }
+
+    pub fn operators(&self) -> &MutationPipeline {
+        &self.operators
+    }
+}
+
+impl<M: MutationMethod> MutationMethod for ExtendedMutation<M> {
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome, innov_number: &mut InnovNumber) {
+        self.base.mutate(rng, child, innov_number);
+        self.operators.mutate(rng, child, innov_number);
+    }
+
+    fn mutate_named(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<&'static str> {
+        let mut applied = self.base.mutate_named(rng, child, innov_number);
+        applied.extend(self.operators.mutate_named(rng, child, innov_number));
+        applied
+    }
+
+    fn mutate_recorded(
+        &self,
+        rng: &mut dyn RngCore,
+        child: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> Vec<MutationStep> {
+        let mut steps = self.base.mutate_recorded(rng, child, innov_number);
+        steps.extend(self.operators.mutate_recorded(rng, child, innov_number));
+        steps
+    }
+
+    fn adapts(&self) -> bool {
+        self.base.adapts()
+    }
+
+    fn adapt(&mut self, stats: &BTreeMap<String, OperatorStats>) {
+        self.base.adapt(stats);
+    }
+
+    fn boost(&mut self, factor: f64) {
+        self.base.boost(factor);
+        self.operators.boost(factor);
+    }
+}
+
+/// Mutate the clamp, aggregation, activation and bias of every output and
+/// hidden node, each with its own probability. Frozen output settings are
+/// skipped. Biases get noise of `bias_perturbation` of scale `bias_sigma`,
+/// and so do the gate parameters of gated nodes, with the bias probability.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeConfigMutation {
+    pub prob: ProbabilityMatrixNode,
+    pub frozen: Frozen,
+    pub activations: ActivationSet,
+    pub bias_sigma: f32,
+    pub bias_perturbation: Perturbation,
+}
+
+impl MutationOperator for NodeConfigMutation {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let mut changed = false;
+        let hidden = genome.node_list.hidden.iter_mut().map(|node| (node, false));
+        let output = genome.node_list.output.iter_mut().map(|node| (node, true));
+        for (Node { config, .. }, is_output) in hidden.chain(output) {
+            let frozen = |part: bool| is_output && part;
+            if !frozen(self.frozen.output_clamp) && rng.gen_bool(self.prob.prob_clamp) {
+                config.clamp.mutate(rng);
+                changed = true;
+            }
+            if !frozen(self.frozen.output_aggregation) && rng.gen_bool(self.prob.prob_aggregation)
+            {
+                config.aggregation.mutate(rng);
+                changed = true;
+            }
+            if !frozen(self.frozen.output_activation) && rng.gen_bool(self.prob.prob_activation) {
+                mutate_activation(&mut config.activation, rng, self.activations);
+                changed = true;
+            }
+            if !frozen(self.frozen.output_bias) && rng.gen_bool(self.prob.prob_bias) {
+                config.bias += self.bias_perturbation.sample(rng, self.bias_sigma);
+                changed = true;
+            }
+            if let Some(gate) = config.gate.as_mut() {
+                if !frozen(self.frozen.output_bias) && rng.gen_bool(self.prob.prob_bias) {
+                    for param in [
+                        &mut gate.input_weight,
+                        &mut gate.input_bias,
+                        &mut gate.forget_weight,
+                        &mut gate.forget_bias,
+                    ] {
+                        *param += self.bias_perturbation.sample(rng, self.bias_sigma);
+                    }
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Flip the enabled flag of every edge with probability `prob`.
+#[derive(Debug, Clone, Copy)]
+pub struct ToggleEnabled {
+    pub prob: f64,
+    pub frozen: Frozen,
+}
+
+impl MutationOperator for ToggleEnabled {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let mut changed = false;
+        let Genome {
+            genome_list,
+            node_list,
+            ..
+        } = genome;
+        for edge in genome_list.iter_mut() {
+            if !is_frozen(self.frozen, node_list, edge) && rng.gen_bool(self.prob) {
+                edge.enabled = !edge.enabled;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Replace every weight with probability `prob_replace`, otherwise add noise
+/// of `perturbation` of scale `sigma` with probability `prob_perturb`, of
+/// `recurrent_sigma` for [recurrent](Genome::is_recurrent) edges. Tied
+/// weights are recurrent if any of their edges is.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightMutation {
+    pub prob_perturb: f64,
+    pub prob_replace: f64,
+    pub sigma: f32,
+    pub recurrent_sigma: f32,
+    pub replace_range: (f32, f32),
+    pub perturbation: Perturbation,
+}
+
+impl WeightMutation {
+    fn mutate(&self, rng: &mut dyn RngCore, weight: &mut f32, sigma: f32) -> bool {
+        if rng.gen_bool(self.prob_replace) {
+            let (min, max) = self.replace_range;
+            *weight = min + rng.gen::<f32>() * (max - min);
+            true
+        } else if rng.gen_bool(self.prob_perturb) {
+            *weight += self.perturbation.sample(rng, sigma);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Tied edges mutate once per group.
+impl MutationOperator for WeightMutation {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let feedback = genome
+            .genome_list
+            .iter()
+            .filter(|edge| genome.is_recurrent(edge))
+            .map(|edge| edge.innov_number)
+            .collect::<BTreeSet<_>>();
+        let mut changed = false;
+        let Genome {
+            genome_list,
+            weight_table,
+            ..
+        } = genome;
+        let tied_feedback = feedback
+            .iter()
+            .filter_map(|innov_number| weight_table.groups.get(innov_number).copied())
+            .collect::<BTreeSet<_>>();
+        for edge in genome_list.iter_mut() {
+            if !weight_table.groups.contains_key(&edge.innov_number) {
+                let sigma = if feedback.contains(&edge.innov_number) {
+                    self.recurrent_sigma
+                } else {
+                    self.sigma
+                };
+                changed |= self.mutate(rng, &mut edge.weight, sigma);
+            }
+        }
+        for (group, weight) in weight_table.weights.iter_mut() {
+            let sigma = if tied_feedback.contains(group) {
+                self.recurrent_sigma
+            } else {
+                self.sigma
+            };
+            changed |= self.mutate(rng, weight, sigma);
+        }
+        genome.sync_weights();
+        changed
+    }
+}
+
+/// Perturb the scale and the offset of every input gene, each with
+/// probability `prob`, with noise of `perturbation` of scale `sigma`.
+/// Genomes without input scaling are left alone.
+#[derive(Debug, Clone, Copy)]
+pub struct InputScalingMutation {
+    pub prob: f64,
+    pub sigma: f32,
+    pub perturbation: Perturbation,
+}
+
+impl MutationOperator for InputScalingMutation {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let mut changed = false;
+        for gene in genome.input_scaling.genes.values_mut() {
+            if rng.gen_bool(self.prob) {
+                gene.scale += self.perturbation.sample(rng, self.sigma);
+                changed = true;
+            }
+            if rng.gen_bool(self.prob) {
+                gene.offset += self.perturbation.sample(rng, self.sigma);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Split an enabled edge, picked by `split_policy`, with a new hidden node
+/// halfway between its endpoints. At the depth limit only edges spanning an
+/// existing hidden level are split, the node joining the level closest to
+/// halfway. Without an enabled edge to split, `fallback` is applied instead.
+/// Skipped, and counted as oversized, when the node or its two edges would
+/// exceed `limits`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AddNode {
+    pub fallback: Option<AddEdge>,
+    pub frozen: Frozen,
+    /// Functions the new node may use.
+    pub activations: ActivationSet,
+    pub limits: SizeLimits,
+    pub split_policy: SplitPolicy,
+    /// Function of the new node, drawn from `activations` if none.
+    pub activation: Option<Activation>,
+}
+
+impl MutationOperator for AddNode {
+    fn apply(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> bool {
+        if !self.limits.allows(genome, 1, 2) {
+            innov_number.oversized_mut().nodes += 1;
+            return false;
+        }
+        let deepen = self.limits.allows_level(genome);
+        let Genome {
+            genome_list,
+            node_list,
+            weight_table,
+            ..
+        } = genome;
+        let concated_list = [
+            node_list.input.iter(),
+            node_list.output.iter(),
+            node_list.hidden.iter(),
+        ]
+        .into_iter()
+        .flatten()
+        .copied()
+        .collect_vec();
+        let find = |id: NodeId| {
+            concated_list[concated_list
+                .binary_search_by(|a| a.node_id.cmp(&id))
+                .expect("Edge endpoints are nodes of the genome")]
+        };
+        let levels = node_list.hidden.iter().map(|node| node.level).collect::<BTreeSet<_>>();
+        let placement = |edge: &GenomeEdge| {
+            let (start, end) = (find(edge.in_node).level, find(edge.out_node).level);
+            let mid = (start + end) / 2;
+            let (low, high) = (start.min(end), start.max(end));
+            // A loop on an output has no level between its ends
+            if deepen && (low != high || levels.contains(&low)) {
+                Some(mid)
+            } else if low == high {
+                None
+            } else {
+                levels
+                    .range((Excluded(low), Excluded(high)))
+                    .min_by_key(|level| if **level < mid { mid - **level } else { **level - mid })
+                    .copied()
+            }
+        };
+        let mut candidates = genome_list
+            .iter_mut()
+            .filter(|edge| edge.enabled && !is_frozen(self.frozen, node_list, edge))
+            .collect_vec();
+        let splittable = candidates.len();
+        candidates.retain(|edge| placement(edge).is_some());
+        if splittable > 0 && candidates.is_empty() {
+            innov_number.oversized_mut().nodes += 1;
+            return false;
+        }
+        let weights = match self.split_policy {
+            SplitPolicy::Uniform => vec![],
+            SplitPolicy::Long => candidates
+                .iter()
+                .map(|edge| {
+                    let (start, end) = (find(edge.in_node).level, find(edge.out_node).level);
+                    let gap = if start < end { end - start } else { start - end };
+                    gap.to_f64().unwrap_or(0.)
+                })
+                .collect_vec(),
+            SplitPolicy::Recent => {
+                let mut weights = vec![0.; candidates.len()];
+                let by_innovation =
+                    (0..candidates.len()).sorted_by_key(|i| candidates[*i].innov_number);
+                for (rank, i) in by_innovation.enumerate() {
+                    weights[i] = (rank + 1) as f64;
+                }
+                weights
+            }
+            SplitPolicy::Weight => candidates
+                .iter()
+                .map(|edge| {
+                    let weight = weight_table.weight(edge.innov_number).unwrap_or(edge.weight);
+                    weight.abs() as f64
+                })
+                .collect_vec(),
+        };
+        let index = match rand::distributions::WeightedIndex::new(&weights) {
+            Ok(weighted) => Some(weighted.sample(rng)),
+            Err(_) => (!candidates.is_empty()).then(|| rng.gen_range(0..candidates.len())),
+        };
+        let Some(edge) = index.map(|i| candidates.swap_remove(i)) else {
+            return self
+                .fallback
+                .is_some_and(|fallback| fallback.apply(rng, genome, innov_number));
+        };
+        let (node_start, node_end) = (find(edge.in_node), find(edge.out_node));
+        let level = placement(edge).expect("Candidates have a placement");
+        edge.enabled = false;
+        // Genomes the registry was not primed with may hold larger ids
+        innov_number.reserve_nodes(concated_list.iter().map(|node| node.node_id));
+        let new_node = Node {
+            node_id: innov_number.next_node(node_start.node_id, node_end.node_id),
+            level,
+            config: Config {
+                aggregation: rng.gen(),
+                clamp: Clamp::default(),
+                activation: self.activation.unwrap_or_else(|| self.activations.sample(rng)),
+                bias: 0.,
+                gate: None,
+            },
+        };
+        for (in_node, out_node) in [
+            (node_start.node_id, new_node.node_id),
+            (new_node.node_id, node_end.node_id),
+        ] {
+            genome_list.edge_list.push(GenomeEdge {
+                innov_number: innov_number.next_edge(in_node, out_node),
+                in_node,
+                out_node,
+                weight: 2. * rng.gen::<f32>() - 1.,
+                enabled: true,
+            });
+        }
+        node_list.hidden.push(new_node);
+        true
+    }
+}
+
+/// Connect two unconnected nodes, unless the genome is fully connected.
+/// Samples up to `max_iteration` candidates; with an edge bias the
+/// candidates are weighted by the gene pool, see `GenePool::edge_weight`.
+/// Skipped, and counted as oversized, when the edge would exceed `limits`.
+#[derive(Debug, Clone, Copy)]
+pub struct AddEdge {
+    pub max_iteration: usize,
+    pub edge_bias: EdgeBias,
+    pub frozen: Frozen,
+    pub limits: SizeLimits,
+}
+
+impl Default for AddEdge {
+    fn default() -> Self {
+        Self {
+            max_iteration: 10,
+            edge_bias: EdgeBias::None,
+            frozen: Frozen::default(),
+            limits: SizeLimits::default(),
+        }
+    }
+}
+
+impl MutationOperator for AddEdge {
+    fn apply(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> bool {
+        if !self.limits.allows(genome, 0, 1) {
+            innov_number.oversized_mut().edges += 1;
+            return false;
+        }
+        let Genome {
+            genome_list,
+            node_list,
+            ..
+        } = genome;
+        let inputs = if self.frozen.input_edges { 0 } else { node_list.input.len() };
+        let p = node_list.hidden.len() + node_list.output.len();
+        let total = inputs * p + p * (p - 1);
+        let existing = genome_list
+            .iter()
+            .filter(|edge| !is_frozen(self.frozen, node_list, edge))
+            .count();
+        if existing >= total {
+            return false;
+        }
+        let ratio = existing as f64 / total as f64;
+        let attempt = (0.01f64.log(ratio).ceil().min(100.) as usize + 2).min(self.max_iteration);
+        let map = BTreeSet::from_iter(genome_list.iter().map(|el| (el.in_node, el.out_node)));
+        let mut candidates = vec![];
+        for _ in 0..attempt {
+            let start = [
+                node_list.input[..inputs].iter(),
+                node_list.hidden.iter(),
+                node_list.output.iter(),
+            ]
+            .into_iter()
+            .flatten()
+            .choose(rng)
+            .unwrap();
+            let end = [node_list.hidden.iter(), node_list.output.iter()]
+                .into_iter()
+                .flatten()
+                .choose(rng)
+                .unwrap();
+            let key = (start.node_id, end.node_id);
+            if !map.contains(&key) && !candidates.contains(&key) {
+                candidates.push(key);
+                if self.edge_bias == EdgeBias::None {
+                    break;
+                }
+            }
+        }
+        let pool = innov_number.gene_pool();
+        let Some((in_node, out_node)) = candidates
+            .choose_weighted(rng, |(in_node, out_node)| {
+                pool.edge_weight(self.edge_bias, *in_node, *out_node)
+            })
+            .ok()
+            .copied()
+        else {
+            return false;
+        };
+        genome_list.edge_list.push(GenomeEdge {
+            innov_number: innov_number.next_edge(in_node, out_node),
+            in_node,
+            out_node,
+            weight: 2. * rng.gen::<f32>() - 1.,
+            enabled: rng.gen_bool(0.9),
+        });
+        true
+    }
+}
+
+/// Remove a random hidden node together with its edges.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeleteNode;
+
+impl MutationOperator for DeleteNode {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let hidden = &mut genome.node_list.hidden;
+        if hidden.is_empty() {
+            return false;
+        }
+        let node_id = hidden.remove(rng.gen_range(0..hidden.len())).node_id;
+        genome
+            .genome_list
+            .edge_list
+            .retain(|edge| edge.in_node != node_id && edge.out_node != node_id);
+        genome.sync_weights();
+        true
+    }
+}
+
+/// Turn a random ungated hidden node into a gated memory cell, with the
+/// nearly transparent default gate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AddGate;
+
+impl MutationOperator for AddGate {
+    fn apply(&self, rng: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+        let ungated = genome
+            .node_list
+            .hidden
+            .iter_mut()
+            .filter(|node| node.config.gate.is_none());
+        let Some(node) = ungated.choose(rng) else {
+            return false;
+        };
+        node.config.gate = Some(Gate::default());
+        true
+    }
+}
+
+/// Copy a module of hidden nodes in parallel with it, as in modular NEAT.
+/// The module grows from a random hidden node over enabled edges between
+/// hidden nodes, up to `max_size` nodes. The copies get fresh ids at the
+/// levels and with the configs of their originals, and every edge of the
+/// module, among its nodes or to the rest of the genome, is copied with a
+/// fresh innovation, except frozen input edges. Edges leaving the module
+/// split their weight with their copies, so sum aggregation sees the same
+/// input; tied ones are untied first. Skipped, and counted as oversized,
+/// when the copy would exceed `limits`.
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateModule {
+    pub max_size: usize,
+    pub frozen: Frozen,
+    pub limits: SizeLimits,
+}
+
+impl Default for DuplicateModule {
+    fn default() -> Self {
+        Self {
+            max_size: 4,
+            frozen: Frozen::default(),
+            limits: SizeLimits::default(),
+        }
+    }
+}
+
+impl DuplicateModule {
+    /// Hidden nodes connected to `seed` by enabled edges, in the order
+    /// they are reached.
+    fn module(&self, genome: &Genome, seed: NodeId) -> Vec<NodeId> {
+        let hidden = genome
+            .node_list
+            .hidden
+            .iter()
+            .map(|node| node.node_id)
+            .collect::<BTreeSet<_>>();
+        let mut module = vec![seed];
+        let mut next = 0;
+        while next < module.len() && module.len() < self.max_size {
+            let node = module[next];
+            next += 1;
+            let neighbours = genome
+                .genome_list
+                .iter()
+                .filter(|edge| edge.enabled)
+                .filter_map(|edge| match (edge.in_node == node, edge.out_node == node) {
+                    (true, _) => Some(edge.out_node),
+                    (_, true) => Some(edge.in_node),
+                    _ => None,
+                })
+                .filter(|id| hidden.contains(id))
+                .sorted()
+                .dedup()
+                .collect_vec();
+            for id in neighbours {
+                if module.len() < self.max_size && !module.contains(&id) {
+                    module.push(id);
+                }
+            }
+        }
+        module
+    }
+}
+
+impl MutationOperator for DuplicateModule {
+    fn apply(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &mut Genome,
+        innov_number: &mut InnovNumber,
+    ) -> bool {
+        let Some(seed) = genome.node_list.hidden.choose(rng).map(|node| node.node_id) else {
+            return false;
+        };
+        let module = self.module(genome, seed);
+        let touching = genome
+            .genome_list
+            .iter()
+            .filter(|edge| module.contains(&edge.in_node) || module.contains(&edge.out_node))
+            .filter(|edge| !is_frozen(self.frozen, &genome.node_list, edge))
+            .copied()
+            .collect_vec();
+        if !self.limits.allows(genome, module.len(), touching.len()) {
+            innov_number.oversized_mut().nodes += 1;
+            return false;
+        }
+        let nodes = &genome.node_list;
+        let ids = nodes
+            .input
+            .iter()
+            .chain(nodes.output.iter())
+            .chain(nodes.hidden.iter());
+        // Genomes the registry was not primed with may hold larger ids
+        innov_number.reserve_nodes(ids.map(|node| node.node_id));
+        let mut copies = Vec::with_capacity(module.len());
+        for id in module.iter().copied() {
+            let original = nodes
+                .hidden
+                .iter()
+                .find(|node| node.node_id == id)
+                .expect("Modules are hidden nodes");
+            let node_id = innov_number.next_copy(id);
+            copies.push(Node { node_id, ..*original });
+        }
+        let copy = |id: NodeId| {
+            module
+                .iter()
+                .position(|m| *m == id)
+                .map_or(id, |index| copies[index].node_id)
+        };
+        let mut added = vec![];
+        for edge in touching {
+            let mut weight = genome.weight(&edge);
+            if !module.contains(&edge.out_node) {
+                weight /= 2.;
+                // The rest of the group keeps its weight
+                genome.weight_table.groups.remove(&edge.innov_number);
+                let original = genome
+                    .genome_list
+                    .iter_mut()
+                    .find(|e| e.innov_number == edge.innov_number);
+                original.expect("Edges of the module are in the genome").weight = weight;
+            }
+            let (in_node, out_node) = (copy(edge.in_node), copy(edge.out_node));
+            added.push(GenomeEdge {
+                innov_number: innov_number.next_edge(in_node, out_node),
+                in_node,
+                out_node,
+                weight,
+                enabled: edge.enabled,
+            });
+        }
+        genome.node_list.hidden.extend(copies);
+        genome.genome_list.edge_list.extend(added);
+        genome.sync_weights();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_chacha::ChaCha8Rng;
+
+    use num::rational::Ratio;
+
+    use super::*;
+    use crate::{
+        individual::genome::{
+            aggregation::Aggregation, genome::GenomeBuilder, network::network::FFNetwork,
+        },
+        mutation::{innovation_number::InnovationKind, mutation::GaussianMutation},
+    };
+
+    /// Doubles every weight.
+    struct DoubleWeights;
+
+    impl MutationOperator for DoubleWeights {
+        fn apply(&self, _: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+            genome
+                .genome_list
+                .iter_mut()
+                .for_each(|edge| edge.weight *= 2.);
+            true
+        }
+    }
+
+    fn genome() -> Genome {
+        GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(50)
+            .connect(0, 2, 1.)
+            .connect(2, 1, 1.)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_custom_operator() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let pipeline = MutationPipeline::new()
+            .with_operator(1., DoubleWeights)
+            .with_operator(0., DeleteNode);
+        let mut child = genome();
+        pipeline.mutate(&mut rng, &mut child, &mut innov_number);
+        assert!(child.genome_list.iter().all(|edge| edge.weight == 2.));
+        assert_eq!(child.node_list.hidden.len(), 1);
+        assert_eq!(pipeline.len(), 2);
+        assert_eq!(
+            pipeline.operators().collect_vec(),
+            vec![("DoubleWeights", 1.), ("DeleteNode", 0.)]
+        );
+    }
+
+    /// Connects the input straight to the output, as a structural operator
+    /// of user code would.
+    struct Bypass;
+
+    impl MutationOperator for Bypass {
+        fn apply(
+            &self,
+            _: &mut dyn RngCore,
+            genome: &mut Genome,
+            innov_number: &mut InnovNumber,
+        ) -> bool {
+            let (in_node, out_node) = (0, 1);
+            if genome
+                .genome_list
+                .iter()
+                .any(|edge| (edge.in_node, edge.out_node) == (in_node, out_node))
+            {
+                return false;
+            }
+            genome.genome_list.edge_list.push(GenomeEdge {
+                innov_number: innov_number.next_edge(in_node, out_node),
+                in_node,
+                out_node,
+                weight: 1.,
+                enabled: true,
+            });
+            true
+        }
+    }
+
+    #[test]
+    fn test_extended_mutation() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::from_population(&[genome()]);
+        let operators = MutationPipeline::new()
+            .with_named_operator("Bypass", 0.5, Bypass)
+            .with_operator(0., DeleteNode);
+        let base = MutationPipeline::new().with_operator(1., DoubleWeights);
+        let mut mutation = ExtendedMutation::new(base, operators);
+        mutation.boost(2.);
+        assert_eq!(
+            mutation.operators().operators().collect_vec(),
+            vec![("Bypass", 1.), ("DeleteNode", 0.)]
+        );
+
+        let mut child = genome();
+        let applied = mutation.mutate_named(&mut rng, &mut child, &mut innov_number);
+        assert_eq!(applied, vec!["DoubleWeights", "Bypass"]);
+        assert_eq!(child.genome_list.edge_list[2].innov_number, 2);
+        assert_eq!(
+            innov_number.history().last().unwrap().kind,
+            InnovationKind::Edge {
+                in_node: 0,
+                out_node: 1
+            }
+        );
+        // Unchanged children do not count for the operator
+        let applied = mutation.mutate_named(&mut rng, &mut child, &mut innov_number);
+        assert_eq!(applied, vec!["DoubleWeights"]);
+        assert!(!mutation.adapts());
+    }
+
+    #[test]
+    fn test_delete_node() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let mut child = genome();
+        child.tie_weights(0, [0, 1]).unwrap();
+        assert!(DeleteNode.apply(&mut rng, &mut child, &mut innov_number));
+        assert!(child.node_list.hidden.is_empty() && child.genome_list.edge_list.is_empty());
+        assert!(child.weight_table.is_empty());
+        assert!(!DeleteNode.apply(&mut rng, &mut child, &mut innov_number));
+    }
+
+    #[test]
+    fn test_add_gate() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let mut child = genome();
+        assert!(AddGate.apply(&mut rng, &mut child, &mut innov_number));
+        assert_eq!(child.node_list.hidden[0].config.gate, Some(Gate::default()));
+        assert!(child.node_list.output[0].config.gate.is_none());
+        assert!(!AddGate.apply(&mut rng, &mut child, &mut innov_number));
+
+        // Gate parameters move with the bias
+        let node_config = NodeConfigMutation {
+            prob: ProbabilityMatrixNode {
+                prob_clamp: 0.,
+                prob_activation: 0.,
+                prob_aggregation: 0.,
+                prob_bias: 1.,
+            },
+            frozen: Frozen::default(),
+            activations: ActivationSet::all(),
+            bias_sigma: 0.1,
+            bias_perturbation: Perturbation::default(),
+        };
+        assert!(node_config.apply(&mut rng, &mut child, &mut innov_number));
+        let gate = child.node_list.hidden[0].config.gate.unwrap();
+        assert_ne!(gate, Gate::default());
+        assert!((gate.input_bias - Gate::default().input_bias).abs() < 1.);
+
+        let mutation = GaussianMutation::default();
+        let names = |mutation: GaussianMutation| {
+            mutation.pipeline().operators().map(|(name, _)| name).collect_vec()
+        };
+        assert!(!names(mutation).contains(&"AddGate"));
+        assert_eq!(names(mutation.with_gates(0.1)).last(), Some(&"AddGate"));
+    }
+
+    #[test]
+    fn test_duplicate_module() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(30)
+            .hidden(60)
+            .activation(3, Activation::Tanh)
+            .aggregation(2, Aggregation::Sum)
+            .connect(0, 3, 0.5)
+            .connect(1, 3, -1.)
+            .connect(3, 4, 1.5)
+            .connect(4, 2, 2.)
+            .connect(3, 2, 1.)
+            .connect(0, 2, 0.3)
+            .build()
+            .unwrap();
+        let mut innov_number = InnovNumber::from_population(std::slice::from_ref(&genome));
+        let mut child = genome.clone();
+        child.genome_list.edge_list[4].enabled = false;
+        let mut parent = child.clone();
+        assert!(DuplicateModule::default().apply(&mut rng, &mut child, &mut innov_number));
+        child.validate().unwrap();
+
+        let hidden = &child.node_list.hidden;
+        assert_eq!(hidden.iter().map(|node| node.node_id).collect_vec(), vec![3, 4, 5, 6]);
+        assert_eq!(hidden[2].level + hidden[3].level, hidden[0].level + hidden[1].level);
+        let copies = innov_number
+            .history()
+            .iter()
+            .filter(|record| matches!(record.kind, InnovationKind::Duplicate { .. }))
+            .count();
+        assert_eq!(copies, 2);
+        // Every edge touching the module is copied, the direct one is not
+        assert_eq!(child.genome_list.edge_list.len(), 11);
+        assert_eq!(child.genome_list.iter().filter(|edge| !edge.enabled).count(), 2);
+        assert!(child.genome_list.iter().any(|edge| edge.weight == 1.));
+        let input = [0.5, -1.];
+        let before: f32 = FFNetwork::from(&parent).forward(&input).unwrap()[0];
+        let after = FFNetwork::from(&child).forward(&input).unwrap()[0];
+        assert!((before - after).abs() < 1e-6);
+
+        // Modules of one node, and none without hidden nodes
+        let mutation = DuplicateModule {
+            max_size: 1,
+            ..Default::default()
+        };
+        let mut single = parent.clone();
+        assert!(mutation.apply(&mut rng, &mut single, &mut innov_number));
+        assert_eq!(single.node_list.hidden.len(), 3);
+        parent.node_list.hidden.clear();
+        parent.genome_list.edge_list.retain(|edge| edge.in_node < 3 && edge.out_node < 3);
+        assert!(!mutation.apply(&mut rng, &mut parent, &mut innov_number));
+
+        let limited = DuplicateModule {
+            limits: SizeLimits {
+                max_nodes: Some(6),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!limited.apply(&mut rng, &mut genome.clone(), &mut innov_number));
+        assert_eq!(innov_number.oversized().nodes, 1);
+
+        let mutation = GaussianMutation::default().with_module_duplication(0.1);
+        let names = mutation.pipeline().operators().map(|(name, _)| name).collect_vec();
+        assert_eq!(names.last(), Some(&"DuplicateModule"));
+    }
+
+    #[test]
+    fn test_duplicate_tied_module() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let mut genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(50)
+            .activation(2, Activation::Tanh)
+            .aggregation(1, Aggregation::Sum)
+            .connect(0, 2, 0.5)
+            .connect(2, 1, 0.8)
+            .connect(0, 1, -0.3)
+            .build()
+            .unwrap();
+        genome.tie_weights(0, [1, 2]).unwrap();
+        let mut innov_number = InnovNumber::from_population(std::slice::from_ref(&genome));
+        let mut child = genome.clone();
+        assert!(DuplicateModule::default().apply(&mut rng, &mut child, &mut innov_number));
+        child.validate().unwrap();
+        // The edge leaving the module is untied, the rest of its group is not
+        assert_eq!(child.weight_table.weight(1), None);
+        assert_eq!(child.weight_table.weight(2), Some(0.8));
+        assert_eq!(child.genome_list.iter().filter(|edge| edge.weight == 0.4).count(), 2);
+        let input = [1.5];
+        let before: f32 = FFNetwork::from(&genome).forward(&input).unwrap()[0];
+        let after = FFNetwork::from(&child).forward(&input).unwrap()[0];
+        assert!((before - after).abs() < 1e-6);
+
+        // Frozen input edges are not copied
+        let mutation = DuplicateModule {
+            frozen: Frozen {
+                input_edges: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut frozen = genome.clone();
+        assert!(mutation.apply(&mut rng, &mut frozen, &mut innov_number));
+        let copy = frozen.node_list.hidden[1].node_id;
+        assert!(frozen.genome_list.iter().all(|edge| edge.out_node != copy));
+        assert_eq!(frozen.genome_list.iter().filter(|edge| edge.in_node == copy).count(), 1);
+    }
+
+    #[test]
+    fn test_split_policy() {
+        // The edge to the output spans three times the levels of the other,
+        // and is the most recent
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(34)
+            .connect(0, 2, 1.)
+            .connect(0, 1, 1.)
+            .build()
+            .unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut splits_output = |split_policy: SplitPolicy, genome: &Genome| {
+            let add_node = AddNode {
+                split_policy,
+                activation: Some(Activation::Tanh),
+                ..Default::default()
+            };
+            (0..300)
+                .filter(|_| {
+                    let mut child = genome.clone();
+                    let mut innov_number = InnovNumber::default();
+                    assert!(add_node.apply(&mut rng, &mut child, &mut innov_number));
+                    assert_eq!(child.node_list.hidden[1].config.activation, Activation::Tanh);
+                    !child.genome_list.edge_list[1].enabled
+                })
+                .count()
+        };
+        assert!((120..180).contains(&splits_output(SplitPolicy::Uniform, &genome)));
+        assert!((195..255).contains(&splits_output(SplitPolicy::Long, &genome)));
+        assert!((170..230).contains(&splits_output(SplitPolicy::Recent, &genome)));
+        let mut unweighted = genome.clone();
+        unweighted.genome_list.edge_list[0].weight = 0.;
+        assert_eq!(splits_output(SplitPolicy::Weight, &unweighted), 300);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(34)
+            .connect(0, 2, 1.)
+            .connect(0, 1, 1.)
+            .build()
+            .unwrap();
+        let add_node = AddNode {
+            limits: SizeLimits {
+                max_depth: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            // Only the edge to the output spans the hidden level
+            let mut child = genome.clone();
+            let mut innov_number = InnovNumber::resume(10, 0);
+            assert!(add_node.apply(&mut rng, &mut child, &mut innov_number));
+            assert!(!child.genome_list.edge_list[1].enabled);
+            assert_eq!(child.node_list.hidden[1].level, Ratio::from_integer(34));
+            assert_eq!(child.node_list.depth(), 1);
+        }
+        let mut shallow = genome.clone();
+        shallow.genome_list.edge_list[1].enabled = false;
+        let mut innov_number = InnovNumber::resume(10, 0);
+        assert!(!add_node.apply(&mut rng, &mut shallow, &mut innov_number));
+        assert_eq!(innov_number.oversized().nodes, 1);
+        let deeper = AddNode {
+            limits: SizeLimits {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(deeper.apply(&mut rng, &mut shallow, &mut innov_number));
+        assert_eq!(shallow.node_list.depth(), 2);
+    }
+
+    #[test]
+    fn test_add_node_fallback() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let mut child = GenomeBuilder::new().input(1).output(1).build().unwrap();
+        assert!(!AddNode::default().apply(&mut rng, &mut child, &mut innov_number));
+        let add_node = AddNode {
+            fallback: Some(AddEdge::default()),
+            ..Default::default()
+        };
+        assert!(add_node.apply(&mut rng, &mut child, &mut innov_number));
+        assert_eq!(child.genome_list.edge_list.len(), 1);
+    }
+
+    #[test]
+    fn test_add_node_output_loop() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut innov_number = InnovNumber::default();
+        let mut genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(1, 1, 0.5)
+            .build()
+            .unwrap();
+        assert!(!AddNode::default().apply(&mut rng, &mut genome, &mut innov_number));
+        assert!(genome.node_list.hidden.is_empty());
+        assert_eq!(genome.validate(), Ok(()));
+    }
+}
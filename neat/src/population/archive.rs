@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    individual::genome::genome::{Genome, InnovationId},
+    mutation::innovation_number::InnovNumber,
+};
+
+/// Members of a population with the counters needed to resume it, see
+/// `Population::archive`. Serializes to JSON or, with the `binary`
+/// feature, to the compact encoding of [`super::binary`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopulationArchive {
+    pub generation: usize,
+    /// Last innovation number handed out.
+    pub innovation: InnovationId,
+    pub genomes: Vec<Arc<Genome>>,
+    /// Fitness of every genome, `None` if it was not evaluated.
+    pub fitness: Vec<Option<f32>>,
+}
+
+impl PopulationArchive {
+    /// Registry handing out innovation numbers past the archived ones, and
+    /// past those of the genomes should the counter lag behind them, for
+    /// `Population::with_innovation`.
+    pub fn innovation_number(&self) -> InnovNumber {
+        let mut registry = InnovNumber::resume(self.innovation, self.generation);
+        registry.prime(self.genomes.iter().map(|genome| &**genome));
+        registry
+    }
+
+    pub fn into_genomes(self) -> Vec<Genome> {
+        self.genomes.into_iter().map(Arc::unwrap_or_clone).collect()
+    }
+}
@@ -0,0 +1,637 @@
+//! Compact binary encoding of populations and checkpoints, behind the
+//! `binary` feature. JSON checkpoints of large populations run into tens of
+//! megabytes; this encoding is several times smaller and faster to write.
+//!
+//! An encoding is a fixed header followed by the payload:
+//!
+//! | bytes   | content                                          |
+//! |---------|--------------------------------------------------|
+//! | `0..4`  | magic `NEAT`                                     |
+//! | `4..6`  | format version, little-endian `u16`              |
+//! | `6..`   | value in bincode 1, little-endian fixed integers |
+//!
+//! Decoding accepts every version up to [`VERSION`] and rejects newer ones,
+//! so files stay readable after upgrades. A version bump comes with the
+//! older layouts of the structs it changed, see `LAYOUTS`, so the domain
+//! types stay free of migrations.
+//!
+//! | version | change                                                   |
+//! |---------|----------------------------------------------------------|
+//! | 1       | initial format                                           |
+//! | 2       | node configs carry a bias, 0 in version 1                |
+//! | 3       | mutation configs carry perturbation distributions,       |
+//! |         | Gaussian before                                          |
+//! | 4       | genomes carry input scaling, none before, and mutation   |
+//! |         | configs its rates, defaults before                       |
+//! | 5       | node configs carry a gate, none before, and mutation     |
+//! |         | configs its rate, 0 before                               |
+//! | 6       | mutation configs carry operator probabilities, none      |
+//! |         | before                                                   |
+//! | 7       | mutation configs carry the module duplication rate, 0    |
+//! |         | before                                                   |
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use bincode::Options;
+use serde::{
+    de::{
+        DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    },
+    Deserializer, Serialize,
+};
+
+pub const MAGIC: [u8; 4] = *b"NEAT";
+/// Version of the encodings this build writes.
+pub const VERSION: u16 = 7;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Older layouts of every struct that gained fields, as the struct, the
+/// version that added fields to it and how many it had before. Structs of
+/// older payloads decode their first fields only, the others take their
+/// serde default.
+const LAYOUTS: &[(&str, u16, usize)] = &[
+    // Node configs without bias, then without gate
+    ("Config", 2, 3),
+    ("Config", 5, 4),
+    // Node mutation rates and frozen parts without the bias ones
+    ("ProbabilityMatrixNode", 2, 3),
+    ("Frozen", 2, 4),
+    // Mutation configs without bias scale, then without perturbation
+    // distributions, then without input scaling rates, then without the
+    // gate rate, then without operator probabilities, then without the
+    // module duplication rate
+    ("MutationConfig", 2, 13),
+    ("MutationConfig", 3, 14),
+    ("MutationConfig", 4, 16),
+    ("MutationConfig", 5, 18),
+    ("MutationConfig", 6, 19),
+    ("MutationConfig", 7, 20),
+    // Genomes without input scaling
+    ("Genome", 4, 3),
+];
+
+/// Fields of the struct `name` encoded in a payload of `version`, `None`
+/// if it has its current layout.
+fn encoded_fields(name: &str, fields: &[&str], version: u16) -> Option<usize> {
+    LAYOUTS
+        .iter()
+        .find(|(layout, added, _)| *layout == name && version < *added)
+        .map(|(_, _, encoded)| *encoded)
+        .filter(|encoded| *encoded < fields.len())
+}
+
+/// Wrapper decoding the structs of a `version` payload with their layout
+/// then, see [`LAYOUTS`]. Wraps the deserializer and everything it hands
+/// out, visitors, seeds and accessors alike, so nested structs migrate too.
+struct Migrated<T> {
+    inner: T,
+    version: u16,
+}
+
+impl<T> Migrated<T> {
+    fn wrap<U>(&self, inner: U) -> Migrated<U> {
+        Migrated {
+            inner,
+            version: self.version,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+                let visitor = self.wrap(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Migrated<D> {
+    type Error = D::Error;
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error> {
+        let visitor = self.wrap(visitor);
+        match encoded_fields(name, fields, self.version) {
+            Some(encoded) => self.inner.deserialize_tuple(encoded, visitor),
+            None => self.inner.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    forward_deserialize! {
+        deserialize_any(), deserialize_bool(), deserialize_i8(), deserialize_i16(),
+        deserialize_i32(), deserialize_i64(), deserialize_i128(), deserialize_u8(),
+        deserialize_u16(), deserialize_u32(), deserialize_u64(), deserialize_u128(),
+        deserialize_f32(), deserialize_f64(), deserialize_char(), deserialize_str(),
+        deserialize_string(), deserialize_bytes(), deserialize_byte_buf(),
+        deserialize_option(), deserialize_unit(), deserialize_seq(), deserialize_map(),
+        deserialize_identifier(), deserialize_ignored_any(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: serde::de::Error>(self, value: $ty) -> Result<V::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Migrated<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool), visit_i8(i8), visit_i16(i16), visit_i32(i32), visit_i64(i64),
+        visit_i128(i128), visit_u8(u8), visit_u16(u16), visit_u32(u32), visit_u64(u64),
+        visit_u128(u128), visit_f32(f32), visit_f64(f64), visit_char(char),
+        visit_str(&str), visit_borrowed_str(&'de str), visit_string(String),
+        visit_bytes(&[u8]), visit_borrowed_bytes(&'de [u8]), visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        let seq = self.wrap(seq);
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        let map = self.wrap(map);
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Migrated<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.deserialize(deserializer)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Migrated<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Migrated<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for Migrated<A> {
+    type Error = A::Error;
+    type Variant = Migrated<A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self::Variant), A::Error> {
+        let (seed, version) = (self.wrap(seed), self.version);
+        let (value, inner) = self.inner.variant_seed(seed)?;
+        Ok((value, Migrated { inner, version }))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Migrated<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+#[derive(Debug)]
+pub enum BinaryError {
+    Io(io::Error),
+    /// Not an encoding, too short or with another magic.
+    NotEncoded,
+    /// Written by a newer build.
+    UnsupportedVersion(u16),
+    /// Malformed payload, or of another type, or a value serde cannot
+    /// encode without self-description.
+    Payload(bincode::Error),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::Io(error) => write!(f, "{error}"),
+            BinaryError::NotEncoded => write!(f, "not a NEAT binary encoding"),
+            BinaryError::UnsupportedVersion(version) => write!(
+                f,
+                "format version {version} is newer than the supported {VERSION}"
+            ),
+            BinaryError::Payload(error) => write!(f, "malformed payload: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, BinaryError> {
+    let mut bytes = Vec::new();
+    write(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, BinaryError> {
+    read(bytes)
+}
+
+pub fn write<T: Serialize>(mut writer: impl Write, value: &T) -> Result<(), BinaryError> {
+    writer.write_all(&MAGIC).map_err(BinaryError::Io)?;
+    writer
+        .write_all(&VERSION.to_le_bytes())
+        .map_err(BinaryError::Io)?;
+    bincode::serialize_into(writer, value).map_err(BinaryError::Payload)
+}
+
+pub fn read<T: DeserializeOwned>(mut reader: impl Read) -> Result<T, BinaryError> {
+    let mut header = [0; HEADER_LEN];
+    reader
+        .read_exact(&mut header)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => BinaryError::NotEncoded,
+            _ => BinaryError::Io(error),
+        })?;
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(BinaryError::NotEncoded);
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    match version {
+        1..=VERSION => {
+            let options = bincode::options()
+                .with_fixint_encoding()
+                .allow_trailing_bytes();
+            let mut deserializer = bincode::Deserializer::with_reader(reader, options);
+            let migrated = Migrated {
+                inner: &mut deserializer,
+                version,
+            };
+            T::deserialize(migrated).map_err(BinaryError::Payload)
+        }
+        version => Err(BinaryError::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{
+        config::neat_config::{MutationConfig, NeatConfig},
+        individual::genome::{
+            activation::Activation,
+            genome::{Genome, GenomeBuilder, InputScaling},
+            node_list::{Config, Gate},
+        },
+        mutation::mutation::{Frozen, Perturbation, ProbabilityMatrixNode},
+        population::archive::PopulationArchive,
+    };
+
+    fn archive() -> PopulationArchive {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .hidden(50)
+            .name(0, "x")
+            .activation(3, Activation::Softplus(0.5))
+            .connect(0, 3, 0.5)
+            .connect(3, 2, -1.)
+            .build()
+            .unwrap();
+        PopulationArchive {
+            generation: 4,
+            innovation: 9,
+            genomes: vec![genome.into(); 3],
+            fitness: vec![Some(1.5), None, Some(f32::NAN)],
+        }
+    }
+
+    /// `bytes` of `genome` as written before gates existed, without the
+    /// `None` tag closing every node config.
+    fn strip_gates(bytes: &[u8], genome: &Genome) -> Vec<u8> {
+        let configs = genome
+            .node_list
+            .input
+            .iter()
+            .chain(genome.node_list.output.iter())
+            .chain(genome.node_list.hidden.iter())
+            .map(|node| bincode::serialize(&node.config).unwrap())
+            .unique()
+            .collect_vec();
+        let mut stripped = bytes.to_vec();
+        for config in configs {
+            let mut start = 0;
+            while let Some(at) = stripped[start..]
+                .windows(config.len())
+                .position(|window| window == config)
+            {
+                let end = start + at + config.len();
+                stripped.remove(end - 1);
+                start = end - 1;
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let archive = archive();
+        let bytes = to_bytes(&archive).unwrap();
+        assert_eq!(bytes[..4], *b"NEAT");
+        assert_eq!(bytes[4..6], [7, 0]);
+        let json = serde_json::to_string(&archive).unwrap();
+        assert!(bytes.len() < json.len());
+
+        let decoded: PopulationArchive = from_bytes(&bytes).unwrap();
+        assert_eq!((decoded.generation, decoded.innovation), (4, 9));
+        assert_eq!(decoded.fitness[..2], [Some(1.5), None]);
+        assert!(decoded.fitness[2].unwrap().is_nan());
+        let genomes = decoded.into_genomes();
+        assert_eq!(
+            genomes
+                .iter()
+                .map(|g| serde_json::to_string(g).unwrap())
+                .collect_vec(),
+            vec![serde_json::to_string(&*archive.genomes[0]).unwrap(); 3]
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let config = NeatConfig {
+            activations: [Activation::Tanh, Activation::Relu].into_iter().collect(),
+            ..Default::default()
+        };
+        let decoded: NeatConfig = from_bytes(&to_bytes(&config).unwrap()).unwrap();
+        assert_eq!(decoded, config);
+    }
+
+    #[test]
+    fn test_header() {
+        let mut bytes = to_bytes(&archive()).unwrap();
+        assert!(matches!(
+            from_bytes::<PopulationArchive>(&bytes[..3]),
+            Err(BinaryError::NotEncoded)
+        ));
+        assert!(matches!(
+            from_bytes::<PopulationArchive>(b"JSON{}"),
+            Err(BinaryError::NotEncoded)
+        ));
+        assert!(matches!(
+            from_bytes::<PopulationArchive>(&bytes[..20]),
+            Err(BinaryError::Payload(_))
+        ));
+        bytes[4] = 8;
+        assert!(matches!(
+            from_bytes::<PopulationArchive>(&bytes),
+            Err(BinaryError::UnsupportedVersion(8))
+        ));
+    }
+
+    #[test]
+    fn test_migrate_bias() {
+        let config = Config {
+            activation: Activation::Relu,
+            bias: 0.5,
+            ..Default::default()
+        };
+        let decoded: Config = from_bytes(&to_bytes(&config).unwrap()).unwrap();
+        assert_eq!(decoded, config);
+
+        // Version 1 configs have no bias
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u16.to_le_bytes());
+        bincode::serialize_into(
+            &mut bytes,
+            &(config.aggregation, config.clamp, config.activation),
+        )
+        .unwrap();
+        let decoded: Config = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, Config { bias: 0., ..config });
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u16.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &(0.2, 0.3, 0.4)).unwrap();
+        let decoded: ProbabilityMatrixNode = from_bytes(&bytes).unwrap();
+        assert_eq!(
+            decoded,
+            ProbabilityMatrixNode {
+                prob_clamp: 0.2,
+                prob_activation: 0.3,
+                prob_aggregation: 0.4,
+                ..Default::default()
+            }
+        );
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u16.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &(true, false, false, true)).unwrap();
+        let decoded: Frozen = from_bytes(&bytes).unwrap();
+        assert_eq!(
+            decoded,
+            Frozen {
+                output_activation: true,
+                input_edges: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_migrate_perturbation() {
+        let config = MutationConfig {
+            weight_perturbation: Perturbation::Cauchy,
+            ..Default::default()
+        };
+        let bytes = to_bytes(&config).unwrap();
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, config);
+
+        // Version 2 configs end before the two distributions, u32 tags each,
+        // the input scaling rate and scale, the gate rate, the operators, an
+        // empty map, and the module duplication rate
+        let mut bytes = bytes[..bytes.len() - 8 - 12 - 8 - 8 - 8].to_vec();
+        bytes[4] = 2;
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, MutationConfig::default());
+    }
+
+    #[test]
+    fn test_migrate_input_scaling() {
+        let genome = Genome::clone(&archive().genomes[0]);
+        let mut scaled = genome.clone();
+        scaled.input_scaling = InputScaling::identity(&scaled.node_list.input);
+        let decoded: Genome = from_bytes(&to_bytes(&scaled).unwrap()).unwrap();
+        assert_eq!(decoded.input_scaling, scaled.input_scaling);
+
+        // Version 3 genomes end before the input scaling, an empty map
+        let bytes = strip_gates(&to_bytes(&genome).unwrap(), &genome);
+        let mut bytes = bytes[..bytes.len() - 8].to_vec();
+        bytes[4] = 3;
+        let decoded: Genome = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, genome);
+
+        // Version 3 mutation configs end before the rate, an f64, the scale,
+        // an f32, the gate rate, the operators and the module duplication
+        // rate
+        let bytes = to_bytes(&MutationConfig::default()).unwrap();
+        let mut bytes = bytes[..bytes.len() - 12 - 8 - 8 - 8].to_vec();
+        bytes[4] = 3;
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, MutationConfig::default());
+    }
+
+    #[test]
+    fn test_migrate_gate() {
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .hidden(50)
+            .gate(2, Gate::default())
+            .connect(0, 2, 0.5)
+            .connect(2, 1, -1.)
+            .build()
+            .unwrap();
+        let decoded: Genome = from_bytes(&to_bytes(&genome).unwrap()).unwrap();
+        assert_eq!(decoded.node_list.hidden[0].config.gate, Some(Gate::default()));
+
+        // Version 4 configs have no gate
+        let plain = Genome::clone(&archive().genomes[0]);
+        let mut bytes = strip_gates(&to_bytes(&plain).unwrap(), &plain);
+        bytes[4] = 4;
+        let decoded: Genome = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, plain);
+
+        // Version 4 mutation configs end before the gate rate, an f64, the
+        // operators and the module duplication rate
+        let bytes = to_bytes(&MutationConfig::default()).unwrap();
+        let mut bytes = bytes[..bytes.len() - 8 - 8 - 8].to_vec();
+        bytes[4] = 4;
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, MutationConfig::default());
+    }
+
+    #[test]
+    fn test_migrate_operators() {
+        let config = MutationConfig {
+            operators: [("AddModule".to_string(), 0.02)].into_iter().collect(),
+            ..Default::default()
+        };
+        let decoded: MutationConfig = from_bytes(&to_bytes(&config).unwrap()).unwrap();
+        assert_eq!(decoded, config);
+
+        // Version 5 configs end before the operators and the module
+        // duplication rate
+        let bytes = to_bytes(&MutationConfig::default()).unwrap();
+        let mut bytes = bytes[..bytes.len() - 8 - 8].to_vec();
+        bytes[4] = 5;
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, MutationConfig::default());
+
+        // Version 6 configs end with the operators
+        let bytes = to_bytes(&MutationConfig::default()).unwrap();
+        let mut bytes = bytes[..bytes.len() - 8].to_vec();
+        bytes[4] = 6;
+        let decoded: MutationConfig = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, MutationConfig::default());
+    }
+}
@@ -0,0 +1,163 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::individual::individual::EvaluatedIndividual;
+
+/// Genomes replacing the members a [`Cataclysm`] wiped out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Refill {
+    /// Minimal genomes of the population's factory, mutated once.
+    #[default]
+    Fresh,
+    /// Clones of random elites, mutated once.
+    MutatedElites,
+}
+
+/// Extinction event restoring diversity: the least fit members of a
+/// generation die without offspring, and new genomes take their place in
+/// the next generation, see `Population::with_cataclysm`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cataclysm {
+    /// Strike every `every` generations, never if none.
+    pub every: Option<usize>,
+    /// Strike when the population's stagnation detector flags a generation.
+    pub on_stagnation: bool,
+    /// Share of the population wiped out.
+    pub fraction: f32,
+    /// Fittest members always spared.
+    pub elites: usize,
+    pub refill: Refill,
+}
+
+impl Default for Cataclysm {
+    fn default() -> Self {
+        Self {
+            every: None,
+            on_stagnation: false,
+            fraction: 0.5,
+            elites: 1,
+            refill: Refill::default(),
+        }
+    }
+}
+
+impl Cataclysm {
+    /// Strike every `generations` generations.
+    pub fn every(generations: usize) -> Self {
+        assert!(generations > 0, "A cataclysm needs a period of at least 1");
+        Self {
+            every: Some(generations),
+            ..Default::default()
+        }
+    }
+
+    /// Strike on stagnation.
+    pub fn on_stagnation() -> Self {
+        Self {
+            on_stagnation: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_fraction(mut self, fraction: f32) -> Self {
+        self.fraction = fraction;
+        self
+    }
+
+    pub fn with_elites(mut self, elites: usize) -> Self {
+        self.elites = elites;
+        self
+    }
+
+    pub fn with_refill(mut self, refill: Refill) -> Self {
+        self.refill = refill;
+        self
+    }
+
+    /// Whether it strikes evaluated generation `generation`.
+    pub fn strikes(&self, generation: usize, stagnated: bool) -> bool {
+        (self.on_stagnation && stagnated)
+            || self
+                .every
+                .is_some_and(|every| generation > 0 && generation.is_multiple_of(every))
+    }
+
+    /// Ids of the members, fittest first. Members without finite fitness
+    /// come last.
+    fn ranked(members: &[EvaluatedIndividual]) -> Vec<usize> {
+        let fitness = |m: &EvaluatedIndividual| {
+            m.fitness
+                .filter(|f| f.is_finite())
+                .unwrap_or(f32::NEG_INFINITY)
+        };
+        members
+            .iter()
+            .sorted_by(|a, b| fitness(b).total_cmp(&fitness(a)))
+            .map(|m| m.id)
+            .collect_vec()
+    }
+
+    /// Ids of the `elites` fittest members.
+    pub fn elite_ids(&self, members: &[EvaluatedIndividual]) -> Vec<usize> {
+        let mut ranked = Self::ranked(members);
+        ranked.truncate(self.elites);
+        ranked
+    }
+
+    /// Ids of the members wiped out: a `fraction` of them, the least fit
+    /// first, sparing the elites.
+    pub fn victims(&self, members: &[EvaluatedIndividual]) -> Vec<usize> {
+        let count = (self.fraction.clamp(0., 1.) * members.len() as f32).round() as usize;
+        let count = count.min(members.len().saturating_sub(self.elites));
+        let mut ranked = Self::ranked(members);
+        ranked.drain(..ranked.len() - count);
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    fn members(fitness: &[Option<f32>]) -> Vec<EvaluatedIndividual> {
+        let genome = GenomeBuilder::new().input(1).output(1).build().unwrap();
+        fitness
+            .iter()
+            .enumerate()
+            .map(|(id, fitness)| EvaluatedIndividual {
+                fitness: *fitness,
+                ..EvaluatedIndividual::new(id, genome.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_strikes() {
+        let periodic = Cataclysm::every(3);
+        assert!(!periodic.strikes(0, true));
+        assert!(!periodic.strikes(2, false));
+        assert!(periodic.strikes(3, false));
+        assert!(periodic.strikes(6, false));
+        let stagnation = Cataclysm::on_stagnation();
+        assert!(stagnation.strikes(1, true));
+        assert!(!stagnation.strikes(3, false));
+    }
+
+    #[test]
+    fn test_victims() {
+        let members = members(&[Some(3.), None, Some(1.), Some(f32::NAN), Some(2.)]);
+        let cataclysm = Cataclysm::every(1).with_fraction(0.4);
+        assert_eq!(cataclysm.victims(&members), vec![1, 3]);
+        assert_eq!(
+            cataclysm.with_fraction(0.6).victims(&members),
+            vec![2, 1, 3]
+        );
+        let all = cataclysm.with_fraction(1.).with_elites(2);
+        assert_eq!(all.victims(&members), vec![2, 1, 3]);
+        assert_eq!(all.elite_ids(&members), vec![0, 4]);
+        assert!(cataclysm.with_fraction(0.).victims(&members).is_empty());
+    }
+}
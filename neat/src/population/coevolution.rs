@@ -0,0 +1,182 @@
+use itertools::Itertools;
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    individual::{genome::genome::Genome, individual::EvaluatedIndividual},
+    selection::selection_trait::SelectionMethod,
+    speciation::speciation::SpeciationMethod,
+};
+
+use super::population::Population;
+
+/// Co-evolution of several populations, e.g. predators and prey. Each
+/// population keeps its own species and innovation registry; fitness comes
+/// from games against members of the other populations.
+pub struct CoEvolution<Spe, Sel> {
+    populations: Vec<Population<Spe, Sel>>,
+    /// Opponents sampled from every other population per individual.
+    samples: usize,
+}
+
+impl<Spe, Sel> CoEvolution<Spe, Sel>
+where
+    Spe: SpeciationMethod<EvaluatedIndividual>,
+    Sel: SelectionMethod,
+{
+    pub fn new(populations: Vec<Population<Spe, Sel>>, samples: usize) -> Self {
+        assert!(
+            populations.len() >= 2,
+            "Co-evolution needs at least two populations"
+        );
+        assert!(samples > 0);
+        Self {
+            populations,
+            samples,
+        }
+    }
+
+    /// Evaluate every unevaluated member against sampled members of the other
+    /// populations. `play(i, fst, j, snd)` scores `fst` from population `i`
+    /// against `snd` from population `j`; the fitness is the mean score.
+    pub fn evaluate(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut play: impl FnMut(usize, &Genome, usize, &Genome) -> f32,
+    ) {
+        let mut scores = Vec::with_capacity(self.populations.len());
+        for (i, population) in self.populations.iter().enumerate() {
+            let fitness = population
+                .members()
+                .iter()
+                .filter(|m| m.fitness.is_none())
+                .map(|member| {
+                    let games = self
+                        .populations
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .flat_map(|(j, other)| {
+                            other
+                                .members()
+                                .choose_multiple(rng, self.samples)
+                                .map(move |o| (j, &o.genome))
+                                .collect_vec()
+                        })
+                        .map(|(j, opponent)| play(i, &member.genome, j, opponent))
+                        .collect_vec();
+                    games.iter().sum::<f32>() / games.len() as f32
+                })
+                .collect_vec();
+            scores.push(fitness);
+        }
+        for (population, fitness) in self.populations.iter_mut().zip(scores) {
+            let mut fitness = fitness.into_iter();
+            population.evaluate(|_| fitness.next().expect("One score per unevaluated member"));
+        }
+    }
+
+    /// Step every population forward.
+    pub fn evolve(&mut self, rng: &mut dyn RngCore) {
+        for population in self.populations.iter_mut() {
+            population.evolve(rng);
+        }
+    }
+
+    pub fn populations(&self) -> &[Population<Spe, Sel>] {
+        &self.populations
+    }
+
+    pub fn population(&self, index: usize) -> Option<&Population<Spe, Sel>> {
+        self.populations.get(index)
+    }
+
+    pub fn generation(&self) -> usize {
+        self.populations[0].generation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        crossover::crossover::NeatCrossover,
+        fixtures::NoMutation,
+        individual::genome::genome::{GenomeEdge, GenomeFactory},
+        selection::selection_trait::RoulleteSelection,
+        speciation::speciation::SpeciationThreshold,
+        GeneticAlgortihm,
+    };
+
+    fn population(weights: &[f32]) -> Population<SpeciationThreshold, RoulleteSelection> {
+        let factory = GenomeFactory::init(1, 1).unwrap();
+        let genomes = weights
+            .iter()
+            .map(|w| {
+                let mut genome = factory.generate_genome();
+                genome.genome_list.edge_list.push(GenomeEdge {
+                    innov_number: 0,
+                    in_node: 0,
+                    out_node: 1,
+                    weight: *w,
+                    enabled: true,
+                });
+                genome
+            })
+            .collect_vec();
+        Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            genomes,
+        )
+    }
+
+    fn weight(genome: &Genome) -> f32 {
+        genome.genome_list.edge_list[0].weight
+    }
+
+    #[test]
+    fn test_paired_evaluation() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let mut coevolution = CoEvolution::new(vec![population(&[1., 2.]), population(&[5.])], 3);
+        let mut games = vec![];
+        // Predators score their weight, prey score minus the predator's
+        coevolution.evaluate(&mut rng, |i, fst, j, snd| {
+            games.push((i, j));
+            if i == 0 {
+                weight(fst)
+            } else {
+                -weight(snd)
+            }
+        });
+        // A single prey can be sampled once per predator, both predators once
+        // for the prey.
+        assert_eq!(games.iter().filter(|g| **g == (0, 1)).count(), 2);
+        assert_eq!(games.iter().filter(|g| **g == (1, 0)).count(), 2);
+
+        let fitness = |index: usize| {
+            coevolution
+                .population(index)
+                .unwrap()
+                .members()
+                .iter()
+                .map(|m| m.fitness)
+                .collect_vec()
+        };
+        assert_eq!(fitness(0), vec![Some(1.), Some(2.)]);
+        assert_eq!(fitness(1), vec![Some(-1.5)]);
+
+        coevolution.evolve(&mut rng);
+        assert_eq!(coevolution.generation(), 1);
+        assert!(coevolution
+            .populations()
+            .iter()
+            .all(|p| p.members().iter().all(|m| m.fitness.is_none())));
+    }
+}
@@ -0,0 +1,195 @@
+use rand::RngCore;
+
+use crate::{
+    individual::{genome::genome::Genome, individual::EvaluatedIndividual},
+    selection::selection_trait::SelectionMethod,
+    speciation::speciation::SpeciationMethod,
+};
+
+use super::population::Population;
+
+/// Advance to the next stage once the population best reaches `threshold`
+/// for `generations` consecutive generations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Promotion {
+    pub threshold: f32,
+    pub generations: usize,
+}
+
+struct Stage<'a> {
+    name: String,
+    fitness: Box<dyn FnMut(&Genome) -> f32 + 'a>,
+    promotion: Option<Promotion>,
+}
+
+/// Stage a generation was evaluated on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StageRecord {
+    pub generation: usize,
+    pub stage: usize,
+    pub best: Option<f32>,
+    /// Whether this generation completed the stage.
+    pub promoted: bool,
+}
+
+/// Incremental evolution: an ordered list of fitness functions, each
+/// replaced by the next once its promotion criterion is met.
+#[derive(Default)]
+pub struct Curriculum<'a> {
+    stages: Vec<Stage<'a>>,
+    current: usize,
+    /// Consecutive generations meeting the current promotion threshold.
+    streak: usize,
+    history: Vec<StageRecord>,
+}
+
+impl<'a> Curriculum<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a stage. Without promotion the curriculum stays on it, as it
+    /// does on the last stage.
+    pub fn with_stage(
+        mut self,
+        name: impl Into<String>,
+        fitness: impl FnMut(&Genome) -> f32 + 'a,
+        promotion: Option<Promotion>,
+    ) -> Self {
+        self.stages.push(Stage {
+            name: name.into(),
+            fitness: Box::new(fitness),
+            promotion,
+        });
+        self
+    }
+
+    /// Index of the current stage.
+    pub fn stage(&self) -> usize {
+        self.current
+    }
+
+    pub fn stage_name(&self) -> &str {
+        &self.stages[self.current].name
+    }
+
+    /// One record per evaluated generation.
+    pub fn history(&self) -> &[StageRecord] {
+        &self.history
+    }
+
+    /// Evaluate the unevaluated members on the current stage and advance to
+    /// the next stage if its promotion criterion is met.
+    pub fn evaluate<Spe, Sel>(&mut self, population: &mut Population<Spe, Sel>) -> StageRecord
+    where
+        Spe: SpeciationMethod<EvaluatedIndividual>,
+        Sel: SelectionMethod,
+    {
+        assert!(!self.stages.is_empty(), "Curriculum should have a stage");
+        let stage = &mut self.stages[self.current];
+        population.evaluate(&mut stage.fitness);
+        let best = population.best(1).first().and_then(|m| m.fitness);
+        let mut record = StageRecord {
+            generation: population.generation(),
+            stage: self.current,
+            best,
+            promoted: false,
+        };
+        let promotion = stage.promotion;
+        let is_last = self.current + 1 == self.stages.len();
+        if let Some(promotion) = promotion.filter(|_| !is_last) {
+            if best.is_some_and(|best| best >= promotion.threshold) {
+                self.streak += 1;
+            } else {
+                self.streak = 0;
+            }
+            if self.streak >= promotion.generations {
+                self.current += 1;
+                self.streak = 0;
+                record.promoted = true;
+            }
+        }
+        self.history.push(record);
+        record
+    }
+
+    /// Evaluate and evolve `population` for `generations` generations.
+    pub fn run<Spe, Sel>(
+        &mut self,
+        rng: &mut dyn RngCore,
+        population: &mut Population<Spe, Sel>,
+        generations: usize,
+    ) where
+        Spe: SpeciationMethod<EvaluatedIndividual>,
+        Sel: SelectionMethod,
+    {
+        for _ in 0..generations {
+            self.evaluate(population);
+            population.evolve(rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        crossover::crossover::NeatCrossover,
+        fixtures::NoMutation,
+        individual::genome::genome::{GenomeEdge, GenomeFactory},
+        selection::selection_trait::RoulleteSelection,
+        speciation::speciation::SpeciationThreshold,
+        GeneticAlgortihm,
+    };
+
+    fn population(weight: f32, size: usize) -> Population<SpeciationThreshold, RoulleteSelection> {
+        let mut genome = GenomeFactory::init(1, 1).unwrap().generate_genome();
+        genome.genome_list.edge_list.push(GenomeEdge {
+            innov_number: 0,
+            in_node: 0,
+            out_node: 1,
+            weight,
+            enabled: true,
+        });
+        Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            vec![genome; size],
+        )
+    }
+
+    fn weight(genome: &Genome) -> f32 {
+        genome.genome_list.edge_list[0].weight
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut pop = population(3., 4);
+        let promotion = Promotion {
+            threshold: 2.,
+            generations: 2,
+        };
+        let mut curriculum = Curriculum::new()
+            .with_stage("balance", weight, Some(promotion))
+            .with_stage("swing up", |g| weight(g) - 10., Some(promotion))
+            .with_stage("walk", |_| 0., None);
+        curriculum.run(&mut rng, &mut pop, 6);
+        let history = curriculum.history();
+        assert_eq!(
+            history.iter().map(|r| (r.generation, r.stage)).collect_vec(),
+            vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 1), (5, 1)]
+        );
+        assert_eq!(history.iter().filter(|r| r.promoted).count(), 1);
+        assert_eq!(history[2].best, Some(-7.));
+        assert_eq!(curriculum.stage_name(), "swing up");
+    }
+}
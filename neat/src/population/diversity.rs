@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    individual::genome::genome::{Genome, InnovationId},
+    speciation::distance::DistanceMetric,
+};
+
+/// Population-level diversity of a generation. Collapsing diversity shows
+/// as a shrinking mean distance, few unique structures and an innovation
+/// entropy close to 0.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Diversity {
+    /// Mean distance over every pair of genomes, 0 below two genomes.
+    pub mean_distance: f32,
+    /// Number of distinct [`Genome::structural_hash`]es.
+    pub unique_structures: usize,
+    /// Mean binary entropy, in bits, of carrying each edge innovation: 0
+    /// when every innovation is carried by all genomes or none, 1 when each
+    /// is carried by half of them.
+    pub innovation_entropy: f32,
+}
+
+impl Diversity {
+    /// Measure `genomes`, with `metric` for the pairwise distances.
+    pub fn measure<'a>(
+        genomes: impl IntoIterator<Item = &'a Genome>,
+        metric: &(impl DistanceMetric<Genome> + ?Sized),
+    ) -> Self {
+        let genomes = genomes.into_iter().collect_vec();
+        let pairs = genomes.len() * genomes.len().saturating_sub(1) / 2;
+        let mean_distance = if pairs == 0 {
+            0.
+        } else {
+            genomes
+                .iter()
+                .tuple_combinations()
+                .map(|(a, b)| metric.distance(a, b))
+                .sum::<f32>()
+                / pairs as f32
+        };
+        let unique_structures = genomes
+            .iter()
+            .map(|genome| genome.structural_hash())
+            .collect::<BTreeSet<_>>()
+            .len();
+        let mut carriers: BTreeMap<InnovationId, usize> = BTreeMap::new();
+        for genome in genomes.iter() {
+            for innov_number in genome
+                .genome_list
+                .iter()
+                .map(|edge| edge.innov_number)
+                .unique()
+            {
+                *carriers.entry(innov_number).or_default() += 1;
+            }
+        }
+        let innovation_entropy = if carriers.is_empty() {
+            0.
+        } else {
+            carriers
+                .values()
+                .map(|count| binary_entropy(*count as f32 / genomes.len() as f32))
+                .sum::<f32>()
+                / carriers.len() as f32
+        };
+        Self {
+            mean_distance,
+            unique_structures,
+            innovation_entropy,
+        }
+    }
+}
+
+fn binary_entropy(p: f32) -> f32 {
+    [p, 1. - p]
+        .into_iter()
+        .filter(|p| *p > 0.)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        individual::genome::genome::GenomeBuilder, speciation::distance::GraphEditDistance,
+    };
+
+    #[test]
+    fn test_measure() {
+        let base = GenomeBuilder::new().input(2).output(1).connect(0, 2, 1.);
+        let a = base.clone().build().unwrap();
+        let b = base.connect(1, 2, 1.).build().unwrap();
+        let metric = GraphEditDistance::default();
+
+        let collapsed = Diversity::measure([&a, &a, &a], &metric);
+        assert_eq!(collapsed.mean_distance, 0.);
+        assert_eq!(collapsed.unique_structures, 1);
+        assert_eq!(collapsed.innovation_entropy, 0.);
+
+        let diverse = Diversity::measure([&a, &b], &metric);
+        assert_eq!(diverse.mean_distance, 1.);
+        assert_eq!(diverse.unique_structures, 2);
+        // Innovation 0 is carried by both, 1 by half of them.
+        assert_relative_eq!(diverse.innovation_entropy, 0.5);
+
+        let single = Diversity::measure([&b], &metric);
+        assert_eq!(single.mean_distance, 0.);
+        assert_eq!(single.innovation_entropy, 0.);
+    }
+}
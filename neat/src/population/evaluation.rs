@@ -0,0 +1,246 @@
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+use itertools::Itertools;
+
+use crate::individual::genome::genome::Genome;
+
+/// Why an evaluation was discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvaluationFailure {
+    /// The fitness or a network output was NaN or infinite.
+    NonFinite,
+    /// The evaluation did not finish in time.
+    Timeout,
+    /// The evaluation panicked.
+    Panicked,
+    /// A remote worker reported the evaluation as failed.
+    Remote,
+}
+
+/// Guards applied to every evaluation of the population driver. Failed
+/// evaluations get `penalty` as fitness and are flagged in the reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvaluationGuard {
+    /// Wall-clock limit of a single evaluation, only enforced by
+    /// `Population::evaluate_with_timeout`.
+    pub timeout: Option<Duration>,
+    /// Fitness of failed evaluations, NaN and infinite ones included.
+    pub penalty: f32,
+}
+
+impl Default for EvaluationGuard {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            penalty: 0.,
+        }
+    }
+}
+
+/// Stochastic evaluation rewarding redundant topologies: every trial drops
+/// each hidden node with probability `rate` and the fitness is the mean over
+/// the trials.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dropout {
+    pub rate: f64,
+    pub trials: usize,
+}
+
+impl Default for Dropout {
+    fn default() -> Self {
+        Self {
+            rate: 0.1,
+            trials: 5,
+        }
+    }
+}
+
+/// How the fitness samples of a repeatedly evaluated genome are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FitnessAggregate {
+    #[default]
+    Mean,
+    Median,
+    /// Conditional value at risk: mean of the worst `alpha` fraction of the
+    /// samples, favouring genomes that rarely fail.
+    Cvar { alpha: f32 },
+}
+
+impl FitnessAggregate {
+    pub fn apply(&self, samples: &[f32]) -> f32 {
+        assert!(!samples.is_empty(), "Aggregating no samples");
+        let sorted = || samples.iter().copied().sorted_by(f32::total_cmp).collect_vec();
+        match *self {
+            FitnessAggregate::Mean => mean_variance(samples).0,
+            FitnessAggregate::Median => {
+                let sorted = sorted();
+                let mid = sorted.len() / 2;
+                if sorted.len() % 2 == 0 {
+                    (sorted[mid - 1] + sorted[mid]) / 2.
+                } else {
+                    sorted[mid]
+                }
+            }
+            FitnessAggregate::Cvar { alpha } => {
+                let tail = ((alpha * samples.len() as f32).ceil() as usize).clamp(1, samples.len());
+                mean_variance(&sorted()[..tail]).0
+            }
+        }
+    }
+}
+
+/// Mean and unbiased variance, 0 for a single sample.
+pub fn mean_variance(samples: &[f32]) -> (f32, f32) {
+    let n = samples.len() as f32;
+    let mean = samples.iter().sum::<f32>() / n;
+    let variance = if samples.len() > 1 {
+        samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / (n - 1.)
+    } else {
+        0.
+    };
+    (mean, variance)
+}
+
+/// Early stopping of repeated evaluations. After `min_repeats` samples, at
+/// least 2, a genome is no longer evaluated once its confidence interval of
+/// `z` standard errors around the mean overlaps no other genome's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Racing {
+    pub min_repeats: usize,
+    pub z: f32,
+}
+
+impl Default for Racing {
+    fn default() -> Self {
+        Self {
+            min_repeats: 3,
+            z: 1.96,
+        }
+    }
+}
+
+/// Evaluation of noisy fitness functions: every genome is evaluated up to
+/// `repeats` times and its samples aggregated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepeatedEvaluation {
+    pub repeats: usize,
+    pub aggregate: FitnessAggregate,
+    pub racing: Option<Racing>,
+}
+
+impl Default for RepeatedEvaluation {
+    fn default() -> Self {
+        Self {
+            repeats: 5,
+            aggregate: FitnessAggregate::default(),
+            racing: None,
+        }
+    }
+}
+
+impl Racing {
+    /// Indices of `samples` whose confidence interval overlaps another's.
+    pub(super) fn unsettled(&self, samples: &[&[f32]]) -> Vec<usize> {
+        let intervals = samples
+            .iter()
+            .map(|samples| {
+                let (mean, variance) = mean_variance(samples);
+                let half = self.z * (variance / samples.len() as f32).sqrt();
+                (mean - half, mean + half)
+            })
+            .collect_vec();
+        (0..intervals.len())
+            .filter(|i| {
+                let (low, high) = intervals[*i];
+                intervals
+                    .iter()
+                    .enumerate()
+                    .any(|(j, (other_low, other_high))| {
+                        *i != j && low <= *other_high && *other_low <= high
+                    })
+            })
+            .collect_vec()
+    }
+}
+
+impl EvaluationGuard {
+    pub fn check(&self, fitness: f32) -> Result<f32, EvaluationFailure> {
+        if fitness.is_finite() {
+            Ok(fitness)
+        } else {
+            Err(EvaluationFailure::NonFinite)
+        }
+    }
+
+    /// Evaluate on a separate thread, giving up after the timeout. An
+    /// evaluation that times out is left running in the background.
+    pub fn run<F>(&self, fitness: &Arc<F>, genome: &Arc<Genome>) -> Result<f32, EvaluationFailure>
+    where
+        F: Fn(&Genome) -> f32 + Send + Sync + 'static,
+    {
+        let Some(timeout) = self.timeout else {
+            return self.check(fitness(genome));
+        };
+        let (sender, receiver) = mpsc::channel();
+        let (fitness, genome) = (Arc::clone(fitness), Arc::clone(genome));
+        thread::spawn(move || {
+            // The receiver is gone if the evaluation timed out
+            let _ = sender.send(fitness(&genome));
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(fitness) => self.check(fitness),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(EvaluationFailure::Timeout),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(EvaluationFailure::Panicked),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::genome::GenomeFactory;
+
+    #[test]
+    fn test_run() {
+        let genome = Arc::new(GenomeFactory::init(1, 1).unwrap().generate_genome());
+        let guard = EvaluationGuard {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        assert_eq!(guard.run(&Arc::new(|_: &Genome| 1.), &genome), Ok(1.));
+        assert_eq!(
+            guard.run(&Arc::new(|_: &Genome| f32::NAN), &genome),
+            Err(EvaluationFailure::NonFinite)
+        );
+        let slow = Arc::new(|_: &Genome| {
+            thread::sleep(Duration::from_secs(1));
+            1.
+        });
+        assert_eq!(guard.run(&slow, &genome), Err(EvaluationFailure::Timeout));
+        let panics = Arc::new(|_: &Genome| -> f32 { panic!("Simulator crashed") });
+        assert_eq!(guard.run(&panics, &genome), Err(EvaluationFailure::Panicked));
+        assert_eq!(
+            EvaluationGuard::default().check(f32::INFINITY),
+            Err(EvaluationFailure::NonFinite)
+        );
+    }
+
+    #[test]
+    fn test_fitness_aggregate() {
+        let samples = [4., -2., 1., 3.];
+        assert_eq!(FitnessAggregate::Mean.apply(&samples), 1.5);
+        assert_eq!(FitnessAggregate::Median.apply(&samples), 2.);
+        assert_eq!(FitnessAggregate::Median.apply(&samples[..3]), 1.);
+        assert_eq!(FitnessAggregate::Cvar { alpha: 0.5 }.apply(&samples), -0.5);
+        assert_eq!(FitnessAggregate::Cvar { alpha: 0. }.apply(&samples), -2.);
+        assert_eq!(mean_variance(&[1., 3.]), (2., 2.));
+
+        let racing = Racing { min_repeats: 2, z: 1. };
+        let samples: [&[f32]; 3] = [&[0., 1.], &[0.5, 1.5], &[10., 11.]];
+        assert_eq!(racing.unsettled(&samples), vec![0, 1]);
+    }
+}
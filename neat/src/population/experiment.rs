@@ -0,0 +1,212 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::{archive::PopulationArchive, population::Population, reporter::Reporter};
+use crate::{
+    config::neat_config::NeatConfig,
+    individual::{
+        genome::genome::{Genome, GenomeFactory},
+        individual::{EvaluatedIndividual, Individual},
+    },
+    selection::selection_trait::RoulleteSelection,
+    speciation::speciation::DistanceSpeciation,
+};
+
+/// Outcome of a run, see [`run_experiment`].
+#[derive(Debug, Clone)]
+pub struct ExperimentResult {
+    /// Fittest genome evaluated during the run and its fitness, none if
+    /// every fitness was NaN.
+    pub champion: Option<(Genome, f32)>,
+    /// Best fitness of every evaluated generation, NaN if it had none.
+    pub history: Vec<f32>,
+    /// Members of the last evaluated generation.
+    pub population: Vec<EvaluatedIndividual>,
+    /// Whether the run stopped on reaching its target fitness.
+    pub solved: bool,
+}
+
+impl ExperimentResult {
+    pub fn generations(&self) -> usize {
+        self.history.len()
+    }
+}
+
+type Checkpoint<'a> = (usize, Box<dyn FnMut(&PopulationArchive) + 'a>);
+
+/// Run of a [`NeatConfig`] from minimal genomes of a factory, with NEAT
+/// crossover and roulette selection. Evolution is seeded with the
+/// configured seed.
+pub struct Experiment<'a> {
+    population: Population<DistanceSpeciation<Genome>, RoulleteSelection>,
+    generations: usize,
+    seed: u64,
+    target: Option<f32>,
+    checkpoint: Option<Checkpoint<'a>>,
+}
+
+impl<'a> Experiment<'a> {
+    pub fn new(config: &NeatConfig, factory: &GenomeFactory) -> Self {
+        let genomes = vec![factory.generate_genome(); config.population_size];
+        Self {
+            population: Population::new(config.algorithm(), genomes)
+                .with_backend(config.backend),
+            generations: config.generations,
+            seed: config.seed,
+            target: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Stop once a genome reaches `target` fitness.
+    pub fn with_target(mut self, target: f32) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Pass the next generation, before its evaluation, to `save` every
+    /// `interval` generations. Resume it with `Population::new` and
+    /// [`PopulationArchive::innovation_number`].
+    pub fn with_checkpoint(
+        mut self,
+        interval: usize,
+        save: impl FnMut(&PopulationArchive) + 'a,
+    ) -> Self {
+        assert!(interval > 0);
+        self.checkpoint = Some((interval, Box::new(save)));
+        self
+    }
+
+    pub fn with_reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.population = self.population.with_reporter(reporter);
+        self
+    }
+
+    /// Evaluate and evolve for the configured generations, at least one, or
+    /// until the target is reached.
+    pub fn run(mut self, mut fitness: impl FnMut(&Genome) -> f32) -> ExperimentResult {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut result = ExperimentResult {
+            champion: None,
+            history: vec![],
+            population: vec![],
+            solved: false,
+        };
+        for step in 1..=self.generations.max(1) {
+            self.population.evaluate(&mut fitness);
+            let best = self.population.best(1).first().copied();
+            result
+                .history
+                .push(best.map_or(f32::NAN, |best| best.fitness()));
+            if let Some(best) = best {
+                if result
+                    .champion
+                    .as_ref()
+                    .is_none_or(|(_, fitness)| best.fitness() > *fitness)
+                {
+                    result.champion = Some((best.to_genome(), best.fitness()));
+                }
+            }
+            result.solved = self.target.is_some_and(|target| {
+                result
+                    .champion
+                    .as_ref()
+                    .is_some_and(|(_, fitness)| *fitness >= target)
+            });
+            if result.solved || step >= self.generations {
+                break;
+            }
+            self.population.evolve(&mut rng);
+            if let Some((interval, save)) = self.checkpoint.as_mut() {
+                if self.population.generation().is_multiple_of(*interval) {
+                    save(&self.population.archive());
+                }
+            }
+        }
+        result.population = self.population.members().to_vec();
+        result
+    }
+}
+
+/// Run `config` from minimal genomes of `factory` for its generations,
+/// evolving on `fitness`, see [`Experiment`] for a target fitness,
+/// checkpoints and reporters.
+pub fn run_experiment(
+    config: &NeatConfig,
+    factory: &GenomeFactory,
+    fitness: impl FnMut(&Genome) -> f32,
+) -> ExperimentResult {
+    Experiment::new(config, factory).run(fitness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::network::network::FFNetwork;
+
+    const XOR: [([f32; 3], f32); 4] = [
+        ([0., 0., 1.], 0.),
+        ([0., 1., 1.], 1.),
+        ([1., 0., 1.], 1.),
+        ([1., 1., 1.], 0.),
+    ];
+
+    fn xor(genome: &Genome) -> f32 {
+        let mut network = FFNetwork::from(genome);
+        let error = XOR
+            .iter()
+            .map(|(input, target)| {
+                network.reset();
+                (network.forward(input).unwrap()[0] - target).powi(2)
+            })
+            .sum::<f32>();
+        4. - error
+    }
+
+    fn config() -> NeatConfig {
+        NeatConfig {
+            population_size: 30,
+            generations: 8,
+            seed: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_experiment() {
+        let factory = GenomeFactory::init(3, 1).unwrap();
+        let result = run_experiment(&config(), &factory, xor);
+        assert_eq!(result.generations(), 8);
+        assert_eq!(result.population.len(), 30);
+        assert!(!result.solved);
+        let (champion, fitness) = result.champion.as_ref().unwrap();
+        assert_eq!(xor(champion), *fitness);
+        let best = result.history.iter().copied().fold(f32::MIN, f32::max);
+        assert_eq!(*fitness, best);
+
+        // Seeded by the config
+        let again = run_experiment(&config(), &factory, xor);
+        assert_eq!(again.history, result.history);
+    }
+
+    #[test]
+    fn test_target_and_checkpoints() {
+        let factory = GenomeFactory::init(3, 1).unwrap();
+        let mut archives = vec![];
+        let result = Experiment::new(&config(), &factory)
+            .with_checkpoint(3, |archive| archives.push(archive.clone()))
+            .run(xor);
+        assert_eq!(
+            archives.iter().map(|a| a.generation).collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        assert!(archives[0].fitness.iter().all(Option::is_none));
+
+        let first = result.history[0];
+        let solved = Experiment::new(&config(), &factory)
+            .with_target(first)
+            .run(xor);
+        assert!(solved.solved);
+        assert_eq!(solved.history, vec![first]);
+    }
+}
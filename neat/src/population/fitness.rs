@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::individual::individual::EvaluatedIndividual;
+
+use super::species::Species;
+
+/// Stage of a [`FitnessPipeline`]. NaN fitness, e.g. of unevaluated
+/// members, stays NaN through every stage.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FitnessStage {
+    /// Explicit fitness sharing: divide by the size of the member's species.
+    /// Meant for non-negative fitness.
+    Sharing,
+    /// Subtract a cost per hidden node and per enabled edge.
+    Complexity { per_node: f32, per_edge: f32 },
+    /// Scale linearly to `[0, 1]`, all 1 if every fitness is equal.
+    Scaling,
+    /// Softmax over the population, lower temperatures favour the fittest.
+    Softmax { temperature: f32 },
+}
+
+impl FitnessStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FitnessStage::Sharing => "sharing",
+            FitnessStage::Complexity { .. } => "complexity",
+            FitnessStage::Scaling => "scaling",
+            FitnessStage::Softmax { .. } => "softmax",
+        }
+    }
+
+    /// Transform `fitness`, the fitness of `members` in order.
+    pub fn apply(
+        &self,
+        members: &[EvaluatedIndividual],
+        species: &[Species],
+        fitness: &[f32],
+    ) -> Vec<f32> {
+        let finite = || fitness.iter().copied().filter(|f| !f.is_nan());
+        match *self {
+            FitnessStage::Sharing => {
+                let sizes = species
+                    .iter()
+                    .flat_map(|s| s.members.iter().map(|id| (*id, s.members.len())))
+                    .collect::<BTreeMap<_, _>>();
+                members
+                    .iter()
+                    .zip(fitness)
+                    .map(|(m, f)| f / sizes.get(&m.id).copied().unwrap_or(1) as f32)
+                    .collect_vec()
+            }
+            FitnessStage::Complexity { per_node, per_edge } => members
+                .iter()
+                .zip(fitness)
+                .map(|(m, f)| {
+                    let nodes = m.genome.node_list.hidden.len() as f32;
+                    let edges = m.genome.genome_list.iter().filter(|e| e.enabled).count() as f32;
+                    f - per_node * nodes - per_edge * edges
+                })
+                .collect_vec(),
+            FitnessStage::Scaling => {
+                let min = finite().fold(f32::INFINITY, f32::min);
+                let max = finite().fold(f32::NEG_INFINITY, f32::max);
+                fitness
+                    .iter()
+                    .map(|f| match f {
+                        f if f.is_nan() => f32::NAN,
+                        f if max > min => (f - min) / (max - min),
+                        _ => 1.,
+                    })
+                    .collect_vec()
+            }
+            FitnessStage::Softmax { temperature } => {
+                let max = finite().fold(f32::NEG_INFINITY, f32::max);
+                let weights = fitness
+                    .iter()
+                    .map(|f| ((f - max) / temperature).exp())
+                    .collect_vec();
+                let total = weights.iter().filter(|w| !w.is_nan()).sum::<f32>();
+                weights.iter().map(|w| w / total).collect_vec()
+            }
+        }
+    }
+}
+
+/// Fitness of the members after every stage of a pipeline, the first being
+/// the raw fitness.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FitnessTrace {
+    pub stages: Vec<(&'static str, Vec<f32>)>,
+}
+
+impl FitnessTrace {
+    /// Fitness after the last stage.
+    pub fn adjusted(&self) -> &[f32] {
+        self.stages.last().map_or(&[], |(_, fitness)| fitness)
+    }
+}
+
+/// Stages turning the raw fitness into the fitness selection sees, e.g.
+/// raw → sharing → complexity penalty → scaling. Applied by the population
+/// before breeding, see `Population::with_fitness_pipeline`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FitnessPipeline {
+    stages: Vec<FitnessStage>,
+}
+
+impl FitnessPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stage(mut self, stage: FitnessStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stages(&self) -> &[FitnessStage] {
+        &self.stages
+    }
+
+    pub fn apply(&self, members: &[EvaluatedIndividual], species: &[Species]) -> FitnessTrace {
+        let raw = members
+            .iter()
+            .map(|m| m.fitness.unwrap_or(f32::NAN))
+            .collect_vec();
+        let mut stages = vec![("raw", raw)];
+        for stage in self.stages.iter() {
+            let fitness = stage.apply(members, species, &stages[stages.len() - 1].1);
+            stages.push((stage.name(), fitness));
+        }
+        FitnessTrace { stages }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    fn members() -> (Vec<EvaluatedIndividual>, Vec<Species>) {
+        let small = GenomeBuilder::new().input(1).output(1).connect(0, 1, 1.);
+        let large = small.clone().hidden(50).connect(0, 2, 1.).connect(2, 1, 1.);
+        let members = vec![
+            EvaluatedIndividual::new(1, small.clone().build().unwrap()).with_fitness(4.),
+            EvaluatedIndividual::new(2, large.build().unwrap()).with_fitness(6.),
+            EvaluatedIndividual::new(3, small.build().unwrap()),
+        ];
+        let species = vec![Species {
+            id: 1,
+            age: 0,
+            representative: members[0].clone(),
+            members: vec![1, 2],
+            best: None,
+            stale: 0,
+        }];
+        (members, species)
+    }
+
+    #[test]
+    fn test_stages() {
+        let (members, species) = members();
+        let fitness = [4., 6., f32::NAN];
+        let shared = FitnessStage::Sharing.apply(&members, &species, &fitness);
+        assert_eq!(shared[..2], [2., 3.]);
+        let penalty = FitnessStage::Complexity {
+            per_node: 1.,
+            per_edge: 0.5,
+        };
+        assert_eq!(penalty.apply(&members, &species, &fitness)[..2], [3.5, 3.5]);
+        let scaled = FitnessStage::Scaling.apply(&members, &species, &fitness);
+        assert_eq!(scaled[..2], [0., 1.]);
+        let equal = FitnessStage::Scaling.apply(&members, &species, &[2., 2., f32::NAN]);
+        assert_eq!(equal[..2], [1., 1.]);
+        let softmax = FitnessStage::Softmax { temperature: 1. }.apply(&members, &species, &fitness);
+        assert_relative_eq!(softmax[0] + softmax[1], 1.);
+        assert!(softmax[1] > softmax[0]);
+        for stage in [shared, scaled, softmax] {
+            assert!(stage[2].is_nan());
+        }
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let (members, species) = members();
+        let pipeline = FitnessPipeline::new()
+            .with_stage(FitnessStage::Sharing)
+            .with_stage(FitnessStage::Scaling);
+        let trace = pipeline.apply(&members, &species);
+        assert_eq!(
+            trace.stages.iter().map(|(name, _)| *name).collect_vec(),
+            vec!["raw", "sharing", "scaling"]
+        );
+        assert_eq!(trace.stages[1].1[..2], [2., 3.]);
+        assert_eq!(trace.adjusted()[..2], [0., 1.]);
+        assert_eq!(
+            FitnessPipeline::new().apply(&members, &species).adjusted()[1],
+            6.
+        );
+    }
+}
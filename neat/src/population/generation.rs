@@ -0,0 +1,77 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::Serialize;
+
+use super::replay::{Breeding, MutationRecord};
+
+/// Mutations applied to the children of a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct MutationCounts {
+    pub nodes_added: usize,
+    pub edges_added: usize,
+    /// Edges enabled or disabled.
+    pub toggled: usize,
+    /// Edges whose weight changed.
+    pub weights_changed: usize,
+}
+
+impl MutationCounts {
+    pub fn add(&mut self, record: &MutationRecord) {
+        self.nodes_added += record.nodes_added.len();
+        self.edges_added += record.edges_added.len();
+        self.toggled += record.toggled.len();
+        self.weights_changed += record.weights.len();
+    }
+}
+
+/// Children a mutation operator changed, and those fitter than their fitter
+/// parent once evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct OperatorStats {
+    pub children: usize,
+    pub improved: usize,
+}
+
+impl OperatorStats {
+    /// Share of the children that improved, 0 without children.
+    pub fn success_rate(&self) -> f32 {
+        if self.children == 0 {
+            0.
+        } else {
+            self.improved as f32 / self.children as f32
+        }
+    }
+}
+
+/// Wall-clock time of the phases of a generation step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct PhaseTimes {
+    /// Selection, crossover and mutation of every species.
+    pub reproduction: Duration,
+    pub speciation: Duration,
+}
+
+/// Structured account of `Population::step`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct GenerationReport {
+    /// Generation that bred.
+    pub generation: usize,
+    /// Children bred by every species, by species id.
+    pub offspring: BTreeMap<usize, usize>,
+    pub mutations: MutationCounts,
+    /// Children changed by every mutation operator, by operator name.
+    pub operators: BTreeMap<String, usize>,
+    /// Children of two distinct parents, the others being mutated clones.
+    pub crossovers: usize,
+    pub times: PhaseTimes,
+}
+
+impl GenerationReport {
+    pub(super) fn add(&mut self, breeding: &Breeding) {
+        self.mutations.add(&breeding.mutation);
+        for operator in breeding.operators() {
+            *self.operators.entry(operator.to_string()).or_default() += 1;
+        }
+        self.crossovers += usize::from(breeding.parents[0] != breeding.parents[1]);
+    }
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::individual::{genome::genome::Genome, individual::EvaluatedIndividual};
+
+/// Champion of a generation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HallOfFameEntry {
+    pub generation: usize,
+    pub fitness: f32,
+    pub structural_hash: u64,
+    pub genome: Arc<Genome>,
+}
+
+/// How fitness is combined with games against archived champions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoEvaluation {
+    /// Number of champions sampled for each individual.
+    pub opponents: usize,
+    /// Weight of the mean score against the opponents, the rest going to
+    /// the individual's own fitness.
+    pub weight: f32,
+}
+
+impl Default for CoEvaluation {
+    fn default() -> Self {
+        Self {
+            opponents: 5,
+            weight: 0.5,
+        }
+    }
+}
+
+/// Archive of the best genome of every generation. Champions sharing a
+/// topology are stored once, keeping the fittest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HallOfFame {
+    entries: Vec<HallOfFameEntry>,
+}
+
+impl HallOfFame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Archive `champion`. Returns `false` if it is unevaluated or an
+    /// equally fit genome with the same topology is already stored.
+    pub fn insert(&mut self, generation: usize, champion: &EvaluatedIndividual) -> bool {
+        let Some(fitness) = champion.fitness.filter(|f| !f.is_nan()) else {
+            return false;
+        };
+        let structural_hash = champion.genome.structural_hash();
+        let entry = HallOfFameEntry {
+            generation,
+            fitness,
+            structural_hash,
+            genome: Arc::clone(&champion.genome),
+        };
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| e.structural_hash == structural_hash)
+        {
+            Some(prev) if prev.fitness >= fitness => false,
+            Some(prev) => {
+                *prev = entry;
+                true
+            }
+            None => {
+                self.entries.push(entry);
+                true
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[HallOfFameEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Up to `count` distinct champions, sampled uniformly.
+    pub fn sample(&self, rng: &mut dyn RngCore, count: usize) -> Vec<&Genome> {
+        self.entries
+            .choose_multiple(rng, count)
+            .map(|e| &*e.genome)
+            .collect_vec()
+    }
+
+    /// Combine `fitness` with the mean score of `genome` against sampled
+    /// champions, where `play` scores its first argument against the second.
+    /// With an empty archive `fitness` is returned unchanged.
+    pub fn co_evaluate(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &Genome,
+        fitness: f32,
+        config: CoEvaluation,
+        mut play: impl FnMut(&Genome, &Genome) -> f32,
+    ) -> f32 {
+        let opponents = self.sample(rng, config.opponents);
+        if opponents.is_empty() {
+            return fitness;
+        }
+        let score = opponents.iter().map(|o| play(genome, o)).sum::<f32>() / opponents.len() as f32;
+        (1. - config.weight) * fitness + config.weight * score
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Hall of fame contains only plain data")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::{
+        genome::{GenomeEdge, GenomeFactory},
+        node_list::NodeId,
+    };
+
+    fn champion(edges: &[(NodeId, NodeId)], fitness: f32) -> EvaluatedIndividual {
+        let mut genome = GenomeFactory::init(2, 1).unwrap().generate_genome();
+        genome.genome_list.edge_list = edges
+            .iter()
+            .zip(0..)
+            .map(|(&(in_node, out_node), innov_number)| GenomeEdge {
+                innov_number,
+                in_node,
+                out_node,
+                weight: fitness,
+                enabled: true,
+            })
+            .collect();
+        EvaluatedIndividual::new(0, genome).with_fitness(fitness)
+    }
+
+    #[test]
+    fn test_insert_deduplicates() {
+        let mut hof = HallOfFame::new();
+        assert!(hof.insert(0, &champion(&[(0, 2)], 1.)));
+        assert!(!hof.insert(1, &champion(&[(0, 2)], 0.5)));
+        assert!(hof.insert(2, &champion(&[(0, 2)], 2.)));
+        assert!(hof.insert(3, &champion(&[(0, 2), (1, 2)], 0.)));
+        let unevaluated = EvaluatedIndividual {
+            fitness: None,
+            ..champion(&[], 0.)
+        };
+        assert!(!hof.insert(4, &unevaluated));
+        assert_eq!(
+            hof.entries().iter().map(|e| (e.generation, e.fitness)).collect_vec(),
+            vec![(2, 2.), (3, 0.)]
+        );
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut hof = HallOfFame::new();
+        hof.insert(0, &champion(&[(0, 2)], 1.));
+        hof.insert(1, &champion(&[(1, 2)], 2.));
+        let restored = HallOfFame::from_json(&hof.to_json()).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(
+            restored.entries()[1].structural_hash,
+            restored.entries()[1].genome.structural_hash()
+        );
+    }
+
+    #[test]
+    fn test_co_evaluate() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut hof = HallOfFame::new();
+        let me = champion(&[], 0.).genome;
+        let config = CoEvaluation {
+            opponents: 2,
+            weight: 0.25,
+        };
+        assert_eq!(hof.co_evaluate(&mut rng, &me, 4., config, |_, _| 0.), 4.);
+
+        hof.insert(0, &champion(&[(0, 2)], 1.));
+        hof.insert(1, &champion(&[(1, 2)], 3.));
+        hof.insert(2, &champion(&[(0, 2), (1, 2)], 5.));
+        // Score against an opponent is its first edge weight
+        let play = |_: &Genome, o: &Genome| o.genome_list.edge_list[0].weight;
+        let fitness = hof.co_evaluate(&mut rng, &me, 4., config, play);
+        assert!((0.75 * 4. + 0.25 * 2. ..=0.75 * 4. + 0.25 * 4.).contains(&fitness));
+        assert_relative_eq!(
+            hof.co_evaluate(&mut rng, &me, 4., CoEvaluation { opponents: 3, ..config }, play),
+            0.75 * 4. + 0.25 * 3.
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use itertools::Itertools;
+use rand::{Rng, RngCore};
+
+use crate::individual::genome::genome::Genome;
+
+/// Decay exponents of the SPSA step size and perturbation, as recommended
+/// by Spall.
+const STEP_DECAY: f32 = 0.602;
+const PERTURBATION_DECAY: f32 = 0.101;
+
+/// What the tuned weights are used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Inheritance {
+    /// Tuned weights only improve the fitness, the genome is unchanged.
+    #[default]
+    Baldwinian,
+    /// Tuned weights are written back to the genome and inherited.
+    Lamarckian,
+}
+
+/// Weight fine-tuning of the enabled edges with simultaneous perturbation
+/// stochastic approximation (SPSA), which estimates the fitness gradient
+/// from two evaluations per step whatever the number of weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalSearch {
+    pub steps: usize,
+    /// Initial step size along the gradient estimate.
+    pub step_size: f32,
+    /// Initial size of the weight perturbation.
+    pub perturbation: f32,
+    pub inheritance: Inheritance,
+}
+
+impl Default for LocalSearch {
+    fn default() -> Self {
+        Self {
+            steps: 10,
+            step_size: 0.1,
+            perturbation: 0.1,
+            inheritance: Inheritance::default(),
+        }
+    }
+}
+
+impl LocalSearch {
+    /// Maximise `fitness` over the enabled weights of `genome`. Returns the
+    /// best genome found and its fitness, `genome` itself if no step helped.
+    /// Evaluates `fitness` `1 + 2 * steps` times.
+    pub fn tune(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &Genome,
+        mut fitness: impl FnMut(&Genome) -> f32,
+    ) -> (Genome, f32) {
+        let enabled = genome
+            .genome_list
+            .iter()
+            .positions(|edge| edge.enabled)
+            .collect_vec();
+        let mut best = (genome.clone(), fitness(genome));
+        if enabled.is_empty() {
+            return best;
+        }
+        let mut current = genome.clone();
+        let mut probe = genome.clone();
+        let mut evaluate = |weights: &Genome, delta: &[f32], scale: f32| {
+            for (edge, (index, d)) in probe
+                .genome_list
+                .edge_list
+                .iter_mut()
+                .filter(|edge| edge.enabled)
+                .zip(enabled.iter().zip(delta))
+            {
+                edge.weight = weights.genome_list.edge_list[*index].weight + scale * d;
+            }
+            (probe.clone(), fitness(&probe))
+        };
+        for step in 0..self.steps {
+            let k = (step + 1) as f32;
+            let step_size = self.step_size / k.powf(STEP_DECAY);
+            let perturbation = self.perturbation / k.powf(PERTURBATION_DECAY);
+            let delta = enabled
+                .iter()
+                .map(|_| if rng.gen_bool(0.5) { 1. } else { -1. })
+                .collect_vec();
+            let plus = evaluate(&current, &delta, perturbation);
+            let minus = evaluate(&current, &delta, -perturbation);
+            let slope = (plus.1 - minus.1) / (2. * perturbation);
+            for candidate in [plus, minus] {
+                if candidate.1 > best.1 {
+                    best = candidate;
+                }
+            }
+            if !slope.is_finite() {
+                break;
+            }
+            // The gradient estimate is slope / delta_i, and delta_i = ±1
+            for (index, d) in enabled.iter().zip(delta) {
+                current.genome_list.edge_list[*index].weight += step_size * slope * d;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    #[test]
+    fn test_tune() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 0.)
+            .connect(1, 2, 0.)
+            .build()
+            .unwrap();
+        // Peak at weights (1, -1)
+        let fitness = |g: &Genome| {
+            let w = g.genome_list.iter().map(|e| e.weight).collect_vec();
+            -(w[0] - 1.).powi(2) - (w[1] + 1.).powi(2)
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let search = LocalSearch {
+            steps: 100,
+            step_size: 0.2,
+            ..Default::default()
+        };
+        let (tuned, value) = search.tune(&mut rng, &genome, fitness);
+        assert!(value > -0.01);
+        assert_eq!(value, fitness(&tuned));
+        assert_eq!(genome.genome_list.iter().map(|e| e.weight).collect_vec(), vec![0., 0.]);
+    }
+}
@@ -0,0 +1,23 @@
+#[allow(clippy::module_inception)]
+pub mod population;
+pub mod species;
+pub mod hall_of_fame;
+pub mod coevolution;
+pub mod evaluation;
+pub mod curriculum;
+pub mod local_search;
+pub mod weight_opt;
+pub mod sensitivity;
+pub mod replay;
+pub mod diversity;
+pub mod reporter;
+pub mod remote;
+pub mod generation;
+pub mod fitness;
+pub mod archive;
+pub mod experiment;
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod stagnation;
+pub mod cataclysm;
+pub mod streams;
@@ -0,0 +1,2692 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    individual::{
+        genome::{
+            genome::{max_level_denominator, renormalize_levels, Genome, GenomeFactory},
+            network::{
+                network::FFNetwork,
+                saturation::{SaturationStats, SaturationWarning},
+                schedule::ScheduleCache,
+                sparse::{Backend, Network},
+            },
+        },
+        individual::{EvaluatedIndividual, Individual, Origin},
+    },
+    mutation::{
+        canonical::{InnovationRemap, MergeStats},
+        innovation_number::InnovNumber,
+    },
+    selection::{minimal_criterion::SATISFIED, selection_trait::SelectionMethod},
+    speciation::{distance::DistanceMetric, speciation::SpeciationMethod},
+    GeneticAlgortihm,
+};
+
+use super::{
+    archive::PopulationArchive,
+    diversity::Diversity,
+    evaluation::{mean_variance, Dropout, EvaluationFailure, EvaluationGuard, RepeatedEvaluation},
+    fitness::FitnessPipeline,
+    generation::{GenerationReport, OperatorStats, PhaseTimes},
+    hall_of_fame::{CoEvaluation, HallOfFame},
+    local_search::{Inheritance, LocalSearch},
+    weight_opt::WeightRefinement,
+    remote::{Disconnected, Job, RemoteReport, Transport},
+    replay::Breeding,
+    reporter::{Event, Reporter},
+    cataclysm::{Cataclysm, Refill},
+    species::{
+        largest_remainder, OffspringAllocation, RepresentativePolicy, Species, SpeciesProtection,
+        SpeciesReport, StaleCutoff,
+    },
+    stagnation::{StagnationDetector, StagnationResponse},
+    streams::{Purpose, RngStreams},
+};
+
+/// Hidden node levels are renormalized once a denominator exceeds this.
+const LEVEL_DENOMINATOR_LIMIT: usize = 1 << 16;
+
+/// Population driver. Owns the current generation, its species and the
+/// innovation registry, and steps them forward with a [`GeneticAlgortihm`].
+pub struct Population<Spe, Sel> {
+    algorithm: GeneticAlgortihm<Spe, Sel>,
+    innovation: InnovNumber,
+    members: Vec<EvaluatedIndividual>,
+    species: Vec<Species>,
+    representative_policy: RepresentativePolicy,
+    offspring_allocation: OffspringAllocation,
+    /// Minimum offspring of the species, none to leave the allocation as is.
+    species_protection: Option<SpeciesProtection>,
+    /// Parents of the species that stopped improving, none to breed from
+    /// every member.
+    stale_cutoff: Option<StaleCutoff>,
+    /// Random streams of the individuals, none to draw everything from the
+    /// rng passed in.
+    streams: Option<RngStreams>,
+    hall_of_fame: Option<HallOfFame>,
+    guard: EvaluationGuard,
+    /// Backend of [`Population::evaluate_compiled`].
+    backend: Backend,
+    /// Failed evaluations of the current generation.
+    failures: BTreeMap<usize, EvaluationFailure>,
+    /// Fitness variance of the members evaluated repeatedly.
+    variances: BTreeMap<usize, f32>,
+    /// Soft wall-time limit of an evaluation pass, none for no limit.
+    time_budget: Option<Duration>,
+    /// Members of the current generation left unevaluated by the time
+    /// budget, with an estimated fitness.
+    estimated: BTreeSet<usize>,
+    /// Members bred from an estimated parent, evaluated first.
+    prioritized: BTreeSet<usize>,
+    /// Fitness of the fitter parent of every member, in order, NaN for the
+    /// first generation and fresh genomes.
+    parent_fitness: Vec<f32>,
+    reporters: Vec<Box<dyn Reporter>>,
+    /// Metric of the reported diversity, none to skip the report.
+    diversity_metric: Option<Box<dyn DistanceMetric<Genome>>>,
+    saturation_warning: Option<SaturationWarning>,
+    /// Adjustment of the fitness selection sees, none to use the raw one.
+    fitness_pipeline: Option<FitnessPipeline>,
+    stagnation: Option<StagnationDetector>,
+    cataclysm: Option<Cataclysm>,
+    /// Factory of the fresh genomes of stagnation responses and cataclysms.
+    factory: Option<GenomeFactory>,
+    /// Fitness of the fitter parent and mutation operators of every member,
+    /// in order, tracked for the reporters and adaptive mutations.
+    lineage: Vec<(f32, Vec<String>)>,
+    /// Origin of every member, in order, `None` for the first generation
+    /// and fresh genomes.
+    origins: Vec<Option<Origin>>,
+    generation: usize,
+    next_id: usize,
+    next_species_id: usize,
+}
+
+impl<Spe, Sel> Population<Spe, Sel>
+where
+    Spe: SpeciationMethod<EvaluatedIndividual>,
+    Sel: SelectionMethod,
+{
+    pub fn new(algorithm: GeneticAlgortihm<Spe, Sel>, genomes: Vec<Genome>) -> Self {
+        assert!(!genomes.is_empty());
+        let mut ret = Self {
+            algorithm,
+            innovation: InnovNumber::from_population(&genomes),
+            members: vec![],
+            species: vec![],
+            representative_policy: RepresentativePolicy::default(),
+            offspring_allocation: OffspringAllocation::default(),
+            species_protection: None,
+            stale_cutoff: None,
+            streams: None,
+            hall_of_fame: None,
+            guard: EvaluationGuard::default(),
+            backend: Backend::default(),
+            failures: BTreeMap::new(),
+            variances: BTreeMap::new(),
+            time_budget: None,
+            estimated: BTreeSet::new(),
+            prioritized: BTreeSet::new(),
+            parent_fitness: vec![],
+            reporters: vec![],
+            diversity_metric: None,
+            saturation_warning: None,
+            fitness_pipeline: None,
+            stagnation: None,
+            cataclysm: None,
+            factory: None,
+            lineage: vec![],
+            origins: vec![],
+            generation: 0,
+            next_id: 0,
+            next_species_id: 0,
+        };
+        ret.replace_members(genomes);
+        ret
+    }
+
+    /// Evaluate every member that has no fitness yet.
+    pub fn evaluate(&mut self, mut fitness: impl FnMut(&Genome) -> f32) {
+        let guard = self.guard;
+        self.evaluate_guarded(|_, genome| guard.check(fitness(genome)));
+    }
+
+    /// Like [`Population::evaluate`], for a stochastic `fitness` drawing from
+    /// the evaluation stream of the member, so every member gets the same
+    /// fitness whatever the order members are evaluated in.
+    ///
+    /// # Panics
+    /// If the population has no seed, see [`Population::with_seed`].
+    pub fn evaluate_seeded(&mut self, mut fitness: impl FnMut(&Genome, &mut dyn RngCore) -> f32) {
+        let (guard, generation) = (self.guard, self.generation);
+        let streams = self.streams.expect("Seeded evaluation needs a seed");
+        self.evaluate_guarded(|id, genome| {
+            let mut rng = streams.stream(Purpose::Evaluation, generation, id);
+            guard.check(fitness(genome, &mut rng))
+        });
+    }
+
+    /// Like [`Population::evaluate_seeded`], evaluating the members in
+    /// parallel on the rayon thread pool. The fitness does not depend on the
+    /// number of threads. Every pending member is evaluated, whatever the
+    /// time budget.
+    ///
+    /// # Panics
+    /// If the population has no seed, see [`Population::with_seed`].
+    #[cfg(feature = "rayon")]
+    pub fn evaluate_par(&mut self, fitness: impl Fn(&Genome, &mut dyn RngCore) -> f32 + Sync) {
+        use rayon::prelude::*;
+
+        phase!("evaluation", generation = self.generation);
+        let generation = self.generation;
+        let streams = self.streams.expect("Seeded evaluation needs a seed");
+        let scores = self
+            .members
+            .par_iter()
+            .enumerate()
+            .filter(|(_, m)| m.fitness.is_none())
+            .map(|(index, m)| {
+                let mut rng = streams.stream(Purpose::Evaluation, generation, m.id);
+                (index, fitness(&m.genome, &mut rng))
+            })
+            .collect::<Vec<_>>();
+        event!(DEBUG, evaluated = scores.len());
+        // Already evaluated, so the scores skip the time budget of
+        // `evaluate_guarded`
+        for (index, score) in scores {
+            self.ingest(index, Ok(score));
+        }
+        event!(DEBUG, failures = self.failures.len());
+    }
+
+    /// Evaluate every member that has no fitness yet on a boolean criterion,
+    /// e.g. reaching the goal, for [minimal criterion
+    /// selection](crate::selection::minimal_criterion). Members meeting it
+    /// get fitness [`SATISFIED`], the others 0.
+    pub fn evaluate_criterion(&mut self, mut criterion: impl FnMut(&Genome) -> bool) {
+        self.evaluate(|genome| if criterion(genome) { SATISFIED } else { 0. });
+    }
+
+    /// Evaluate every member that has no fitness yet on its compiled network.
+    /// Members whose network produced a NaN or infinite output fail. Members
+    /// of the same structure share a [`ScheduleCache`] schedule.
+    pub fn evaluate_network(&mut self, fitness: impl FnMut(&mut FFNetwork) -> f32) {
+        self.evaluate_networks(fitness);
+    }
+
+    /// Like [`Population::evaluate_network`], also storing the behaviour
+    /// descriptor `evaluate` returns in the member, e.g. with
+    /// [`RolloutRunner::fitness_and_behavior`](crate::environment::rollout::RolloutRunner::fitness_and_behavior).
+    /// Failed members keep their descriptor.
+    pub fn evaluate_network_behavior(
+        &mut self,
+        mut evaluate: impl FnMut(&mut FFNetwork) -> (f32, Option<Vec<f32>>),
+    ) {
+        let mut behaviors = vec![];
+        let ids = self.evaluate_networks(|network| {
+            let (fitness, behavior) = evaluate(network);
+            behaviors.push(behavior);
+            fitness
+        });
+        for (id, behavior) in ids.into_iter().zip(behaviors) {
+            let index = self
+                .members
+                .binary_search_by_key(&id, |m| m.id)
+                .expect("Members are alive");
+            self.members[index].behavior = behavior;
+        }
+    }
+
+    /// Evaluate on the compiled networks, returning the ids of the evaluated
+    /// members in evaluation order.
+    fn evaluate_networks(&mut self, mut fitness: impl FnMut(&mut FFNetwork) -> f32) -> Vec<usize> {
+        let guard = self.guard;
+        let warning = self.saturation_warning;
+        let mut saturation = vec![];
+        let mut schedules = ScheduleCache::new();
+        let ids = self.evaluate_guarded(|_, genome| {
+            let mut network = schedules.compile(genome);
+            let value = fitness(&mut network);
+            saturation.push(warning.and_then(|w| w.check(&network.saturation_stats())));
+            if network.diverged() {
+                return Err(EvaluationFailure::NonFinite);
+            }
+            guard.check(value)
+        });
+        self.report_saturation(ids.clone(), saturation);
+        ids
+    }
+
+    /// Like [`Population::evaluate_network`], on networks compiled with the
+    /// backend set by [`Population::with_backend`]. The sparse backend
+    /// compiles faster, which pays off when every network runs only a few
+    /// passes, but keeps no saturation statistics, so none are reported.
+    pub fn evaluate_compiled(&mut self, mut fitness: impl FnMut(&mut Network) -> f32) {
+        let (guard, backend) = (self.guard, self.backend);
+        self.evaluate_guarded(|_, genome| {
+            let mut network = Network::new(genome, backend);
+            let value = fitness(&mut network);
+            if network.diverged() {
+                return Err(EvaluationFailure::NonFinite);
+            }
+            guard.check(value)
+        });
+    }
+
+    /// Like [`Population::evaluate_network`], averaging the fitness over
+    /// `dropout.trials` networks with randomly dropped hidden nodes. A member
+    /// fails if any trial diverged.
+    pub fn evaluate_with_dropout(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut fitness: impl FnMut(&mut FFNetwork) -> f32,
+        dropout: Dropout,
+    ) {
+        assert!(dropout.trials > 0, "Dropout needs at least one trial");
+        let guard = self.guard;
+        let warning = self.saturation_warning;
+        let mut saturation = vec![];
+        let mut schedules = ScheduleCache::new();
+        let ids = self.evaluate_guarded(|_, genome| {
+            let mut total = 0.;
+            let mut stats = BTreeMap::new();
+            let mut diverged = false;
+            let compiled = schedules.compile(genome);
+            for _ in 0..dropout.trials {
+                let mut network = compiled.clone();
+                network.drop_hidden(rng, dropout.rate);
+                total += fitness(&mut network);
+                for (node, trial) in network.saturation_stats() {
+                    stats.entry(node).or_insert_with(SaturationStats::default).merge(&trial);
+                }
+                if network.diverged() {
+                    diverged = true;
+                    break;
+                }
+            }
+            saturation.push(warning.and_then(|w| w.check(&stats)));
+            if diverged {
+                return Err(EvaluationFailure::NonFinite);
+            }
+            guard.check(total / dropout.trials as f32)
+        });
+        self.report_saturation(ids, saturation);
+    }
+
+    /// Like [`Population::evaluate`], enforcing the guard's timeout by running
+    /// every evaluation on its own thread.
+    pub fn evaluate_with_timeout<F>(&mut self, fitness: F)
+    where
+        F: Fn(&Genome) -> f32 + Send + Sync + 'static,
+    {
+        let (guard, fitness) = (self.guard, Arc::new(fitness));
+        self.evaluate_guarded(|_, genome| guard.run(&fitness, genome));
+    }
+
+    /// Evaluate every member that has no fitness yet after fine-tuning its
+    /// weights against `fitness`, see [`LocalSearch`].
+    pub fn evaluate_with_local_search(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut fitness: impl FnMut(&Genome) -> f32,
+        search: LocalSearch,
+    ) {
+        let guard = self.guard;
+        self.evaluate_guarded(|_, genome| {
+            let (tuned, value) = search.tune(rng, genome, &mut fitness);
+            if search.inheritance == Inheritance::Lamarckian {
+                *genome = Arc::new(tuned);
+            }
+            guard.check(value)
+        });
+    }
+
+    /// Every `refinement.interval` generations, tune the weights of the
+    /// `refinement.elites` fittest members against `fitness`, see
+    /// [`WeightOptimizer`](super::weight_opt::WeightOptimizer). A member
+    /// whose weights improve takes the new fitness, and the tuned genome if
+    /// Lamarckian. Returns the ids of the improved members.
+    pub fn refine_weights(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut fitness: impl FnMut(&Genome) -> f32,
+        refinement: WeightRefinement,
+    ) -> Vec<usize> {
+        if refinement.interval == 0 || !self.generation.is_multiple_of(refinement.interval) {
+            return vec![];
+        }
+        let elites = self
+            .best(refinement.elites)
+            .into_iter()
+            .filter(|m| !self.estimated.contains(&m.id))
+            .map(|m| m.id)
+            .collect_vec();
+        let mut improved = vec![];
+        for member in self.members.iter_mut().filter(|m| elites.contains(&m.id)) {
+            let (tuned, value) = refinement.optimizer.optimize(rng, &member.genome, &mut fitness);
+            let Ok(value) = self.guard.check(value) else {
+                continue;
+            };
+            if value <= member.fitness() {
+                continue;
+            }
+            member.fitness = Some(value);
+            if refinement.inheritance == Inheritance::Lamarckian {
+                member.genome = Arc::new(tuned);
+            }
+            improved.push(member.id);
+        }
+        event!(DEBUG, improved = improved.len());
+        improved
+    }
+
+    /// Evaluate every member that has no fitness yet `config.repeats` times,
+    /// or fewer with racing, and aggregate the samples. A member fails on its
+    /// first failed sample. Reports an [`Event::FitnessVariance`] of the
+    /// evaluated members. Returns the number of evaluations.
+    pub fn evaluate_repeated(
+        &mut self,
+        mut fitness: impl FnMut(&Genome) -> f32,
+        config: RepeatedEvaluation,
+    ) -> usize {
+        assert!(config.repeats > 0, "Repeated evaluation needs a repeat");
+        let pending = self
+            .members
+            .iter()
+            .positions(|m| m.fitness.is_none())
+            .collect_vec();
+        let mut samples = vec![vec![]; pending.len()];
+        let mut failures = vec![None; pending.len()];
+        let mut active = (0..pending.len()).collect_vec();
+        let mut evaluations = 0;
+        for round in 0..config.repeats {
+            if let Some(racing) = config.racing.filter(|r| round >= r.min_repeats.max(2)) {
+                let alive = (0..pending.len()).filter(|i| failures[*i].is_none()).collect_vec();
+                let unsettled =
+                    racing.unsettled(&alive.iter().map(|i| &samples[*i][..]).collect_vec());
+                active = unsettled.into_iter().map(|i| alive[i]).collect_vec();
+            }
+            if active.is_empty() {
+                break;
+            }
+            for i in active.iter().copied() {
+                evaluations += 1;
+                match self.guard.check(fitness(&self.members[pending[i]].genome)) {
+                    Ok(sample) => samples[i].push(sample),
+                    Err(failure) => failures[i] = Some(failure),
+                }
+            }
+            active.retain(|i| failures[*i].is_none());
+        }
+        let mut variances = vec![];
+        for (i, index) in pending.into_iter().enumerate() {
+            let fitness = match failures[i] {
+                Some(failure) => Err(failure),
+                None => {
+                    let id = self.members[index].id;
+                    let variance = mean_variance(&samples[i]).1;
+                    self.variances.insert(id, variance);
+                    variances.push((id, variance));
+                    Ok(config.aggregate.apply(&samples[i]))
+                }
+            };
+            self.ingest(index, fitness);
+        }
+        self.report(Event::FitnessVariance(variances));
+        evaluations
+    }
+
+    /// Evaluate every member that has no fitness yet on remote workers: one
+    /// job per member is sent through `transport`, then the results of this
+    /// generation are awaited. Members without a result after `timeout` fail
+    /// with [`EvaluationFailure::Timeout`]. On disconnection the remaining
+    /// members stay unevaluated.
+    pub fn evaluate_remote(
+        &mut self,
+        transport: &mut impl Transport,
+        timeout: Duration,
+    ) -> Result<RemoteReport, Disconnected> {
+        phase!("evaluation", generation = self.generation);
+        let generation = self.generation;
+        let mut pending = BTreeSet::new();
+        for member in self.members.iter().filter(|m| m.fitness.is_none()) {
+            transport.send(Job {
+                generation,
+                id: member.id,
+                genome: Arc::clone(&member.genome),
+            })?;
+            pending.insert(member.id);
+        }
+        let deadline = Instant::now() + timeout;
+        let mut report = RemoteReport::default();
+        while !pending.is_empty() {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            let Some(result) = transport.receive(left)? else {
+                break;
+            };
+            if result.generation != generation || !pending.remove(&result.id) {
+                report.stale += 1;
+                continue;
+            }
+            report.received += 1;
+            let fitness = match result.fitness {
+                Some(fitness) => self.guard.check(fitness),
+                None => Err(EvaluationFailure::Remote),
+            };
+            self.set_fitness(result.id, fitness);
+        }
+        report.stragglers = pending.len();
+        for id in pending {
+            self.set_fitness(id, Err(EvaluationFailure::Timeout));
+        }
+        event!(DEBUG, failures = self.failures.len());
+        Ok(report)
+    }
+
+    fn set_fitness(&mut self, id: usize, fitness: Result<f32, EvaluationFailure>) {
+        let index = self
+            .members
+            .binary_search_by_key(&id, |m| m.id)
+            .expect("Members are alive");
+        self.ingest(index, fitness);
+    }
+
+    /// Store the fitness of the member at `index`. Every evaluation goes
+    /// through here, so non-finite fitness, whichever path it came from,
+    /// fails and gets the guard penalty: the rest of the run only ever sees
+    /// finite fitness.
+    fn ingest(&mut self, index: usize, fitness: Result<f32, EvaluationFailure>) {
+        let member = &mut self.members[index];
+        member.fitness = Some(match fitness.and_then(|f| self.guard.check(f)) {
+            Ok(fitness) => fitness,
+            Err(failure) => {
+                event!(WARN, id = member.id, ?failure, "evaluation failed");
+                self.failures.insert(member.id, failure);
+                self.guard.penalty
+            }
+        });
+    }
+
+    /// Report the saturation warnings of the members `ids`, in order.
+    fn report_saturation(&mut self, ids: Vec<usize>, warnings: Vec<Option<(usize, usize)>>) {
+        for (id, warning) in ids.into_iter().zip(warnings) {
+            if let Some((saturated, nodes)) = warning {
+                self.report(Event::Saturation {
+                    id,
+                    saturated,
+                    nodes,
+                });
+            }
+        }
+    }
+
+    /// Evaluate the members without fitness, by id and genome, the
+    /// prioritized ones first, until the time budget runs out. Returns the
+    /// ids of the evaluated members, in evaluation order.
+    fn evaluate_guarded(
+        &mut self,
+        mut fitness: impl FnMut(usize, &mut Arc<Genome>) -> Result<f32, EvaluationFailure>,
+    ) -> Vec<usize> {
+        phase!("evaluation", generation = self.generation);
+        let deadline = self.time_budget.map(|budget| Instant::now() + budget);
+        let pending = self
+            .members
+            .iter()
+            .positions(|m| m.fitness.is_none())
+            .sorted_by_key(|index| !self.prioritized.contains(&self.members[*index].id))
+            .collect_vec();
+        event!(DEBUG, evaluated = pending.len());
+        let mut evaluated = Vec::with_capacity(pending.len());
+        for index in pending {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+            let member = &mut self.members[index];
+            let value = fitness(member.id, &mut member.genome);
+            evaluated.push(member.id);
+            self.ingest(index, value);
+        }
+        if deadline.is_some() {
+            self.estimate_unevaluated();
+        }
+        event!(DEBUG, failures = self.failures.len());
+        evaluated
+    }
+
+    /// Give the members without fitness an estimate: the fitness of their
+    /// fitter parent, else the mean of their evaluated species mates, else
+    /// the guard penalty.
+    fn estimate_unevaluated(&mut self) {
+        let pending = self.members.iter().positions(|m| m.fitness.is_none()).collect_vec();
+        if pending.is_empty() {
+            return;
+        }
+        let means = self
+            .species
+            .iter()
+            .filter_map(|species| {
+                let evaluated = species
+                    .members
+                    .iter()
+                    .filter(|id| !self.estimated.contains(id))
+                    .filter_map(|id| member_by_id(&self.members, *id)?.fitness)
+                    .filter(|f| !f.is_nan())
+                    .collect_vec();
+                let mean = evaluated.iter().sum::<f32>() / evaluated.len() as f32;
+                (!evaluated.is_empty()).then_some((species.id, mean))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for index in pending {
+            let id = self.members[index].id;
+            let estimate = self
+                .parent_fitness
+                .get(index)
+                .copied()
+                .filter(|f| !f.is_nan())
+                .or_else(|| means.get(&self.species_of(id)?).copied())
+                .unwrap_or(self.guard.penalty);
+            self.members[index].fitness = Some(estimate);
+            self.estimated.insert(id);
+        }
+        event!(WARN, estimated = self.estimated.len());
+    }
+
+    /// Guard evaluations against NaN/Inf fitness and, optionally, slow
+    /// simulations. The penalty must be finite.
+    pub fn with_evaluation_guard(mut self, guard: EvaluationGuard) -> Self {
+        assert!(guard.penalty.is_finite(), "The evaluation penalty is a fitness");
+        self.guard = guard;
+        self
+    }
+
+    /// Backend of the networks of [`Population::evaluate_compiled`],
+    /// [`Backend::Auto`] by default.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Soft wall-time limit of every evaluation pass, e.g. for interactive
+    /// applications. Running evaluations finish; once `budget` has passed,
+    /// the remaining members get an estimated fitness, see
+    /// [`Population::estimated`], and their children are evaluated first in
+    /// the next generation. Repeated and remote evaluations ignore it.
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Evaluate every member that has no fitness yet, mixing `fitness` with
+    /// games against champions of the hall of fame. `play` scores its first
+    /// argument against the second.
+    pub fn evaluate_against_hall_of_fame(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut fitness: impl FnMut(&Genome) -> f32,
+        mut play: impl FnMut(&Genome, &Genome) -> f32,
+        config: CoEvaluation,
+    ) {
+        let (hall_of_fame, guard) = (self.hall_of_fame.clone().unwrap_or_default(), self.guard);
+        self.evaluate_guarded(|_, genome| {
+            let own = fitness(genome);
+            guard.check(hall_of_fame.co_evaluate(rng, genome, own, config, &mut play))
+        });
+    }
+
+    /// Archive the champion of every generation in `hall_of_fame`.
+    pub fn with_hall_of_fame(mut self, hall_of_fame: HallOfFame) -> Self {
+        self.hall_of_fame = Some(hall_of_fame);
+        self
+    }
+
+    pub fn with_representative_policy(mut self, policy: RepresentativePolicy) -> Self {
+        self.representative_policy = policy;
+        self
+    }
+
+    /// Share out the offspring by `allocation` rather than by species size.
+    pub fn with_offspring_allocation(mut self, allocation: OffspringAllocation) -> Self {
+        self.offspring_allocation = allocation;
+        self
+    }
+
+    /// Give every species `protection` covers at least its minimum of the
+    /// offspring, taken from the species with the most.
+    pub fn with_species_protection(mut self, protection: SpeciesProtection) -> Self {
+        self.species_protection = Some(protection);
+        self
+    }
+
+    /// Breed the species without improvement for `cutoff.patience`
+    /// generations from their fittest members only.
+    pub fn with_stale_cutoff(mut self, cutoff: StaleCutoff) -> Self {
+        self.stale_cutoff = Some(cutoff);
+        self
+    }
+
+    /// Breed every child, and evaluate with [`Population::evaluate_seeded`],
+    /// on its own stream of a run seeded with `seed`, see [`RngStreams`]. The
+    /// rng passed in still makes the population-wide choices, e.g. the
+    /// species representatives.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.streams = Some(RngStreams::new(seed));
+        self
+    }
+
+    pub fn streams(&self) -> Option<RngStreams> {
+        self.streams
+    }
+
+    /// Use `innovation` as registry, e.g. to configure its gene pool. It is
+    /// primed with the members, so it never hands out their numbers again.
+    pub fn with_innovation(mut self, innovation: InnovNumber) -> Self {
+        self.innovation = innovation;
+        self.innovation.prime(self.members.iter().map(|member| &*member.genome));
+        self
+    }
+
+    /// Reporters are called on the thread stepping the population; see
+    /// [`ReporterHub`](super::reporter::ReporterHub) for events of other
+    /// threads.
+    pub fn with_reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.push(Box::new(reporter));
+        self
+    }
+
+    /// Report an [`Event::Saturation`] for every member evaluated on a
+    /// network whose nodes are largely saturated, see
+    /// [`Population::evaluate_network`].
+    pub fn with_saturation_warning(mut self, warning: SaturationWarning) -> Self {
+        self.saturation_warning = Some(warning);
+        self
+    }
+
+    /// Breed on the fitness adjusted by `pipeline`, reporting every stage.
+    /// Elites, archives and reports keep the raw fitness.
+    pub fn with_fitness_pipeline(mut self, pipeline: FitnessPipeline) -> Self {
+        self.fitness_pipeline = Some(pipeline);
+        self
+    }
+
+    /// Report an [`Event::Stagnation`] when `detector` flags a generation,
+    /// and apply its responses to the children. Fresh genomes are minimal
+    /// genomes of `factory` mutated once.
+    pub fn with_stagnation(mut self, detector: StagnationDetector, factory: GenomeFactory) -> Self {
+        self.stagnation = Some(detector);
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Wipe out members as `cataclysm` strikes, refilling the next
+    /// generation from `factory` or the elites. Needs
+    /// [`Population::with_stagnation`] to strike on stagnation.
+    pub fn with_cataclysm(mut self, cataclysm: Cataclysm, factory: GenomeFactory) -> Self {
+        self.cataclysm = Some(cataclysm);
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Report the [`Diversity`] of every generation, measured with `metric`.
+    pub fn with_diversity(mut self, metric: impl DistanceMetric<Genome> + 'static) -> Self {
+        self.diversity_metric = Some(Box::new(metric));
+        self
+    }
+
+    fn report(&mut self, event: Event) {
+        for reporter in self.reporters.iter_mut() {
+            reporter.report(self.generation, &event);
+        }
+    }
+
+    /// Grow or shrink the evaluated generation to `size` members, e.g. as
+    /// a run converges. Every species keeps a share in proportion to its
+    /// size: shrinking drops its least fit members, unevaluated ones first,
+    /// and growing adds mutated clones of its fittest members, left
+    /// unevaluated. Species left without members go extinct. Later
+    /// generations breed `size` children.
+    pub fn resize(&mut self, rng: &mut dyn RngCore, size: usize) {
+        assert!(size > 0, "A population needs members");
+        if size == self.members.len() {
+            return;
+        }
+        let groups = if self.species.is_empty() {
+            vec![self.members.iter().map(|m| m.id).collect_vec()]
+        } else {
+            self.species.iter().map(|s| s.members.clone()).collect_vec()
+        };
+        let sizes = groups.iter().map(|group| group.len() as f64).collect_vec();
+        let quotas = largest_remainder(&sizes, size);
+        let key = |id: &usize| {
+            member_by_id(&self.members, *id)
+                .and_then(|m| m.fitness)
+                .filter(|f| !f.is_nan())
+                .unwrap_or(f32::NEG_INFINITY)
+        };
+        let mut removed = BTreeSet::new();
+        let mut clones = vec![];
+        for (index, (group, quota)) in groups.iter().zip(quotas).enumerate() {
+            let fittest = group
+                .iter()
+                .copied()
+                .sorted_by(|a, b| key(b).total_cmp(&key(a)))
+                .collect_vec();
+            removed.extend(fittest.iter().skip(quota));
+            let missing = quota.saturating_sub(fittest.len());
+            clones.extend(fittest.iter().cycle().take(missing).map(|id| (index, *id)));
+        }
+        // Vectors in the order of the members, unless empty
+        fn keep<T>(vec: &mut Vec<T>, kept: &[bool]) {
+            if vec.len() == kept.len() {
+                let mut kept = kept.iter();
+                vec.retain(|_| *kept.next().unwrap());
+            }
+        }
+        let kept = self.members.iter().map(|m| !removed.contains(&m.id)).collect_vec();
+        keep(&mut self.origins, &kept);
+        keep(&mut self.parent_fitness, &kept);
+        keep(&mut self.lineage, &kept);
+        keep(&mut self.members, &kept);
+        for id in removed.iter() {
+            self.failures.remove(id);
+            self.variances.remove(id);
+            self.estimated.remove(id);
+            self.prioritized.remove(id);
+        }
+        let tracked = (
+            self.parent_fitness.len() == self.members.len(),
+            self.lineage.len() == self.members.len(),
+        );
+        for (index, elite) in clones {
+            let genome = member_by_id(&self.members, elite)
+                .expect("Elites are kept")
+                .to_genome();
+            let genome = self.mutated(genome, rng);
+            self.next_id += 1;
+            self.members.push(EvaluatedIndividual::new(self.next_id, genome));
+            self.origins.push(None);
+            if tracked.0 {
+                self.parent_fitness.push(f32::NAN);
+            }
+            if tracked.1 {
+                self.lineage.push((f32::NAN, vec![]));
+            }
+            if let Some(species) = self.species.get_mut(index) {
+                species.members.push(self.next_id);
+            }
+        }
+        for species in self.species.iter_mut() {
+            species.members.retain(|id| !removed.contains(id));
+        }
+        self.species.retain(|species| !species.members.is_empty());
+        event!(DEBUG, size, removed = removed.len());
+    }
+
+    /// Replace the population with the offspring of each species.
+    pub fn evolve(&mut self, rng: &mut dyn RngCore) {
+        self.evolve_observed(rng, None);
+    }
+
+    /// Like [`Population::evolve`], reporting the parents and mutations of
+    /// every child to `observer`, in the order of the next members.
+    pub fn evolve_observed(
+        &mut self,
+        rng: &mut dyn RngCore,
+        observer: Option<&mut dyn FnMut(Breeding)>,
+    ) {
+        self.breed(rng, observer);
+    }
+
+    /// Like [`Population::evolve`], returning what happened.
+    pub fn step(&mut self, rng: &mut dyn RngCore) -> GenerationReport {
+        let mut report = GenerationReport {
+            generation: self.generation,
+            ..Default::default()
+        };
+        let (offspring, times) = self.breed(rng, Some(&mut |breeding| report.add(&breeding)));
+        report.offspring = offspring;
+        report.times = times;
+        report
+    }
+
+    /// Evolve one generation, returning the children bred by every species
+    /// and the phase times.
+    fn breed(
+        &mut self,
+        rng: &mut dyn RngCore,
+        mut observer: Option<&mut dyn FnMut(Breeding)>,
+    ) -> (BTreeMap<usize, usize>, PhaseTimes) {
+        span!(INFO, "generation", generation = self.generation);
+        event!(
+            INFO,
+            members = self.members.len(),
+            species = self.species.len(),
+            failures = self.failures.len()
+        );
+        if let Some(metric) = self.diversity_metric.as_deref() {
+            let diversity = self.diversity(metric);
+            self.report(Event::Diversity(diversity));
+        }
+        self.algorithm.selection_mut().set_generation(self.generation);
+        if let Some(stats) = self.operator_stats() {
+            self.algorithm.mutation_mut().adapt(&stats);
+            self.report(Event::Operators(stats));
+        }
+        let stagnation = self.observe_stagnation();
+        let responses = stagnation.clone().unwrap_or_default();
+        for response in responses.iter() {
+            if let StagnationResponse::RaiseMutation { factor } = response {
+                self.algorithm.mutation_mut().boost(*factor);
+            }
+        }
+        self.innovation
+            .gene_pool_mut()
+            .update(self.members.iter().map(|m| (&*m.genome, m.fitness())));
+        if let Some(hall_of_fame) = self.hall_of_fame.as_mut() {
+            if let Some(champion) = self
+                .members
+                .iter()
+                .filter(|m| m.fitness.is_some_and(|f| !f.is_nan()))
+                .filter(|m| !self.estimated.contains(&m.id))
+                .max_by(|a, b| a.fitness().total_cmp(&b.fitness()))
+            {
+                hall_of_fame.insert(self.generation, champion);
+            }
+        }
+        let victims = match self.cataclysm {
+            Some(c) if c.strikes(self.generation, stagnation.is_some()) => {
+                c.victims(&self.members).into_iter().collect::<BTreeSet<_>>()
+            }
+            _ => BTreeSet::new(),
+        };
+        if !victims.is_empty() {
+            event!(WARN, victims = victims.len());
+            self.report(Event::Cataclysm {
+                victims: victims.len(),
+            });
+        }
+        self.track_improvement();
+        let adjusted = self.adjusted_members();
+        let members = adjusted.as_deref().unwrap_or(&self.members);
+        let start = Instant::now();
+        let mut offspring = Vec::with_capacity(self.members.len());
+        let mut origins = Vec::with_capacity(self.members.len());
+        let mut counts = BTreeMap::new();
+        let mut lengths = Vec::with_capacity(self.species.len());
+        let mut representatives = Vec::with_capacity(self.species.len());
+        let track = !self.reporters.is_empty() || self.algorithm.mutation().adapts();
+        let observing = track || observer.is_some();
+        let mut bred = vec![];
+        let mut forward = |breeding: Breeding| match observer.as_mut() {
+            Some(observer) if track => {
+                observer(breeding.clone());
+                bred.push(breeding);
+            }
+            Some(observer) => observer(breeding),
+            None => bred.push(breeding),
+        };
+        let sub_pops = self
+            .species
+            .iter()
+            .map(|species| {
+                species
+                    .members
+                    .iter()
+                    .filter(|id| !victims.contains(id))
+                    .map(|id| member_by_id(members, *id).expect("Species members are alive"))
+                    .collect_vec()
+            })
+            .collect_vec();
+        // The cataclysm refills the victims, the species breed the rest
+        let size = self.members.len();
+        let mut quotas = self.offspring_allocation.quotas(&sub_pops, size - victims.len());
+        if let Some(protection) = self.species_protection {
+            let protected = self
+                .species
+                .iter()
+                .zip(sub_pops.iter())
+                .map(|(species, sub_pop)| protection.protects(species.age, sub_pop.len()))
+                .collect_vec();
+            protection.apply(&mut quotas, &protected);
+        }
+        for ((species, sub_pop), quota) in self.species.iter().zip(sub_pops).zip(quotas) {
+            counts.insert(species.id, quota);
+            lengths.push(quota);
+            if sub_pop.is_empty() {
+                representatives.push(None);
+                continue;
+            }
+            representatives.push(self.representative_policy.choose(rng, &sub_pop).cloned());
+            let parents = match self.stale_cutoff {
+                Some(cutoff) => cutoff.parents(species.stale, &sub_pop),
+                None => sub_pop,
+            };
+            let children = match self.streams {
+                None => self.algorithm.reproduce_with_origins(
+                    rng,
+                    &parents,
+                    quota,
+                    &mut self.innovation,
+                    species.id,
+                    observing.then_some(&mut forward as &mut dyn FnMut(Breeding)),
+                ),
+                // Children take the ids after the current ones, in order
+                Some(streams) => (0..quota)
+                    .flat_map(|child| {
+                        let id = self.next_id + 1 + offspring.len() + child;
+                        let mut rng = streams.stream(Purpose::Breeding, self.generation, id);
+                        self.algorithm.reproduce_with_origins(
+                            &mut rng,
+                            &parents,
+                            1,
+                            &mut self.innovation,
+                            species.id,
+                            observing.then_some(&mut forward as &mut dyn FnMut(Breeding)),
+                        )
+                    })
+                    .collect_vec(),
+            };
+            for (child, origin) in children {
+                offspring.push(child);
+                origins.push(Some(origin));
+            }
+        }
+        for (species, representative) in self.species.iter_mut().zip(representatives) {
+            if let Some(representative) = representative {
+                species.representative = representative;
+            }
+        }
+        let mut fresh = self.respond_to_stagnation(rng, &responses, &lengths, &mut offspring);
+        if self.species_protection.is_some_and(|p| p.remove_childless) {
+            let mut lengths = lengths.iter();
+            self.species.retain(|_| lengths.next().is_some_and(|length| *length > 0));
+        }
+        let refill = self.refill(rng, victims.len());
+        fresh.extend(offspring.len()..offspring.len() + refill.len());
+        offspring.extend(refill);
+        // Keep the population size whatever the speciation and responses
+        // did, e.g. when no species is left to breed
+        let filling = self.fill(rng, size.saturating_sub(offspring.len()));
+        fresh.extend(offspring.len()..offspring.len() + filling.len());
+        offspring.extend(filling);
+        offspring.truncate(size);
+        fresh.retain(|index| *index < size);
+        origins.resize(offspring.len(), None);
+        for index in fresh.iter() {
+            origins[*index] = None;
+        }
+        if max_level_denominator(&offspring) > LEVEL_DENOMINATOR_LIMIT {
+            renormalize_levels(&mut offspring);
+        }
+        let reproduction = start.elapsed();
+        self.generation += 1;
+        self.innovation.advance_generation();
+        let start = Instant::now();
+        let mut lineage = bred
+            .into_iter()
+            .map(|breeding| {
+                let parent_fitness = breeding
+                    .parents
+                    .iter()
+                    .map(|id| member_by_id(&self.members, *id).and_then(|m| m.fitness))
+                    .map(|fitness| fitness.unwrap_or(f32::NAN))
+                    .fold(f32::NAN, f32::max);
+                let operators = breeding.operators().map(str::to_string).collect_vec();
+                (parent_fitness, operators)
+            })
+            .collect_vec();
+        if !lineage.is_empty() {
+            lineage.resize(offspring.len(), (f32::NAN, vec![]));
+            for index in fresh {
+                lineage[index] = (f32::NAN, vec![]);
+            }
+        }
+        let parent_fitness = origins
+            .iter()
+            .map(|origin| {
+                origin.map_or(f32::NAN, |origin| {
+                    origin
+                        .parents
+                        .iter()
+                        .filter_map(|id| member_by_id(&self.members, *id)?.fitness)
+                        .fold(f32::NAN, f32::max)
+                })
+            })
+            .collect_vec();
+        let estimated = std::mem::take(&mut self.estimated);
+        self.replace_members(offspring);
+        self.prioritized = self
+            .members
+            .iter()
+            .zip(origins.iter())
+            .filter(|(_, origin)| {
+                origin.is_some_and(|o| o.parents.iter().any(|id| estimated.contains(id)))
+            })
+            .map(|(member, _)| member.id)
+            .collect();
+        self.parent_fitness = parent_fitness;
+        self.lineage = lineage;
+        self.origins = origins;
+        let times = PhaseTimes {
+            reproduction,
+            speciation: start.elapsed(),
+        };
+        (counts, times)
+    }
+
+    /// Responses to the stagnation of the evaluated generation, reported,
+    /// `None` unless it stagnates.
+    fn observe_stagnation(&mut self) -> Option<Vec<StagnationResponse>> {
+        let detector = self.stagnation.as_mut()?;
+        let event = detector.observe(&self.members)?;
+        let responses = detector.responses.clone();
+        event!(
+            WARN,
+            fingerprint = event.fingerprint,
+            generations = event.generations
+        );
+        self.report(Event::Stagnation(event));
+        Some(responses)
+    }
+
+    /// Replace children with fresh genomes as `responses` ask, returning
+    /// their indices. `offspring` holds the children of every species in
+    /// order, `lengths` of them.
+    fn respond_to_stagnation(
+        &mut self,
+        rng: &mut dyn RngCore,
+        responses: &[StagnationResponse],
+        lengths: &[usize],
+        offspring: &mut [Genome],
+    ) -> BTreeSet<usize> {
+        let mut replaced = BTreeSet::new();
+        for response in responses.iter() {
+            match *response {
+                StagnationResponse::RaiseMutation { .. } => {}
+                StagnationResponse::InjectRandom { count } => {
+                    let count = count.min(offspring.len());
+                    replaced.extend(rand::seq::index::sample(rng, offspring.len(), count));
+                }
+                StagnationResponse::RestartWorstSpecies => {
+                    let best = |s: &Species| {
+                        s.members
+                            .iter()
+                            .filter_map(|id| member_by_id(&self.members, *id)?.fitness)
+                            .filter(|f| !f.is_nan())
+                            .fold(f32::NEG_INFINITY, f32::max)
+                    };
+                    let mut start = 0;
+                    let ranges = self.species.iter().zip(lengths).map(|(s, length)| {
+                        start += length;
+                        (best(s), start - length..start)
+                    });
+                    if let Some((_, range)) = ranges.min_by(|a, b| a.0.total_cmp(&b.0)) {
+                        replaced.extend(range);
+                    }
+                }
+            }
+        }
+        for index in replaced.iter() {
+            offspring[*index] = self.fresh_genome(rng);
+        }
+        replaced
+    }
+
+    /// `count` genomes refilling the population after a cataclysm.
+    fn refill(&mut self, rng: &mut dyn RngCore, count: usize) -> Vec<Genome> {
+        let Some(cataclysm) = self.cataclysm.filter(|_| count > 0) else {
+            return vec![];
+        };
+        // Mutated elites refill from the best member at least
+        let elites = cataclysm.with_elites(cataclysm.elites.max(1)).elite_ids(&self.members);
+        (0..count)
+            .map(|_| match cataclysm.refill {
+                Refill::Fresh => self.fresh_genome(rng),
+                Refill::MutatedElites => {
+                    let id = elites.choose(rng).expect("Members are never empty");
+                    let genome = self.genome_by_id(*id).expect("Elites are alive");
+                    self.mutated((*genome).clone(), rng)
+                }
+            })
+            .collect_vec()
+    }
+
+    /// `count` mutated copies of the fittest members, fittest first, filling
+    /// the population up to its size.
+    fn fill(&mut self, rng: &mut dyn RngCore, count: usize) -> Vec<Genome> {
+        if count == 0 {
+            return vec![];
+        }
+        let sources = self
+            .members
+            .iter()
+            .sorted_by(|a, b| b.fitness().total_cmp(&a.fitness()))
+            .map(|m| Arc::clone(&m.genome))
+            .collect_vec();
+        sources
+            .iter()
+            .cycle()
+            .take(count)
+            .map(|genome| self.mutated((**genome).clone(), rng))
+            .collect_vec()
+    }
+
+    /// Minimal genome of the factory, mutated once.
+    fn fresh_genome(&mut self, rng: &mut dyn RngCore) -> Genome {
+        let factory = self.factory.as_ref().expect("Fresh genomes need a factory");
+        self.mutated(factory.generate_genome(), rng)
+    }
+
+    fn mutated(&mut self, mut genome: Genome, rng: &mut dyn RngCore) -> Genome {
+        self.algorithm.mutation().mutate(rng, &mut genome, &mut self.innovation);
+        genome
+    }
+
+    /// Success of the operators that bred the members, `None` unless tracked.
+    fn operator_stats(&self) -> Option<BTreeMap<String, OperatorStats>> {
+        if self.lineage.len() != self.members.len() {
+            return None;
+        }
+        let mut stats = BTreeMap::<_, OperatorStats>::new();
+        for (member, (parent_fitness, operators)) in self.members.iter().zip(self.lineage.iter()) {
+            let improved = member.fitness.is_some_and(|f| f > *parent_fitness);
+            for operator in operators.iter() {
+                let entry = stats.entry(operator.clone()).or_default();
+                entry.children += 1;
+                entry.improved += usize::from(improved);
+            }
+        }
+        (!stats.is_empty()).then_some(stats)
+    }
+
+    /// Members with the fitness of the pipeline, `None` without pipeline.
+    fn adjusted_members(&mut self) -> Option<Vec<EvaluatedIndividual>> {
+        let trace = self.fitness_pipeline.as_ref()?.apply(&self.members, &self.species);
+        for (stage, fitness) in trace.stages.iter() {
+            let fitness = self.members.iter().map(|m| m.id).zip(fitness.iter().copied());
+            let event = Event::Fitness {
+                stage,
+                fitness: fitness.collect_vec(),
+            };
+            self.report(event);
+        }
+        let adjusted = self
+            .members
+            .iter()
+            .zip(trace.adjusted())
+            .map(|(member, fitness)| EvaluatedIndividual {
+                fitness: Some(*fitness),
+                ..member.clone()
+            })
+            .collect_vec();
+        Some(adjusted)
+    }
+
+    fn replace_members(&mut self, genomes: Vec<Genome>) {
+        self.failures.clear();
+        self.lineage.clear();
+        self.variances.clear();
+        self.estimated.clear();
+        self.prioritized.clear();
+        self.parent_fitness.clear();
+        self.members = genomes
+            .into_iter()
+            .map(|genome| {
+                self.next_id += 1;
+                EvaluatedIndividual::new(self.next_id, genome)
+            })
+            .collect_vec();
+        self.origins = vec![None; self.members.len()];
+        self.speciate();
+    }
+
+    /// Update the best fitness of every species with its evaluated members,
+    /// estimated ones aside, and count the generations it did not improve.
+    fn track_improvement(&mut self) {
+        for species in self.species.iter_mut() {
+            let best = species
+                .members
+                .iter()
+                .filter(|id| !self.estimated.contains(id))
+                .filter_map(|id| member_by_id(&self.members, *id)?.fitness)
+                .filter(|f| f.is_finite())
+                .reduce(f32::max);
+            match (best, species.best) {
+                (Some(best), Some(prev)) if best <= prev => species.stale += 1,
+                (Some(best), _) => {
+                    species.best = Some(best);
+                    species.stale = 0;
+                }
+                (None, _) => species.stale += 1,
+            }
+        }
+    }
+
+    fn speciate(&mut self) {
+        phase!("speciation", generation = self.generation);
+        let representatives = self.species.iter().map(|s| &s.representative).collect_vec();
+        let groups = self
+            .algorithm
+            .speciation()
+            .speciate_with(&representatives, self.members.iter());
+        let mut species = Vec::with_capacity(groups.len());
+        for (index, group) in groups.into_iter().enumerate() {
+            let Some(representative) = group.first() else {
+                continue; // Extinct species
+            };
+            let (id, age, best, stale) = match self.species.get(index) {
+                Some(prev) => (prev.id, prev.age + 1, prev.best, prev.stale),
+                None => {
+                    self.next_species_id += 1;
+                    (self.next_species_id, 0, None, 0)
+                }
+            };
+            species.push(Species {
+                id,
+                age,
+                representative: (*representative).clone(),
+                members: group.iter().map(|m| m.id).collect_vec(),
+                best,
+                stale,
+            });
+        }
+        self.species = species;
+        event!(DEBUG, species = self.species.len());
+    }
+
+    /// Overwrite the fitness of member `id`, e.g. when replaying a run.
+    pub(super) fn restore_fitness(&mut self, id: usize, fitness: Option<f32>) -> bool {
+        let Some(member) = self.members.iter_mut().find(|m| m.id == id) else {
+            return false;
+        };
+        member.fitness = fitness;
+        true
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn members(&self) -> &[EvaluatedIndividual] {
+        &self.members
+    }
+
+    /// Members and counters to save, e.g. with the `binary` feature.
+    pub fn archive(&self) -> PopulationArchive {
+        PopulationArchive {
+            generation: self.generation,
+            innovation: self.innovation.last(),
+            genomes: self.members.iter().map(|m| Arc::clone(&m.genome)).collect(),
+            fitness: self.members.iter().map(|m| m.fitness).collect(),
+        }
+    }
+
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+
+    /// Merge the innovation numbers of edges with the same endpoints, among
+    /// the members and the species representatives, see [`InnovationRemap`].
+    /// Genomes count the renumbered members.
+    pub fn merge_innovations(&mut self) -> MergeStats {
+        let remap = InnovationRemap::new(
+            self.members
+                .iter()
+                .chain(self.species.iter().map(|s| &s.representative))
+                .map(|m| &*m.genome),
+        );
+        let mut stats = MergeStats {
+            merged_ids: remap.len(),
+            ..Default::default()
+        };
+        let members = self.members.len();
+        let individuals = self
+            .members
+            .iter_mut()
+            .chain(self.species.iter_mut().map(|s| &mut s.representative));
+        for (index, individual) in individuals.enumerate() {
+            let genome_list = &individual.genome.genome_list;
+            if genome_list.iter().all(|edge| remap.get(edge.innov_number) == edge.innov_number) {
+                continue;
+            }
+            let remapped = remap.apply(Arc::make_mut(&mut individual.genome));
+            if index < members && remapped > 0 {
+                stats.remapped_edges += remapped;
+                stats.genomes += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn innovation(&self) -> &InnovNumber {
+        &self.innovation
+    }
+
+    pub fn hall_of_fame(&self) -> Option<&HallOfFame> {
+        self.hall_of_fame.as_ref()
+    }
+
+    /// Top `k` evaluated members, fittest first. NaN fitness is skipped.
+    pub fn best(&self, k: usize) -> Vec<&EvaluatedIndividual> {
+        self.members
+            .iter()
+            .filter(|m| m.fitness.is_some_and(|f| !f.is_nan()))
+            .sorted_by(|a, b| b.fitness().total_cmp(&a.fitness()))
+            .take(k)
+            .collect_vec()
+    }
+
+    pub fn species_report(&self) -> Vec<SpeciesReport> {
+        self.species
+            .iter()
+            .map(|s| {
+                let fitness = s
+                    .members
+                    .iter()
+                    .filter_map(|id| member_by_id(&self.members, *id)?.fitness)
+                    .filter(|f| !f.is_nan())
+                    .collect_vec();
+                SpeciesReport {
+                    id: s.id,
+                    size: s.members.len(),
+                    best: fitness.iter().copied().reduce(f32::max),
+                    mean: (!fitness.is_empty())
+                        .then(|| fitness.iter().sum::<f32>() / fitness.len() as f32),
+                    age: s.age,
+                    failed: s
+                        .members
+                        .iter()
+                        .filter(|id| self.failures.contains_key(id))
+                        .count(),
+                }
+            })
+            .collect_vec()
+    }
+
+    pub fn diversity(&self, metric: &(impl DistanceMetric<Genome> + ?Sized)) -> Diversity {
+        Diversity::measure(self.members.iter().map(|m| &*m.genome), metric)
+    }
+
+    /// Members of the current generation whose fitness is an estimate, see
+    /// [`Population::with_time_budget`].
+    pub fn estimated(&self) -> &BTreeSet<usize> {
+        &self.estimated
+    }
+
+    /// Failed evaluations of the current generation, by member id.
+    pub fn failures(&self) -> &BTreeMap<usize, EvaluationFailure> {
+        &self.failures
+    }
+
+    /// Fitness variance of member `id` over its samples, if it was evaluated
+    /// repeatedly.
+    pub fn fitness_variance(&self, id: usize) -> Option<f32> {
+        self.variances.get(&id).copied()
+    }
+
+    /// Id of the species the individual `id` belongs to.
+    pub fn species_of(&self, id: usize) -> Option<usize> {
+        self.species
+            .iter()
+            .find(|s| s.members.contains(&id))
+            .map(|s| s.id)
+    }
+
+    /// Parents and species of the member `id`, `None` for members of the
+    /// first generation and fresh genomes.
+    pub fn origin(&self, id: usize) -> Option<Origin> {
+        let index = self.members.binary_search_by_key(&id, |m| m.id).ok()?;
+        self.origins[index]
+    }
+
+    /// Origin of every member, in the order of [`Population::members`].
+    pub fn origins(&self) -> &[Option<Origin>] {
+        &self.origins
+    }
+
+    pub fn genome_by_id(&self, id: usize) -> Option<&Genome> {
+        member_by_id(&self.members, id).map(|m| &*m.genome)
+    }
+}
+
+/// Member of `members` with the id `id`, if still alive. Members are
+/// appended with increasing ids and removals keep their order, so they are
+/// binary searched.
+fn member_by_id(members: &[EvaluatedIndividual], id: usize) -> Option<&EvaluatedIndividual> {
+    members
+        .binary_search_by_key(&id, |m| m.id)
+        .ok()
+        .map(|index| &members[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        crossover::crossover::NeatCrossover,
+        fixtures::NoMutation,
+        individual::genome::{
+            activation::Activation,
+            aggregation::Aggregation,
+            clamp::Clamp,
+            genome::{GenomeBuilder, GenomeEdge, GenomeFactory},
+            node_list::{Config, NodeId},
+        },
+        mutation::mutation::MutationMethod,
+        selection::{
+            minimal_criterion::MinimalCriterionSelection, selection_trait::RoulleteSelection,
+        },
+        speciation::speciation::SpeciationThreshold,
+    };
+
+    fn genomes(weights: &[f32]) -> Vec<Genome> {
+        let factory = GenomeFactory::init(2, 1).unwrap();
+        weights
+            .iter()
+            .map(|w| {
+                let mut genome = factory.generate_genome();
+                genome.genome_list.edge_list.push(GenomeEdge {
+                    innov_number: 0,
+                    in_node: 0,
+                    out_node: 2,
+                    weight: *w,
+                    enabled: true,
+                });
+                genome
+            })
+            .collect_vec()
+    }
+
+    fn population(weights: &[f32]) -> Population<SpeciationThreshold, RoulleteSelection> {
+        population_of(genomes(weights))
+    }
+
+    fn population_of(genomes: Vec<Genome>) -> Population<SpeciationThreshold, RoulleteSelection> {
+        Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            genomes,
+        )
+    }
+
+    fn weight(genome: &Genome) -> f32 {
+        genome.genome_list.edge_list[0].weight
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(32))]
+        #[test]
+        fn test_size_invariance(
+            weights in proptest::collection::vec(-3f32..3., 1..40),
+            threshold in 0.01f32..2.,
+            by_fitness: bool,
+            seed: u64,
+        ) {
+            use crate::mutation::mutation::GaussianMutation;
+
+            let allocation = if by_fitness {
+                OffspringAllocation::AdjustedFitness
+            } else {
+                OffspringAllocation::SpeciesSize
+            };
+            let mut pop = Population::new(
+                GeneticAlgortihm::new(
+                    SpeciationThreshold::new(threshold),
+                    RoulleteSelection::new(),
+                    Box::new(NeatCrossover::default()),
+                    Box::new(GaussianMutation::default()),
+                ),
+                genomes(&weights),
+            )
+            .with_innovation(InnovNumber::resume(10, 0))
+            .with_offspring_allocation(allocation);
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            for _ in 0..4 {
+                pop.evaluate(|g| weight(g).abs());
+                let report = pop.step(&mut rng);
+                proptest::prop_assert_eq!(pop.members().len(), weights.len());
+                proptest::prop_assert_eq!(report.offspring.values().sum::<usize>(), weights.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_without_species() {
+        struct NoSpecies;
+
+        impl SpeciationMethod<EvaluatedIndividual> for NoSpecies {
+            fn speciate_with<'a>(
+                &self,
+                _: &[&EvaluatedIndividual],
+                _: impl Iterator<Item = &'a EvaluatedIndividual>,
+            ) -> Vec<Vec<&'a EvaluatedIndividual>> {
+                vec![]
+            }
+        }
+
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                NoSpecies,
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            genomes(&[1., 3., 2.]),
+        );
+        assert!(pop.species().is_empty());
+        pop.evaluate(weight);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let report = pop.step(&mut rng);
+        assert!(report.offspring.is_empty());
+        let weights = pop.members().iter().map(|m| weight(&m.genome)).collect_vec();
+        assert_eq!(weights, vec![3., 2., 1.]);
+    }
+
+    #[test]
+    fn test_best() {
+        let mut pop = population(&[1., 4., 2., 3.]);
+        assert!(pop.best(2).is_empty());
+        pop.evaluate(weight);
+        let best = pop.best(2);
+        assert_eq!(best.iter().map(|m| m.fitness).collect_vec(), vec![Some(4.), Some(3.)]);
+        assert_eq!(pop.best(10).len(), 4);
+    }
+
+    #[test]
+    fn test_genome_by_id() {
+        let mut pop = population(&[1., 2.]);
+        pop.evaluate(weight);
+        let best = pop.best(1)[0];
+        assert_eq!(weight(pop.genome_by_id(best.id).unwrap()), 2.);
+        assert!(pop.genome_by_id(usize::MAX).is_none());
+    }
+
+    #[test]
+    fn test_hall_of_fame() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut pop = population(&[1., 3., 2.]).with_hall_of_fame(HallOfFame::new());
+        pop.evaluate(weight);
+        pop.evolve(&mut rng);
+        let hof = pop.hall_of_fame().unwrap();
+        assert_eq!(hof.entries().iter().map(|e| e.fitness).collect_vec(), vec![3.]);
+
+        // Only co-evaluation scores count
+        let config = CoEvaluation {
+            opponents: 1,
+            weight: 1.,
+        };
+        pop.evaluate_against_hall_of_fame(&mut rng, |_| 0., |_, o| weight(o), config);
+        assert!(pop.members().iter().all(|m| m.fitness == Some(3.)));
+    }
+
+    #[test]
+    fn test_stable_species_ids() {
+        let policies = [
+            RepresentativePolicy::Random,
+            RepresentativePolicy::Champion,
+            RepresentativePolicy::Medoid,
+        ];
+        for policy in policies {
+            let mut rng = ChaCha8Rng::seed_from_u64(13);
+            let mut pop =
+                population(&[0., 0.5, 1., 10., 10.5, 20.]).with_representative_policy(policy);
+            let ids = pop.species().iter().map(|s| s.id).collect_vec();
+            assert_eq!(ids.len(), 3);
+            for generation in 1..=10 {
+                pop.evaluate(weight);
+                pop.evolve(&mut rng);
+                assert_eq!(pop.species().iter().map(|s| s.id).collect_vec(), ids);
+                assert!(pop.species().iter().all(|s| s.age == generation));
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize() {
+        use crate::mutation::mutation::GaussianMutation;
+
+        let consistent = |pop: &Population<SpeciationThreshold, RoulleteSelection>| {
+            let ids = pop.species().iter().flat_map(|s| s.members.clone()).sorted().collect_vec();
+            ids == pop.members().iter().map(|m| m.id).collect_vec()
+                && pop.origins().len() == pop.members().len()
+        };
+        let new = || {
+            let mut pop = Population::new(
+                GeneticAlgortihm::new(
+                    SpeciationThreshold::new(0.5),
+                    RoulleteSelection::new(),
+                    Box::new(NeatCrossover::default()),
+                    Box::new(GaussianMutation::default()),
+                ),
+                genomes(&[0., 0.5, 1., 10., 10.5, 11., 11.5, 12.]),
+            )
+            .with_innovation(InnovNumber::resume(10, 0));
+            pop.evaluate(weight);
+            assert_eq!(pop.species().len(), 2);
+            pop
+        };
+        let weights = |pop: &Population<_, _>| {
+            pop.members().iter().map(|m| weight(&m.genome)).collect_vec()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        // The worst of every species go
+        let mut pop = new();
+        pop.resize(&mut rng, 4);
+        assert_eq!(weights(&pop), vec![0.5, 1., 11.5, 12.]);
+        assert!(consistent(&pop));
+        pop.evolve(&mut rng);
+        assert_eq!(pop.members().len(), 4);
+
+        // Mutated clones of the best of every species come
+        let mut pop = new();
+        pop.resize(&mut rng, 12);
+        assert_eq!(pop.members().len(), 12);
+        assert!(consistent(&pop));
+        assert_eq!(
+            pop.species().iter().map(|s| s.members.len()).collect_vec(),
+            vec![5, 7]
+        );
+        assert_eq!(pop.members().iter().filter(|m| m.fitness.is_none()).count(), 4);
+        pop.evaluate(weight);
+        pop.evolve(&mut rng);
+        assert_eq!(pop.members().len(), 12);
+
+        // Too small for every species
+        let mut pop = new();
+        pop.resize(&mut rng, 1);
+        assert_eq!(weights(&pop), vec![12.]);
+        assert_eq!(pop.species().len(), 1);
+        assert!(consistent(&pop));
+    }
+
+    #[test]
+    fn test_nan_fitness() {
+        use crate::mutation::mutation::GaussianMutation;
+
+        let finite = |pop: &Population<SpeciationThreshold, RoulleteSelection>| {
+            pop.members().iter().all(|m| m.fitness().is_finite())
+        };
+        let guard = EvaluationGuard {
+            penalty: -5.,
+            ..Default::default()
+        };
+        // Half the members, and then the whole population, evaluate to NaN
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(GaussianMutation::default()),
+            ),
+            genomes(&[0., 1., 2., 10., 11., 12.]),
+        )
+        .with_innovation(InnovNumber::resume(10, 0))
+        .with_seed(2)
+        .with_evaluation_guard(guard)
+        .with_offspring_allocation(OffspringAllocation::AdjustedFitness);
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for generation in 0..6 {
+            pop.evaluate_seeded(|g, rng| {
+                if generation == 3 || rng.next_u32() % 2 == 0 {
+                    f32::NAN
+                } else {
+                    weight(g)
+                }
+            });
+            assert!(finite(&pop));
+            assert!(pop
+                .failures()
+                .values()
+                .all(|failure| *failure == EvaluationFailure::NonFinite));
+            if generation == 3 {
+                assert!(pop.members().iter().all(|m| m.fitness() == -5.));
+            }
+            pop.evolve(&mut rng);
+            assert_eq!(pop.members().len(), 6);
+        }
+
+        // Finite samples aggregating to an infinite fitness fail too
+        let mut pop = population(&[0., 1.]).with_evaluation_guard(guard);
+        let config = RepeatedEvaluation {
+            repeats: 2,
+            ..Default::default()
+        };
+        pop.evaluate_repeated(|g| if weight(g) > 0. { f32::MAX } else { 1. }, config);
+        assert_eq!(
+            pop.members().iter().map(|m| m.fitness).collect_vec(),
+            vec![Some(1.), Some(-5.)]
+        );
+        assert_eq!(pop.failures().values().collect_vec(), vec![&EvaluationFailure::NonFinite]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The evaluation penalty is a fitness")]
+    fn test_nan_penalty() {
+        let guard = EvaluationGuard {
+            penalty: f32::NAN,
+            ..Default::default()
+        };
+        let _ = population(&[0.]).with_evaluation_guard(guard);
+    }
+
+    #[test]
+    fn test_evaluation_guard() {
+        let guard = EvaluationGuard {
+            timeout: Some(std::time::Duration::from_millis(50)),
+            penalty: -1.,
+        };
+        let mut pop = population(&[0., 0.5, 10., 10.5, 11.]).with_evaluation_guard(guard);
+        pop.evaluate(|g| if weight(g) == 0.5 { f32::NAN } else { weight(g) });
+        assert_eq!(
+            pop.members().iter().map(|m| m.fitness).collect_vec(),
+            vec![Some(0.), Some(-1.), Some(10.), Some(10.5), Some(11.)]
+        );
+        assert_eq!(pop.failures().values().collect_vec(), vec![&EvaluationFailure::NonFinite]);
+        let report = pop.species_report();
+        assert_eq!(report.iter().map(|r| r.failed).collect_vec(), vec![1, 0]);
+
+        let mut pop = population(&[0., 1.]).with_evaluation_guard(guard);
+        pop.evaluate_with_timeout(|g| {
+            if weight(g) > 0. {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+            weight(g)
+        });
+        assert_eq!(
+            pop.members().iter().map(|m| m.fitness).collect_vec(),
+            vec![Some(0.), Some(-1.)]
+        );
+        assert_eq!(pop.failures().values().collect_vec(), vec![&EvaluationFailure::Timeout]);
+
+        // Unclamped identity outputs overflow for large weights. `min` hides
+        // the infinite output from the fitness, not from the network.
+        let mut unclamped = genomes(&[1., f32::MAX]);
+        for genome in unclamped.iter_mut() {
+            genome.node_list.output[0].config = Config {
+                activation: Activation::Identity,
+                clamp: Clamp::new(None, None).unwrap(),
+                ..Default::default()
+            };
+        }
+        let mut pop = population_of(unclamped).with_evaluation_guard(guard);
+        pop.evaluate_network(|network| network.forward(&[f32::MAX, 0.]).unwrap()[0].min(1.));
+        assert_eq!(
+            pop.members().iter().map(|m| m.fitness).collect_vec(),
+            vec![Some(1.), Some(-1.)]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with_dropout() {
+        // The output survives dropout if any of its hidden paths does
+        let paths = |count: usize| {
+            (0..count)
+                .fold(GenomeBuilder::new().input(1).output(1), |builder, i| {
+                    let node = 2 + i as NodeId;
+                    builder.hidden(50).connect(0, node, 1.).connect(node, 1, 1.)
+                })
+                .aggregation(1, Aggregation::Sum)
+                .build()
+                .unwrap()
+        };
+        let fitness = |network: &mut FFNetwork| network.forward(&[1.]).unwrap()[0].min(1.);
+        let mut rng = ChaCha8Rng::seed_from_u64(17);
+
+        let mut pop = population_of(vec![paths(1), paths(3)]);
+        pop.evaluate_with_dropout(&mut rng, fitness, Dropout { rate: 0., trials: 3 });
+        assert!(pop.members().iter().all(|m| m.fitness == Some(1.)));
+
+        let mut pop = population_of(vec![paths(1), paths(3)]);
+        let dropout = Dropout {
+            rate: 0.5,
+            trials: 200,
+        };
+        pop.evaluate_with_dropout(&mut rng, fitness, dropout);
+        let fitness = pop.members().iter().map(|m| m.fitness.unwrap()).collect_vec();
+        assert!(fitness[0] < 0.7 && fitness[1] > 0.8);
+    }
+
+    #[test]
+    fn test_evaluate_compiled() {
+        let score = |output: f32| output.min(1.);
+        let mut graph = population(&[0., 2., 10.]);
+        graph.evaluate_network(|network| score(network.forward(&[1., 0.]).unwrap()[0]));
+        let mut sparse = population(&[0., 2., 10.]).with_backend(Backend::Sparse);
+        sparse.evaluate_compiled(|network| {
+            assert!(matches!(network, Network::Sparse(_)));
+            score(network.forward(&[1., 0.]).unwrap()[0])
+        });
+        let fitness = |pop: &Population<_, _>| {
+            pop.members().iter().map(|m| m.fitness).collect_vec()
+        };
+        assert_eq!(fitness(&sparse), fitness(&graph));
+    }
+
+    #[test]
+    fn test_evaluate_with_local_search() {
+        let mut rng = ChaCha8Rng::seed_from_u64(19);
+        let fitness = |g: &Genome| -(weight(g) - 2.).powi(2);
+        for inheritance in [Inheritance::Baldwinian, Inheritance::Lamarckian] {
+            let mut pop = population(&[0., 0.5]);
+            let search = LocalSearch {
+                steps: 50,
+                step_size: 0.3,
+                inheritance,
+                ..Default::default()
+            };
+            pop.evaluate_with_local_search(&mut rng, fitness, search);
+            assert!(pop.members().iter().all(|m| m.fitness.unwrap() > -0.01));
+            let weights = pop.members().iter().map(|m| weight(&m.genome)).collect_vec();
+            match inheritance {
+                Inheritance::Baldwinian => assert_eq!(weights, vec![0., 0.5]),
+                Inheritance::Lamarckian => assert!(weights.iter().all(|w| (w - 2.).abs() < 0.1)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_refine_weights() {
+        let mut rng = ChaCha8Rng::seed_from_u64(19);
+        let fitness = |g: &Genome| -(weight(g) - 2.).powi(2);
+        let mut pop = population(&[0., 0.5, 1.]);
+        pop.evaluate(fitness);
+        let refinement = WeightRefinement {
+            interval: 2,
+            elites: 2,
+            ..Default::default()
+        };
+        let ids = pop.members().iter().map(|m| m.id).collect_vec();
+        assert_eq!(pop.refine_weights(&mut rng, fitness, refinement), ids[1..]);
+        let weights = pop.members().iter().map(|m| weight(&m.genome)).collect_vec();
+        assert_eq!(weights[0], 0.);
+        assert!(weights[1..].iter().all(|w| (w - 2.).abs() < 0.05));
+        assert!(pop.members()[1..].iter().all(|m| m.fitness.unwrap() > -0.01));
+
+        pop.evolve(&mut rng);
+        pop.evaluate(fitness);
+        assert!(pop.refine_weights(&mut rng, fitness, refinement).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_repeated() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use rand::Rng;
+
+        use crate::population::evaluation::{FitnessAggregate, Racing};
+
+        // Noise of ±0.5 around the weight, NaN for a negative weight
+        let mut rng = ChaCha8Rng::seed_from_u64(23);
+        let mut fitness = |g: &Genome| match weight(g) {
+            w if w < 0. => f32::NAN,
+            w => w + rng.gen_range(-0.5..0.5),
+        };
+        let config = RepeatedEvaluation {
+            repeats: 20,
+            ..Default::default()
+        };
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let mut pop = population(&[0., 5., -1.])
+            .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        assert_eq!(pop.evaluate_repeated(&mut fitness, config), 41);
+        let members = pop.members().iter().map(|m| (m.id, m.fitness.unwrap())).collect_vec();
+        assert!((members[0].1 - 0.).abs() < 0.3 && (members[1].1 - 5.).abs() < 0.3);
+        assert_eq!(members[2].1, pop.guard.penalty);
+        assert!(pop.fitness_variance(members[0].0).unwrap() > 0.01);
+        assert_eq!(pop.fitness_variance(members[2].0), None);
+        let reported = members[..2]
+            .iter()
+            .map(|(id, _)| (*id, pop.fitness_variance(*id).unwrap()))
+            .collect_vec();
+        assert_eq!(events.borrow()[..], [Event::FitnessVariance(reported)]);
+
+        // Distinct genomes settle after the minimum, close ones keep racing
+        let racing = RepeatedEvaluation {
+            repeats: 20,
+            aggregate: FitnessAggregate::Median,
+            racing: Some(Racing {
+                min_repeats: 5,
+                z: 3.,
+            }),
+        };
+        let mut pop = population(&[0., 5., 10.]);
+        assert_eq!(pop.evaluate_repeated(&mut fitness, racing), 15);
+        let mut pop = population(&[0., 0., 10.]);
+        let evaluations = pop.evaluate_repeated(&mut fitness, racing);
+        assert!(evaluations > 25 && evaluations <= 45, "{evaluations}");
+        pop.evolve(&mut rng.clone());
+        assert!(pop.members().iter().all(|m| pop.fitness_variance(m.id).is_none()));
+    }
+
+    #[test]
+    fn test_shared_genomes() {
+        let mut pop = population(&[0., 10.]).with_hall_of_fame(HallOfFame::new());
+        // Representatives share the genome of their member
+        for species in pop.species() {
+            let representative = &species.representative.genome;
+            assert!(pop.members().iter().any(|m| Arc::ptr_eq(&m.genome, representative)));
+        }
+        pop.evaluate(weight);
+        let champion = Arc::clone(&pop.best(1)[0].genome);
+        pop.evolve(&mut ChaCha8Rng::seed_from_u64(0));
+        let entry = &pop.hall_of_fame().unwrap().entries()[0];
+        assert!(Arc::ptr_eq(&entry.genome, &champion));
+    }
+
+    #[test]
+    fn test_species_report() {
+        // Weights within 1.25 of each other share a species at threshold 0.5.
+        let mut pop = population(&[0., 0.5, 10., 10.5, 11.]);
+        let report = pop.species_report();
+        assert_eq!(report.iter().map(|r| r.size).collect_vec(), vec![2, 3]);
+        assert!(report.iter().all(|r| r.best.is_none() && r.age == 0));
+
+        pop.evaluate(weight);
+        let report = pop.species_report();
+        assert_eq!(report[0].best, Some(0.5));
+        assert_eq!(report[1].mean, Some(10.5));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        pop.evolve(&mut rng);
+        let next = pop.species_report();
+        assert_eq!(pop.generation(), 1);
+        assert_eq!(next.iter().map(|r| r.size).sum::<usize>(), 5);
+        assert_eq!(
+            next.iter().map(|r| (r.id, r.age)).collect_vec(),
+            report.iter().map(|r| (r.id, r.age + 1)).collect_vec()
+        );
+        assert!(pop.members().iter().all(|m| pop.species_of(m.id).is_some()));
+    }
+
+    #[test]
+    fn test_diversity_report() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use approx::assert_relative_eq;
+
+        use crate::speciation::distance::GraphEditDistance;
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let metric = GraphEditDistance {
+            weight_cost: 1.,
+            ..Default::default()
+        };
+        let mut pop = population(&[0., 0., 1.])
+            .with_diversity(metric)
+            .with_reporter(move |generation, event: &Event| {
+                sink.borrow_mut().push((generation, event.clone()))
+            });
+        pop.evaluate(weight);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        pop.evolve(&mut rng);
+        pop.evaluate(weight);
+        pop.evolve(&mut rng);
+        let events = events.borrow();
+        assert_eq!(events.iter().map(|(g, _)| *g).collect_vec(), vec![0, 1]);
+        let Event::Diversity(first) = events[0].1 else {
+            panic!("Expected a diversity report");
+        };
+        assert_relative_eq!(first.mean_distance, 2. / 3.);
+        assert_eq!(first.unique_structures, 1);
+        assert_eq!(first.innovation_entropy, 0.);
+    }
+
+    #[test]
+    fn test_fitness_pipeline() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::population::fitness::FitnessStage;
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let pipeline = FitnessPipeline::new()
+            .with_stage(FitnessStage::Sharing)
+            .with_stage(FitnessStage::Scaling);
+        let mut pop = population(&[1., 2., 10.])
+            .with_fitness_pipeline(pipeline)
+            .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        pop.evaluate(weight);
+        let ids = pop.members().iter().map(|m| m.id).collect_vec();
+        pop.evolve(&mut ChaCha8Rng::seed_from_u64(0));
+        let events = events.borrow();
+        let stages = events
+            .iter()
+            .map(|event| match event {
+                Event::Fitness { stage, fitness } => (*stage, fitness.clone()),
+                _ => panic!("Expected fitness stages only"),
+            })
+            .collect_vec();
+        // The first two members share a species
+        assert_eq!(stages[0], ("raw", ids.iter().copied().zip([1., 2., 10.]).collect_vec()));
+        assert_eq!(stages[1].1.iter().map(|(_, f)| *f).collect_vec(), vec![0.5, 1., 10.]);
+        assert_eq!(stages[2].1.iter().map(|(_, f)| *f).collect_vec(), vec![0., 0.5 / 9.5, 1.]);
+    }
+
+    #[test]
+    fn test_evaluate_network_behavior() {
+        let mut pop = population(&[0., 2., 10.]);
+        pop.evaluate_network_behavior(|network| {
+            let output = network.forward(&[1., 0.]).unwrap()[0];
+            (output, (output > 0.5).then(|| vec![output, 1.]))
+        });
+        assert!(pop.members().iter().all(|m| m.fitness.is_some()));
+        let behaviors = pop.members().iter().map(|m| m.behavior.clone()).collect_vec();
+        assert_eq!(behaviors[0], None);
+        assert!(behaviors[1..].iter().all(|b| b.as_ref().is_some_and(|b| b[1] == 1.)));
+    }
+
+    #[test]
+    fn test_saturation_warning() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::individual::genome::network::saturation::SaturationWarning;
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let mut identity = genomes(&[1., 100.]);
+        for genome in identity.iter_mut() {
+            genome.node_list.output[0].config.activation = Activation::Identity;
+        }
+        let mut pop = population_of(identity)
+            .with_saturation_warning(SaturationWarning::default())
+            .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        pop.evaluate_network(|network| network.forward(&[1., 0.]).unwrap()[0]);
+        let saturated = pop.members()[1].id;
+        assert_eq!(pop.members()[1].fitness, Some(5.));
+        assert_eq!(
+            *events.borrow(),
+            vec![Event::Saturation {
+                id: saturated,
+                saturated: 1,
+                nodes: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_remote() {
+        use std::thread;
+
+        use crate::population::remote::{channel, JobResult};
+
+        let mut pop = population(&[1., 2., f32::NAN, 4.]);
+        let straggler = pop.members()[3].id;
+        let (mut transport, worker) = channel();
+        // A late result of an earlier generation
+        worker
+            .send(JobResult {
+                generation: 99,
+                id: straggler,
+                fitness: Some(0.),
+            })
+            .unwrap();
+        let handle = thread::spawn(move || {
+            while let Some(job) = worker.next_job() {
+                if job.id != straggler {
+                    worker.send(job.result(Some(weight(&job.genome)))).unwrap();
+                }
+            }
+        });
+        let report = pop.evaluate_remote(&mut transport, Duration::from_millis(200)).unwrap();
+        assert_eq!(
+            report,
+            RemoteReport {
+                received: 3,
+                stragglers: 1,
+                stale: 1,
+            }
+        );
+        let fitness = pop.members().iter().map(|m| m.fitness).collect_vec();
+        assert_eq!(fitness[..2], [Some(1.), Some(2.)]);
+        assert_eq!(
+            pop.failures().values().collect_vec(),
+            vec![&EvaluationFailure::NonFinite, &EvaluationFailure::Timeout]
+        );
+        drop(transport);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_step() {
+        let genomes = genomes(&[0., 0.5, 10., 10.5, 11.]);
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new().with_no_self_mating(true),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            genomes,
+        );
+        pop.evaluate(weight);
+        let species = pop.species().iter().map(|s| s.id).collect_vec();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let report = pop.step(&mut rng);
+        assert_eq!(report.generation, 0);
+        assert_eq!(pop.generation(), 1);
+        assert_eq!(report.offspring, BTreeMap::from([(species[0], 2), (species[1], 3)]));
+        assert_eq!(report.crossovers, 5);
+        assert_eq!(report.mutations, Default::default());
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut pop = population(&[0., 0.5, 10., 10.5, 11.])
+            .with_time_budget(Duration::from_millis(30));
+        pop.evaluate(|g| {
+            if weight(g) == 10. {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            weight(g)
+        });
+        // The last two are estimated by the mean of their evaluated species mate
+        let fitness = pop.members().iter().map(|m| m.fitness).collect_vec();
+        assert_eq!(fitness, vec![Some(0.), Some(0.5), Some(10.), Some(10.), Some(10.)]);
+        let estimated = pop.estimated().clone();
+        assert_eq!(estimated, BTreeSet::from([4, 5]));
+
+        // Children of estimated parents go first
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        pop.evolve(&mut rng);
+        let prioritized = pop
+            .members()
+            .iter()
+            .filter(|m| {
+                let origin = pop.origin(m.id).unwrap();
+                origin.parents.iter().any(|id| estimated.contains(id))
+            })
+            .map(|m| Arc::as_ptr(&m.genome))
+            .collect::<BTreeSet<_>>();
+        assert!(!prioritized.is_empty());
+        let mut pop = pop.with_time_budget(Duration::from_secs(10));
+        let mut order = vec![];
+        pop.evaluate(|g| {
+            order.push(prioritized.contains(&(g as *const Genome)));
+            weight(g)
+        });
+        assert!(order.iter().is_sorted_by_key(|first| !first));
+        assert!(pop.estimated().is_empty());
+
+        // Out of time right away, members take the fitness of their fitter parent
+        let parents = pop.members().iter().map(|m| (m.id, m.fitness())).collect::<BTreeMap<_, _>>();
+        pop.evolve(&mut rng);
+        let mut pop = pop.with_time_budget(Duration::ZERO);
+        pop.evaluate(|_| unreachable!("No time to evaluate"));
+        assert_eq!(pop.estimated().len(), 5);
+        for member in pop.members() {
+            let [fst, snd] = pop.origin(member.id).unwrap().parents;
+            assert_eq!(member.fitness, Some(parents[&fst].max(parents[&snd])));
+        }
+    }
+
+    #[test]
+    fn test_origins() {
+        let mut pop = population(&[0., 0.5, 10., 10.5, 11.]);
+        assert!(pop.origins().iter().all(Option::is_none));
+        pop.evaluate(weight);
+        let species = pop
+            .species()
+            .iter()
+            .map(|s| (s.id, s.members.clone()))
+            .collect::<BTreeMap<_, _>>();
+        pop.evolve(&mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!(pop.origins().len(), 5);
+        for member in pop.members() {
+            let origin = pop.origin(member.id).unwrap();
+            assert!(origin
+                .parents
+                .iter()
+                .all(|parent| species[&origin.species].contains(parent)));
+        }
+        let bred = |id| {
+            pop.origins()
+                .iter()
+                .filter(|o| o.unwrap().species == id)
+                .count()
+        };
+        assert!(species.iter().all(|(id, members)| bred(*id) == members.len()));
+        assert_eq!(pop.origin(0), None);
+    }
+
+    #[test]
+    fn test_stale_cutoff() {
+        use crate::population::species::StaleParents;
+
+        let mut pop = population(&[0., 0.5, 10., 10.5, 11.])
+            .with_stale_cutoff(StaleCutoff::new(1, StaleParents::Champion));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        pop.evaluate(|_| 1.);
+        pop.evolve(&mut rng);
+        assert!(pop.species().iter().all(|s| s.stale == 0));
+        // Persisting species do not improve on 1, ties keep the first member
+        let champions = pop
+            .species()
+            .iter()
+            .filter(|s| s.best.is_some())
+            .map(|s| (s.id, s.members[0]))
+            .collect::<BTreeMap<_, _>>();
+        assert!(!champions.is_empty());
+        pop.evaluate(|_| 1.);
+        pop.evolve(&mut rng);
+        let mut bred = 0;
+        for origin in pop.origins().iter().flatten() {
+            if let Some(champion) = champions.get(&origin.species) {
+                assert_eq!(origin.parents, [*champion; 2]);
+                bred += 1;
+            }
+        }
+        assert!(bred > 0);
+        assert!(pop
+            .species()
+            .iter()
+            .filter(|s| champions.contains_key(&s.id))
+            .all(|s| s.stale == 1 && s.best == Some(1.)));
+    }
+
+    #[test]
+    fn test_species_protection() {
+        // A species of one unfit member and a dominant one
+        let run = |protection: Option<SpeciesProtection>| {
+            let mut pop = population(&[0., 10., 10.2, 10.4, 10.6, 10.8])
+                .with_offspring_allocation(OffspringAllocation::AdjustedFitness);
+            if let Some(protection) = protection {
+                pop = pop.with_species_protection(protection);
+            }
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+            pop.evaluate(weight);
+            let ids = pop.species().iter().map(|s| s.id).collect_vec();
+            assert_eq!(ids.len(), 2);
+            let offspring = pop.step(&mut rng).offspring;
+            pop.evaluate(weight);
+            (ids, offspring, pop.step(&mut rng).offspring)
+        };
+        let (ids, offspring, _) = run(None);
+        assert_eq!(offspring, BTreeMap::from([(ids[0], 0), (ids[1], 6)]));
+        let (ids, offspring, next) = run(Some(SpeciesProtection::new(2)));
+        assert_eq!(offspring, BTreeMap::from([(ids[0], 2), (ids[1], 4)]));
+        assert_eq!(next, offspring);
+        // Only while young
+        let (ids, offspring, next) = run(Some(SpeciesProtection::new(2).with_max_age(0)));
+        assert_eq!(offspring[&ids[0]], 2);
+        assert_eq!(next, BTreeMap::from([(ids[0], 0), (ids[1], 6)]));
+    }
+
+    #[test]
+    fn test_remove_childless() {
+        // The children of the dominant species are close enough to the
+        // representative of the unfit one to join it
+        let run = |remove_childless: bool| {
+            let mut pop = population(&[0., 2., 4., 4.])
+                .with_offspring_allocation(OffspringAllocation::AdjustedFitness)
+                .with_representative_policy(RepresentativePolicy::Champion)
+                .with_species_protection(
+                    SpeciesProtection::new(0).with_remove_childless(remove_childless),
+                );
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+            pop.evaluate(|g| if weight(g) > 3. { weight(g) } else { 0. });
+            let ids = pop.species().iter().map(|s| s.id).collect_vec();
+            assert_eq!(ids.len(), 2);
+            pop.evolve(&mut rng);
+            (ids, pop.species().iter().map(|s| s.id).collect_vec())
+        };
+        let (ids, kept) = run(false);
+        assert_eq!(kept, vec![ids[0]]);
+        let (ids, removed) = run(true);
+        assert_eq!(removed, vec![ids[1]]);
+    }
+
+    fn seeded_run(seed: u64, evaluate: impl Fn(&mut SeededPopulation)) -> Vec<(Genome, f32)> {
+        use crate::mutation::mutation::GaussianMutation;
+
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(GaussianMutation::default()),
+            ),
+            genomes(&[0., 0.5, 1., 2., 2.5, 4., 5., 7.]),
+        )
+        .with_innovation(InnovNumber::resume(10, 0))
+        .with_seed(seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..4 {
+            evaluate(&mut pop);
+            pop.evolve(&mut rng);
+        }
+        evaluate(&mut pop);
+        pop.members()
+            .iter()
+            .map(|m| ((*m.genome).clone(), m.fitness()))
+            .collect()
+    }
+
+    type SeededPopulation = Population<SpeciationThreshold, RoulleteSelection>;
+
+    fn noisy(genome: &Genome, rng: &mut dyn RngCore) -> f32 {
+        use rand::Rng;
+
+        weight(genome).abs() + rng.gen::<f32>()
+    }
+
+    #[test]
+    fn test_evaluate_seeded() {
+        let mut pop = population(&[0., 0.5, 10.]).with_seed(3);
+        pop.evaluate_seeded(noisy);
+        let streams = pop.streams().unwrap();
+        for member in pop.members() {
+            let mut rng = streams.stream(Purpose::Evaluation, 0, member.id);
+            assert_eq!(member.fitness(), noisy(&member.genome, &mut rng));
+        }
+        let run = seeded_run(1, |pop| pop.evaluate_seeded(noisy));
+        assert_eq!(run, seeded_run(1, |pop| pop.evaluate_seeded(noisy)));
+        assert_ne!(run, seeded_run(2, |pop| pop.evaluate_seeded(noisy)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_evaluate_par() {
+        let sequential = seeded_run(5, |pop| pop.evaluate_seeded(noisy));
+        for threads in [1, 4] {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let parallel = pool.install(|| seeded_run(5, |pop| pop.evaluate_par(noisy)));
+            assert_eq!(parallel, sequential);
+        }
+
+        // Every member is evaluated, whatever the time budget
+        let mut pop = population(&[0., 0.5, 10.])
+            .with_seed(5)
+            .with_time_budget(Duration::ZERO);
+        pop.evaluate_par(|genome, _| weight(genome));
+        assert!(pop.estimated().is_empty());
+        assert!(pop.members().iter().all(|m| m.fitness == Some(weight(&m.genome))));
+    }
+
+    #[test]
+    fn test_operator_stats() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::mutation::operator::{MutationOperator, MutationPipeline};
+
+        /// Raise every weight by 1.
+        struct Raise;
+
+        impl MutationOperator for Raise {
+            fn apply(&self, _: &mut dyn RngCore, genome: &mut Genome, _: &mut InnovNumber) -> bool {
+                genome.genome_list.edge_list.iter_mut().for_each(|e| e.weight += 1.);
+                true
+            }
+        }
+
+        struct Unchanged;
+
+        impl MutationOperator for Unchanged {
+            fn apply(&self, _: &mut dyn RngCore, _: &mut Genome, _: &mut InnovNumber) -> bool {
+                false
+            }
+        }
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let pipeline = MutationPipeline::new()
+            .with_operator(1., Raise)
+            .with_operator(1., Unchanged);
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(pipeline),
+            ),
+            genomes(&[1., 1., 1.]),
+        )
+        .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        pop.evaluate(weight);
+        let report = pop.step(&mut rng);
+        assert_eq!(report.operators, BTreeMap::from([("Raise".to_string(), 3)]));
+        assert!(events.borrow().is_empty());
+
+        // Raised children are fitter than their parents, but for the first
+        let mut evaluated = 0;
+        pop.evaluate(|genome| {
+            evaluated += 1;
+            if evaluated == 1 {
+                0.
+            } else {
+                weight(genome)
+            }
+        });
+        pop.evolve(&mut rng);
+        let expected = OperatorStats {
+            children: 3,
+            improved: 2,
+        };
+        assert_eq!(
+            *events.borrow(),
+            vec![Event::Operators(BTreeMap::from([("Raise".to_string(), expected)]))]
+        );
+        assert_eq!(expected.success_rate(), 2. / 3.);
+    }
+
+    #[test]
+    fn test_archive() {
+        let mut pop = population(&[1., 2.]).with_innovation(InnovNumber::resume(7, 0));
+        pop.evaluate(weight);
+        pop.evolve(&mut ChaCha8Rng::seed_from_u64(0));
+        let archive = pop.archive();
+        assert_eq!((archive.generation, archive.innovation), (1, 7));
+        assert_eq!(archive.fitness, vec![None, None]);
+        assert_eq!(archive.innovation_number().last(), 7);
+        let resumed = population_of(archive.into_genomes());
+        assert_eq!(
+            resumed.members().iter().map(|m| weight(&m.genome)).collect_vec(),
+            pop.members().iter().map(|m| weight(&m.genome)).collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_merge_innovations() {
+        let mut genomes = genomes(&[1., 2., 3.]);
+        genomes[1].genome_list.edge_list[0].innov_number = 4;
+        genomes[2].genome_list.edge_list[0].innov_number = 6;
+        let mut pop = population_of(genomes);
+        let stats = pop.merge_innovations();
+        assert_eq!(
+            stats,
+            MergeStats {
+                merged_ids: 2,
+                remapped_edges: 2,
+                genomes: 2,
+            }
+        );
+        assert!(pop.members().iter().all(|m| m.genome.genome_list.edge_list[0].innov_number == 0));
+        assert_eq!(pop.merge_innovations(), MergeStats::default());
+    }
+
+    #[test]
+    fn test_cataclysm() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::population::cataclysm::{Cataclysm, Refill};
+
+        let weights = |pop: &Population<_, _>| {
+            pop.members()
+                .iter()
+                .map(|m| m.genome.genome_list.edge_list.first().map(|e| e.weight))
+                .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+                .collect_vec()
+        };
+        let fitness = |genome: &Genome| genome.genome_list.iter().map(|e| e.weight).sum();
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let factory = GenomeFactory::init(2, 1).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut pop = population(&[1., 2., 3., 4.])
+            .with_cataclysm(Cataclysm::every(2), factory.clone())
+            .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        pop.evaluate(fitness);
+        pop.evolve(&mut rng);
+        assert!(weights(&pop).iter().all(Option::is_some));
+        pop.evaluate(fitness);
+        pop.evolve(&mut rng);
+        assert!(events.borrow().is_empty());
+        pop.evaluate(fitness);
+        let best = pop.best(2).iter().map(|m| m.fitness).collect_vec();
+        pop.evolve(&mut rng);
+        assert!(matches!(events.borrow()[..], [Event::Cataclysm { victims: 2 }]));
+        let next = weights(&pop);
+        assert_eq!(next[..2], [None, None]);
+        assert!(next[2..].iter().all(|w| best.contains(w)));
+
+        let cataclysm = Cataclysm::every(1)
+            .with_fraction(0.75)
+            .with_refill(Refill::MutatedElites);
+        let mut pop = population(&[1., 2., 3., 4.]).with_cataclysm(cataclysm, factory);
+        pop.evaluate(fitness);
+        pop.evolve(&mut rng);
+        pop.evaluate(fitness);
+        let best = pop.best(1)[0].fitness;
+        pop.evolve(&mut rng);
+        assert!(weights(&pop).iter().all(|w| *w == best));
+    }
+
+    #[test]
+    fn test_stagnation() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let edges = |pop: &Population<_, _>| {
+            pop.members()
+                .iter()
+                .map(|m| m.genome.genome_list.edge_list.len())
+                .collect_vec()
+        };
+        let fitness = |genome: &Genome| genome.genome_list.iter().map(|e| e.weight).sum();
+        let events = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&events);
+        let factory = GenomeFactory::init(2, 1).unwrap();
+        let detector = StagnationDetector::new(2, 1)
+            .with_response(StagnationResponse::RaiseMutation { factor: 2. })
+            .with_response(StagnationResponse::InjectRandom { count: 2 });
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut pop = population(&[1., 1., 1., 1.])
+            .with_stagnation(detector, factory.clone())
+            .with_reporter(move |_, event: &Event| sink.borrow_mut().push(event.clone()));
+        pop.evaluate(fitness);
+        pop.evolve(&mut rng);
+        let Some(Event::Stagnation(event)) = events.borrow().first().cloned() else {
+            panic!("Stagnation was not reported");
+        };
+        assert_eq!(event.carriers, 2);
+        assert_eq!(edges(&pop).iter().filter(|e| **e == 0).count(), 2);
+
+        let detector = StagnationDetector::new(2, 1)
+            .with_dominance(0.)
+            .with_response(StagnationResponse::RestartWorstSpecies);
+        let mut pop = population(&[0., 0., 10., 10.]).with_stagnation(detector, factory);
+        assert_eq!(pop.species().len(), 2);
+        pop.evaluate(fitness);
+        pop.evolve(&mut rng);
+        assert_eq!(edges(&pop).iter().sorted().collect_vec(), vec![&0, &0, &1, &1]);
+        assert!(pop
+            .members()
+            .iter()
+            .all(|m| m.genome.genome_list.edge_list.first().is_none_or(|e| e.weight == 10.)));
+    }
+
+    #[test]
+    fn test_minimal_criterion() {
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.1),
+                MinimalCriterionSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(NoMutation),
+            ),
+            genomes(&[0.5, 2., 0.5, 3., 0.5, 0.5]),
+        );
+        pop.evaluate_criterion(|genome| weight(genome) > 1.);
+        let fitness = pop.members().iter().map(|m| m.fitness()).collect_vec();
+        assert_eq!(fitness, vec![0., SATISFIED, 0., SATISFIED, 0., 0.]);
+        pop.evolve(&mut ChaCha8Rng::seed_from_u64(0));
+        // A single species, breeding from the satisfiers alone
+        assert!(pop.members().iter().all(|m| weight(&m.genome) > 1.));
+    }
+
+    #[test]
+    fn test_adaptive_mutation() {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::mutation::mutation::GaussianMutation;
+
+        /// Mutation recording the statistics it adapts to.
+        struct Adapting(GaussianMutation, Rc<RefCell<Vec<usize>>>);
+
+        impl MutationMethod for Adapting {
+            fn mutate(&self, rng: &mut dyn RngCore, child: &mut Genome, innov: &mut InnovNumber) {
+                self.0.mutate(rng, child, innov);
+            }
+
+            fn mutate_named(
+                &self,
+                rng: &mut dyn RngCore,
+                child: &mut Genome,
+                innov: &mut InnovNumber,
+            ) -> Vec<&'static str> {
+                self.0.mutate_named(rng, child, innov)
+            }
+
+            fn adapts(&self) -> bool {
+                true
+            }
+
+            fn adapt(&mut self, stats: &BTreeMap<String, OperatorStats>) {
+                let children = stats.values().map(|s| s.children).sum();
+                self.1.borrow_mut().push(children);
+            }
+        }
+
+        // Adapts without any reporter
+        let adapted = Rc::new(RefCell::new(vec![]));
+        // Weights only, the test genomes have no innovation history
+        let mut base = GaussianMutation::default();
+        base.prob.prob_edge.prob_new_node = 0.;
+        base.prob.prob_edge.prob_new_edge = 0.;
+        let mutation = Adapting(base, Rc::clone(&adapted));
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(mutation),
+            ),
+            genomes(&[1., 2., 3.]),
+        );
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..3 {
+            pop.evaluate(weight);
+            pop.evolve(&mut rng);
+        }
+        let adapted = adapted.borrow();
+        assert_eq!(adapted.len(), 2);
+        assert!(adapted.iter().all(|children| *children > 0));
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::individual::genome::genome::Genome;
+
+/// Evaluation request for a remote worker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub generation: usize,
+    /// Member id, echoed by the result.
+    pub id: usize,
+    pub genome: Arc<Genome>,
+}
+
+/// Outcome of a [`Job`], `None` fitness if the worker failed to evaluate it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JobResult {
+    pub generation: usize,
+    pub id: usize,
+    pub fitness: Option<f32>,
+}
+
+impl Job {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Jobs contain only plain data")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn result(&self, fitness: Option<f32>) -> JobResult {
+        JobResult {
+            generation: self.generation,
+            id: self.id,
+            fitness,
+        }
+    }
+}
+
+impl JobResult {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Results contain only plain data")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+/// The workers are gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disconnected;
+
+/// Link between the population driver and its evaluation workers, e.g. a
+/// message queue carrying [`Job::to_json`] and [`JobResult::to_json`].
+pub trait Transport {
+    fn send(&mut self, job: Job) -> Result<(), Disconnected>;
+
+    /// Next result, `None` if none arrived within `timeout`.
+    fn receive(&mut self, timeout: Duration) -> Result<Option<JobResult>, Disconnected>;
+}
+
+/// Outcome of `Population::evaluate_remote`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RemoteReport {
+    pub received: usize,
+    /// Members whose result did not arrive in time.
+    pub stragglers: usize,
+    /// Discarded results, of earlier generations or duplicated.
+    pub stale: usize,
+}
+
+/// In-process [`Transport`] over channels, see [`channel`].
+pub struct ChannelTransport {
+    jobs: mpsc::Sender<Job>,
+    results: mpsc::Receiver<JobResult>,
+}
+
+/// Worker end of a [`ChannelTransport`]. Clones share the job queue.
+#[derive(Clone)]
+pub struct WorkerChannel {
+    jobs: Arc<Mutex<mpsc::Receiver<Job>>>,
+    results: mpsc::Sender<JobResult>,
+}
+
+pub fn channel() -> (ChannelTransport, WorkerChannel) {
+    let (job_sender, job_receiver) = mpsc::channel();
+    let (result_sender, result_receiver) = mpsc::channel();
+    let transport = ChannelTransport {
+        jobs: job_sender,
+        results: result_receiver,
+    };
+    let worker = WorkerChannel {
+        jobs: Arc::new(Mutex::new(job_receiver)),
+        results: result_sender,
+    };
+    (transport, worker)
+}
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, job: Job) -> Result<(), Disconnected> {
+        self.jobs.send(job).map_err(|_| Disconnected)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<JobResult>, Disconnected> {
+        match self.results.recv_timeout(timeout) {
+            Ok(result) => Ok(Some(result)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Disconnected),
+        }
+    }
+}
+
+impl WorkerChannel {
+    /// Next job, `None` once the driver is gone.
+    pub fn next_job(&self) -> Option<Job> {
+        self.jobs
+            .lock()
+            .expect("No worker panics holding the queue")
+            .recv()
+            .ok()
+    }
+
+    pub fn send(&self, result: JobResult) -> Result<(), Disconnected> {
+        self.results.send(result).map_err(|_| Disconnected)
+    }
+
+    /// Evaluate jobs with `fitness` until the driver is gone.
+    pub fn serve(&self, mut fitness: impl FnMut(&Genome) -> Option<f32>) {
+        while let Some(job) = self.next_job() {
+            if self.send(job.result(fitness(&job.genome))).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    #[test]
+    fn test_channel() {
+        let genome = GenomeBuilder::new()
+            .input(1)
+            .output(1)
+            .connect(0, 1, 2.)
+            .build()
+            .unwrap();
+        let job = Job {
+            generation: 3,
+            id: 7,
+            genome: Arc::new(genome),
+        };
+        assert_eq!(Job::from_json(&job.to_json()).unwrap(), job);
+
+        let (mut transport, worker) = channel();
+        let workers = (0..2)
+            .map(|_| {
+                let worker = worker.clone();
+                thread::spawn(move || worker.serve(|g| Some(g.genome_list.edge_list[0].weight)))
+            })
+            .collect::<Vec<_>>();
+        drop(worker);
+        transport.send(job.clone()).unwrap();
+        let result = transport.receive(Duration::from_secs(10)).unwrap().unwrap();
+        assert_eq!(result, job.result(Some(2.)));
+        assert_eq!(JobResult::from_json(&result.to_json()).unwrap(), result);
+        assert_eq!(transport.receive(Duration::from_millis(10)), Ok(None));
+        drop(transport);
+        workers.into_iter().for_each(|w| w.join().unwrap());
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::BTreeMap;
+
+use itertools::{EitherOrBoth, Itertools};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    individual::{
+        genome::{
+            genome::{Genome, InnovationId},
+            node_list::NodeId,
+        },
+        individual::EvaluatedIndividual,
+    },
+    selection::selection_trait::SelectionMethod,
+    speciation::speciation::SpeciationMethod,
+};
+
+use super::population::Population;
+
+/// Difference made by mutating a child.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MutationRecord {
+    /// Innovation numbers of the new edges.
+    pub edges_added: Vec<InnovationId>,
+    pub nodes_added: Vec<NodeId>,
+    pub nodes_removed: Vec<NodeId>,
+    /// Weight change of every edge whose weight changed, by innovation
+    /// number.
+    pub weights: Vec<(InnovationId, f32)>,
+    /// Bias change of every node whose bias changed, by node id.
+    pub biases: Vec<(NodeId, f32)>,
+    /// Innovation numbers of the edges enabled or disabled.
+    pub toggled: Vec<InnovationId>,
+}
+
+impl MutationRecord {
+    pub fn between(before: &Genome, after: &Genome) -> Self {
+        let edges = before
+            .genome_list
+            .iter()
+            .map(|edge| (edge.innov_number, edge))
+            .collect::<BTreeMap<_, _>>();
+        let mut record = Self::default();
+        for edge in after.genome_list.iter() {
+            match edges.get(&edge.innov_number) {
+                None => record.edges_added.push(edge.innov_number),
+                Some(old) => {
+                    if old.weight != edge.weight {
+                        record
+                            .weights
+                            .push((edge.innov_number, edge.weight - old.weight));
+                    }
+                    if old.enabled != edge.enabled {
+                        record.toggled.push(edge.innov_number);
+                    }
+                }
+            }
+        }
+        let biases = before
+            .node_list
+            .output
+            .iter()
+            .chain(before.node_list.hidden.iter())
+            .map(|node| (node.node_id, node.config.bias))
+            .collect::<BTreeMap<_, _>>();
+        for node in after
+            .node_list
+            .output
+            .iter()
+            .chain(after.node_list.hidden.iter())
+        {
+            match biases.get(&node.node_id) {
+                Some(old) if *old != node.config.bias => {
+                    record.biases.push((node.node_id, node.config.bias - old))
+                }
+                _ => {}
+            }
+        }
+        let hidden = |genome: &Genome| {
+            genome
+                .node_list
+                .hidden
+                .iter()
+                .map(|node| node.node_id)
+                .collect_vec()
+        };
+        let (old, new) = (hidden(before), hidden(after));
+        record.nodes_added = new.iter().filter(|id| !old.contains(id)).copied().collect();
+        record.nodes_removed = old.iter().filter(|id| !new.contains(id)).copied().collect();
+        record
+    }
+
+    /// Whether the mutation changed nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Mutation operator that changed a child, see
+/// `MutationMethod::mutate_recorded`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MutationStep {
+    pub operator: String,
+    /// Change made by the operator alone, `None` for methods that only name
+    /// their operators.
+    pub record: Option<MutationRecord>,
+}
+
+/// Parents and mutations of a child.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Breeding {
+    /// Member ids of the selected parents.
+    pub parents: [usize; 2],
+    /// Change made by every mutation of the crossed child.
+    pub mutation: MutationRecord,
+    /// Mutation operators that changed the child, in order.
+    #[serde(default)]
+    pub steps: Vec<MutationStep>,
+}
+
+impl Breeding {
+    /// Names of the mutation operators that changed the child, in order.
+    pub fn operators(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|step| step.operator.as_str())
+    }
+}
+
+/// Entry of a [`Recording`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordEvent {
+    /// Start of the evolution of `generation`.
+    Generation { generation: usize },
+    /// Fitness of a member when its generation evolved.
+    Fitness { id: usize, fitness: Option<f32> },
+    /// Member `id` of the next generation.
+    Offspring { id: usize, breeding: Breeding },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The log does not reach the requested generation.
+    MissingGeneration(usize),
+    /// The log has a fitness for a member the replay does not have.
+    UnknownMember(usize),
+    /// Replayed breeding differs from the log, so the population, algorithm
+    /// or code differ from the recorded run. `step` is the first mutation
+    /// step that differs, `None` when the members, the parents or only the
+    /// overall change differ.
+    Diverged {
+        generation: usize,
+        id: usize,
+        step: Option<usize>,
+    },
+}
+
+/// Log of every stochastic decision of an evolution run. Evolution draws
+/// from a generator seeded with `seed`, so together with the recorded
+/// fitness values the log determines every generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub events: Vec<RecordEvent>,
+}
+
+impl Recording {
+    /// Events of the evolution of `generation`.
+    pub fn generation(&self, generation: usize) -> Option<&[RecordEvent]> {
+        let start = self
+            .events
+            .iter()
+            .position(|event| *event == RecordEvent::Generation { generation })?;
+        let len = self.events[start + 1..]
+            .iter()
+            .position(|event| matches!(event, RecordEvent::Generation { .. }))
+            .unwrap_or(self.events.len() - start - 1);
+        Some(&self.events[start + 1..start + 1 + len])
+    }
+
+    /// Number of recorded generations.
+    pub fn generations(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, RecordEvent::Generation { .. }))
+            .count()
+    }
+
+    /// Rebuild the members of `generation` by evolving `population`, which
+    /// must be built like the initial population of the recorded run.
+    /// Fitness values come from the log, so nothing is evaluated.
+    pub fn replay<Spe, Sel>(
+        &self,
+        population: &mut Population<Spe, Sel>,
+        generation: usize,
+    ) -> Result<(), ReplayError>
+    where
+        Spe: SpeciationMethod<EvaluatedIndividual>,
+        Sel: SelectionMethod,
+    {
+        assert_eq!(
+            population.generation(),
+            0,
+            "Replay starts from the initial population"
+        );
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        while population.generation() < generation {
+            let current = population.generation();
+            let events = self
+                .generation(current)
+                .ok_or(ReplayError::MissingGeneration(current))?;
+            let mut offspring = vec![];
+            for event in events {
+                match event {
+                    RecordEvent::Fitness { id, fitness } => {
+                        if !population.restore_fitness(*id, *fitness) {
+                            return Err(ReplayError::UnknownMember(*id));
+                        }
+                    }
+                    RecordEvent::Offspring { id, breeding } => offspring.push((*id, breeding)),
+                    RecordEvent::Generation { .. } => unreachable!(),
+                }
+            }
+            let mut bred = vec![];
+            population.evolve_observed(&mut rng, Some(&mut |breeding| bred.push(breeding)));
+            let replayed = population
+                .members()
+                .iter()
+                .map(|member| member.id)
+                .zip(bred);
+            let diverged = replayed.zip_longest(offspring).find_map(|pair| match pair {
+                EitherOrBoth::Both((id, breeding), (recorded_id, recorded))
+                    if id == recorded_id && breeding.parents == recorded.parents =>
+                {
+                    let step = breeding
+                        .steps
+                        .iter()
+                        .zip_longest(recorded.steps.iter())
+                        .position(|steps| !matches!(steps, EitherOrBoth::Both(a, b) if a == b));
+                    (step.is_some() || breeding.mutation != recorded.mutation).then_some((id, step))
+                }
+                EitherOrBoth::Both((id, _), _)
+                | EitherOrBoth::Left((id, _))
+                | EitherOrBoth::Right((id, _)) => Some((id, None)),
+            });
+            if let Some((id, step)) = diverged {
+                return Err(ReplayError::Diverged {
+                    generation: current,
+                    id,
+                    step,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drives the evolution of a population while recording it, see
+/// [`Recording`].
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    rng: ChaCha8Rng,
+    recording: Recording,
+}
+
+impl Recorder {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            recording: Recording {
+                seed,
+                events: vec![],
+            },
+        }
+    }
+
+    /// Evolve `population` with the recorder's generator, logging the
+    /// fitness of its members and the breeding of the next generation.
+    pub fn evolve<Spe, Sel>(&mut self, population: &mut Population<Spe, Sel>)
+    where
+        Spe: SpeciationMethod<EvaluatedIndividual>,
+        Sel: SelectionMethod,
+    {
+        let events = &mut self.recording.events;
+        events.push(RecordEvent::Generation {
+            generation: population.generation(),
+        });
+        events.extend(
+            population
+                .members()
+                .iter()
+                .map(|member| RecordEvent::Fitness {
+                    id: member.id,
+                    fitness: member.fitness,
+                }),
+        );
+        let mut bred = vec![];
+        population.evolve_observed(&mut self.rng, Some(&mut |breeding| bred.push(breeding)));
+        events.extend(
+            population
+                .members()
+                .iter()
+                .zip_eq(bred)
+                .map(|(member, breeding)| RecordEvent::Offspring {
+                    id: member.id,
+                    breeding,
+                }),
+        );
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> Recording {
+        self.recording
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crossover::crossover::NeatCrossover,
+        individual::genome::genome::GenomeBuilder,
+        mutation::{
+            mutation::{Frozen, Perturbation},
+            operator::{AddEdge, MutationPipeline, ToggleEnabled, WeightMutation},
+        },
+        selection::selection_trait::RoulleteSelection,
+        speciation::speciation::SpeciationThreshold,
+        GeneticAlgortihm,
+    };
+
+    fn population() -> Population<SpeciationThreshold, RoulleteSelection> {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 1.)
+            .connect(1, 2, -1.)
+            .build()
+            .unwrap();
+        Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                // No node additions, whose ids can collide with the factory's
+                Box::new(
+                    MutationPipeline::new()
+                        .with_operator(
+                            1.,
+                            WeightMutation {
+                                prob_perturb: 0.5,
+                                prob_replace: 0.1,
+                                sigma: 0.5,
+                                recurrent_sigma: 0.1,
+                                replace_range: (-1., 1.),
+                                perturbation: Perturbation::Gaussian,
+                            },
+                        )
+                        .with_operator(0.3, AddEdge::default())
+                        .with_operator(0.1, ToggleEnabled {
+                            prob: 0.2,
+                            frozen: Frozen::default(),
+                        }),
+                ),
+            ),
+            vec![genome; 10],
+        )
+    }
+
+    fn fitness(genome: &Genome) -> f32 {
+        genome
+            .genome_list
+            .iter()
+            .map(|edge| edge.weight.abs())
+            .sum::<f32>()
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut pop = population();
+        let mut recorder = Recorder::new(11);
+        let mut snapshots = vec![];
+        for _ in 0..5 {
+            pop.evaluate(fitness);
+            recorder.evolve(&mut pop);
+            snapshots.push(pop.members().iter().map(|m| m.genome.clone()).collect_vec());
+        }
+        let json = serde_json::to_string(recorder.recording()).unwrap();
+        let recording: Recording = serde_json::from_str(&json).unwrap();
+        assert_eq!(recording.generations(), 5);
+        assert!(recording
+            .generation(2)
+            .unwrap()
+            .iter()
+            .any(|event| matches!(
+                event,
+                RecordEvent::Offspring { breeding, .. } if !breeding.mutation.is_empty()
+            )));
+        // Every step records the change of its operator alone
+        let steps = recording
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                RecordEvent::Offspring { breeding, .. } => Some(&breeding.steps),
+                _ => None,
+            })
+            .flatten()
+            .collect_vec();
+        assert!(steps.iter().any(|step| step.operator == "WeightMutation"));
+        assert!(steps.iter().all(|step| match &step.record {
+            Some(record) if step.operator == "WeightMutation" => {
+                !record.weights.is_empty() && record.edges_added.is_empty()
+            }
+            Some(record) => !record.is_empty(),
+            None => false,
+        }));
+
+        let mut replayed = population();
+        recording.replay(&mut replayed, 3).unwrap();
+        assert_eq!(replayed.generation(), 3);
+        let genomes = replayed
+            .members()
+            .iter()
+            .map(|m| m.genome.clone())
+            .collect_vec();
+        assert_eq!(
+            serde_json::to_string(&genomes).unwrap(),
+            serde_json::to_string(&snapshots[2]).unwrap()
+        );
+
+        assert_eq!(
+            recording.replay(&mut population(), 6),
+            Err(ReplayError::MissingGeneration(5))
+        );
+        // A tampered weight delta pins the divergence on its step
+        let mut tampered = recording.clone();
+        let (index, id) = tampered
+            .generation(1)
+            .unwrap()
+            .iter()
+            .find_map(|event| match event {
+                RecordEvent::Offspring { id, breeding } => {
+                    let index = breeding.steps.iter().position(|step| {
+                        step.record.as_ref().is_some_and(|r| !r.weights.is_empty())
+                    })?;
+                    Some((index, *id))
+                }
+                _ => None,
+            })
+            .unwrap();
+        for event in tampered.events.iter_mut() {
+            if let RecordEvent::Offspring {
+                id: offspring,
+                breeding,
+            } = event
+            {
+                if *offspring == id {
+                    breeding.steps[index].record.as_mut().unwrap().weights[0].1 += 1.;
+                }
+            }
+        }
+        assert_eq!(
+            tampered.replay(&mut population(), 3),
+            Err(ReplayError::Diverged {
+                generation: 1,
+                id,
+                step: Some(index),
+            })
+        );
+
+        let other = Recording {
+            seed: 12,
+            ..recording
+        };
+        assert!(matches!(
+            other.replay(&mut population(), 3),
+            Err(ReplayError::Diverged { generation: 0, .. })
+        ));
+    }
+}
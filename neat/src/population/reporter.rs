@@ -0,0 +1,197 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
+
+use super::{diversity::Diversity, generation::OperatorStats, stagnation::StagnationEvent};
+
+/// Event the population driver reports during a run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Diversity of the evaluated generation, before it breeds.
+    Diversity(Diversity),
+    /// Network of a member with many saturated nodes, see
+    /// [`super::population::Population::with_saturation_warning`].
+    Saturation {
+        id: usize,
+        saturated: usize,
+        nodes: usize,
+    },
+    /// Fitness of every member, by id, after a stage of the fitness
+    /// pipeline, see [`super::fitness::FitnessTrace`].
+    Fitness {
+        stage: &'static str,
+        fitness: Vec<(usize, f32)>,
+    },
+    /// Success of the mutation operators, by name, that bred the evaluated
+    /// generation.
+    Operators(BTreeMap<String, OperatorStats>),
+    /// The evaluated generation stagnates, see
+    /// [`super::population::Population::with_stagnation`].
+    Stagnation(StagnationEvent),
+    /// Members of the evaluated generation a cataclysm wiped out, see
+    /// [`super::population::Population::with_cataclysm`].
+    Cataclysm { victims: usize },
+    /// Fitness variance over the samples of every member, by id, once a
+    /// repeated evaluation finishes, see
+    /// [`super::population::Population::evaluate_repeated`]. Failed members
+    /// are left out.
+    FitnessVariance(Vec<(usize, f32)>),
+}
+
+/// Observer of a run, see [`super::population::Population::with_reporter`].
+pub trait Reporter {
+    fn report(&mut self, generation: usize, event: &Event);
+}
+
+impl<F: FnMut(usize, &Event)> Reporter for F {
+    fn report(&mut self, generation: usize, event: &Event) {
+        self(generation, event)
+    }
+}
+
+/// Event sent to a [`ReporterHub`], with its generation and send order.
+type Envelope = (usize, u64, Event);
+
+/// Handle of a [`ReporterHub`] for reporting from other threads, e.g.
+/// parallel evaluations.
+#[derive(Debug, Clone)]
+pub struct ReportSender {
+    sender: Sender<Envelope>,
+    sequence: Arc<AtomicU64>,
+}
+
+impl ReportSender {
+    /// Queue `event`. Dropped if the hub is gone.
+    pub fn send(&self, generation: usize, event: Event) {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let _ = self.sender.send((generation, sequence, event));
+    }
+}
+
+/// Reporter collecting the events of any thread through a channel and
+/// fanning them out to its reporters on the thread owning it, so that
+/// reporters need not be thread-safe. Events are delivered by generation,
+/// and in the order they were sent within a generation. Reporting an event
+/// to the hub, e.g. from the population, delivers it with the events queued
+/// up to its generation; the rest are delivered by [`ReporterHub::flush`]
+/// or when the hub is dropped.
+pub struct ReporterHub {
+    reporters: Vec<Box<dyn Reporter>>,
+    sender: ReportSender,
+    receiver: Receiver<Envelope>,
+    /// Received events not delivered yet, by generation and send order.
+    pending: BTreeMap<(usize, u64), Event>,
+}
+
+impl Default for ReporterHub {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            reporters: vec![],
+            sender: ReportSender {
+                sender,
+                sequence: Arc::new(AtomicU64::new(0)),
+            },
+            receiver,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl ReporterHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.push(Box::new(reporter));
+        self
+    }
+
+    pub fn sender(&self) -> ReportSender {
+        self.sender.clone()
+    }
+
+    /// Deliver the queued events of generations up to `generation` and
+    /// return how many.
+    pub fn flush_until(&mut self, generation: usize) -> usize {
+        self.pending.extend(
+            self.receiver
+                .try_iter()
+                .map(|(generation, sequence, event)| ((generation, sequence), event)),
+        );
+        let later = self.pending.split_off(&(generation.saturating_add(1), 0));
+        let due = std::mem::replace(&mut self.pending, later);
+        let delivered = due.len();
+        for ((generation, _), event) in due {
+            for reporter in self.reporters.iter_mut() {
+                reporter.report(generation, &event);
+            }
+        }
+        delivered
+    }
+
+    /// Deliver every queued event and return how many.
+    pub fn flush(&mut self) -> usize {
+        self.flush_until(usize::MAX)
+    }
+}
+
+impl Reporter for ReporterHub {
+    fn report(&mut self, generation: usize, event: &Event) {
+        self.sender.send(generation, event.clone());
+        self.flush_until(generation);
+    }
+}
+
+impl Drop for ReporterHub {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_hub() {
+        let received = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&received);
+        let mut hub = ReporterHub::new().with_reporter(move |generation, event: &Event| {
+            let Event::Cataclysm { victims } = event else {
+                unreachable!()
+            };
+            log.borrow_mut().push((generation, *victims));
+        });
+        let sender = hub.sender();
+        // Generation 1 is sent first, generation 0 from another thread
+        sender.send(1, Event::Cataclysm { victims: 10 });
+        thread::scope(|scope| {
+            let sender = hub.sender();
+            scope.spawn(move || {
+                sender.send(0, Event::Cataclysm { victims: 0 });
+                sender.send(0, Event::Cataclysm { victims: 1 });
+            });
+        });
+        hub.report(0, &Event::Cataclysm { victims: 2 });
+        assert_eq!(*received.borrow(), vec![(0, 0), (0, 1), (0, 2)]);
+        sender.send(2, Event::Cataclysm { victims: 20 });
+        sender.send(1, Event::Cataclysm { victims: 11 });
+        assert_eq!(hub.flush_until(1), 2);
+        drop(hub);
+        assert_eq!(
+            *received.borrow(),
+            vec![(0, 0), (0, 1), (0, 2), (1, 10), (1, 11), (2, 20)]
+        );
+        // The hub is gone
+        sender.send(3, Event::Cataclysm { victims: 30 });
+    }
+}
@@ -0,0 +1,228 @@
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::individual::genome::{
+    genome::{Genome, InnovationId},
+    network::network::FFNetwork,
+    node_list::NodeId,
+};
+
+/// Sensitivity of a single enabled edge.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EdgeSensitivity {
+    pub innov_number: InnovationId,
+    pub in_node: NodeId,
+    pub out_node: NodeId,
+    pub weight: f32,
+    /// Change caused by every offset of the analysis.
+    pub changes: Vec<f32>,
+    /// Largest absolute change, NaN if a change is.
+    pub sensitivity: f32,
+}
+
+/// Per-edge result of a [`WeightSensitivity`] analysis, in innovation
+/// order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SensitivityReport {
+    pub offsets: Vec<f32>,
+    pub edges: Vec<EdgeSensitivity>,
+}
+
+impl SensitivityReport {
+    /// Edges from the most to the least sensitive, NaN first.
+    pub fn ranked(&self) -> Vec<&EdgeSensitivity> {
+        self.edges
+            .iter()
+            .sorted_by(|a, b| {
+                b.sensitivity
+                    .is_nan()
+                    .cmp(&a.sensitivity.is_nan())
+                    .then(b.sensitivity.total_cmp(&a.sensitivity))
+            })
+            .collect_vec()
+    }
+
+    /// One row per edge. Changes are separated by `;`, in offset order.
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::from("innov_number,in_node,out_node,weight,sensitivity,changes\n");
+        for edge in self.edges.iter() {
+            ret.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                edge.innov_number,
+                edge.in_node,
+                edge.out_node,
+                edge.weight,
+                edge.sensitivity,
+                edge.changes.iter().join(";")
+            ));
+        }
+        ret
+    }
+}
+
+/// Probing of the fitness landscape around a genome: every enabled weight
+/// is moved by each offset in turn, the others being kept, and the change
+/// of the fitness or of the network outputs is recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightSensitivity {
+    /// Offsets added to the weight under probe.
+    pub offsets: Vec<f32>,
+}
+
+impl Default for WeightSensitivity {
+    /// Eight offsets evenly spread over ±1.
+    fn default() -> Self {
+        Self::linear(1., 4)
+    }
+}
+
+impl WeightSensitivity {
+    /// `steps` offsets evenly spread on either side of 0, up to ±`range`.
+    pub fn linear(range: f32, steps: usize) -> Self {
+        let offsets = (1..=steps)
+            .rev()
+            .map(|step| -range * step as f32 / steps as f32)
+            .chain((1..=steps).map(|step| range * step as f32 / steps as f32))
+            .collect_vec();
+        Self { offsets }
+    }
+
+    /// Signed change of `fitness` over the fitness of `genome`. Evaluates
+    /// `fitness` once plus once per offset and enabled edge.
+    pub fn fitness(
+        &self,
+        genome: &Genome,
+        mut fitness: impl FnMut(&Genome) -> f32,
+    ) -> SensitivityReport {
+        let baseline = fitness(genome);
+        self.probe(genome, |probe| fitness(probe) - baseline)
+    }
+
+    /// Mean absolute change of the network outputs over `inputs`, every
+    /// input being fed to a freshly reset network. `None` if an input does
+    /// not match the genome inputs.
+    pub fn output(&self, genome: &Genome, inputs: &[Vec<f32>]) -> Option<SensitivityReport> {
+        let outputs = |genome: &Genome| {
+            let mut network = FFNetwork::from(genome);
+            inputs
+                .iter()
+                .map(|input| {
+                    network.reset();
+                    network.forward(input)
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        let baseline = outputs(genome)?;
+        let count = baseline.iter().map(Vec::len).sum::<usize>().max(1) as f32;
+        Some(self.probe(genome, |probe| {
+            let probed = outputs(probe).expect("Probes keep the genome inputs");
+            baseline
+                .iter()
+                .flatten()
+                .zip(probed.iter().flatten())
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>()
+                / count
+        }))
+    }
+
+    fn probe(&self, genome: &Genome, mut change: impl FnMut(&Genome) -> f32) -> SensitivityReport {
+        let mut probe = genome.clone();
+        let edges = genome
+            .genome_list
+            .edge_list
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| edge.enabled)
+            .map(|(index, edge)| {
+                let changes = self
+                    .offsets
+                    .iter()
+                    .map(|offset| {
+                        probe.genome_list.edge_list[index].weight = edge.weight + offset;
+                        change(&probe)
+                    })
+                    .collect_vec();
+                probe.genome_list.edge_list[index].weight = edge.weight;
+                let sensitivity = if changes.iter().any(|change| change.is_nan()) {
+                    f32::NAN
+                } else {
+                    changes
+                        .iter()
+                        .fold(0., |max: f32, change| max.max(change.abs()))
+                };
+                EdgeSensitivity {
+                    innov_number: edge.innov_number,
+                    in_node: edge.in_node,
+                    out_node: edge.out_node,
+                    weight: edge.weight,
+                    changes,
+                    sensitivity,
+                }
+            })
+            .collect_vec();
+        SensitivityReport {
+            offsets: self.offsets.clone(),
+            edges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+    };
+
+    fn genome() -> Genome {
+        GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .aggregation(2, Aggregation::Sum)
+            .activation(2, Activation::Identity)
+            .connect(0, 2, 1.)
+            .connect(1, 2, 1.)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_linear() {
+        let analysis = WeightSensitivity::linear(1., 2);
+        assert_eq!(analysis.offsets, vec![-1., -0.5, 0.5, 1.]);
+        assert_eq!(WeightSensitivity::default().offsets.len(), 8);
+    }
+
+    #[test]
+    fn test_fitness_sensitivity() {
+        let mut genome = genome();
+        // Only the first weight matters, quadratically.
+        let fitness = |g: &Genome| -g.genome_list.edge_list[0].weight.powi(2);
+        let analysis = WeightSensitivity::linear(1., 1);
+        let report = analysis.fitness(&genome, fitness);
+        assert_eq!(report.edges.len(), 2);
+        assert_eq!(report.edges[0].changes, vec![1., -3.]);
+        assert_eq!(report.edges[0].sensitivity, 3.);
+        assert_eq!(report.edges[1].changes, vec![0., 0.]);
+        assert_eq!(report.ranked()[0].innov_number, 0);
+        let csv = report.to_csv();
+        let lines = csv.lines().collect_vec();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "0,0,2,1,3,1;-3");
+        assert_eq!(lines[2], "1,1,2,1,0,0;0");
+        genome.genome_list.edge_list[1].enabled = false;
+        assert_eq!(analysis.fitness(&genome, fitness).edges.len(), 1);
+    }
+
+    #[test]
+    fn test_output_sensitivity() {
+        let genome = genome();
+        let analysis = WeightSensitivity::linear(1., 1);
+        let inputs = vec![vec![1., 0.], vec![1., 0.]];
+        let report = analysis.output(&genome, &inputs).unwrap();
+        assert_eq!(report.edges[0].changes, vec![1., 1.]);
+        assert_eq!(report.edges[1].sensitivity, 0.);
+        assert!(analysis.output(&genome, &[vec![1.]]).is_none());
+    }
+}
@@ -0,0 +1,460 @@
+use itertools::Itertools;
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    individual::individual::{EvaluatedIndividual, Individual},
+    speciation::speciation::Comparable,
+};
+
+/// Species persisting across generations.
+#[derive(Debug, Clone)]
+pub struct Species {
+    pub id: usize,
+    /// Number of generations the species has survived.
+    pub age: usize,
+    /// Individual new members are compared against when speciating.
+    pub representative: EvaluatedIndividual,
+    /// Ids of the current members.
+    pub members: Vec<usize>,
+    /// Best finite fitness its members ever reached, `None` until one is
+    /// evaluated.
+    pub best: Option<f32>,
+    /// Generations since `best` last improved.
+    pub stale: usize,
+}
+
+/// Summary of a species for the evaluated part of its members.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeciesReport {
+    pub id: usize,
+    pub size: usize,
+    pub best: Option<f32>,
+    pub mean: Option<f32>,
+    pub age: usize,
+    /// Members whose evaluation failed and got the penalty fitness.
+    pub failed: usize,
+}
+
+/// How a persisting species picks the representative the next generation is
+/// compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepresentativePolicy {
+    /// A random member, as in the original NEAT.
+    #[default]
+    Random,
+    /// The fittest evaluated member.
+    Champion,
+    /// The member most similar to the rest of the species.
+    Medoid,
+}
+
+impl RepresentativePolicy {
+    /// Representative among `members`, `None` if there are none. Champion
+    /// falls back to a random member when nobody is evaluated.
+    pub fn choose<'a>(
+        &self,
+        rng: &mut dyn RngCore,
+        members: &[&'a EvaluatedIndividual],
+    ) -> Option<&'a EvaluatedIndividual> {
+        match self {
+            RepresentativePolicy::Random => members.choose(rng).copied(),
+            RepresentativePolicy::Champion => members
+                .iter()
+                .filter(|m| m.fitness.is_some_and(|f| !f.is_nan()))
+                .max_by(|a, b| a.fitness().total_cmp(&b.fitness()))
+                .or_else(|| members.choose(rng))
+                .copied(),
+            RepresentativePolicy::Medoid => members
+                .iter()
+                .map(|m| (m, members.iter().map(|o| m.compare(o)).sum::<f32>()))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(m, _)| *m),
+        }
+    }
+}
+
+/// How the offspring of a generation are shared out between the species.
+/// Every policy breeds exactly as many children as asked for, rounding the
+/// shares with the largest remainder method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OffspringAllocation {
+    /// In proportion to the size of the species.
+    #[default]
+    SpeciesSize,
+    /// In proportion to the mean fitness of the species, shifted so the
+    /// least fit member counts for zero, as in the original NEAT. Members
+    /// without a finite fitness count as the least fit. Falls back to the
+    /// species sizes when every share is zero.
+    AdjustedFitness,
+}
+
+impl OffspringAllocation {
+    /// Children of every species, `total` in all. Species without members
+    /// get none, and all of them none if no species has members.
+    pub fn quotas(&self, species: &[Vec<&EvaluatedIndividual>], total: usize) -> Vec<usize> {
+        let sizes = species.iter().map(|members| members.len() as f64).collect_vec();
+        let shares = match self {
+            OffspringAllocation::SpeciesSize => sizes.clone(),
+            OffspringAllocation::AdjustedFitness => {
+                let finite = |m: &&EvaluatedIndividual| m.fitness.filter(|f| f.is_finite());
+                let min = species
+                    .iter()
+                    .flatten()
+                    .filter_map(finite)
+                    .fold(f32::INFINITY, f32::min);
+                species
+                    .iter()
+                    .map(|members| {
+                        let total = members
+                            .iter()
+                            .map(|m| finite(m).map_or(0., |f| (f - min) as f64))
+                            .sum::<f64>();
+                        if members.is_empty() {
+                            0.
+                        } else {
+                            total / members.len() as f64
+                        }
+                    })
+                    .collect_vec()
+            }
+        };
+        if shares.iter().any(|share| *share > 0.) {
+            largest_remainder(&shares, total)
+        } else {
+            largest_remainder(&sizes, total)
+        }
+    }
+}
+
+/// Minimum share of the offspring of the species with members, so rounding
+/// the allocation does not wipe out a lineage in a single generation, see
+/// `Population::with_species_protection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeciesProtection {
+    /// Children every protected species gets at least.
+    pub min_offspring: usize,
+    /// Only species at most this many generations old are protected, none
+    /// to protect every species.
+    pub max_age: Option<usize>,
+    /// Drop the species left without children, rather than keeping their
+    /// representatives for the next speciation.
+    pub remove_childless: bool,
+}
+
+impl SpeciesProtection {
+    pub fn new(min_offspring: usize) -> Self {
+        Self {
+            min_offspring,
+            max_age: None,
+            remove_childless: false,
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: usize) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn with_remove_childless(mut self, remove_childless: bool) -> Self {
+        self.remove_childless = remove_childless;
+        self
+    }
+
+    /// Whether a species `age` generations old with `size` breeding members
+    /// is protected.
+    pub fn protects(&self, age: usize, size: usize) -> bool {
+        size > 0 && self.max_age.is_none_or(|max| age <= max)
+    }
+
+    /// Raise the quotas of the `protected` species to the minimum, taking
+    /// children one at a time from the quota furthest above its own minimum,
+    /// earlier ones on ties, so the total is kept. When the total cannot
+    /// cover every minimum, the protected species with the largest quotas,
+    /// earlier ones on ties, get theirs first.
+    pub fn apply(&self, quotas: &mut [usize], protected: &[bool]) {
+        let mut budget = quotas.iter().sum::<usize>();
+        let mut floors = vec![0; quotas.len()];
+        let by_quota = (0..quotas.len())
+            .filter(|i| protected[*i])
+            .sorted_by(|a, b| quotas[*b].cmp(&quotas[*a]));
+        for i in by_quota {
+            floors[i] = self.min_offspring.min(budget);
+            budget -= floors[i];
+        }
+        for i in 0..quotas.len() {
+            while quotas[i] < floors[i] {
+                let donor = (0..quotas.len())
+                    .filter(|j| quotas[*j] > floors[*j])
+                    .rev()
+                    .max_by_key(|j| quotas[*j] - floors[*j])
+                    .expect("The minimums fit in the total");
+                quotas[donor] -= 1;
+                quotas[i] += 1;
+            }
+        }
+    }
+}
+
+/// Which members breed in species that went `patience` generations without
+/// improving their best fitness, see `Population::with_stale_cutoff`. Every
+/// member of fresher species breeds. Only selection within the species
+/// tightens: its share of the offspring, and its extinction, are left to the
+/// allocation and the stagnation responses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StaleCutoff {
+    pub patience: usize,
+    pub parents: StaleParents,
+}
+
+/// Parents of a stale species, fittest first. Members without a finite
+/// fitness count as the least fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StaleParents {
+    /// The fittest share of the members, at least one.
+    TopFraction(f32),
+    /// The fittest member alone.
+    Champion,
+}
+
+impl StaleCutoff {
+    pub fn new(patience: usize, parents: StaleParents) -> Self {
+        Self { patience, parents }
+    }
+
+    /// Members of a species `stale` generations without improvement that may
+    /// breed, all of them if it is not stale yet.
+    pub fn parents<'a>(
+        &self,
+        stale: usize,
+        members: &[&'a EvaluatedIndividual],
+    ) -> Vec<&'a EvaluatedIndividual> {
+        if stale < self.patience || members.is_empty() {
+            return members.to_vec();
+        }
+        let count = match self.parents {
+            StaleParents::TopFraction(fraction) => {
+                ((fraction as f64 * members.len() as f64).ceil() as usize).clamp(1, members.len())
+            }
+            StaleParents::Champion => 1,
+        };
+        let key = |m: &EvaluatedIndividual| {
+            m.fitness
+                .filter(|f| f.is_finite())
+                .unwrap_or(f32::NEG_INFINITY)
+        };
+        members
+            .iter()
+            .copied()
+            .sorted_by(|a, b| key(b).total_cmp(&key(a)))
+            .take(count)
+            .collect_vec()
+    }
+}
+
+/// Split `total` in proportion to `shares`: every part gets the integer part
+/// of its exact quota, and the parts with the largest remainders, earlier
+/// ones on ties, one more until the parts sum to `total`. Shares that are
+/// not positive and finite get nothing, and every part nothing if no share
+/// is.
+pub fn largest_remainder(shares: &[f64], total: usize) -> Vec<usize> {
+    let shares = shares
+        .iter()
+        .map(|share| if share.is_finite() && *share > 0. { *share } else { 0. })
+        .collect_vec();
+    let sum = shares.iter().sum::<f64>();
+    if sum <= 0. || !sum.is_finite() {
+        return vec![0; shares.len()];
+    }
+    let exact = shares.iter().map(|share| share / sum * total as f64).collect_vec();
+    let mut parts = exact.iter().map(|quota| quota.floor() as usize).collect_vec();
+    let by_remainder = (0..exact.len())
+        .filter(|i| shares[*i] > 0.)
+        .sorted_by(|a, b| {
+            let remainder = |i: usize| exact[i] - parts[i] as f64;
+            remainder(*b).total_cmp(&remainder(*a))
+        })
+        .collect_vec();
+    // Rounding may leave the integer parts off by a few either way
+    let assigned = parts.iter().sum::<usize>();
+    for i in by_remainder.iter().cycle().take(total.saturating_sub(assigned)) {
+        parts[*i] += 1;
+    }
+    let mut excess = assigned.saturating_sub(total);
+    for i in by_remainder.iter().rev().cycle() {
+        if excess == 0 {
+            break;
+        }
+        if parts[*i] > 0 {
+            parts[*i] -= 1;
+            excess -= 1;
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use proptest::prelude::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::genome::{GenomeEdge, GenomeFactory};
+
+    fn member(id: usize, weight: f32, fitness: Option<f32>) -> EvaluatedIndividual {
+        let mut genome = GenomeFactory::init(1, 1).unwrap().generate_genome();
+        genome.genome_list.edge_list.push(GenomeEdge {
+            innov_number: 0,
+            in_node: 0,
+            out_node: 1,
+            weight,
+            enabled: true,
+        });
+        EvaluatedIndividual {
+            fitness,
+            ..EvaluatedIndividual::new(id, genome)
+        }
+    }
+
+    #[test]
+    fn test_choose() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let members = [
+            member(1, 0., Some(1.)),
+            member(2, 1., Some(2.)),
+            member(3, 1.5, None),
+            member(4, 4., Some(3.)),
+        ];
+        let members = members.iter().collect_vec();
+        let chosen = |policy: RepresentativePolicy, rng: &mut ChaCha8Rng| {
+            policy.choose(rng, &members).map(|m| m.id)
+        };
+        assert_eq!(chosen(RepresentativePolicy::Champion, &mut rng), Some(4));
+        assert_eq!(chosen(RepresentativePolicy::Medoid, &mut rng), Some(2));
+        assert!(chosen(RepresentativePolicy::Random, &mut rng).is_some());
+        assert!(RepresentativePolicy::Medoid.choose(&mut rng, &[]).is_none());
+    }
+
+    #[test]
+    fn test_quotas() {
+        assert_eq!(largest_remainder(&[1., 1., 1.], 10), vec![4, 3, 3]);
+        assert_eq!(largest_remainder(&[0.5, 2.5, 0., f64::NAN], 7), vec![1, 6, 0, 0]);
+        assert_eq!(largest_remainder(&[0., -1.], 5), vec![0, 0]);
+
+        let members = [
+            member(1, 0., Some(1.)),
+            member(2, 0., Some(3.)),
+            member(3, 0., None),
+            member(4, 0., Some(5.)),
+        ];
+        let species = vec![
+            vec![&members[0], &members[1]],
+            vec![&members[2]],
+            vec![&members[3]],
+            vec![],
+        ];
+        let quotas = |allocation: OffspringAllocation| allocation.quotas(&species, 8);
+        assert_eq!(quotas(OffspringAllocation::SpeciesSize), vec![4, 2, 2, 0]);
+        // Shares 1, 0 and 4 above the least fit member
+        assert_eq!(quotas(OffspringAllocation::AdjustedFitness), vec![2, 0, 6, 0]);
+        let tied = [member(1, 0., Some(2.)), member(2, 0., Some(2.))];
+        let species = vec![vec![&tied[0]], vec![&tied[1]]];
+        assert_eq!(OffspringAllocation::AdjustedFitness.quotas(&species, 3), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_species_protection() {
+        let protection = SpeciesProtection::new(2);
+        let apply = |protection: SpeciesProtection, quotas: &[usize], protected: &[bool]| {
+            let mut quotas = quotas.to_vec();
+            protection.apply(&mut quotas, protected);
+            quotas
+        };
+        // One dominant species feeds the others
+        assert_eq!(apply(protection, &[10, 0, 0], &[true; 3]), vec![6, 2, 2]);
+        assert_eq!(apply(protection, &[10, 0, 0], &[true, false, true]), vec![8, 0, 2]);
+        // Many singletons, more than the total covers
+        assert_eq!(
+            apply(protection, &[3, 1, 1, 0, 0], &[true; 5]),
+            vec![2, 2, 1, 0, 0]
+        );
+        assert_eq!(apply(SpeciesProtection::new(1), &[3, 1, 1, 0, 0], &[true; 5]), vec![1; 5]);
+        // The largest quotas give first, earlier ones on ties
+        assert_eq!(apply(protection, &[5, 4, 0], &[false, false, true]), vec![3, 4, 2]);
+        assert_eq!(apply(protection, &[], &[]), Vec::<usize>::new());
+        assert_eq!(apply(protection, &[0, 0], &[true, true]), vec![0, 0]);
+
+        let young = protection.with_max_age(3);
+        assert!(young.protects(3, 1));
+        assert!(!young.protects(4, 1));
+        assert!(!young.protects(0, 0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_protection_total(
+            quotas in proptest::collection::vec(0usize..20, 0..12),
+            min_offspring in 0usize..4,
+            seed: u64,
+        ) {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let protected = quotas.iter().map(|_| rng.gen_bool(0.5)).collect_vec();
+            let mut protected_quotas = quotas.clone();
+            SpeciesProtection::new(min_offspring).apply(&mut protected_quotas, &protected);
+            let total = quotas.iter().sum::<usize>();
+            prop_assert_eq!(protected_quotas.iter().sum::<usize>(), total);
+            let short = protected_quotas
+                .iter()
+                .zip(&protected)
+                .filter(|(quota, protected)| **protected && **quota < min_offspring)
+                .count();
+            let covered = total / min_offspring.max(1);
+            let count = protected.iter().filter(|p| **p).count();
+            prop_assert!(short == 0 || count > covered);
+        }
+    }
+
+    #[test]
+    fn test_stale_cutoff() {
+        let members = [
+            member(1, 0., Some(1.)),
+            member(2, 1., None),
+            member(3, 1.5, Some(3.)),
+            member(4, 4., Some(2.)),
+            member(5, 2., Some(f32::NAN)),
+        ];
+        let members = members.iter().collect_vec();
+        let ids = |parents: Vec<&EvaluatedIndividual>| parents.iter().map(|m| m.id).collect_vec();
+        let top = StaleCutoff::new(3, StaleParents::TopFraction(0.5));
+        assert_eq!(ids(top.parents(2, &members)), [1, 2, 3, 4, 5]);
+        assert_eq!(ids(top.parents(3, &members)), [3, 4, 1]);
+        assert_eq!(ids(top.parents(3, &members[..2])), [1]);
+        let tiny = StaleCutoff::new(0, StaleParents::TopFraction(0.));
+        assert_eq!(ids(tiny.parents(0, &members)), [3]);
+        let champion = StaleCutoff::new(1, StaleParents::Champion);
+        assert_eq!(ids(champion.parents(5, &members)), [3]);
+        assert_eq!(ids(champion.parents(5, &members[1..2])), [2]);
+        assert!(champion.parents(5, &[]).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn test_largest_remainder(
+            shares in proptest::collection::vec(0f64..100., 1..20),
+            total in 0usize..500,
+        ) {
+            let parts = largest_remainder(&shares, total);
+            let sum = shares.iter().sum::<f64>();
+            if sum > 0. {
+                prop_assert_eq!(parts.iter().sum::<usize>(), total);
+                for (part, share) in parts.iter().zip(shares.iter()) {
+                    let exact = share / sum * total as f64;
+                    prop_assert!((*part as f64 - exact).abs() < 1. + 1e-9);
+                }
+            } else {
+                prop_assert!(parts.iter().all(|part| *part == 0));
+            }
+        }
+    }
+}
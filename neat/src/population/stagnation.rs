@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::individual::individual::{EvaluatedIndividual, Individual};
+
+/// Response of the population to a [`StagnationEvent`], see
+/// `Population::with_stagnation`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "response")]
+pub enum StagnationResponse {
+    /// Scale every mutation rate by `factor`, see `MutationMethod::boost`.
+    RaiseMutation { factor: f64 },
+    /// Replace `count` random children with fresh genomes.
+    InjectRandom { count: usize },
+    /// Replace the children of the species with the lowest best fitness
+    /// with fresh genomes.
+    RestartWorstSpecies,
+}
+
+/// The same structure dominated the fittest members for too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StagnationEvent {
+    /// [`Genome::structural_hash`](crate::individual::genome::genome::Genome::structural_hash)
+    /// of the dominating structure.
+    pub fingerprint: u64,
+    /// Consecutive generations it dominated.
+    pub generations: usize,
+    /// Members of the last top-k carrying it.
+    pub carriers: usize,
+}
+
+/// Flags search stagnation: a structural fingerprint carried by at least a
+/// `dominance` share of the `top_k` fittest members for `patience`
+/// consecutive generations. Flags again every `patience` generations while
+/// it keeps dominating.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StagnationDetector {
+    pub top_k: usize,
+    pub dominance: f32,
+    pub patience: usize,
+    pub responses: Vec<StagnationResponse>,
+    /// Dominating fingerprint and for how many generations.
+    #[serde(skip)]
+    streak: Option<(u64, usize)>,
+}
+
+impl Default for StagnationDetector {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            dominance: 0.5,
+            patience: 15,
+            responses: vec![],
+            streak: None,
+        }
+    }
+}
+
+impl StagnationDetector {
+    pub fn new(top_k: usize, patience: usize) -> Self {
+        Self {
+            top_k,
+            patience,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_dominance(mut self, dominance: f32) -> Self {
+        self.dominance = dominance;
+        self
+    }
+
+    pub fn with_response(mut self, response: StagnationResponse) -> Self {
+        self.responses.push(response);
+        self
+    }
+
+    /// Dominating fingerprint and for how many generations, if any.
+    pub fn streak(&self) -> Option<(u64, usize)> {
+        self.streak
+    }
+
+    /// Observe an evaluated generation, members without finite fitness
+    /// aside. `Some` if it stagnates.
+    pub fn observe(&mut self, members: &[EvaluatedIndividual]) -> Option<StagnationEvent> {
+        assert!(self.patience > 0, "Stagnation needs a patience of at least 1");
+        let top = members
+            .iter()
+            .filter(|m| m.fitness.is_some_and(f32::is_finite))
+            .sorted_by(|a, b| b.fitness().total_cmp(&a.fitness()))
+            .take(self.top_k)
+            .collect_vec();
+        let mut counts = BTreeMap::<_, usize>::new();
+        for member in top.iter() {
+            *counts.entry(member.genome.structural_hash()).or_default() += 1;
+        }
+        let dominant = counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .filter(|(_, count)| *count as f32 >= self.dominance * top.len() as f32);
+        let Some((fingerprint, carriers)) = dominant else {
+            self.streak = None;
+            return None;
+        };
+        let generations = match self.streak {
+            Some((prev, generations)) if prev == fingerprint => generations + 1,
+            _ => 1,
+        };
+        self.streak = Some((fingerprint, generations));
+        (generations % self.patience == 0).then_some(StagnationEvent {
+            fingerprint,
+            generations,
+            carriers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::individual::genome::genome::GenomeBuilder;
+
+    fn members(fitness: &[(f32, bool)]) -> Vec<EvaluatedIndividual> {
+        let small = GenomeBuilder::new().input(1).output(1).connect(0, 1, 1.);
+        let large = small.clone().hidden(50).connect(0, 2, 1.).connect(2, 1, 1.);
+        fitness
+            .iter()
+            .enumerate()
+            .map(|(id, (fitness, is_large))| {
+                let genome = if *is_large { &large } else { &small };
+                EvaluatedIndividual::new(id, genome.clone().build().unwrap()).with_fitness(*fitness)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut detector = StagnationDetector::new(2, 2);
+        let small = members(&[(3., false), (2., false), (1., true)]);
+        let fingerprint = small[0].genome.structural_hash();
+        assert_eq!(detector.observe(&small), None);
+        assert_eq!(detector.streak(), Some((fingerprint, 1)));
+        let event = detector.observe(&small).unwrap();
+        assert_eq!(
+            event,
+            StagnationEvent {
+                fingerprint,
+                generations: 2,
+                carriers: 2,
+            }
+        );
+        assert_eq!(detector.observe(&small), None);
+        assert!(detector.observe(&small).is_some());
+
+        // A new structure among the fittest restarts the streak
+        let large = members(&[(3., true), (2., true), (1., false)]);
+        assert_eq!(detector.observe(&large), None);
+        assert_eq!(detector.streak().unwrap().1, 1);
+        let split = members(&[(3., true), (2., false)]);
+        let mut strict = detector.clone().with_dominance(0.6);
+        assert_eq!(strict.observe(&split), None);
+        assert_eq!(strict.streak(), None);
+        assert_eq!(detector.observe(&members(&[])), None);
+        assert_eq!(detector.streak(), None);
+    }
+
+    #[test]
+    fn test_serde() {
+        let detector: StagnationDetector = serde_json::from_str(
+            r#"{"top_k": 3, "responses": [
+                {"response": "raise_mutation", "factor": 2.0},
+                {"response": "restart_worst_species"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            detector,
+            StagnationDetector::new(3, 15)
+                .with_response(StagnationResponse::RaiseMutation { factor: 2. })
+                .with_response(StagnationResponse::RestartWorstSpecies)
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// What a stream is drawn for, so breeding and evaluating the same
+/// individual draw independent numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    /// Selecting the parents, crossing them over and mutating the child.
+    Breeding,
+    Evaluation,
+}
+
+/// Random streams of a seeded run, one per individual, generation and
+/// purpose. What happens to an individual then depends on the run seed, the
+/// generation and its id only, not on the order or the threads the other
+/// individuals are processed in. See `Population::with_seed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngStreams {
+    pub seed: u64,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Stream of individual `id` in `generation`.
+    pub fn stream(&self, purpose: Purpose, generation: usize, id: usize) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(mix(self.seed ^ mix(generation as u64)));
+        rng.set_stream(mix(id as u64) ^ purpose as u64);
+        rng
+    }
+}
+
+/// SplitMix64 finalizer, so nearby inputs give unrelated outputs.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_stream() {
+        let streams = RngStreams::new(7);
+        let draw = |purpose, generation, id| streams.stream(purpose, generation, id).gen::<u64>();
+        assert_eq!(draw(Purpose::Breeding, 3, 5), draw(Purpose::Breeding, 3, 5));
+        let draws = [
+            draw(Purpose::Breeding, 3, 5),
+            draw(Purpose::Evaluation, 3, 5),
+            draw(Purpose::Breeding, 4, 5),
+            draw(Purpose::Breeding, 3, 6),
+            draw(Purpose::Breeding, 5, 3),
+            RngStreams::new(8).stream(Purpose::Breeding, 3, 5).gen(),
+        ];
+        for (i, a) in draws.iter().enumerate() {
+            assert!(draws[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Evolution strategies over the weights of a fixed topology, to tune a
+//! genome on its own or to refine the best members every few generations.
+
+use std::collections::BTreeSet;
+
+use itertools::Itertools;
+use num::Float;
+use rand::RngCore;
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::individual::genome::{genome::Genome, network::network::FFNetwork, node_list::to_float};
+
+use super::local_search::Inheritance;
+
+/// How the weight vector is searched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// A single parent and a single mutant per step, the step size adapted
+    /// with the one-fifth success rule.
+    OnePlusOne,
+    /// Separable CMA-ES: a diagonal covariance, so every step is linear in
+    /// the number of weights. `offspring` defaults to `4 + 3 ln n`.
+    SepCma { offspring: Option<usize> },
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self::SepCma { offspring: None }
+    }
+}
+
+/// Weight optimization by an evolution strategy. Fitness is maximised.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightOptimizer {
+    pub strategy: Strategy,
+    /// Fitness evaluations after the one of the starting weights.
+    pub evaluations: usize,
+    /// Initial step size.
+    pub sigma: f32,
+}
+
+impl Default for WeightOptimizer {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::default(),
+            evaluations: 200,
+            sigma: 0.5,
+        }
+    }
+}
+
+/// A free weight of a genome: an untied enabled edge or a weight group.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Edge(usize),
+    Group(usize),
+}
+
+impl WeightOptimizer {
+    /// Maximise `fitness` over `initial`. Returns the best weights found and
+    /// their fitness. Evaluates `fitness` at most `1 + evaluations` times.
+    pub fn search(
+        &self,
+        rng: &mut dyn RngCore,
+        initial: Vec<f32>,
+        mut fitness: impl FnMut(&[f32]) -> f32,
+    ) -> (Vec<f32>, f32) {
+        let value = fitness(&initial);
+        if initial.is_empty() {
+            return (initial, value);
+        }
+        match self.strategy {
+            Strategy::OnePlusOne => self.one_plus_one(rng, (initial, value), fitness),
+            Strategy::SepCma { offspring } => {
+                let offspring = offspring
+                    .unwrap_or(4 + (3. * (initial.len() as f32).ln()) as usize)
+                    .max(2);
+                self.sep_cma(rng, (initial, value), offspring, fitness)
+            }
+        }
+    }
+
+    /// Maximise `fitness` over the free weights of `genome`: its untied
+    /// enabled edges and the groups of its tied ones. Returns the best genome
+    /// found and its fitness, `genome` itself if nothing helped.
+    pub fn optimize(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &Genome,
+        mut fitness: impl FnMut(&Genome) -> f32,
+    ) -> (Genome, f32) {
+        let mut groups = BTreeSet::new();
+        let mut slots = vec![];
+        for (index, edge) in genome.genome_list.iter().enumerate() {
+            if !edge.enabled {
+                continue;
+            }
+            match genome.weight_table.groups.get(&edge.innov_number) {
+                Some(group) if groups.insert(*group) => slots.push(Slot::Group(*group)),
+                Some(_) => {}
+                None => slots.push(Slot::Edge(index)),
+            }
+        }
+        let initial = slots
+            .iter()
+            .map(|slot| match slot {
+                Slot::Edge(index) => genome.genome_list.edge_list[*index].weight,
+                Slot::Group(group) => genome.weight_table.weights[group],
+            })
+            .collect_vec();
+        let mut probe = genome.clone();
+        let write = |probe: &mut Genome, weights: &[f32]| {
+            for (slot, weight) in slots.iter().zip(weights) {
+                match slot {
+                    Slot::Edge(index) => probe.genome_list.edge_list[*index].weight = *weight,
+                    Slot::Group(group) => {
+                        probe.weight_table.weights.insert(*group, *weight);
+                    }
+                }
+            }
+            probe.sync_weights();
+        };
+        let (best, value) = self.search(rng, initial.clone(), |weights| {
+            write(&mut probe, weights);
+            fitness(&probe)
+        });
+        if best == initial {
+            return (genome.clone(), value);
+        }
+        write(&mut probe, &best);
+        (probe, value)
+    }
+
+    /// Maximise `fitness` over the weights of a compiled network, in the
+    /// order of [`FFNetwork::weights`]. The network is left with the best
+    /// weights found, see [`FFNetwork::write_back_to_genome`] to keep them.
+    pub fn optimize_network<F: Float>(
+        &self,
+        rng: &mut dyn RngCore,
+        network: &mut FFNetwork<F>,
+        mut fitness: impl FnMut(&mut FFNetwork<F>) -> f32,
+    ) -> f32 {
+        let initial = network
+            .weights()
+            .into_iter()
+            .map(|(_, _, weight)| to_float::<f32>(weight))
+            .collect_vec();
+        let write = |network: &mut FFNetwork<F>, weights: &[f32]| {
+            for (slot, weight) in network.weights_mut().zip(weights) {
+                *slot = to_float(*weight);
+            }
+        };
+        let (best, value) = self.search(rng, initial, |weights| {
+            write(network, weights);
+            fitness(network)
+        });
+        write(network, &best);
+        value
+    }
+
+    fn one_plus_one(
+        &self,
+        rng: &mut dyn RngCore,
+        mut best: (Vec<f32>, f32),
+        mut fitness: impl FnMut(&[f32]) -> f32,
+    ) -> (Vec<f32>, f32) {
+        let damping = 1. + best.0.len() as f32 / 2.;
+        let mut sigma = self.sigma;
+        for _ in 0..self.evaluations {
+            let mutant = best
+                .0
+                .iter()
+                .map(|w| w + sigma * gaussian(rng))
+                .collect_vec();
+            let value = fitness(&mutant);
+            // Grow the step on success, shrink it otherwise, so that about a
+            // fifth of the mutants succeed
+            if value > best.1 || best.1.is_nan() {
+                best = (mutant, value);
+                sigma *= (0.8 / damping).exp();
+            } else {
+                sigma *= (-0.2 / damping).exp();
+            }
+        }
+        best
+    }
+
+    /// Separable CMA-ES after Ros and Hansen (2008).
+    fn sep_cma(
+        &self,
+        rng: &mut dyn RngCore,
+        mut best: (Vec<f32>, f32),
+        offspring: usize,
+        mut fitness: impl FnMut(&[f32]) -> f32,
+    ) -> (Vec<f32>, f32) {
+        let n = best.0.len() as f32;
+        let parents = offspring / 2;
+        let recombination = (1..=parents)
+            .map(|i| (parents as f32 + 0.5).ln() - (i as f32).ln())
+            .collect_vec();
+        let total = recombination.iter().sum::<f32>();
+        let recombination = recombination.iter().map(|w| w / total).collect_vec();
+        let mu_eff = 1. / recombination.iter().map(|w| w * w).sum::<f32>();
+
+        let c_sigma = (mu_eff + 2.) / (n + mu_eff + 5.);
+        let d_sigma = 1. + 2. * (((mu_eff - 1.) / (n + 1.)).sqrt() - 1.).max(0.) + c_sigma;
+        let c_c = (4. + mu_eff / n) / (n + 4. + 2. * mu_eff / n);
+        let c_1 = 2. / ((n + 1.3).powi(2) + mu_eff);
+        let c_mu = (2. * (mu_eff - 2. + 1. / mu_eff) / ((n + 2.).powi(2) + mu_eff)).min(1. - c_1);
+        // The diagonal learns faster than a full covariance matrix
+        let (c_1, c_mu) = {
+            let scale = (n + 2.) / 3.;
+            let (c_1, c_mu) = (c_1 * scale, c_mu * scale);
+            let total = c_1 + c_mu;
+            if total > 1. {
+                (c_1 / total, c_mu / total)
+            } else {
+                (c_1, c_mu)
+            }
+        };
+        let chi_n = n.sqrt() * (1. - 1. / (4. * n) + 1. / (21. * n * n));
+
+        let mut mean = best.0.clone();
+        let mut sigma = self.sigma;
+        let mut variance = vec![1f32; mean.len()];
+        let mut path_sigma = vec![0f32; mean.len()];
+        let mut path_c = vec![0f32; mean.len()];
+        let mut budget = self.evaluations;
+        let mut generation = 0;
+        while budget >= offspring {
+            budget -= offspring;
+            generation += 1;
+            let mut samples = (0..offspring)
+                .map(|_| {
+                    let z = mean.iter().map(|_| gaussian(rng)).collect_vec();
+                    let x = mean
+                        .iter()
+                        .zip(&z)
+                        .zip(&variance)
+                        .map(|((m, z), v)| m + sigma * v.sqrt() * z)
+                        .collect_vec();
+                    let value = fitness(&x);
+                    (z, x, value)
+                })
+                .collect_vec();
+            samples.sort_by(|a, b| b.2.total_cmp(&a.2));
+            if samples[0].2 > best.1 || best.1.is_nan() {
+                best = (samples[0].1.clone(), samples[0].2);
+            }
+
+            let mut z_w = vec![0f32; mean.len()];
+            for (weight, (z, _, _)) in recombination.iter().zip(&samples) {
+                for (acc, z) in z_w.iter_mut().zip(z) {
+                    *acc += weight * z;
+                }
+            }
+            let y_w = z_w
+                .iter()
+                .zip(&variance)
+                .map(|(z, v)| v.sqrt() * z)
+                .collect_vec();
+            for (m, y) in mean.iter_mut().zip(&y_w) {
+                *m += sigma * y;
+            }
+
+            let scale = (c_sigma * (2. - c_sigma) * mu_eff).sqrt();
+            for (p, z) in path_sigma.iter_mut().zip(&z_w) {
+                *p = (1. - c_sigma) * *p + scale * z;
+            }
+            let norm = path_sigma.iter().map(|p| p * p).sum::<f32>().sqrt();
+            let correction = (1. - (1. - c_sigma).powi(2 * generation)).sqrt();
+            let stalled = norm / correction >= (1.4 + 2. / (n + 1.)) * chi_n;
+            let h_sigma = if stalled { 0. } else { 1. };
+            let scale = (c_c * (2. - c_c) * mu_eff).sqrt();
+            for (p, y) in path_c.iter_mut().zip(&y_w) {
+                *p = (1. - c_c) * *p + h_sigma * scale * y;
+            }
+
+            for (j, v) in variance.iter_mut().enumerate() {
+                let rank_mu = recombination
+                    .iter()
+                    .zip(&samples)
+                    .map(|(w, (z, _, _))| w * z[j] * z[j])
+                    .sum::<f32>();
+                let rank_one = path_c[j] * path_c[j] + (1. - h_sigma) * c_c * (2. - c_c) * *v;
+                *v = (1. - c_1 - c_mu) * *v + c_1 * rank_one + c_mu * rank_mu * *v;
+            }
+            sigma *= ((c_sigma / d_sigma) * (norm / chi_n - 1.)).exp();
+            if !sigma.is_finite() || sigma <= 0. || mean.iter().any(|m| !m.is_finite()) {
+                break;
+            }
+        }
+        best
+    }
+}
+
+fn gaussian(rng: &mut dyn RngCore) -> f32 {
+    StandardNormal.sample(rng)
+}
+
+/// Periodic weight refinement of the fittest members, see
+/// [`Population::refine_weights`](super::population::Population::refine_weights).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightRefinement {
+    /// Generations between refinements, refining every generation if 1.
+    pub interval: usize,
+    /// Members refined, fittest first.
+    pub elites: usize,
+    pub optimizer: WeightOptimizer,
+    pub inheritance: Inheritance,
+}
+
+impl Default for WeightRefinement {
+    fn default() -> Self {
+        Self {
+            interval: 10,
+            elites: 1,
+            optimizer: WeightOptimizer::default(),
+            inheritance: Inheritance::Lamarckian,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::{
+        activation::Activation, aggregation::Aggregation, genome::GenomeBuilder,
+    };
+
+    /// Peak at `(1, -2, 3, ...)`, badly scaled along the first weight.
+    fn sphere(weights: &[f32]) -> f32 {
+        -weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                let target = (i + 1) as f32 * if i % 2 == 0 { 1. } else { -1. };
+                let scale = if i == 0 { 10. } else { 1. };
+                scale * (w - target).powi(2)
+            })
+            .sum::<f32>()
+    }
+
+    #[test]
+    fn test_search() {
+        for strategy in [
+            Strategy::OnePlusOne,
+            Strategy::default(),
+            Strategy::SepCma {
+                offspring: Some(12),
+            },
+        ] {
+            let mut rng = ChaCha8Rng::seed_from_u64(5);
+            let optimizer = WeightOptimizer {
+                strategy,
+                evaluations: 2000,
+                sigma: 1.,
+            };
+            let mut calls = 0;
+            let (best, value) = optimizer.search(&mut rng, vec![0.; 4], |w| {
+                calls += 1;
+                sphere(w)
+            });
+            assert!(value > -1e-3, "{strategy:?}: {value}");
+            assert_eq!(value, sphere(&best));
+            assert!(calls <= 2001);
+        }
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let (best, _) = WeightOptimizer::default().search(&mut rng, vec![], sphere);
+        assert!(best.is_empty());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let mut genome = GenomeBuilder::new()
+            .input(2)
+            .output(2)
+            .connect(0, 2, 0.)
+            .connect(1, 2, 0.)
+            .connect(0, 3, 0.)
+            .connect(1, 3, 5.)
+            .build()
+            .unwrap();
+        genome.genome_list.edge_list[3].enabled = false;
+        let innovations = genome
+            .genome_list
+            .iter()
+            .map(|e| e.innov_number)
+            .collect_vec();
+        genome
+            .tie_weights(0, vec![innovations[1], innovations[2]])
+            .unwrap();
+        // Edges 1 and 2 share a weight, the disabled edge 3 is left alone
+        let fitness = |g: &Genome| {
+            let w = g.genome_list.iter().map(|e| g.weight(e)).collect_vec();
+            -(w[0] - 1.).powi(2) - (w[1] + 2.).powi(2) - (w[2] + 2.).powi(2)
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let (tuned, value) = WeightOptimizer::default().optimize(&mut rng, &genome, fitness);
+        assert!(value > -1e-3);
+        assert_eq!(value, fitness(&tuned));
+        assert_eq!(tuned.genome_list.edge_list[3].weight, 5.);
+        assert_eq!(
+            tuned.genome_list.edge_list[1].weight,
+            tuned.weight_table.weights[&0]
+        );
+        assert_eq!(
+            tuned.genome_list.edge_list[2].weight,
+            tuned.weight_table.weights[&0]
+        );
+        assert!(genome.genome_list.iter().take(3).all(|e| e.weight == 0.));
+    }
+
+    #[test]
+    fn test_optimize_network() {
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .activation(2, Activation::Identity)
+            .aggregation(2, Aggregation::Sum)
+            .connect(0, 2, 0.)
+            .connect(1, 2, 0.)
+            .build()
+            .unwrap();
+        let mut network = FFNetwork::<f32>::from(&genome);
+        // Fit y = 2a - b
+        let cases = [([1., 0.], 2.), ([0., 1.], -1.), ([1., 1.], 1.)];
+        let fitness = |network: &mut FFNetwork<f32>| {
+            -cases
+                .iter()
+                .map(|(input, target)| (network.forward(input).unwrap()[0] - target).powi(2))
+                .sum::<f32>()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let optimizer = WeightOptimizer {
+            strategy: Strategy::OnePlusOne,
+            ..Default::default()
+        };
+        let value = optimizer.optimize_network(&mut rng, &mut network, fitness);
+        assert!(value > -1e-3);
+        let mut tuned = genome.clone();
+        network.write_back_to_genome(&mut tuned).unwrap();
+        let weights = tuned.genome_list.iter().map(|e| e.weight).collect_vec();
+        assert!((weights[0] - 2.).abs() < 0.05 && (weights[1] + 1.).abs() < 0.05);
+    }
+}
@@ -0,0 +1,154 @@
+use rand::RngCore;
+
+use super::selection_trait::{FitnessTransform, RoulleteSelection, SelectionMethod};
+use crate::individual::individual::Individual;
+
+/// Temperature of Boltzmann selection by generation. High temperatures
+/// select nearly uniformly, low ones nearly always the fittest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemperatureSchedule {
+    Constant(f32),
+    /// `initial * rate^generation`, never below `min`.
+    Exponential { initial: f32, rate: f32, min: f32 },
+    /// From `initial` down to `last` over `generations`, `last` after.
+    Linear {
+        initial: f32,
+        last: f32,
+        generations: usize,
+    },
+}
+
+impl TemperatureSchedule {
+    pub fn temperature(&self, generation: usize) -> f32 {
+        match *self {
+            TemperatureSchedule::Constant(temperature) => temperature,
+            TemperatureSchedule::Exponential { initial, rate, min } => {
+                (initial * rate.powi(generation.min(i32::MAX as usize) as i32)).max(min)
+            }
+            TemperatureSchedule::Linear {
+                initial,
+                last,
+                generations,
+            } => {
+                let t = match generations {
+                    0 => 1.,
+                    generations => generation.min(generations) as f32 / generations as f32,
+                };
+                initial + (last - initial) * t
+            }
+        }
+    }
+}
+
+/// Roulette selection on the softmax of the fitness, with the temperature
+/// of the schedule at the current generation, so a run moves from
+/// exploration to exploitation. The population sets the generation before
+/// breeding, see [`SelectionMethod::set_generation`].
+pub struct BoltzmannSelection {
+    schedule: TemperatureSchedule,
+    generation: usize,
+    roulette: RoulleteSelection,
+}
+
+impl BoltzmannSelection {
+    /// Panics if the schedule starts at a temperature that is not positive.
+    pub fn new(schedule: TemperatureSchedule) -> Self {
+        let mut selection = Self {
+            schedule,
+            generation: 0,
+            roulette: RoulleteSelection::new(),
+        };
+        assert!(selection.temperature() > 0., "Temperature should be positive");
+        selection.set_generation(0);
+        selection
+    }
+
+    /// Never pair an individual with itself, unless it is alone.
+    pub fn with_no_self_mating(self, no_self_mating: bool) -> Self {
+        Self {
+            roulette: self.roulette.with_no_self_mating(no_self_mating),
+            ..self
+        }
+    }
+
+    /// Temperature at the current generation.
+    pub fn temperature(&self) -> f32 {
+        self.schedule.temperature(self.generation)
+    }
+}
+
+impl SelectionMethod for BoltzmannSelection {
+    fn select<'b, I>(&self, rng: &mut dyn RngCore, population: &[&'b I]) -> &'b I
+    where
+        I: Individual,
+    {
+        self.roulette.select(rng, population)
+    }
+
+    fn select_pair<'a, I>(&self, rng: &mut dyn RngCore, population: &[&'a I]) -> (&'a I, &'a I)
+    where
+        I: Individual,
+    {
+        self.roulette.select_pair(rng, population)
+    }
+
+    /// Temperatures that are not positive fall back to rank weights, as
+    /// every degenerate softmax does.
+    fn set_generation(&mut self, generation: usize) {
+        self.generation = generation;
+        self.roulette.transform = FitnessTransform::Softmax {
+            temperature: self.temperature(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::selection::selection_trait::tests::TestIndividual;
+
+    #[test]
+    fn test_schedule() {
+        let exponential = TemperatureSchedule::Exponential {
+            initial: 8.,
+            rate: 0.5,
+            min: 0.5,
+        };
+        let temperatures = (0..6).map(|g| exponential.temperature(g)).collect_vec();
+        assert_eq!(temperatures, [8., 4., 2., 1., 0.5, 0.5]);
+        let linear = TemperatureSchedule::Linear {
+            initial: 2.,
+            last: 1.,
+            generations: 4,
+        };
+        assert_eq!(linear.temperature(2), 1.5);
+        assert_eq!(linear.temperature(10), 1.);
+        assert_eq!(TemperatureSchedule::Constant(3.).temperature(100), 3.);
+    }
+
+    #[test]
+    fn test_boltzmann() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let population = [1., 2., 3.].map(TestIndividual::new);
+        let refs = population.iter().collect_vec();
+        let mut method = BoltzmannSelection::new(TemperatureSchedule::Exponential {
+            initial: 100.,
+            rate: 0.1,
+            min: 0.01,
+        });
+        let mut fittest = |method: &BoltzmannSelection| {
+            (0..1_000)
+                .filter(|_| method.select(&mut rng, &refs).fitness() == 3.)
+                .count()
+        };
+        // Nearly uniform when hot, nearly greedy when cold
+        assert!((280..390).contains(&fittest(&method)));
+        method.set_generation(5);
+        assert_eq!(method.temperature(), 0.01);
+        assert_eq!(fittest(&method), 1_000);
+    }
+}
@@ -0,0 +1,113 @@
+use rand::{Rng, RngCore};
+
+use super::selection_trait::SelectionMethod;
+use crate::individual::individual::Individual;
+
+/// Fitness `Population::evaluate_criterion` gives the members meeting the
+/// criterion, the others get 0.
+pub const SATISFIED: f32 = 1.;
+
+/// Minimal criterion selection, as in minimal criterion coevolution:
+/// parents are drawn uniformly among the individuals whose fitness is at
+/// least the threshold, however far above it. Without any, among everyone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimalCriterionSelection {
+    threshold: f32,
+}
+
+impl Default for MinimalCriterionSelection {
+    fn default() -> Self {
+        Self {
+            threshold: SATISFIED,
+        }
+    }
+}
+
+impl MinimalCriterionSelection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Criterion on a raw fitness, e.g. a minimal episode reward.
+    pub fn with_threshold(self, threshold: f32) -> Self {
+        Self { threshold }
+    }
+
+    /// Whether `fitness` meets the criterion, never for NaN.
+    pub fn satisfies(&self, fitness: f32) -> bool {
+        fitness >= self.threshold
+    }
+}
+
+impl SelectionMethod for MinimalCriterionSelection {
+    fn select<'b, I>(&self, rng: &mut dyn RngCore, population: &[&'b I]) -> &'b I
+    where
+        I: Individual,
+    {
+        assert!(!population.is_empty(), "Population should not be empty");
+        let satisfied = population
+            .iter()
+            .filter(|individual| self.satisfies(individual.fitness()))
+            .collect::<Vec<_>>();
+        if satisfied.is_empty() {
+            population[rng.gen_range(0..population.len())]
+        } else {
+            satisfied[rng.gen_range(0..satisfied.len())]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::individual::genome::genome::Genome;
+
+    struct Fit(f32);
+
+    impl Individual for Fit {
+        fn fitness(&self) -> f32 {
+            self.0
+        }
+
+        fn to_genome(&self) -> Genome {
+            unimplemented!("Selection only reads the fitness")
+        }
+    }
+
+    fn counts(selection: MinimalCriterionSelection, fitness: &[f32]) -> Vec<usize> {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let population = fitness.iter().map(|f| Fit(*f)).collect_vec();
+        let population = population.iter().collect_vec();
+        let mut counts = vec![0; fitness.len()];
+        for _ in 0..3000 {
+            let selected = selection.select(&mut rng, &population);
+            let index = population
+                .iter()
+                .position(|p| std::ptr::eq(*p, selected))
+                .unwrap();
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_select() {
+        let selection = MinimalCriterionSelection::new();
+        let counts = counts(selection, &[0., SATISFIED, f32::NAN, 100.]);
+        assert_eq!((counts[0], counts[2]), (0, 0));
+        // Uniform among the satisfiers, however fit
+        assert!(counts[1].abs_diff(counts[3]) < 200);
+
+        let counts = self::counts(selection, &[0., 0.5]);
+        assert!(counts.iter().all(|c| *c > 1000));
+
+        let raw = selection.with_threshold(200.);
+        assert!(raw.satisfies(250.));
+        assert!(!raw.satisfies(100.));
+        assert!(!raw.satisfies(f32::NAN));
+    }
+}
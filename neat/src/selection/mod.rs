@@ -1 +1,3 @@
-pub mod selection_trait;
\ No newline at end of file
+pub mod selection_trait;
+pub mod minimal_criterion;
+pub mod boltzmann;
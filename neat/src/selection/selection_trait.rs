@@ -1,39 +1,184 @@
+use std::cmp::Ordering;
+
 use itertools::Itertools;
-use rand::{seq::SliceRandom, RngCore};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng, RngCore,
+};
 
 use crate::individual::individual::Individual;
 
 pub trait SelectionMethod {
-    fn select<'a, 'b, I>(&self, rng: &mut dyn RngCore, population: &'a [&'b I]) -> &'b I
+    fn select<'b, I>(&self, rng: &mut dyn RngCore, population: &[&'b I]) -> &'b I
     where
         I: Individual;
+
+    /// Parents of a child. By default two independent selections, which may
+    /// pick the same individual twice.
+    fn select_pair<'a, I>(&self, rng: &mut dyn RngCore, population: &[&'a I]) -> (&'a I, &'a I)
+    where
+        I: Individual,
+    {
+        (self.select(rng, population), self.select(rng, population))
+    }
+
+    /// Called by the population before breeding every generation, for
+    /// methods that change over the run. Nothing by default.
+    fn set_generation(&mut self, _generation: usize) {}
+}
+
+/// How fitness values are turned into roulette weights.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FitnessTransform {
+    /// Fitness is used as the weight. Negative and NaN fitness get no weight.
+    #[default]
+    Raw,
+    /// Shift fitness so the worst individual has (almost) zero weight.
+    ShiftToPositive,
+    /// Softmax over fitness, lower temperatures favour the fittest.
+    Softmax { temperature: f32 },
+    /// Weight by rank, the worst individual having weight 1.
+    Rank,
+}
+
+/// Smallest weight given after shifting, so that the worst individual
+/// can still be selected.
+const SHIFT_EPSILON: f32 = 1e-3;
+
+impl FitnessTransform {
+    /// Weights for the given fitness values. NaN fitness is ranked below
+    /// every other value. If the transform yields no usable weights, rank
+    /// weights are used instead.
+    pub fn weights(&self, fitness: &[f32]) -> Vec<f32> {
+        let finite = || fitness.iter().copied().filter(|f| !f.is_nan());
+        let weights = match self {
+            FitnessTransform::Raw => fitness
+                .iter()
+                .map(|f| if *f > 0. { *f } else { 0. })
+                .collect_vec(),
+            FitnessTransform::ShiftToPositive => {
+                let min = finite().fold(f32::INFINITY, f32::min);
+                fitness
+                    .iter()
+                    .map(|f| if f.is_nan() { 0. } else { f - min + SHIFT_EPSILON })
+                    .collect_vec()
+            }
+            FitnessTransform::Softmax { temperature } => {
+                let max = finite().fold(f32::NEG_INFINITY, f32::max);
+                fitness
+                    .iter()
+                    .map(|f| {
+                        if f.is_nan() {
+                            0.
+                        } else {
+                            ((f - max) / temperature).exp()
+                        }
+                    })
+                    .collect_vec()
+            }
+            FitnessTransform::Rank => return rank_weights(fitness),
+        };
+        if is_degenerate(&weights) {
+            rank_weights(fitness)
+        } else {
+            weights
+        }
+    }
+}
+
+fn is_degenerate(weights: &[f32]) -> bool {
+    let total = weights.iter().sum::<f32>();
+    !total.is_finite() || total <= 0. || weights.iter().any(|w| !w.is_finite() || *w < 0.)
+}
+
+fn rank_weights(fitness: &[f32]) -> Vec<f32> {
+    let mut weights = vec![0.; fitness.len()];
+    fitness
+        .iter()
+        .enumerate()
+        .sorted_by(|(_, a), (_, b)| match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => a.total_cmp(b),
+        })
+        .enumerate()
+        .for_each(|(rank, (index, _))| weights[index] = (rank + 1) as f32);
+    weights
 }
 
 #[derive(Default)]
-pub struct RoulleteSelection;
+pub struct RoulleteSelection {
+    pub(super) transform: FitnessTransform,
+    no_self_mating: bool,
+}
 
 impl RoulleteSelection {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_transform(transform: FitnessTransform) -> Self {
+        Self {
+            transform,
+            ..Self::default()
+        }
+    }
+
+    /// Never pair an individual with itself, unless it is alone.
+    pub fn with_no_self_mating(self, no_self_mating: bool) -> Self {
+        Self {
+            no_self_mating,
+            ..self
+        }
+    }
+
+    fn weights<I: Individual>(&self, population: &[&I]) -> Vec<f32> {
+        self.transform
+            .weights(&population.iter().map(|s| s.fitness()).collect_vec())
     }
 }
 
 impl SelectionMethod for RoulleteSelection {
-    fn select<'a, 'b, I>(&self, rng: &mut dyn RngCore, population: &'a [&'b I]) -> &'b I
+    /// Degenerate fitness (all zero, negative, NaN) falls back to rank weights
+    /// instead of panicking.
+    fn select<'b, I>(&self, rng: &mut dyn RngCore, population: &[&'b I]) -> &'b I
     where
         I: Individual,
     {
-        let total_weight = population.iter().map(|s| s.fitness()).sum::<f32>();
-        population
-            .choose_weighted(rng, |el| el.fitness() / total_weight)
-            .expect("should not surpass")
+        let index = WeightedIndex::new(self.weights(population))
+            .expect("Population should not be empty")
+            .sample(rng);
+        population[index]
+    }
+
+    /// Without self-mating, the second parent is drawn among the others,
+    /// uniformly if none of them has weight.
+    fn select_pair<'a, I>(&self, rng: &mut dyn RngCore, population: &[&'a I]) -> (&'a I, &'a I)
+    where
+        I: Individual,
+    {
+        if !self.no_self_mating || population.len() < 2 {
+            return (self.select(rng, population), self.select(rng, population));
+        }
+        let mut weights = self.weights(population);
+        let first = WeightedIndex::new(&weights)
+            .expect("Population should not be empty")
+            .sample(rng);
+        weights[first] = 0.;
+        let second = match WeightedIndex::new(&weights) {
+            Ok(index) => index.sample(rng),
+            Err(_) => (first + rng.gen_range(1..population.len())) % population.len(),
+        };
+        (population[first], population[second])
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(super) mod tests {
     use std::collections::BTreeMap;
 
+    use proptest::prelude::*;
     use rand::SeedableRng;
     use rand_chacha::ChaCha8Rng;
 
@@ -68,7 +213,7 @@ mod tests {
         let method = RoulleteSelection::new();
         let mut rng = ChaCha8Rng::from_seed(Default::default());
 
-        let population = vec![
+        let population = [
             TestIndividual::new(2.0),
             TestIndividual::new(1.0),
             TestIndividual::new(4.0),
@@ -87,8 +232,94 @@ mod tests {
         let els = actual_histogram
             .iter()
             .sorted_by(|(_, a2), (_, b2)| a2.cmp(b2))
-            .map(|(x, _)| (*x))
+            .map(|(x, _)| *x)
             .collect_vec();
         assert_eq!(els, vec![1, 2, 3, 4]);
     }
+
+    fn degenerate_fitness() -> impl Strategy<Value = Vec<f32>> {
+        prop::collection::vec(
+            prop_oneof![
+                Just(f32::NAN),
+                Just(0.),
+                Just(f32::INFINITY),
+                Just(f32::NEG_INFINITY),
+                Just(f32::MAX),
+                -10.0f32..0.,
+                any::<f32>(),
+            ],
+            1..20,
+        )
+    }
+
+    const TRANSFORMS: [FitnessTransform; 5] = [
+        FitnessTransform::Raw,
+        FitnessTransform::ShiftToPositive,
+        FitnessTransform::Softmax { temperature: 1. },
+        FitnessTransform::Softmax { temperature: 0. },
+        FitnessTransform::Rank,
+    ];
+
+    proptest! {
+        #[test]
+        fn weights_are_usable(fitness in degenerate_fitness()) {
+            for transform in TRANSFORMS {
+                let weights = transform.weights(&fitness);
+                prop_assert_eq!(weights.len(), fitness.len());
+                prop_assert!(weights.iter().all(|w| w.is_finite() && *w >= 0.));
+                prop_assert!(weights.iter().sum::<f32>() > 0.);
+            }
+        }
+
+        #[test]
+        fn select_degenerate_population(fitness in degenerate_fitness()) {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let population = fitness.iter().copied().map(TestIndividual::new).collect_vec();
+            let refs = population.iter().collect_vec();
+            for transform in TRANSFORMS {
+                let method = RoulleteSelection::with_transform(transform);
+                for _ in 0..10 {
+                    let selected = method.select(&mut rng, &refs);
+                    prop_assert!(refs.iter().any(|r| std::ptr::eq(*r, selected)));
+                }
+            }
+        }
+
+        #[test]
+        fn nan_ranked_last(fitness in prop::collection::vec(-1e6f32..1e6, 1..20)) {
+            let mut with_nan = fitness.clone();
+            with_nan.push(f32::NAN);
+            let weights = FitnessTransform::Rank.weights(&with_nan);
+            prop_assert!(weights[..fitness.len()].iter().all(|w| *w > weights[fitness.len()]));
+        }
+    }
+
+    #[test]
+    fn zero_sum_falls_back_to_rank() {
+        let fitness = [0., 0., 0.];
+        let weights = FitnessTransform::Raw.weights(&fitness);
+        assert!(weights.iter().all(|w| *w > 0.));
+
+        let fitness = [-3., -1., -2.];
+        let weights = FitnessTransform::Raw.weights(&fitness);
+        assert_eq!(weights, vec![1., 3., 2.]);
+    }
+
+    #[test]
+    fn test_select_pair() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let population = [5., 0., 0.].map(TestIndividual::new);
+        let refs = population.iter().collect_vec();
+        let method = RoulleteSelection::new().with_no_self_mating(true);
+        for _ in 0..100 {
+            let (a, b) = method.select_pair(&mut rng, &refs);
+            assert!(!std::ptr::eq(a, b));
+            assert_eq!(a.fitness(), 5.);
+        }
+        let (a, b) = method.select_pair(&mut rng, &refs[..1]);
+        assert!(std::ptr::eq(a, b));
+        // Duplicates are allowed by default
+        let (a, b) = RoulleteSelection::new().select_pair(&mut rng, &refs);
+        assert!(std::ptr::eq(a, b));
+    }
 }
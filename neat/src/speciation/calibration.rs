@@ -0,0 +1,167 @@
+use itertools::Itertools;
+use rand::{Rng, RngCore};
+
+use super::{
+    distance::DistanceMetric,
+    speciation::{speciate_greedy, Assignment},
+};
+
+/// Threshold found by [`ThresholdCalibration::calibrate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub threshold: f32,
+    /// Species the population splits into at `threshold`.
+    pub species: usize,
+}
+
+/// Search for the starting distance threshold splitting a population into
+/// about `target_species` species, instead of tuning it by hand. Candidate
+/// thresholds are sampled pairwise distances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdCalibration {
+    pub target_species: usize,
+    /// Pairs of individuals to measure, every pair if there are fewer.
+    pub samples: usize,
+}
+
+impl ThresholdCalibration {
+    pub fn new(target_species: usize) -> Self {
+        Self {
+            target_species,
+            samples: 1000,
+        }
+    }
+
+    pub fn with_samples(self, samples: usize) -> Self {
+        Self { samples, ..self }
+    }
+
+    /// Sampled distances between distinct individuals of `population`, sorted.
+    pub fn sample_distances<G: ?Sized, C: AsRef<G>>(
+        &self,
+        rng: &mut dyn RngCore,
+        metric: &dyn DistanceMetric<G>,
+        population: &[C],
+    ) -> Vec<f32> {
+        let n = population.len();
+        let distance =
+            |i: usize, j: usize| metric.distance(population[i].as_ref(), population[j].as_ref());
+        let mut distances = if n < 2 {
+            vec![]
+        } else if n * (n - 1) / 2 <= self.samples {
+            (0..n)
+                .tuple_combinations()
+                .map(|(i, j)| distance(i, j))
+                .collect_vec()
+        } else {
+            (0..self.samples)
+                .map(|_| {
+                    let i = rng.gen_range(0..n);
+                    let j = (i + rng.gen_range(1..n)) % n;
+                    distance(i, j)
+                })
+                .collect_vec()
+        };
+        distances.sort_unstable_by(f32::total_cmp);
+        distances
+    }
+
+    /// Threshold whose species count is nearest the target, preferring
+    /// fewer species on ties. `None` if no two individuals are apart, as in
+    /// a population of identical genomes, when any threshold fits.
+    pub fn calibrate<G: ?Sized, C: AsRef<G>>(
+        &self,
+        rng: &mut dyn RngCore,
+        metric: &dyn DistanceMetric<G>,
+        assignment: Assignment,
+        population: &[C],
+    ) -> Option<Calibration> {
+        let distances = self
+            .sample_distances(rng, metric, population)
+            .into_iter()
+            .filter(|d| *d > 0. && d.is_finite())
+            .dedup()
+            .collect_vec();
+        if distances.is_empty() {
+            return None;
+        }
+        let candidates = [0.].into_iter().chain(distances).collect_vec();
+        let species = |threshold: f32| {
+            speciate_greedy(&[], population.iter(), assignment, |a: &C, b: &C| {
+                let distance = metric.distance(a.as_ref(), b.as_ref());
+                (distance <= threshold).then_some(distance)
+            })
+            .len()
+        };
+        // Species only get fewer as the threshold grows, up to greedy noise
+        let (mut low, mut high) = (0, candidates.len());
+        let mut best: Option<Calibration> = None;
+        while low < high {
+            let mid = (low + high) / 2;
+            let calibration = Calibration {
+                threshold: candidates[mid],
+                species: species(candidates[mid]),
+            };
+            let error = |c: &Calibration| c.species.abs_diff(self.target_species);
+            if best
+                .is_none_or(|b| (error(&calibration), calibration.species) < (error(&b), b.species))
+            {
+                best = Some(calibration);
+            }
+            if calibration.species > self.target_species {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::speciation::distance::Euclidean;
+
+    /// Three clusters of 10 points, 10 apart.
+    fn clusters() -> Vec<[f32; 1]> {
+        (0..30)
+            .map(|i| [(i / 10) as f32 * 10. + (i % 10) as f32 * 0.1])
+            .collect()
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let points = clusters();
+        for target in [1, 3, 30] {
+            let calibration = ThresholdCalibration::new(target)
+                .calibrate(&mut rng, &Euclidean, Assignment::First, &points)
+                .unwrap();
+            assert_eq!(calibration.species, target);
+        }
+        let sampled = ThresholdCalibration::new(3)
+            .with_samples(100)
+            .calibrate(&mut rng, &Euclidean, Assignment::First, &points)
+            .unwrap();
+        assert_eq!(sampled.species, 3);
+        assert!((0.9..10.).contains(&sampled.threshold));
+    }
+
+    #[test]
+    fn test_uniform() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let calibration = ThresholdCalibration::new(3);
+        let points = vec![[1f32]; 5];
+        assert_eq!(
+            calibration.calibrate(&mut rng, &Euclidean, Assignment::First, &points),
+            None
+        );
+        assert!(calibration
+            .sample_distances(&mut rng, &Euclidean, &points[..1])
+            .is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::individual::genome::genome::Genome;
+
+use super::{distance::DistanceMetric, speciation::Comparable};
+
+/// Genomes smaller than this are not normalised by their size.
+pub const SMALL_GENOME: usize = 20;
+
+/// NEAT compatibility distance `c1 * E / N + c2 * D / N + c3 * W`, where `E`
+/// and `D` are the excess and disjoint edge counts and `W` the mean weight
+/// difference of matching edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compatibility {
+    pub excess: f32,
+    pub disjoint: f32,
+    pub weight: f32,
+}
+
+impl Default for Compatibility {
+    fn default() -> Self {
+        Self {
+            excess: 1.,
+            disjoint: 1.,
+            weight: 0.4,
+        }
+    }
+}
+
+impl Compatibility {
+    pub fn new(excess: f32, disjoint: f32, weight: f32) -> Self {
+        Self {
+            excess,
+            disjoint,
+            weight,
+        }
+    }
+
+    pub fn distance(&self, fst: &Genome, snd: &Genome) -> f32 {
+        let fst_list = &fst.genome_list.edge_list;
+        let snd_list = &snd.genome_list.edge_list;
+        let (mut i, mut j) = (0, 0);
+        let (mut disjoint, mut matching, mut weight_diff) = (0, 0, 0.);
+        while i < fst_list.len() && j < snd_list.len() {
+            match fst_list[i].cmp(&snd_list[j]) {
+                std::cmp::Ordering::Less => {
+                    disjoint += 1;
+                    i += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    disjoint += 1;
+                    j += 1;
+                }
+                std::cmp::Ordering::Equal => {
+                    matching += 1;
+                    weight_diff += (fst_list[i].weight - snd_list[j].weight).abs();
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let excess = (fst_list.len() - i) + (snd_list.len() - j);
+        let size = fst_list.len().max(snd_list.len());
+        self.combine(excess, disjoint, matching, weight_diff, size)
+    }
+
+    /// Distance from the gene counts, `weight_diff` being the summed weight
+    /// difference of the matching genes and `size` the larger genome size.
+    pub(super) fn combine(
+        &self,
+        excess: usize,
+        disjoint: usize,
+        matching: usize,
+        weight_diff: f32,
+        size: usize,
+    ) -> f32 {
+        let norm = if size < SMALL_GENOME { 1. } else { size as f32 };
+        let mean_weight = if matching == 0 {
+            0.
+        } else {
+            weight_diff / matching as f32
+        };
+        self.excess * excess as f32 / norm
+            + self.disjoint * disjoint as f32 / norm
+            + self.weight * mean_weight
+    }
+
+    /// Similarity in `(0, 1]` used by [`Comparable`], `1` for identical genomes.
+    pub fn similarity(&self, fst: &Genome, snd: &Genome) -> f32 {
+        (1. + self.distance(fst, snd)).recip()
+    }
+}
+
+impl DistanceMetric<Genome> for Compatibility {
+    fn distance(&self, fst: &Genome, snd: &Genome) -> f32 {
+        Compatibility::distance(self, fst, snd)
+    }
+}
+
+/// Genomes are compared with the default [`Compatibility`] coefficients.
+impl Comparable for Genome {
+    fn compare(&self, other: &Self) -> f32 {
+        Compatibility::default().similarity(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::fixtures::genes;
+
+    #[test]
+    fn test_distance() {
+        let fst = genes(&[(1, 1.), (2, 1.), (4, 0.5)]);
+        let snd = genes(&[(1, 0.), (3, 1.), (4, 0.5), (5, 1.), (6, 1.)]);
+        // matching 1, 4; disjoint 2, 3; excess 5, 6
+        let metric = Compatibility::new(1., 2., 1.);
+        assert_relative_eq!(metric.distance(&fst, &snd), 2. + 2. * 2. + 0.5);
+        assert_relative_eq!(metric.distance(&snd, &fst), metric.distance(&fst, &snd));
+        assert_relative_eq!(metric.distance(&fst, &fst), 0.);
+        assert_relative_eq!(fst.compare(&fst), 1.);
+    }
+}
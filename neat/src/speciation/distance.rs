@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use crate::individual::genome::genome::Genome;
+
+/// Distance between two values of `G`, independent of the individual type
+/// carrying them. Smaller is closer, `0` for identical values.
+pub trait DistanceMetric<G: ?Sized> {
+    fn distance(&self, fst: &G, snd: &G) -> f32;
+}
+
+impl<G: ?Sized, F: Fn(&G, &G) -> f32> DistanceMetric<G> for F {
+    fn distance(&self, fst: &G, snd: &G) -> f32 {
+        self(fst, snd)
+    }
+}
+
+/// Graph-edit-like distance: cost of the node and edge insertions and
+/// deletions turning one topology into the other, plus weight substitution
+/// for connections present in both. Connections are matched by their
+/// endpoints rather than their innovation number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphEditDistance {
+    pub node_cost: f32,
+    pub edge_cost: f32,
+    pub weight_cost: f32,
+}
+
+impl Default for GraphEditDistance {
+    fn default() -> Self {
+        Self {
+            node_cost: 1.,
+            edge_cost: 1.,
+            weight_cost: 0.,
+        }
+    }
+}
+
+impl DistanceMetric<Genome> for GraphEditDistance {
+    fn distance(&self, fst: &Genome, snd: &Genome) -> f32 {
+        let edges = |genome: &Genome| {
+            genome
+                .genome_list
+                .iter()
+                .filter(|edge| edge.enabled)
+                .map(|edge| ((edge.in_node, edge.out_node), edge.weight))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (fst_edges, snd_edges) = (edges(fst), edges(snd));
+        let edge_cost = fst_edges
+            .iter()
+            .map(|(key, w)| match snd_edges.get(key) {
+                Some(other) => self.weight_cost * (w - other).abs(),
+                None => self.edge_cost,
+            })
+            .sum::<f32>()
+            + snd_edges
+                .keys()
+                .filter(|key| !fst_edges.contains_key(key))
+                .count() as f32
+                * self.edge_cost;
+
+        let fst_hidden = &fst.node_list.hidden;
+        let snd_hidden = &snd.node_list.hidden;
+        let shared = fst_hidden
+            .iter()
+            .filter(|node| snd_hidden.binary_search(node).is_ok())
+            .count();
+        let node_cost = (fst_hidden.len() + snd_hidden.len() - 2 * shared) as f32 * self.node_cost;
+        node_cost + edge_cost
+    }
+}
+
+/// Euclidean distance between behaviour descriptors, e.g. novelty search
+/// characterisations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Euclidean;
+
+impl<D: AsRef<[f32]> + ?Sized> DistanceMetric<D> for Euclidean {
+    fn distance(&self, fst: &D, snd: &D) -> f32 {
+        let (fst, snd) = (fst.as_ref(), snd.as_ref());
+        assert_eq!(fst.len(), snd.len(), "Descriptors should have the same length");
+        fst.iter()
+            .zip(snd.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+            .sqrt()
+    }
+}
+
+impl AsRef<Genome> for Genome {
+    fn as_ref(&self) -> &Genome {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        fixtures::genome,
+        speciation::{
+            compatibility::Compatibility,
+            speciation::{DistanceSpeciation, SpeciationMethod},
+        },
+    };
+
+    #[test]
+    fn test_graph_edit_distance() {
+        // Same connection 0 -> 1 under different innovation numbers
+        let fst = genome(1, 1, &[], &[(1, 0, 1, 1.)]);
+        let snd = genome(1, 1, &[2], &[(7, 0, 1, 0.5), (8, 0, 2, 1.), (9, 2, 1, 1.)]);
+        let metric = GraphEditDistance {
+            weight_cost: 2.,
+            ..Default::default()
+        };
+        assert_relative_eq!(metric.distance(&fst, &snd), 1. + 2. + 2. * 0.5);
+        assert_relative_eq!(metric.distance(&snd, &fst), metric.distance(&fst, &snd));
+        assert_relative_eq!(metric.distance(&snd, &snd), 0.);
+    }
+
+    #[test]
+    fn test_euclidean() {
+        assert_relative_eq!(Euclidean.distance(&[0., 0.][..], &[3., 4.][..]), 5.);
+        assert_relative_eq!(Euclidean.distance(&vec![1., 2.], &vec![1., 2.]), 0.);
+    }
+
+    #[test]
+    fn test_metric_selected_at_run_time() {
+        let population = [
+            genome(1, 1, &[], &[(1, 0, 1, 0.)]),
+            genome(1, 1, &[], &[(2, 0, 1, 0.)]),
+            genome(1, 1, &[], &[(1, 0, 1, 5.)]),
+        ];
+        let metrics: [(Box<dyn DistanceMetric<Genome>>, usize); 2] = [
+            // Innovation numbers differ, weights matter
+            (Box::new(Compatibility::default()), 3),
+            // Endpoints match, weights ignored
+            (Box::new(GraphEditDistance::default()), 1),
+        ];
+        for (metric, expected) in metrics {
+            let spec = DistanceSpeciation::new(metric, 1.);
+            assert_eq!(spec.speciate(population.iter()).len(), expected);
+        }
+
+        let closure = DistanceSpeciation::new(
+            Box::new(|a: &Genome, b: &Genome| {
+                (a.genome_list.edge_list.len() as f32 - b.genome_list.edge_list.len() as f32).abs()
+            }),
+            0.,
+        );
+        assert_eq!(closure.speciate(population.iter()).len(), 1);
+    }
+}
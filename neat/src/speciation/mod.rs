@@ -1 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod speciation;
+pub mod compatibility;
+pub mod distance;
+pub mod signature;
+pub mod calibration;
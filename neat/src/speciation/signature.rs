@@ -0,0 +1,234 @@
+use itertools::Itertools;
+
+use crate::individual::genome::genome::{Genome, InnovationId};
+
+use super::{
+    compatibility::Compatibility,
+    distance::DistanceMetric,
+    speciation::{speciate_greedy, Assignment, SpeciationMethod},
+};
+
+const WORD: InnovationId = u64::BITS as InnovationId;
+
+/// Innovation ids of a genome as a bitset, with the edge weights in
+/// innovation order. Computed once per genome, it turns the compatibility
+/// distance into word-wise set operations instead of a merge of the edge
+/// lists. Innovation numbers are assumed to be unique within a genome.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InnovationSignature {
+    bits: Vec<u64>,
+    /// Set bits before each word, to find the weight of an innovation.
+    ranks: Vec<u32>,
+    weights: Vec<f32>,
+    max: Option<InnovationId>,
+}
+
+impl InnovationSignature {
+    pub fn new(genome: &Genome) -> Self {
+        let edges = genome
+            .genome_list
+            .iter()
+            .sorted_by_key(|edge| edge.innov_number)
+            .dedup_by(|a, b| a.innov_number == b.innov_number)
+            .collect_vec();
+        let max = edges.last().map(|edge| edge.innov_number);
+        let mut bits = vec![0u64; max.map_or(0, |max| (max / WORD) as usize + 1)];
+        for edge in edges.iter() {
+            bits[(edge.innov_number / WORD) as usize] |= 1 << (edge.innov_number % WORD);
+        }
+        let ranks = bits
+            .iter()
+            .scan(0, |acc, word| {
+                let rank = *acc;
+                *acc += word.count_ones();
+                Some(rank)
+            })
+            .collect_vec();
+        Self {
+            bits,
+            ranks,
+            weights: edges.iter().map(|edge| edge.weight).collect_vec(),
+            max,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    pub fn contains(&self, innov_number: InnovationId) -> bool {
+        self.bits
+            .get((innov_number / WORD) as usize)
+            .is_some_and(|word| word & (1 << (innov_number % WORD)) != 0)
+    }
+
+    fn weight(&self, word: usize, bit: u32) -> f32 {
+        let below = self.bits[word] & ((1u64 << bit) - 1);
+        self.weights[(self.ranks[word] + below.count_ones()) as usize]
+    }
+
+    /// Number of innovations greater than `limit`.
+    fn count_above(&self, limit: InnovationId) -> usize {
+        let (word, bit) = ((limit / WORD) as usize, limit % WORD);
+        let Some(first) = self.bits.get(word) else {
+            return 0;
+        };
+        let mask = if bit + 1 == WORD { 0 } else { !0 << (bit + 1) };
+        (first & mask).count_ones() as usize
+            + self.bits[word + 1..]
+                .iter()
+                .map(|w| w.count_ones() as usize)
+                .sum::<usize>()
+    }
+}
+
+impl From<&Genome> for InnovationSignature {
+    fn from(genome: &Genome) -> Self {
+        Self::new(genome)
+    }
+}
+
+impl Compatibility {
+    /// Same value as [`Compatibility::distance`] on the genomes the
+    /// signatures were built from.
+    pub fn signature_distance(&self, fst: &InnovationSignature, snd: &InnovationSignature) -> f32 {
+        let (mut matching, mut weight_diff) = (0, 0.);
+        for (word, (a, b)) in fst.bits.iter().zip(snd.bits.iter()).enumerate() {
+            let mut shared = a & b;
+            while shared != 0 {
+                let bit = shared.trailing_zeros();
+                matching += 1;
+                weight_diff += (fst.weight(word, bit) - snd.weight(word, bit)).abs();
+                shared &= shared - 1;
+            }
+        }
+        let excess = match (fst.max, snd.max) {
+            (Some(a), Some(b)) => fst.count_above(a.min(b)) + snd.count_above(a.min(b)),
+            _ => fst.len() + snd.len(),
+        };
+        let disjoint = fst.len() + snd.len() - 2 * matching - excess;
+        self.combine(
+            excess,
+            disjoint,
+            matching,
+            weight_diff,
+            fst.len().max(snd.len()),
+        )
+    }
+}
+
+impl DistanceMetric<InnovationSignature> for Compatibility {
+    fn distance(&self, fst: &InnovationSignature, snd: &InnovationSignature) -> f32 {
+        self.signature_distance(fst, snd)
+    }
+}
+
+/// Speciation on the compatibility distance, building the signature of every
+/// genome once per call rather than merging edge lists for every pair.
+/// Individuals belong together when their distance is at most the threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignatureSpeciation {
+    compatibility: Compatibility,
+    threshold: f32,
+}
+
+impl SignatureSpeciation {
+    pub fn new(compatibility: Compatibility, threshold: f32) -> Self {
+        Self {
+            compatibility,
+            threshold,
+        }
+    }
+}
+
+impl<C: AsRef<Genome>> SpeciationMethod<C> for SignatureSpeciation {
+    fn speciate_with<'a>(
+        &self,
+        representatives: &[&C],
+        population: impl Iterator<Item = &'a C>,
+    ) -> Vec<Vec<&'a C>>
+    where
+        C: 'a,
+    {
+        // Signatures are keyed by population index, representatives never
+        // appear in the result.
+        let signed = |(i, c): (usize, &C)| (i, InnovationSignature::new(c.as_ref()));
+        let representatives = representatives
+            .iter()
+            .map(|c| signed((usize::MAX, c)))
+            .collect_vec();
+        let population = population.collect_vec();
+        let signatures = population.iter().copied().enumerate().map(signed).collect_vec();
+        speciate_greedy(
+            &representatives.iter().collect_vec(),
+            signatures.iter(),
+            Assignment::First,
+            |(_, a), (_, b)| {
+                let distance = self.compatibility.signature_distance(a, b);
+                (distance <= self.threshold).then_some(distance)
+            },
+        )
+        .into_iter()
+        .map(|group| group.into_iter().map(|(i, _)| population[*i]).collect_vec())
+        .collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{fixtures::genes, speciation::speciation::DistanceSpeciation};
+
+    fn edges() -> impl Strategy<Value = Vec<(InnovationId, f32)>> {
+        proptest::collection::btree_map(0u64..300, -2f32..2., 0..40)
+            .prop_map(|edges| edges.into_iter().collect_vec())
+    }
+
+    #[test]
+    fn test_signature() {
+        let signature = InnovationSignature::new(&genes(&[(0, 1.), (63, 2.), (64, 3.), (200, 4.)]));
+        assert_eq!(signature.len(), 4);
+        assert!(signature.contains(63) && signature.contains(64) && !signature.contains(65));
+        assert_eq!(signature.count_above(63), 2);
+        assert_eq!(signature.count_above(0), 3);
+        assert_eq!(signature.count_above(500), 0);
+        assert_eq!(signature.weight(1, 0), 3.);
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_naive(fst in edges(), snd in edges(), excess in 0f32..3., weight in 0f32..3.) {
+            let metric = Compatibility::new(excess, 1., weight);
+            let (fst, snd) = (genes(&fst), genes(&snd));
+            assert_relative_eq!(
+                metric.signature_distance(&(&fst).into(), &(&snd).into()),
+                metric.distance(&fst, &snd),
+                epsilon = 1e-4
+            );
+        }
+
+        #[test]
+        fn test_same_species(population in proptest::collection::vec(edges(), 1..20)) {
+            let population = population.iter().map(|e| genes(e)).collect_vec();
+            let ids = |groups: Vec<Vec<&Genome>>| {
+                groups
+                    .iter()
+                    .map(|g| g.iter().map(|m| *m as *const Genome).collect_vec())
+                    .collect_vec()
+            };
+            let naive = DistanceSpeciation::new(Box::new(Compatibility::default()), 3.);
+            let fast = SignatureSpeciation::new(Compatibility::default(), 3.);
+            prop_assert_eq!(
+                ids(naive.speciate(population.iter())),
+                ids(fast.speciate(population.iter()))
+            );
+        }
+    }
+}
@@ -1,52 +1,200 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::{calibration::ThresholdCalibration, distance::DistanceMetric};
+
 pub trait Comparable {
     fn compare(&self, other: &Self) -> f32;
 }
 
-pub trait SpeciationMethod {
-    fn speciate<'a, C: Comparable>(
+pub trait SpeciationMethod<C> {
+    fn speciate<'a>(&self, population: impl Iterator<Item = &'a C>) -> Vec<Vec<&'a C>>
+    where
+        C: 'a,
+    {
+        self.speciate_with(&[], population)
+    }
+
+    /// Speciate against the representatives of existing species. The `i`-th
+    /// group of the result belongs to `representatives[i]` and may be empty,
+    /// new species are appended after them.
+    fn speciate_with<'a>(
         &self,
+        representatives: &[&C],
         population: impl Iterator<Item = &'a C>,
-    ) -> Vec<Vec<&'a C>>;
+    ) -> Vec<Vec<&'a C>>
+    where
+        C: 'a;
+}
+
+/// How an individual chooses among the species it belongs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Assignment {
+    /// The first species in order, so the partition depends on the order of
+    /// the population.
+    #[default]
+    First,
+    /// The nearest species. New species are still founded greedily, then
+    /// every individual moves to its nearest representative or founder, so
+    /// well separated groups are found whatever the population order.
+    Nearest,
+}
+
+/// Greedy speciation: every individual joins a species whose representative
+/// (or first member) is close enough, or founds a new one. `distance` is
+/// `None` when an individual does not belong with a species.
+pub(super) fn speciate_greedy<'a, C>(
+    representatives: &[&C],
+    population: impl Iterator<Item = &'a C>,
+    assignment: Assignment,
+    distance: impl Fn(&C, &C) -> Option<f32>,
+) -> Vec<Vec<&'a C>> {
+    let belongs = |a: &C, b: &C| distance(a, b).is_some();
+    let mut ret: Vec<Vec<&C>> = vec![vec![]; representatives.len()];
+    for el in population {
+        let v = representatives
+            .iter()
+            .position(|x| belongs(x, el))
+            .or_else(|| {
+                (representatives.len()..ret.len()).find(|i| {
+                    belongs(
+                        ret[*i]
+                            .first()
+                            .expect("At speciate, first element should exist"),
+                        el,
+                    )
+                })
+            });
+        match v {
+            Some(x) => ret[x].push(el),
+            None => ret.push(vec![el]),
+        }
+    }
+    if assignment == Assignment::First {
+        return ret;
+    }
+    let founders = representatives
+        .iter()
+        .copied()
+        .chain(ret[representatives.len()..].iter().map(|group| group[0]))
+        .collect::<Vec<_>>();
+    let mut nearest: Vec<Vec<&C>> = vec![vec![]; founders.len()];
+    for el in ret.into_iter().flatten() {
+        let index = founders
+            .iter()
+            .enumerate()
+            .filter_map(|(i, founder)| Some((i, distance(founder, el)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .expect("Every individual belongs with the species it joined");
+        nearest[index].push(el);
+    }
+    nearest
 }
 
+/// Speciation on [`Comparable`] similarity: individuals belong together
+/// when their similarity is at least the threshold.
 pub struct SpeciationThreshold {
     threshold: f32,
+    assignment: Assignment,
 }
 
 impl SpeciationThreshold {
-    fn new(t: f32) -> Self {
-        Self { threshold: t }
+    pub fn new(t: f32) -> Self {
+        Self {
+            threshold: t,
+            assignment: Assignment::default(),
+        }
+    }
+
+    pub fn with_assignment(self, assignment: Assignment) -> Self {
+        Self { assignment, ..self }
     }
 }
 
-impl SpeciationMethod for SpeciationThreshold {
-    fn speciate<'a, C>(
+impl<C: Comparable> SpeciationMethod<C> for SpeciationThreshold {
+    fn speciate_with<'a>(
         &self,
+        representatives: &[&C],
         population: impl Iterator<Item = &'a C>,
-    ) -> Vec<Vec<&'a C>> where C: Comparable {
-        let mut ret: Vec<Vec<&C>> = vec![];
-        for el in population {
-            let v = ret
-                .iter_mut()
-                .filter(|x| {
-                    x.first()
-                        .expect("At speciate, first element should exist")
-                        .compare(el)
-                        >= self.threshold
-                })
-                .next();
-            match v {
-                Some(x) => x.push(el),
-                None => ret.push(vec![el]),
-            }
+    ) -> Vec<Vec<&'a C>>
+    where
+        C: 'a,
+    {
+        // Larger similarities are nearer
+        speciate_greedy(representatives, population, self.assignment, |a, b| {
+            let similarity = a.compare(b);
+            (similarity >= self.threshold).then_some(-similarity)
+        })
+    }
+}
+
+/// Speciation on a [`DistanceMetric`] over `G`, chosen at run time. Any
+/// individual exposing a `G` through [`AsRef`] can be speciated without
+/// wrapping it; individuals belong together when their distance is at most
+/// the threshold.
+pub struct DistanceSpeciation<G: ?Sized> {
+    metric: Box<dyn DistanceMetric<G>>,
+    threshold: f32,
+    assignment: Assignment,
+}
+
+impl<G: ?Sized> DistanceSpeciation<G> {
+    pub fn new(metric: Box<dyn DistanceMetric<G>>, threshold: f32) -> Self {
+        Self {
+            metric,
+            threshold,
+            assignment: Assignment::default(),
         }
-        ret
+    }
+
+    pub fn with_assignment(self, assignment: Assignment) -> Self {
+        Self { assignment, ..self }
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Threshold calibrated on `population`, e.g. the initial one, kept if
+    /// the population is too uniform to calibrate on.
+    pub fn calibrated<C: AsRef<G>>(
+        self,
+        rng: &mut dyn RngCore,
+        calibration: &ThresholdCalibration,
+        population: &[C],
+    ) -> Self {
+        let threshold = calibration
+            .calibrate(rng, &*self.metric, self.assignment, population)
+            .map_or(self.threshold, |c| c.threshold);
+        Self { threshold, ..self }
+    }
+}
+
+impl<G: ?Sized, C: AsRef<G>> SpeciationMethod<C> for DistanceSpeciation<G> {
+    fn speciate_with<'a>(
+        &self,
+        representatives: &[&C],
+        population: impl Iterator<Item = &'a C>,
+    ) -> Vec<Vec<&'a C>>
+    where
+        C: 'a,
+    {
+        speciate_greedy(representatives, population, self.assignment, |a, b| {
+            let distance = self.metric.distance(a.as_ref(), b.as_ref());
+            (distance <= self.threshold).then_some(distance)
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::speciation::distance::Euclidean;
+    use itertools::Itertools;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
     use std::f32::consts::FRAC_2_PI as HALF_PI;
 
     #[derive(Debug, PartialEq)]
@@ -69,7 +217,7 @@ mod tests {
 
     #[test]
     fn test_simple_speciation() {
-        let population = vec![
+        let population = [
             TestIndividual(generate_from_angle(0.)),
             TestIndividual(generate_from_angle(f32::EPSILON)),
             TestIndividual(generate_from_angle(-f32::EPSILON)),
@@ -90,4 +238,79 @@ mod tests {
         assert_eq!(*v[1][1], population[4]);
         assert_eq!(*v[1][2], population[5]);
     }
+
+    #[test]
+    fn test_speciate_with_representatives() {
+        let representatives = [
+            TestIndividual(generate_from_angle(HALF_PI)),
+            TestIndividual(generate_from_angle(1.)),
+        ];
+        let population = [
+            TestIndividual(generate_from_angle(0.)),
+            TestIndividual(generate_from_angle(HALF_PI + f32::EPSILON)),
+        ];
+
+        let spec = SpeciationThreshold::new(0.99);
+        let v = spec.speciate_with(&representatives.iter().collect::<Vec<_>>(), population.iter());
+        assert_eq!(v.len(), 3);
+        assert_eq!(*v[0][0], population[1]);
+        assert!(v[1].is_empty());
+        assert_eq!(*v[2][0], population[0]);
+    }
+
+    /// Sorted species sizes of every ordering of `points`.
+    fn partition_sizes(spec: &DistanceSpeciation<[f32]>, points: &[[f32; 1]]) -> Vec<Vec<usize>> {
+        points
+            .iter()
+            .permutations(points.len())
+            .map(|order| {
+                spec.speciate(order.into_iter())
+                    .iter()
+                    .map(Vec::len)
+                    .sorted()
+                    .collect_vec()
+            })
+            .unique()
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_nearest_assignment() {
+        // Two groups, closer to each other than the threshold at their edges
+        let points = [[0.], [0.1], [0.2], [1.], [1.1], [1.2]];
+        let first = DistanceSpeciation::new(Box::new(Euclidean), 0.95);
+        assert!(partition_sizes(&first, &points).len() > 1);
+        let nearest =
+            DistanceSpeciation::new(Box::new(Euclidean), 0.95).with_assignment(Assignment::Nearest);
+        assert_eq!(partition_sizes(&nearest, &points), vec![vec![3, 3]]);
+    }
+
+    #[test]
+    fn test_calibrated() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let points = [[0.], [0.1], [0.2], [5.], [5.1], [5.2]];
+        let calibration = ThresholdCalibration::new(2);
+        let spec = DistanceSpeciation::new(Box::new(Euclidean), 0.01)
+            .calibrated(&mut rng, &calibration, &points);
+        assert_eq!(spec.speciate(points.iter()).len(), 2);
+        let uniform = DistanceSpeciation::<[f32]>::new(Box::new(Euclidean), 0.01)
+            .calibrated(&mut rng, &calibration, &[[1.]; 3]);
+        assert_eq!(uniform.threshold(), 0.01);
+    }
+
+    #[test]
+    fn test_nearest_representative() {
+        let representatives = [
+            TestIndividual(generate_from_angle(0.)),
+            TestIndividual(generate_from_angle(0.3)),
+        ];
+        let population = [TestIndividual(generate_from_angle(0.25))];
+        let representatives = representatives.iter().collect_vec();
+        let first = SpeciationThreshold::new(0.9);
+        let v = first.speciate_with(&representatives, population.iter());
+        assert_eq!(v.iter().map(Vec::len).collect_vec(), vec![1, 0]);
+        let nearest = SpeciationThreshold::new(0.9).with_assignment(Assignment::Nearest);
+        let v = nearest.speciate_with(&representatives, population.iter());
+        assert_eq!(v.iter().map(Vec::len).collect_vec(), vec![0, 1]);
+    }
 }
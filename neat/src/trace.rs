@@ -0,0 +1,152 @@
+//! Instrumentation of the evolve loop, compiled out without the `tracing`
+//! feature.
+
+/// Enter a span until the end of the enclosing scope.
+macro_rules! span {
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $name $(, $($fields)*)?).entered();
+    };
+}
+
+/// Like [`span`], also emitting the elapsed time as `elapsed_us` when the
+/// scope ends.
+macro_rules! phase {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _phase = $crate::trace::Phase::new(
+            tracing::debug_span!($name $(, $($fields)*)?),
+        );
+    };
+}
+
+macro_rules! event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($args)*);
+    };
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) struct Phase {
+    span: tracing::span::EnteredSpan,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Phase {
+    pub(crate) fn new(span: tracing::Span) -> Self {
+        Self {
+            span: span.entered(),
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Phase {
+    fn drop(&mut self) {
+        let elapsed_us = self.start.elapsed().as_micros() as u64;
+        let phase = self.span.metadata().map_or("", |m| m.name());
+        tracing::debug!(phase, elapsed_us, "phase finished");
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        crossover::crossover::NeatCrossover, individual::genome::genome::GenomeBuilder,
+        mutation::mutation::GaussianMutation, population::population::Population,
+        selection::selection_trait::RoulleteSelection, speciation::speciation::SpeciationThreshold,
+        GeneticAlgortihm,
+    };
+
+    /// Records span names and the fields of every event.
+    #[derive(Default, Clone)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<&'static str>>>,
+        fields: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, _: &dyn std::fmt::Debug) {
+            self.fields.lock().unwrap().push(field.name().to_string());
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span.metadata().name());
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_generation_spans() {
+        let recorder = Recorder::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let genome = GenomeBuilder::new()
+            .input(2)
+            .output(1)
+            .connect(0, 2, 1.)
+            .build()
+            .unwrap();
+        let mut pop = Population::new(
+            GeneticAlgortihm::new(
+                SpeciationThreshold::new(0.5),
+                RoulleteSelection::new(),
+                Box::new(NeatCrossover::default()),
+                Box::new(GaussianMutation::default()),
+            ),
+            vec![genome; 4],
+        );
+        tracing::subscriber::with_default(recorder.clone(), || {
+            pop.evaluate(|_| 1.);
+            pop.evolve(&mut rng);
+        });
+        let spans = recorder.spans.lock().unwrap();
+        for name in [
+            "evaluation",
+            "generation",
+            "reproduction",
+            "selection",
+            "crossover",
+            "mutation",
+            "speciation",
+        ] {
+            assert!(spans.contains(&name), "{name} not in {spans:?}");
+        }
+        let fields = recorder.fields.lock().unwrap();
+        for name in ["species", "elapsed_us", "evaluated", "operator"] {
+            assert!(fields.iter().any(|f| f == name), "{name} not in {fields:?}");
+        }
+    }
+}